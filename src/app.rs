@@ -6,20 +6,26 @@ use std::sync::{
     mpsc,
 };
 
-use image::DynamicImage;
 use image::codecs::jpeg::JpegEncoder;
 use image::codecs::png::{
     CompressionType as PngCompressionType, FilterType as PngFilterType, PngEncoder,
 };
 use image::codecs::webp::WebPEncoder;
 use image::imageops::FilterType;
+use image::{DynamicImage, ImageEncoder};
 use rayon::prelude::*;
 
 use crate::{
-    browser::Browser,
+    browser::{Browser, KeywordEdit, PairPrimary},
     config::AppConfig,
-    state::EditState,
+    geotag,
+    inspector::{InfoEdit, MetadataInspector},
+    map::MapView,
+    processing::icc::{self, MonitorProfile, OutputProfile},
+    state::{EditState, PhotoInfo},
+    timeline::Timeline,
     viewer::{PreviewBackend, Viewer},
+    xmp::{self, XmpMetadata},
 };
 
 const SIDEBAR_WIDTH: f32 = 220.0;
@@ -30,6 +36,36 @@ const FILMSTRIP_HEIGHT: f32 = 100.0;
 enum ViewMode {
     Library,
     Detail,
+    /// The active photo and a candidate side by side, zoomed and panned together.
+    Compare,
+    /// All marked photos in one layout, for picking the keeper.
+    Survey,
+    /// Geotagged photos of the current folder or collection on a map.
+    Map,
+    /// Photos under the current folder grouped by capture date.
+    Timeline,
+}
+
+impl ViewMode {
+    const ALL: [ViewMode; 6] = [
+        ViewMode::Library,
+        ViewMode::Detail,
+        ViewMode::Compare,
+        ViewMode::Survey,
+        ViewMode::Map,
+        ViewMode::Timeline,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            ViewMode::Library => "Library",
+            ViewMode::Detail => "Detail",
+            ViewMode::Compare => "Compare",
+            ViewMode::Survey => "Survey",
+            ViewMode::Map => "Map",
+            ViewMode::Timeline => "Timeline",
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -48,10 +84,13 @@ struct RenderJob {
 #[derive(Clone, Copy)]
 struct RenderOptions {
     format: RenderFormat,
+    profile: OutputProfile,
     jpg_quality: u8,
     png_compression: u8,
     resize_enabled: bool,
     resize_long_edge: u32,
+    /// Write the image's keywords and info into the exported file as XMP.
+    embed_metadata: bool,
 }
 
 enum RenderEvent {
@@ -128,16 +167,26 @@ pub struct PhotographApp {
     preview_status_details: Option<String>,
     preview_status_vendor: Option<GpuVendor>,
     viewer: Viewer,
+    /// Candidate shown next to `viewer` in Compare mode.
+    compare_viewer: Viewer,
+    compare_zoom: f32,
+    compare_pan: egui::Vec2,
+    /// Rating shortcuts in Compare mode apply to the candidate.
+    compare_focus_candidate: bool,
+    /// Photo the rating shortcuts apply to in Survey mode.
+    survey_focus: Option<PathBuf>,
     view_mode: ViewMode,
     prev_selected: Option<PathBuf>,
     show_render_window: bool,
     render_output_path: String,
     render_format: RenderFormat,
+    render_profile: OutputProfile,
     render_speed_profile: RenderSpeedProfile,
     render_jpg_quality: u8,
     render_png_compression: u8,
     render_resize_enabled: bool,
     render_resize_long_edge: u32,
+    render_embed_metadata: bool,
     render_status: String,
     render_in_progress: bool,
     render_total: usize,
@@ -146,6 +195,14 @@ pub struct PhotographApp {
     render_failed: usize,
     render_current: String,
     render_rx: Option<mpsc::Receiver<RenderEvent>>,
+    show_display_window: bool,
+    metadata_inspector: MetadataInspector,
+    map: MapView,
+    timeline: Timeline,
+    monitor_profile: MonitorProfile,
+    /// Path being typed for a file-based monitor profile.
+    monitor_profile_path: String,
+    monitor_status: String,
     config: AppConfig,
 }
 
@@ -319,26 +376,53 @@ impl PhotographApp {
     ) -> Self {
         configure_fonts(&cc.egui_ctx);
         configure_visuals(&cc.egui_ctx);
-        let browser = Browser::new(config.browse_path.clone());
+        let mut browser = Browser::new(config.browse_path.clone());
+        if let Some(primary) = config
+            .raw_jpeg_primary
+            .as_deref()
+            .and_then(PairPrimary::parse)
+        {
+            browser.set_pair_primary(primary);
+        }
+        let mut map = MapView::default();
+        if let Some(dir) = config.map_tile_dir.clone() {
+            map.set_tile_dir(dir);
+        }
         let output_dir = default_render_dir();
         let (preview_status_label, preview_status_details, preview_status_vendor) =
             preview_status_summary(preview_backend);
-        Self {
+        let monitor_profile = config
+            .monitor_profile
+            .as_deref()
+            .map(MonitorProfile::parse)
+            .unwrap_or_default();
+        let monitor_profile_path = match &monitor_profile {
+            MonitorProfile::File(path) => path.display().to_string(),
+            _ => String::new(),
+        };
+        let mut app = Self {
             browser,
             preview_status_label,
             preview_status_details,
             preview_status_vendor,
             viewer: Viewer::new(0, preview_backend),
+            compare_viewer: Viewer::new(1, preview_backend),
+            compare_zoom: 1.0,
+            compare_pan: egui::Vec2::ZERO,
+            compare_focus_candidate: false,
+            survey_focus: None,
             view_mode: ViewMode::Library,
             prev_selected: None,
             show_render_window: false,
             render_output_path: output_dir.display().to_string(),
             render_format: RenderFormat::Jpg,
+            render_profile: OutputProfile::Srgb,
             render_speed_profile: RenderSpeedProfile::Balanced,
             render_jpg_quality: 90,
             render_png_compression: 6,
             render_resize_enabled: false,
             render_resize_long_edge: 3000,
+            render_embed_metadata: true,
             render_status: String::new(),
             render_in_progress: false,
             render_total: 0,
@@ -347,7 +431,40 @@ impl PhotographApp {
             render_failed: 0,
             render_current: String::new(),
             render_rx: None,
+            show_display_window: false,
+            metadata_inspector: MetadataInspector::default(),
+            map,
+            timeline: Timeline::default(),
+            monitor_profile,
+            monitor_profile_path,
+            monitor_status: String::new(),
             config,
+        };
+        app.apply_monitor_profile();
+        app
+    }
+
+    /// Loads the selected monitor profile into the viewer, falling back to
+    /// sRGB when it cannot be read.
+    fn apply_monitor_profile(&mut self) {
+        match self.monitor_profile.load() {
+            Ok(profile) => {
+                self.monitor_status = match &profile {
+                    Some(p) => format!(
+                        "Using {}",
+                        icc::profile_description(p).unwrap_or_else(|| "monitor profile".into())
+                    ),
+                    None => "Display treated as sRGB".to_string(),
+                };
+                self.compare_viewer.set_monitor_profile(profile.clone());
+                self.viewer.set_monitor_profile(profile);
+                self.config.monitor_profile = Some(self.monitor_profile.config_value());
+            }
+            Err(err) => {
+                self.monitor_status = format!("Monitor profile unavailable, using sRGB: {err}");
+                self.viewer.set_monitor_profile(None);
+                self.compare_viewer.set_monitor_profile(None);
+            }
         }
     }
 
@@ -381,7 +498,9 @@ impl PhotographApp {
                 let edit_state = if self.viewer.path() == Some(&source_path) {
                     self.viewer.edit_state.clone()
                 } else {
-                    EditState::load(&source_path).unwrap_or_default()
+                    let companion = self.browser.companion(&source_path);
+                    EditState::load(&source_path, companion.map(PathBuf::as_path))
+                        .unwrap_or_default()
                 };
                 RenderTask {
                     source_path,
@@ -419,10 +538,12 @@ impl PhotographApp {
 
         let options = RenderOptions {
             format: self.render_format,
+            profile: self.render_profile,
             jpg_quality: self.render_jpg_quality.clamp(1, 100),
             png_compression: self.render_png_compression.min(9),
             resize_enabled: self.render_resize_enabled,
             resize_long_edge: self.render_resize_long_edge.max(1),
+            embed_metadata: self.render_embed_metadata,
         };
         let jobs = build_render_jobs(tasks, &output_dir, options.format);
         let total = jobs.len();
@@ -565,12 +686,400 @@ impl PhotographApp {
         }
         let new_idx = (idx as i32 + delta).rem_euclid(len) as usize;
         let new_path = images[new_idx].0.clone();
-        self.viewer.set_image(new_path.clone(), ctx);
+        let companion = self.browser.companion(&new_path).cloned();
+        self.viewer.set_image(new_path.clone(), companion, ctx);
         self.browser.selected = Some(new_path.clone());
         self.prev_selected = Some(new_path);
     }
 }
 
+impl PhotographApp {
+    /// Switches to `mode`, setting up its images. Compare needs an active
+    /// photo and Survey a marked one; otherwise the mode is left unchanged.
+    fn set_view_mode(&mut self, mode: ViewMode, ctx: &egui::Context) {
+        match mode {
+            ViewMode::Library | ViewMode::Survey | ViewMode::Map => {}
+            ViewMode::Timeline => {
+                let root = self.browser.current_dir.clone();
+                if self.timeline.root() != Some(root.as_path()) {
+                    self.timeline.scan(root);
+                }
+            }
+            ViewMode::Detail | ViewMode::Compare if self.viewer.path().is_none() => return,
+            ViewMode::Detail => {}
+            ViewMode::Compare => {
+                let candidate = self.compare_viewer.path().cloned();
+                if candidate.is_none() || candidate.as_ref() == self.viewer.path() {
+                    self.step_compare_candidate(1, ctx);
+                }
+                self.compare_zoom = 1.0;
+                self.compare_pan = egui::Vec2::ZERO;
+                self.compare_focus_candidate = false;
+            }
+        }
+        self.view_mode = mode;
+    }
+
+    /// Steps the Compare candidate through the current folder, skipping the
+    /// active photo.
+    fn step_compare_candidate(&mut self, delta: i32, ctx: &egui::Context) {
+        let images = &self.browser.images;
+        let select = self.viewer.path();
+        let len = images.len() as i32;
+        if len < 2 {
+            return;
+        }
+        let start = self
+            .compare_viewer
+            .path()
+            .or(select)
+            .and_then(|path| images.iter().position(|(p, _)| p == path))
+            .unwrap_or(0) as i32;
+        let mut idx = start;
+        loop {
+            idx = (idx + delta).rem_euclid(len);
+            if Some(&images[idx as usize].0) != select || idx == start {
+                break;
+            }
+        }
+        let path = images[idx as usize].0.clone();
+        let companion = self.browser.companion(&path).cloned();
+        self.compare_viewer.set_image(path, companion, ctx);
+    }
+
+    /// Makes the Compare candidate the active photo and moves on to the next
+    /// candidate.
+    fn promote_compare_candidate(&mut self, ctx: &egui::Context) {
+        let Some(candidate) = self.compare_viewer.path().cloned() else {
+            return;
+        };
+        let companion = self.browser.companion(&candidate).cloned();
+        self.viewer.set_image(candidate.clone(), companion, ctx);
+        self.browser.selected = Some(candidate.clone());
+        self.prev_selected = Some(candidate);
+        self.step_compare_candidate(1, ctx);
+    }
+
+    /// Photo the rating shortcuts apply to in the current mode.
+    fn rating_target(&self) -> Option<PathBuf> {
+        match self.view_mode {
+            ViewMode::Library | ViewMode::Map | ViewMode::Timeline => self.browser.selected.clone(),
+            ViewMode::Detail => self.viewer.path().cloned(),
+            ViewMode::Compare if self.compare_focus_candidate => {
+                self.compare_viewer.path().cloned()
+            }
+            ViewMode::Compare => self.viewer.path().cloned(),
+            ViewMode::Survey => self.survey_focus.clone(),
+        }
+    }
+
+    /// Sets the star rating of `path` and saves it to its sidecar, through
+    /// whichever viewer has it open so pending edits are kept.
+    fn set_rating(&mut self, path: &Path, rating: u8) {
+        self.edit_sidecar(path, |state| state.rating = rating);
+        self.browser.set_rating(path.to_path_buf(), rating);
+    }
+
+    fn set_keywords(&mut self, path: &Path, keywords: Vec<String>) {
+        let state = self.edit_sidecar(path, |state| state.keywords = keywords.clone());
+        write_xmp_sidecar(path, self.browser.companion(path), &state);
+        self.browser.set_keywords(path.to_path_buf(), keywords);
+    }
+
+    fn set_info(&mut self, path: &Path, info: PhotoInfo) {
+        // Keywords only known from an `.xmp` sidecar are adopted so that
+        // rewriting the sidecar keeps them.
+        let keywords = self.browser.keywords(path).to_vec();
+        let state = self.edit_sidecar(path, |state| {
+            state.info = info.clone();
+            if state.keywords.is_empty() {
+                state.keywords = keywords.clone();
+            }
+        });
+        write_xmp_sidecar(path, self.browser.companion(path), &state);
+        self.browser.refresh_capture_time(path);
+    }
+
+    /// Saved info of `path`; RAW files described elsewhere start from their
+    /// `.xmp` sidecar.
+    fn saved_info(&self, path: &Path) -> PhotoInfo {
+        let mut info = match self.viewer.path() {
+            Some(open) if open == path => self.viewer.edit_state.info.clone(),
+            _ => {
+                let companion = self.browser.companion(path).map(PathBuf::as_path);
+                EditState::load(path, companion).unwrap_or_default().info
+            }
+        };
+        if info == PhotoInfo::default()
+            && let Some(raw) = xmp_sidecar_owner(path, self.browser.companion(path))
+            && let Some(meta) = xmp::read_sidecar(&raw)
+        {
+            info.title = meta.title;
+            info.caption = meta.caption;
+            info.creator = meta.creator;
+            info.copyright = meta.copyright;
+            info.location = meta.location;
+        }
+        info
+    }
+
+    /// Geotag window, tagging the marked photos (or all shown) with their
+    /// position on the loaded GPX track.
+    fn show_geotag_window(&mut self, ctx: &egui::Context) {
+        let mut targets = self.browser.marked_in_order();
+        if targets.is_empty() {
+            targets = self.browser.images.iter().map(|(p, _)| p.clone()).collect();
+        }
+        let Some(offset) = self.map.show_geotag_window(ctx, targets.len()) else {
+            return;
+        };
+        let mut tagged = 0;
+        for path in &targets {
+            let mut info = self.saved_info(path);
+            // Capture times are camera-local; the track is in UTC
+            let local = info
+                .capture_time
+                .as_deref()
+                .and_then(crate::metadata::parse_datetime)
+                .or_else(|| crate::metadata::capture_time(path));
+            let Some(location) =
+                local.and_then(|t| geotag::locate(self.map.track(), t - offset as f64))
+            else {
+                continue;
+            };
+            info.location = Some(location);
+            self.set_info(path, info);
+            self.browser.set_location(path.clone(), location);
+            tagged += 1;
+        }
+        self.map.status = format!("Tagged {tagged} of {} photos", targets.len());
+        // The metadata window must not save over the new location
+        if let Some(path) = self.rating_target()
+            && self.metadata_inspector.shows(&path)
+        {
+            let info = self.saved_info(&path);
+            self.metadata_inspector.load(&path, info);
+        }
+    }
+
+    /// Metadata window for the photo in focus, saving its info edits.
+    fn show_metadata_window(&mut self, ctx: &egui::Context) {
+        if !self.metadata_inspector.open {
+            return;
+        }
+        let path = self.rating_target();
+        if let Some(path) = &path
+            && !self.metadata_inspector.shows(path)
+        {
+            let info = self.saved_info(path);
+            self.metadata_inspector.load(path, info);
+        }
+        let mut targets = self.browser.marked_in_order();
+        if targets.is_empty() {
+            targets.extend(path.clone());
+        }
+        let Some(edit) = self.metadata_inspector.show(ctx, &targets) else {
+            return;
+        };
+        match edit {
+            InfoEdit::Save(path, info) => self.set_info(&path, info),
+            InfoEdit::ShiftTimes(paths, delta) => {
+                let mut shifted = 0;
+                for path in &paths {
+                    let mut info = self.saved_info(path);
+                    let time = info
+                        .capture_time
+                        .clone()
+                        .or_else(|| crate::metadata::original_datetime(path));
+                    if let Some(time) =
+                        time.and_then(|t| crate::metadata::shift_datetime(&t, delta))
+                    {
+                        info.capture_time = Some(time);
+                        self.set_info(path, info);
+                        shifted += 1;
+                    }
+                }
+                self.metadata_inspector.status =
+                    format!("Shifted {shifted} of {} capture times", paths.len());
+            }
+        }
+        // Reload the form from what was saved
+        if let Some(path) = &path {
+            let info = self.saved_info(path);
+            let status = std::mem::take(&mut self.metadata_inspector.status);
+            self.metadata_inspector.load(path, info);
+            self.metadata_inspector.status = status;
+        }
+    }
+
+    /// Applies keyword changes made in the sidebar.
+    fn apply_keyword_edits(&mut self) {
+        for edit in self.browser.take_keyword_edits() {
+            let changes: Vec<(PathBuf, Vec<String>)> = match edit {
+                KeywordEdit::Add(paths, keyword) => paths
+                    .into_iter()
+                    .filter_map(|path| {
+                        let mut keywords = self.browser.keywords(&path).to_vec();
+                        if keywords.contains(&keyword) {
+                            return None;
+                        }
+                        keywords.push(keyword.clone());
+                        keywords.sort();
+                        Some((path, keywords))
+                    })
+                    .collect(),
+                KeywordEdit::Remove(path, keyword) => {
+                    let mut keywords = self.browser.keywords(&path).to_vec();
+                    keywords.retain(|k| *k != keyword);
+                    vec![(path, keywords)]
+                }
+            };
+            for (path, keywords) in changes {
+                self.set_keywords(&path, keywords);
+            }
+        }
+    }
+
+    /// Applies `edit` to the saved edits of `path`, through any viewer that
+    /// has it open so the viewer's copy does not later overwrite the change,
+    /// and returns the result.
+    fn edit_sidecar(&mut self, path: &Path, edit: impl Fn(&mut EditState)) -> EditState {
+        let companion = self.browser.companion(path).map(PathBuf::as_path);
+        let mut saved = None;
+        for viewer in [&mut self.viewer, &mut self.compare_viewer] {
+            if viewer.path().map(|p| p.as_path()) == Some(path) {
+                edit(&mut viewer.edit_state);
+                let _ = viewer.edit_state.save(path, companion);
+                saved = Some(viewer.edit_state.clone());
+            }
+        }
+        saved.unwrap_or_else(|| {
+            let mut state = EditState::load(path, companion).unwrap_or_default();
+            edit(&mut state);
+            let _ = state.save(path, companion);
+            state
+        })
+    }
+
+    /// Compare mode: the active photo and a candidate with shared zoom/pan.
+    fn show_compare(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            if ui.button("\u{2039} Back to Detail").clicked() {
+                self.view_mode = ViewMode::Detail;
+            }
+            ui.separator();
+            if ui
+                .button("\u{2039}")
+                .on_hover_text("Previous candidate")
+                .clicked()
+            {
+                self.step_compare_candidate(-1, ctx);
+            }
+            if ui
+                .button("\u{203A}")
+                .on_hover_text("Next candidate")
+                .clicked()
+            {
+                self.step_compare_candidate(1, ctx);
+            }
+            if ui
+                .button("Make select")
+                .on_hover_text("Keep the candidate and compare it against the next photo")
+                .clicked()
+            {
+                self.promote_compare_candidate(ctx);
+            }
+            ui.separator();
+            if ui
+                .selectable_label(self.viewer.focus_peaking, "Peaking")
+                .on_hover_text("Highlight in-focus edges")
+                .clicked()
+            {
+                self.viewer.focus_peaking = !self.viewer.focus_peaking;
+            }
+        });
+        self.compare_viewer.focus_peaking = self.viewer.focus_peaking;
+        ui.separator();
+
+        let half_w = (ui.available_width() - ui.spacing().item_spacing.x) / 2.0;
+        let height = ui.available_height();
+        ui.horizontal(|ui| {
+            for candidate in [false, true] {
+                let viewer = if candidate {
+                    &mut self.compare_viewer
+                } else {
+                    &mut self.viewer
+                };
+                let Some(path) = viewer.path().cloned() else {
+                    continue;
+                };
+                let focused = self.compare_focus_candidate == candidate;
+                let stroke = if focused {
+                    ui.visuals().selection.stroke
+                } else {
+                    egui::Stroke::NONE
+                };
+                let mut new_rating = None;
+                let mut clicked = false;
+                egui::Frame::new().stroke(stroke).show(ui, |ui| {
+                    ui.set_width(half_w - 2.0);
+                    ui.set_height(height - 2.0);
+                    ui.vertical(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.strong(if candidate { "Candidate" } else { "Select" });
+                            ui.label(viewer.filename());
+                            new_rating = rating_stars(ui, self.browser.rating(&path));
+                            if let Some(score) = self.browser.sharpness(&path) {
+                                let text = format!("Sharpness {score:.0}");
+                                if self.browser.is_soft(&path) {
+                                    ui.colored_label(ui.visuals().warn_fg_color, text)
+                                        .on_hover_text("Likely out of focus");
+                                } else {
+                                    ui.weak(text);
+                                }
+                            }
+                        });
+                        let resp = viewer.show_synced_image(
+                            ui,
+                            &mut self.compare_zoom,
+                            &mut self.compare_pan,
+                        );
+                        clicked = resp.is_some_and(|r| r.clicked() || r.dragged());
+                    });
+                });
+                if clicked || new_rating.is_some() {
+                    self.compare_focus_candidate = candidate;
+                }
+                if let Some(rating) = new_rating {
+                    self.set_rating(&path, rating);
+                }
+            }
+        });
+    }
+}
+
+/// Five clickable stars; returns the new rating when one is clicked
+/// (clicking the current rating clears it).
+fn rating_stars(ui: &mut egui::Ui, rating: u8) -> Option<u8> {
+    let mut new_rating = None;
+    ui.spacing_mut().item_spacing.x = 0.0;
+    for star in 1..=5u8 {
+        let text = if star <= rating {
+            "\u{2605}"
+        } else {
+            "\u{2606}"
+        };
+        if ui
+            .add(egui::Button::new(text).frame(false))
+            .on_hover_text(format!("Rate {star} (key {star})"))
+            .clicked()
+        {
+            new_rating = Some(if star == rating { 0 } else { star });
+        }
+    }
+    new_rating
+}
+
 fn default_render_dir() -> PathBuf {
     dirs::picture_dir()
         .or_else(dirs::home_dir)
@@ -578,7 +1087,8 @@ fn default_render_dir() -> PathBuf {
         .join("Rendered")
 }
 
-fn expand_home_prefix(raw: &str) -> PathBuf {
+/// `raw` with a leading `~` replaced by the home directory.
+pub fn expand_home_prefix(raw: &str) -> PathBuf {
     if raw == "~" {
         return dirs::home_dir().unwrap_or_else(|| PathBuf::from("~"));
     }
@@ -590,6 +1100,30 @@ fn expand_home_prefix(raw: &str) -> PathBuf {
     PathBuf::from(raw)
 }
 
+/// The RAW file whose `.xmp` sidecar describes `path`: itself, or the RAW
+/// half of the RAW+JPEG pair it belongs to, `companion` being the other half.
+fn xmp_sidecar_owner(path: &Path, companion: Option<&PathBuf>) -> Option<PathBuf> {
+    if xmp::uses_sidecar(path) {
+        return Some(path.to_path_buf());
+    }
+    companion.filter(|raw| xmp::uses_sidecar(raw)).cloned()
+}
+
+/// Mirrors the keywords and info in `state` into the `.xmp` sidecar of the
+/// RAW file, where other editors read them; for a RAW+JPEG pair that is the
+/// RAW half whichever half was edited.
+fn write_xmp_sidecar(path: &Path, companion: Option<&PathBuf>, state: &EditState) {
+    let Some(raw) = xmp_sidecar_owner(path, companion) else {
+        return;
+    };
+    if let Err(err) = xmp::write_sidecar(&raw, &XmpMetadata::from_state(state)) {
+        eprintln!(
+            "photograph: failed to write {}: {err}",
+            xmp::sidecar_path(&raw).display()
+        );
+    }
+}
+
 fn render_single_image(
     source_path: &Path,
     state: &EditState,
@@ -609,8 +1143,15 @@ fn render_single_image(
             );
         }
     };
-    let rendered = apply_export_resize(processed, options);
-    write_rendered_image(&rendered, output_path, options)?;
+    let rendered = icc::to_output(apply_export_resize(processed, options), options.profile)?;
+    let mut metadata = XmpMetadata::default();
+    if options.embed_metadata {
+        metadata = XmpMetadata::from_state(state);
+        if metadata.keywords.is_empty() && xmp::uses_sidecar(source_path) {
+            metadata.keywords = xmp::read_sidecar_keywords(source_path);
+        }
+    }
+    write_rendered_image(&rendered, output_path, options, &metadata)?;
     Ok(())
 }
 
@@ -695,25 +1236,40 @@ fn write_rendered_image(
     rendered: &DynamicImage,
     output_path: &Path,
     options: RenderOptions,
+    metadata: &XmpMetadata,
 ) -> anyhow::Result<()> {
-    let file = std::fs::File::create(output_path)?;
-    let writer = std::io::BufWriter::new(file);
+    let icc_profile = options.profile.icc_bytes()?;
+    let mut bytes = Vec::new();
+    let writer = &mut bytes;
     match options.format {
         RenderFormat::Jpg => {
-            let encoder = JpegEncoder::new_with_quality(writer, options.jpg_quality.clamp(1, 100));
+            let mut encoder =
+                JpegEncoder::new_with_quality(writer, options.jpg_quality.clamp(1, 100));
+            encoder.set_icc_profile(icc_profile)?;
             rendered.write_with_encoder(encoder)?;
         }
         RenderFormat::Png => {
             let compression = PngCompressionType::Level(options.png_compression.min(9));
-            let encoder =
+            let mut encoder =
                 PngEncoder::new_with_quality(writer, compression, PngFilterType::Adaptive);
+            encoder.set_icc_profile(icc_profile)?;
             rendered.write_with_encoder(encoder)?;
         }
         RenderFormat::Webp => {
-            let encoder = WebPEncoder::new_lossless(writer);
+            let mut encoder = WebPEncoder::new_lossless(writer);
+            encoder.set_icc_profile(icc_profile)?;
             rendered.write_with_encoder(encoder)?;
         }
     }
+    if !metadata.is_empty() {
+        let packet = xmp::packet(metadata);
+        bytes = match options.format {
+            RenderFormat::Jpg => xmp::embed_in_jpeg(&bytes, &packet)?,
+            RenderFormat::Png => xmp::embed_in_png(&bytes, &packet)?,
+            RenderFormat::Webp => xmp::embed_in_webp(&bytes, &packet)?,
+        };
+    }
+    std::fs::write(output_path, bytes)?;
     Ok(())
 }
 
@@ -731,9 +1287,11 @@ mod tests {
     use std::time::{SystemTime, UNIX_EPOCH};
 
     use super::{
-        RenderFormat, RenderSpeedProfile, build_output_path, render_profile_defaults,
-        resized_dimensions,
+        OutputProfile, RenderFormat, RenderOptions, RenderSpeedProfile, XmpMetadata,
+        build_output_path, render_profile_defaults, resized_dimensions, write_rendered_image,
+        write_xmp_sidecar,
     };
+    use crate::state::EditState;
 
     fn unique_test_dir(name: &str) -> std::path::PathBuf {
         let nanos = SystemTime::now()
@@ -811,6 +1369,63 @@ mod tests {
     fn render_profile_speed_prioritizes_throughput() {
         assert_eq!(render_profile_defaults(RenderSpeedProfile::Speed), (82, 1));
     }
+
+    #[test]
+    fn exports_embed_keywords_in_every_format() {
+        let output_dir = unique_test_dir("embed-keywords");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let rendered = image::DynamicImage::new_rgb8(16, 16);
+        let metadata = XmpMetadata {
+            keywords: vec!["Places|Paris".to_string()],
+            title: "Rooftops".to_string(),
+            ..XmpMetadata::default()
+        };
+
+        for format in RenderFormat::ALL {
+            let options = RenderOptions {
+                format,
+                profile: OutputProfile::Srgb,
+                jpg_quality: 90,
+                png_compression: 6,
+                resize_enabled: false,
+                resize_long_edge: 3000,
+                embed_metadata: true,
+            };
+            let path = output_dir.join(format!("out.{}", format.extension()));
+            write_rendered_image(&rendered, &path, options, &metadata).unwrap();
+
+            let bytes = std::fs::read(&path).unwrap();
+            let text = String::from_utf8_lossy(&bytes);
+            assert!(
+                text.contains("<rdf:li>Paris</rdf:li>"),
+                "{}",
+                format.label()
+            );
+            assert!(text.contains(">Rooftops</rdf:li>"), "{}", format.label());
+            assert!(image::open(&path).is_ok(), "{}", format.label());
+        }
+
+        let _ = std::fs::remove_dir_all(&output_dir);
+    }
+
+    #[test]
+    fn jpeg_half_of_a_pair_writes_the_raw_xmp_sidecar() {
+        let dir = unique_test_dir("pair-xmp");
+        std::fs::create_dir_all(&dir).unwrap();
+        let (raw, jpeg) = (dir.join("DSC_0001.NEF"), dir.join("DSC_0001.JPG"));
+        std::fs::write(&raw, b"").unwrap();
+        std::fs::write(&jpeg, b"").unwrap();
+        let state = EditState {
+            keywords: vec!["Places|Paris".to_string()],
+            ..EditState::default()
+        };
+
+        write_xmp_sidecar(&jpeg, Some(&raw), &state);
+        let xmp = std::fs::read_to_string(dir.join("DSC_0001.xmp")).unwrap();
+        assert!(xmp.contains("<rdf:li>Places|Paris</rdf:li>"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
 
 impl eframe::App for PhotographApp {
@@ -827,7 +1442,9 @@ impl eframe::App for PhotographApp {
 
         // Poll background work before rendering panels
         self.browser.poll(ctx);
+        self.apply_keyword_edits();
         self.viewer.drain(ctx);
+        self.compare_viewer.drain(ctx);
         self.poll_render_events();
 
         // When a thumbnail is clicked (grid or filmstrip), load it into the
@@ -835,23 +1452,58 @@ impl eframe::App for PhotographApp {
         let sel = self.browser.selected.clone();
         if sel != self.prev_selected {
             if let Some(path) = sel.clone() {
-                self.viewer.set_image(path, ctx);
+                let companion = self.browser.companion(&path).cloned();
+                self.viewer.set_image(path, companion, ctx);
                 self.view_mode = ViewMode::Detail;
             }
             self.prev_selected = sel;
         }
 
-        // Keyboard navigation while in Detail mode (skip while a text field
-        // like the sidebar path bar has focus).
-        if self.view_mode == ViewMode::Detail && ctx.memory(|m| m.focused().is_none()) {
-            if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
-                self.view_mode = ViewMode::Library;
-            }
-            if ctx.input(|i| i.key_pressed(egui::Key::ArrowRight)) {
-                self.step_active_photo(1, ctx);
+        // Keyboard navigation (skip while a text field like the sidebar path
+        // bar has focus). Number keys rate the photo in focus in every mode.
+        if ctx.memory(|m| m.focused().is_none()) {
+            let escape = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+            let step = ctx.input(|i| {
+                i32::from(i.key_pressed(egui::Key::ArrowRight))
+                    - i32::from(i.key_pressed(egui::Key::ArrowLeft))
+            });
+            match self.view_mode {
+                ViewMode::Library => {}
+                ViewMode::Detail => {
+                    if escape {
+                        self.view_mode = ViewMode::Library;
+                    }
+                    if step != 0 {
+                        self.step_active_photo(step, ctx);
+                    }
+                }
+                ViewMode::Compare => {
+                    if escape {
+                        self.view_mode = ViewMode::Detail;
+                    }
+                    if step != 0 {
+                        self.step_compare_candidate(step, ctx);
+                    }
+                }
+                ViewMode::Survey | ViewMode::Map | ViewMode::Timeline => {
+                    if escape {
+                        self.view_mode = ViewMode::Library;
+                    }
+                }
             }
-            if ctx.input(|i| i.key_pressed(egui::Key::ArrowLeft)) {
-                self.step_active_photo(-1, ctx);
+            const RATING_KEYS: [egui::Key; 6] = [
+                egui::Key::Num0,
+                egui::Key::Num1,
+                egui::Key::Num2,
+                egui::Key::Num3,
+                egui::Key::Num4,
+                egui::Key::Num5,
+            ];
+            let rating = RATING_KEYS
+                .iter()
+                .position(|key| ctx.input(|i| i.key_pressed(*key)));
+            if let (Some(rating), Some(path)) = (rating, self.rating_target()) {
+                self.set_rating(&path, rating as u8);
             }
         }
 
@@ -866,6 +1518,27 @@ impl eframe::App for PhotographApp {
                     if ui.button("Render").clicked() {
                         self.show_render_window = true;
                     }
+                    if ui.button("Display").clicked() {
+                        self.show_display_window = true;
+                    }
+                    if ui.button("Metadata").clicked() {
+                        self.metadata_inspector.open = true;
+                    }
+                    ui.separator();
+                    for mode in ViewMode::ALL {
+                        let enabled = match mode {
+                            ViewMode::Library | ViewMode::Map | ViewMode::Timeline => true,
+                            ViewMode::Detail | ViewMode::Compare => self.viewer.path().is_some(),
+                            ViewMode::Survey => self.browser.marked_count() > 0,
+                        };
+                        let resp = ui.add_enabled(
+                            enabled,
+                            egui::Button::selectable(self.view_mode == mode, mode.label()),
+                        );
+                        if resp.clicked() {
+                            self.set_view_mode(mode, ctx);
+                        }
+                    }
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         if let Some(vendor) = self.preview_status_vendor {
                             let (rect, response) = ui
@@ -929,7 +1602,8 @@ impl eframe::App for PhotographApp {
                 .show(ui, |ui| {
                     let active_path = self.viewer.path().map(|p| p.as_path());
                     if let Some(clicked) = self.browser.show_filmstrip(ui, active_path) {
-                        self.viewer.set_image(clicked.clone(), ctx);
+                        let companion = self.browser.companion(&clicked).cloned();
+                        self.viewer.set_image(clicked.clone(), companion, ctx);
                         self.browser.selected = Some(clicked.clone());
                         self.prev_selected = Some(clicked);
                     }
@@ -950,6 +1624,15 @@ impl eframe::App for PhotographApp {
                         }
                         ui.separator();
                         ui.label(self.viewer.filename());
+                        if let Some(companion) = self
+                            .viewer
+                            .path()
+                            .and_then(|path| self.browser.companion(path))
+                        {
+                            let name = companion.file_name().unwrap_or_default().to_string_lossy();
+                            ui.weak(format!("+ {name}"))
+                                .on_hover_text("RAW+JPEG pair: ratings and edits apply to both");
+                        }
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                             if let Some(path) = self.viewer.path().cloned() {
                                 let marked = self.browser.is_marked(&path);
@@ -959,16 +1642,153 @@ impl eframe::App for PhotographApp {
                                     "\u{2606} Mark"
                                 };
                                 if ui.selectable_label(marked, star).clicked() {
-                                    self.browser.toggle_mark(path);
+                                    self.browser.toggle_mark(path.clone());
                                 }
+                                ui.separator();
+                                ui.with_layout(
+                                    egui::Layout::left_to_right(egui::Align::Center),
+                                    |ui| {
+                                        let rating = self.browser.rating(&path);
+                                        if let Some(rating) = rating_stars(ui, rating) {
+                                            self.set_rating(&path, rating);
+                                        }
+                                    },
+                                );
                             }
                         });
                     });
                     ui.separator();
                     self.viewer.show_image(ui);
                 }
+                ViewMode::Compare => self.show_compare(ui, ctx),
+                ViewMode::Survey => {
+                    ui.horizontal(|ui| {
+                        if ui.button("\u{2039} Back to Library").clicked() {
+                            self.view_mode = ViewMode::Library;
+                        }
+                        ui.separator();
+                        ui.label(format!("{} marked", self.browser.marked_count()));
+                        ui.label(
+                            egui::RichText::new(
+                                "Click to focus, 0\u{2013}5 to rate, double-click to open",
+                            )
+                            .weak(),
+                        );
+                    });
+                    ui.separator();
+                    if let Some(path) = self.browser.show_survey(ui, &mut self.survey_focus) {
+                        let companion = self.browser.companion(&path).cloned();
+                        self.viewer.set_image(path.clone(), companion, ctx);
+                        self.browser.selected = Some(path.clone());
+                        self.prev_selected = Some(path);
+                        self.view_mode = ViewMode::Detail;
+                    }
+                }
+                ViewMode::Map => {
+                    ui.horizontal(|ui| {
+                        if ui.button("\u{2039} Back to Library").clicked() {
+                            self.view_mode = ViewMode::Library;
+                        }
+                        ui.separator();
+                        if ui.button("Geotag\u{2026}").clicked() {
+                            self.map.geotag_open = true;
+                        }
+                        ui.label(
+                            egui::RichText::new("Click a photo to select it, drag to pan").weak(),
+                        );
+                    });
+                    ui.separator();
+                    let photos = self.browser.located_images();
+                    let selected = self.browser.selected.clone();
+                    if let Some(path) = self.map.show(ui, &photos, selected.as_deref()) {
+                        // Selected without leaving the map
+                        let companion = self.browser.companion(&path).cloned();
+                        self.viewer.set_image(path.clone(), companion, ctx);
+                        self.browser.selected = Some(path.clone());
+                        self.prev_selected = Some(path);
+                    }
+                }
+                ViewMode::Timeline => {
+                    ui.horizontal(|ui| {
+                        if ui.button("\u{2039} Back to Library").clicked() {
+                            self.view_mode = ViewMode::Library;
+                        }
+                        ui.separator();
+                        if let Some(root) = self.timeline.root() {
+                            ui.label(format!("Capture dates under {}", root.display()));
+                        }
+                        if ui
+                            .button("Rescan")
+                            .on_hover_text("Index the current folder and its subfolders again")
+                            .clicked()
+                        {
+                            self.timeline.scan(self.browser.current_dir.clone());
+                        }
+                    });
+                    ui.separator();
+                    if let Some((label, paths)) = self.timeline.show(ui) {
+                        self.browser.open_date_group(label, paths);
+                        self.view_mode = ViewMode::Library;
+                    }
+                }
             });
 
+        self.browser.show_duplicates_window(ctx);
+        self.browser.show_smart_collection_window(ctx);
+        self.show_metadata_window(ctx);
+        self.show_geotag_window(ctx);
+
+        // Display profile window
+        if self.show_display_window {
+            let mut show_display_window = self.show_display_window;
+            egui::Window::new("Display Profile")
+                .open(&mut show_display_window)
+                .default_pos([40.0, 70.0])
+                .show(ctx, |ui| {
+                    let mut apply = false;
+                    if ui
+                        .radio(self.monitor_profile == MonitorProfile::Srgb, "sRGB")
+                        .clicked()
+                    {
+                        self.monitor_profile = MonitorProfile::Srgb;
+                        apply = true;
+                    }
+                    if ui
+                        .radio(
+                            self.monitor_profile == MonitorProfile::System,
+                            "System profile (colord)",
+                        )
+                        .clicked()
+                    {
+                        self.monitor_profile = MonitorProfile::System;
+                        apply = true;
+                    }
+                    ui.horizontal(|ui| {
+                        let is_file = matches!(self.monitor_profile, MonitorProfile::File(_));
+                        let file_clicked = ui.radio(is_file, "ICC file").clicked();
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.monitor_profile_path)
+                                .hint_text("/path/to/monitor.icc")
+                                .font(egui::TextStyle::Monospace),
+                        );
+                        let load = ui.button("Load").clicked()
+                            || (file_clicked && !self.monitor_profile_path.trim().is_empty());
+                        if load {
+                            self.monitor_profile = MonitorProfile::File(expand_home_prefix(
+                                self.monitor_profile_path.trim(),
+                            ));
+                            apply = true;
+                        }
+                    });
+                    if apply {
+                        self.apply_monitor_profile();
+                    }
+                    ui.add_space(8.0);
+                    ui.label(egui::RichText::new(&self.monitor_status).weak());
+                });
+            self.show_display_window = show_display_window;
+        }
+
         // Render window
         if self.show_render_window {
             let mut show_render_window = self.show_render_window;
@@ -993,6 +1813,26 @@ impl eframe::App for PhotographApp {
                             }
                         });
 
+                    ui.add_space(8.0);
+                    egui::ComboBox::from_label("Color profile")
+                        .selected_text(self.render_profile.label())
+                        .show_ui(ui, |ui| {
+                            for profile in OutputProfile::ALL {
+                                ui.selectable_value(
+                                    &mut self.render_profile,
+                                    profile,
+                                    profile.label(),
+                                );
+                            }
+                        });
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Edited in {}; the profile is embedded in the file.",
+                            icc::working_space().label()
+                        ))
+                        .weak(),
+                    );
+
                     ui.add_space(8.0);
                     ui.horizontal(|ui| {
                         ui.label("Speed profile");
@@ -1064,6 +1904,11 @@ impl eframe::App for PhotographApp {
                             ui.label("px");
                         }
                     });
+                    ui.checkbox(&mut self.render_embed_metadata, "Embed keywords and info")
+                        .on_hover_text(
+                            "Write keywords, title, caption, creator, copyright and capture \
+                             time into the exported file as XMP",
+                        );
 
                     ui.add_space(8.0);
                     let render_count = self.render_target_count();
@@ -1143,6 +1988,14 @@ impl eframe::App for PhotographApp {
                                 .map(|p| p.display().to_string())
                                 .unwrap_or_default()
                         ),
+                        ViewMode::Compare => format!(
+                            "Mode: Compare ({} vs {})",
+                            self.viewer.filename(),
+                            self.compare_viewer.filename()
+                        ),
+                        ViewMode::Survey => "Mode: Survey".to_string(),
+                        ViewMode::Map => "Mode: Map".to_string(),
+                        ViewMode::Timeline => "Mode: Timeline".to_string(),
                     });
                     ui.label(format!("Marked: {}", self.browser.marked_count()));
                 });
@@ -1152,6 +2005,8 @@ impl eframe::App for PhotographApp {
     fn on_exit(&mut self) {
         self.viewer.save_edits();
         self.config.browse_path = Some(self.browser.current_dir.clone());
+        self.config.raw_jpeg_primary = Some(self.browser.pair_primary().config_value().to_string());
+        self.config.map_tile_dir = Some(self.map.tile_dir().to_path_buf());
         self.config.save();
     }
 }
@@ -1,12 +1,24 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     path::PathBuf,
     sync::mpsc,
 };
 
+use crate::collections::{Collection, Collections, SmartRules};
+use crate::keywords::{self, KeywordNode};
+use crate::processing::focus;
+use crate::stacks::{self, FrameInfo};
+use crate::state::EditState;
+use crate::xmp;
+
 const CELL: f32 = 170.0;
 const FILMSTRIP_CELL: f32 = 64.0;
 const MAX_THUMB_JOBS: usize = 4;
+/// Longest edge of the previews shown in the survey layout.
+const SURVEY_PREVIEW_MAX: u32 = 1280;
+const SURVEY_SPACING: f32 = 8.0;
+/// Badge color for frames flagged as likely out of focus.
+const SOFT_COLOR: egui::Color32 = egui::Color32::from_rgb(210, 90, 40);
 
 enum ThumbState {
     Loading,
@@ -14,9 +26,154 @@ enum ThumbState {
     Failed,
 }
 
+/// Order of the images in the grid and filmstrip.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Name,
+    /// Sharpest first; frames not yet scored go last.
+    Sharpness,
+}
+
+/// A keyword change made in the sidebar, saved by the app.
+pub enum KeywordEdit {
+    /// Tag each image with the keyword.
+    Add(Vec<PathBuf>, String),
+    Remove(PathBuf, String),
+}
+
+/// Unmultiplied RGBA pixels with their width and height.
+type ThumbPixels = (Vec<u8>, usize, usize);
+
+/// Which half of a RAW+JPEG pair represents it in the grid and the viewer.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PairPrimary {
+    Raw,
+    Jpeg,
+}
+
+impl PairPrimary {
+    pub fn label(self) -> &'static str {
+        match self {
+            PairPrimary::Raw => "RAW",
+            PairPrimary::Jpeg => "JPEG",
+        }
+    }
+
+    pub fn config_value(self) -> &'static str {
+        match self {
+            PairPrimary::Raw => "raw",
+            PairPrimary::Jpeg => "jpeg",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "raw" => Some(PairPrimary::Raw),
+            "jpeg" | "jpg" => Some(PairPrimary::Jpeg),
+            _ => None,
+        }
+    }
+}
+
 struct ThumbResult {
     path: PathBuf,
-    rgba: Option<(Vec<u8>, usize, usize)>,
+    rgba: Option<ThumbPixels>,
+    /// Sharpness score measured alongside a grid thumbnail.
+    sharpness: Option<f32>,
+    /// Capture time and thumbnail hash, alongside a grid thumbnail.
+    frame: Option<FrameInfo>,
+    /// Position from EXIF GPS, alongside a grid thumbnail.
+    gps: Option<[f64; 2]>,
+    /// A survey preview rather than a grid thumbnail.
+    survey: bool,
+}
+
+enum DuplicateMsg {
+    Progress(usize, usize),
+    Done(Vec<Vec<PathBuf>>),
+}
+
+/// Background search for near-identical images under a folder.
+#[derive(Default)]
+struct DuplicateReport {
+    root: PathBuf,
+    rx: Option<mpsc::Receiver<DuplicateMsg>>,
+    progress: (usize, usize),
+    groups: Option<Vec<Vec<PathBuf>>>,
+    open: bool,
+}
+
+impl DuplicateReport {
+    fn start(&mut self, root: PathBuf, ctx: &egui::Context) {
+        let (tx, rx) = mpsc::channel();
+        let ctx = ctx.clone();
+        let dir = root.clone();
+        std::thread::spawn(move || {
+            let groups = stacks::find_duplicates(&dir, |done, total| {
+                let _ = tx.send(DuplicateMsg::Progress(done, total));
+                ctx.request_repaint();
+            });
+            let _ = tx.send(DuplicateMsg::Done(groups));
+            ctx.request_repaint();
+        });
+        *self = Self {
+            root,
+            rx: Some(rx),
+            open: true,
+            ..Self::default()
+        };
+    }
+
+    fn poll(&mut self) {
+        let Some(rx) = &self.rx else {
+            return;
+        };
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                DuplicateMsg::Progress(done, total) => self.progress = (done, total),
+                DuplicateMsg::Done(groups) => {
+                    self.groups = Some(groups);
+                    self.rx = None;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Smart collection being created or edited.
+#[derive(Default)]
+struct SmartForm {
+    name: String,
+    /// Name of the collection being edited, replaced on save.
+    editing: Option<String>,
+    root: String,
+    min_rating: u8,
+    keyword: String,
+    camera: String,
+}
+
+impl SmartForm {
+    fn edit(name: &str, rules: &SmartRules) -> Self {
+        Self {
+            name: name.to_string(),
+            editing: Some(name.to_string()),
+            root: rules.root.display().to_string(),
+            min_rating: rules.min_rating,
+            keyword: rules.keyword.clone().unwrap_or_default(),
+            camera: rules.camera.clone().unwrap_or_default(),
+        }
+    }
+
+    fn rules(&self) -> SmartRules {
+        let camera = self.camera.trim();
+        SmartRules {
+            root: PathBuf::from(self.root.trim()),
+            min_rating: self.min_rating,
+            keyword: keywords::normalize(&self.keyword),
+            camera: (!camera.is_empty()).then(|| camera.to_string()),
+        }
+    }
 }
 
 /// File browser state for directory navigation and thumbnail selection.
@@ -26,6 +183,50 @@ pub struct Browser {
     pub images: Vec<(PathBuf, String)>,
     pending_nav: Option<PathBuf>,
     thumbnails: HashMap<PathBuf, ThumbState>,
+    /// Larger previews for the survey layout, loaded on demand.
+    survey_previews: HashMap<PathBuf, ThumbState>,
+    /// Star ratings read from the sidecars of the current folder.
+    ratings: HashMap<PathBuf, u8>,
+    /// Keywords of the current folder, from the edit sidecars or, for RAW
+    /// files not yet tagged here, their `.xmp` sidecars.
+    keywords: HashMap<PathBuf, Vec<String>>,
+    /// Positions of the current images: geotagged in the sidecar, else from
+    /// EXIF GPS.
+    positions: HashMap<PathBuf, [f64; 2]>,
+    /// Every keyword seen this session, for autocomplete.
+    known_keywords: BTreeSet<String>,
+    /// Grid shows only images tagged with this keyword or a descendant.
+    keyword_filter: Option<String>,
+    keyword_input: String,
+    keyword_edits: Vec<KeywordEdit>,
+    /// Sharpness scores (see `focus::sharpness_score`) of the current folder.
+    sharpness: HashMap<PathBuf, f32>,
+    sort: SortOrder,
+    /// Capture times and hashes of the current folder, for stacking.
+    frames: HashMap<PathBuf, FrameInfo>,
+    /// Bursts and near-duplicates in the current folder, in name order.
+    stacks: Vec<Vec<PathBuf>>,
+    stack_of: HashMap<PathBuf, usize>,
+    /// Stacks shown in full, keyed by their first frame.
+    expanded_stacks: HashSet<PathBuf>,
+    /// Collapse stacks to their cover image in the grid.
+    stacking: bool,
+    duplicates: DuplicateReport,
+    /// RAW+JPEG pairs of the current folder: the shown file to the hidden one.
+    pairs: HashMap<PathBuf, PathBuf>,
+    pair_primary: PairPrimary,
+    /// Image to select once a pending navigation has scanned its folder.
+    pending_select: Option<PathBuf>,
+    collections: Collections,
+    /// Collection browsed instead of `current_dir`, by name.
+    collection: Option<String>,
+    /// Members of the smart collection being searched for.
+    collection_rx: Option<mpsc::Receiver<Vec<PathBuf>>>,
+    new_collection_name: String,
+    smart_form: Option<SmartForm>,
+    /// Photos of a timeline date browsed instead of `current_dir`: the
+    /// date's label and its photos.
+    date_group: Option<(String, Vec<PathBuf>)>,
     tx: mpsc::SyncSender<ThumbResult>,
     rx: mpsc::Receiver<ThumbResult>,
     pub selected: Option<PathBuf>,
@@ -53,6 +254,31 @@ impl Browser {
             images: Vec::new(),
             pending_nav: None,
             thumbnails: HashMap::new(),
+            survey_previews: HashMap::new(),
+            ratings: HashMap::new(),
+            keywords: HashMap::new(),
+            positions: HashMap::new(),
+            known_keywords: BTreeSet::new(),
+            keyword_filter: None,
+            keyword_input: String::new(),
+            keyword_edits: Vec::new(),
+            sharpness: HashMap::new(),
+            sort: SortOrder::Name,
+            frames: HashMap::new(),
+            stacks: Vec::new(),
+            stack_of: HashMap::new(),
+            expanded_stacks: HashSet::new(),
+            stacking: true,
+            duplicates: DuplicateReport::default(),
+            pending_select: None,
+            collections: Collections::load(),
+            collection: None,
+            collection_rx: None,
+            new_collection_name: String::new(),
+            smart_form: None,
+            date_group: None,
+            pairs: HashMap::new(),
+            pair_primary: PairPrimary::Raw,
             tx,
             rx,
             selected: None,
@@ -71,7 +297,43 @@ impl Browser {
         self.subdirs.clear();
         self.images.clear();
         self.thumbnails.clear();
+        self.survey_previews.clear();
+        self.ratings.clear();
+        self.keywords.clear();
+        self.positions.clear();
+        self.sharpness.clear();
+        self.frames.clear();
+        self.stacks.clear();
+        self.stack_of.clear();
+        self.expanded_stacks.clear();
+        self.pairs.clear();
         self.scan_error = None;
+        self.collection_rx = None;
+
+        if let Some(name) = &self.collection {
+            match self.collections.get(name).cloned() {
+                Some(Collection {
+                    smart: Some(rules), ..
+                }) => {
+                    let (tx, rx) = mpsc::channel();
+                    std::thread::spawn(move || {
+                        let _ = tx.send(rules.evaluate());
+                    });
+                    self.collection_rx = Some(rx);
+                }
+                Some(collection) => {
+                    let paths = collection.paths.into_iter().filter(|p| p.is_file());
+                    self.load_images(named_paths(paths));
+                }
+                None => self.scan_error = Some(format!("Collection \"{name}\" no longer exists")),
+            }
+            return;
+        }
+        if let Some((_, paths)) = &self.date_group {
+            let paths: Vec<PathBuf> = paths.iter().filter(|p| p.is_file()).cloned().collect();
+            self.load_images(named_paths(paths.into_iter()));
+            return;
+        }
 
         let rd = match std::fs::read_dir(&self.current_dir) {
             Ok(rd) => rd,
@@ -108,7 +370,193 @@ impl Browser {
         }
 
         self.subdirs.sort_by(|a, b| a.1.cmp(&b.1));
-        self.images.sort_by(|a, b| a.1.cmp(&b.1));
+        let images = std::mem::take(&mut self.images);
+        self.load_images(images);
+    }
+
+    /// Shows `images`, pairing RAW+JPEG files and reading their sidecars.
+    fn load_images(&mut self, images: Vec<(PathBuf, String)>) {
+        let (images, pairs) = pair_raw_jpeg(images, self.pair_primary);
+        self.images = images;
+        self.pairs = pairs;
+        for (path, _) in &self.images {
+            let companion = self.pairs.get(path).map(PathBuf::as_path);
+            let state = EditState::load(path, companion).unwrap_or_default();
+            if state.rating > 0 {
+                self.ratings.insert(path.clone(), state.rating);
+            }
+            if let Some(location) = state.info.location {
+                self.positions.insert(path.clone(), location);
+            }
+            let mut keywords = state.keywords;
+            if keywords.is_empty() && xmp::uses_sidecar(path) {
+                keywords = xmp::read_sidecar_keywords(path);
+            }
+            if !keywords.is_empty() {
+                self.known_keywords.extend(keywords.iter().cloned());
+                self.keywords.insert(path.clone(), keywords);
+            }
+        }
+        self.sort_images();
+    }
+
+    /// Browses collection `name` in place of the current folder.
+    fn open_collection(&mut self, name: String) {
+        self.collection = Some(name);
+        self.date_group = None;
+        self.selected = None;
+        self.marked.clear();
+        self.keyword_filter = None;
+        self.scan();
+    }
+
+    /// Browses `paths`, the photos of timeline date `label`, in place of the
+    /// current folder.
+    pub fn open_date_group(&mut self, label: String, paths: Vec<PathBuf>) {
+        self.date_group = Some((label, paths));
+        self.collection = None;
+        self.selected = None;
+        self.marked.clear();
+        self.keyword_filter = None;
+        self.scan();
+    }
+
+    /// Whether the grid shows `current_dir` rather than a collection or a
+    /// timeline date.
+    fn browsing_folder(&self) -> bool {
+        self.collection.is_none() && self.date_group.is_none()
+    }
+
+    /// Marked images, or else the selected one: what collection and keyword
+    /// actions apply to.
+    fn action_targets(&self) -> Vec<PathBuf> {
+        if self.marked.is_empty() {
+            self.selected.iter().cloned().collect()
+        } else {
+            self.marked_in_order()
+        }
+    }
+
+    /// Which half of a RAW+JPEG pair is shown and opened.
+    pub fn pair_primary(&self) -> PairPrimary {
+        self.pair_primary
+    }
+
+    /// Switches which half of RAW+JPEG pairs is shown, rescanning the folder.
+    pub fn set_pair_primary(&mut self, primary: PairPrimary) {
+        if primary == self.pair_primary {
+            return;
+        }
+        self.pair_primary = primary;
+        let swap = |p: &PathBuf| self.pairs.get(p).cloned();
+        self.selected = None;
+        self.marked = self
+            .marked
+            .iter()
+            .map(|p| swap(p).unwrap_or(p.clone()))
+            .collect();
+        self.scan();
+    }
+
+    /// Rereads the capture time of `path` after it was corrected, and
+    /// regroups the stacks.
+    pub fn refresh_capture_time(&mut self, path: &std::path::Path) {
+        let captured = frame_capture_time(path, self.companion(path).map(PathBuf::as_path));
+        if let Some(frame) = self.frames.get_mut(path) {
+            frame.captured = captured;
+            self.rebuild_stacks();
+        }
+    }
+
+    /// The other half of the RAW+JPEG pair `path` belongs to: the hidden
+    /// half for a shown image, or the shown half for a hidden one.
+    pub fn companion(&self, path: &std::path::Path) -> Option<&PathBuf> {
+        self.pairs.get(path).or_else(|| {
+            self.pairs
+                .iter()
+                .find(|(_, hidden)| hidden.as_path() == path)
+                .map(|(shown, _)| shown)
+        })
+    }
+
+    /// Regroups the folder's frames into stacks, in shooting order.
+    fn rebuild_stacks(&mut self) {
+        let named: Vec<(&str, FrameInfo)> = self
+            .images
+            .iter()
+            .map(|(p, name)| {
+                (
+                    name.as_str(),
+                    self.frames.get(p).copied().unwrap_or_default(),
+                )
+            })
+            .collect();
+        let order = stacks::shooting_order(&named);
+        let frames: Vec<FrameInfo> = order.iter().map(|&i| named[i].1).collect();
+        self.stacks = stacks::group_stacks(&frames)
+            .into_iter()
+            .map(|members| {
+                members
+                    .into_iter()
+                    .map(|i| self.images[order[i]].0.clone())
+                    .collect()
+            })
+            .collect();
+        self.stack_of = self
+            .stacks
+            .iter()
+            .enumerate()
+            .flat_map(|(i, members)| members.iter().map(move |p| (p.clone(), i)))
+            .collect();
+    }
+
+    /// Frame shown for a collapsed stack: the best rated, then the
+    /// sharpest, then the first.
+    fn stack_cover(&self, stack: usize) -> &PathBuf {
+        let members = &self.stacks[stack];
+        let mut cover = &members[0];
+        for path in &members[1..] {
+            let better = (self.rating(path), self.sharpness(path).unwrap_or(0.0))
+                > (self.rating(cover), self.sharpness(cover).unwrap_or(0.0));
+            if better {
+                cover = path;
+            }
+        }
+        cover
+    }
+
+    /// Whether `path` has a grid cell: stacks collapse to their cover, and
+    /// a keyword filter shows matching frames whether stacked or not.
+    fn shown_in_grid(&self, path: &PathBuf) -> bool {
+        if let Some(filter) = &self.keyword_filter {
+            return self
+                .keywords(path)
+                .iter()
+                .any(|k| keywords::matches(k, filter));
+        }
+        match self.stack_of.get(path) {
+            Some(&stack) if self.stacking => {
+                self.expanded_stacks.contains(&self.stacks[stack][0])
+                    || self.stack_cover(stack) == path
+            }
+            _ => true,
+        }
+    }
+
+    /// Reorders `images` by the current sort order.
+    fn sort_images(&mut self) {
+        match self.sort {
+            SortOrder::Name => self.images.sort_by(|a, b| a.1.cmp(&b.1)),
+            SortOrder::Sharpness => {
+                let sharpness = &self.sharpness;
+                self.images.sort_by(|a, b| {
+                    let score = |p: &PathBuf| sharpness.get(p).copied().unwrap_or(-1.0);
+                    score(&b.0)
+                        .total_cmp(&score(&a.0))
+                        .then_with(|| a.1.cmp(&b.1))
+                });
+            }
+        }
     }
 
     fn scan_locations(&mut self) {
@@ -154,7 +602,8 @@ impl Browser {
                     let path = entry.path();
                     if path.is_dir() && seen.insert(path.clone()) {
                         let raw = entry.file_name().to_string_lossy().into_owned();
-                        self.network_locations.push((path, friendly_gvfs_label(&raw)));
+                        self.network_locations
+                            .push((path, friendly_gvfs_label(&raw)));
                     }
                 }
             }
@@ -239,6 +688,79 @@ impl Browser {
         self.marked.contains(path)
     }
 
+    /// Star rating (0–5) of `path`, as last read or set.
+    pub fn rating(&self, path: &std::path::Path) -> u8 {
+        self.ratings.get(path).copied().unwrap_or(0)
+    }
+
+    /// Records a rating already saved to the sidecar of `path`.
+    pub fn set_rating(&mut self, path: PathBuf, rating: u8) {
+        if rating == 0 {
+            self.ratings.remove(&path);
+        } else {
+            self.ratings.insert(path, rating);
+        }
+    }
+
+    /// Keywords of `path`, as last read or set.
+    pub fn keywords(&self, path: &std::path::Path) -> &[String] {
+        self.keywords.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Records keywords already saved for `path`.
+    pub fn set_keywords(&mut self, path: PathBuf, keywords: Vec<String>) {
+        if keywords.is_empty() {
+            self.keywords.remove(&path);
+        } else {
+            self.known_keywords.extend(keywords.iter().cloned());
+            self.keywords.insert(path, keywords);
+        }
+    }
+
+    /// Images of the current folder or collection with a known position,
+    /// in grid order.
+    pub fn located_images(&self) -> Vec<(PathBuf, [f64; 2])> {
+        self.images
+            .iter()
+            .filter_map(|(path, _)| Some((path.clone(), *self.positions.get(path)?)))
+            .collect()
+    }
+
+    /// Records a geotag already saved for `path`.
+    pub fn set_location(&mut self, path: PathBuf, location: [f64; 2]) {
+        self.positions.insert(path, location);
+    }
+
+    /// Keyword changes made in the sidebar since the last call.
+    pub fn take_keyword_edits(&mut self) -> Vec<KeywordEdit> {
+        std::mem::take(&mut self.keyword_edits)
+    }
+
+    /// Sharpness score of `path`, once measured.
+    pub fn sharpness(&self, path: &std::path::Path) -> Option<f32> {
+        self.sharpness.get(path).copied()
+    }
+
+    /// Whether `path` looks out of focus next to the sharpest frame in the
+    /// folder, making it a candidate for reject.
+    pub fn is_soft(&self, path: &std::path::Path) -> bool {
+        self.sharpness(path)
+            .is_some_and(|score| focus::is_soft(score, self.best_sharpness()))
+    }
+
+    fn best_sharpness(&self) -> f32 {
+        self.sharpness.values().copied().fold(0.0, f32::max)
+    }
+
+    /// Marked images in folder order, as shown by the survey layout.
+    pub fn marked_in_order(&self) -> Vec<PathBuf> {
+        self.images
+            .iter()
+            .filter(|(p, _)| self.marked.contains(p))
+            .map(|(p, _)| p.clone())
+            .collect()
+    }
+
     fn queue_pending_thumbs(&mut self, ctx: &egui::Context) {
         let in_flight = self
             .thumbnails
@@ -262,30 +784,109 @@ impl Browser {
             self.thumbnails.insert(path.clone(), ThumbState::Loading);
             let tx = self.tx.clone();
             let ctx2 = ctx.clone();
-            let cache_dir = self.current_dir.join(".thumbnails");
+            // Collections span folders; each keeps its own cache.
+            let cache_dir = path
+                .parent()
+                .unwrap_or(&self.current_dir)
+                .join(".thumbnails");
+            let companion = self.pairs.get(&path).cloned();
             std::thread::spawn(move || {
-                let result = generate_thumb(&path, &cache_dir);
-                let _ = tx.send(ThumbResult { path, rgba: result });
+                let _ = tx.send(generate_thumb(path, companion.as_deref(), &cache_dir));
                 ctx2.request_repaint();
             });
         }
     }
 
+    fn queue_survey_preview(&mut self, path: &std::path::Path, ctx: &egui::Context) {
+        if self.survey_previews.contains_key(path) {
+            return;
+        }
+        let path = path.to_path_buf();
+        self.survey_previews
+            .insert(path.clone(), ThumbState::Loading);
+        let tx = self.tx.clone();
+        let ctx2 = ctx.clone();
+        std::thread::spawn(move || {
+            let rgba = crate::thumbnail::open_image_for_preview(&path)
+                .ok()
+                .map(|img| {
+                    let preview = crate::processing::icc::to_display(
+                        &img.thumbnail(SURVEY_PREVIEW_MAX, SURVEY_PREVIEW_MAX),
+                    )
+                    .to_rgba8();
+                    let (w, h) = (preview.width() as usize, preview.height() as usize);
+                    (preview.into_raw(), w, h)
+                });
+            let _ = tx.send(ThumbResult {
+                path,
+                rgba,
+                sharpness: None,
+                frame: None,
+                gps: None,
+                survey: true,
+            });
+            ctx2.request_repaint();
+        });
+    }
+
     fn drain_channel(&mut self, ctx: &egui::Context) {
-        while let Ok(ThumbResult { path, rgba }) = self.rx.try_recv() {
+        let mut scored = false;
+        let mut framed = false;
+        while let Ok(ThumbResult {
+            path,
+            rgba,
+            sharpness,
+            frame,
+            gps,
+            survey,
+        }) = self.rx.try_recv()
+        {
+            let in_folder = self.images.iter().any(|(p, _)| *p == path);
+            if let Some(score) = sharpness
+                && in_folder
+            {
+                self.sharpness.insert(path.clone(), score);
+                scored = true;
+            }
+            if let Some(frame) = frame
+                && in_folder
+            {
+                self.frames.insert(path.clone(), frame);
+                framed = true;
+            }
+            // Geotags saved in the sidecar take precedence
+            if let Some(position) = gps
+                && in_folder
+            {
+                self.positions.entry(path.clone()).or_insert(position);
+            }
             let state = match rgba {
                 Some((data, w, h)) => {
                     let img = egui::ColorImage::from_rgba_unmultiplied([w, h], &data);
-                    let tex = ctx.load_texture(
-                        path.to_string_lossy().as_ref(),
-                        img,
-                        egui::TextureOptions::LINEAR,
-                    );
+                    let name = if survey {
+                        format!("survey:{}", path.to_string_lossy())
+                    } else {
+                        path.to_string_lossy().into_owned()
+                    };
+                    let tex = ctx.load_texture(name, img, egui::TextureOptions::LINEAR);
                     ThumbState::Ready(tex)
                 }
                 None => ThumbState::Failed,
             };
-            self.thumbnails.insert(path, state);
+            if survey {
+                // Dropped if the folder changed meanwhile
+                if self.images.iter().any(|(p, _)| *p == path) {
+                    self.survey_previews.insert(path, state);
+                }
+            } else {
+                self.thumbnails.insert(path, state);
+            }
+        }
+        if scored && self.sort == SortOrder::Sharpness {
+            self.sort_images();
+        }
+        if framed {
+            self.rebuild_stacks();
         }
     }
 
@@ -297,12 +898,30 @@ impl Browser {
             self.path_edit = self.current_dir.display().to_string();
             self.selected = None;
             self.marked.clear();
+            self.keyword_filter = None;
+            self.collection = None;
+            self.date_group = None;
             self.scan_locations();
             self.scan_network_locations();
             self.scan();
+            self.selected = self.pending_select.take();
+        }
+
+        if let Some(rx) = &self.collection_rx {
+            match rx.try_recv() {
+                Ok(paths) => {
+                    self.collection_rx = None;
+                    self.load_images(named_paths(paths.into_iter()));
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    ctx.request_repaint_after(std::time::Duration::from_millis(100));
+                }
+                Err(mpsc::TryRecvError::Disconnected) => self.collection_rx = None,
+            }
         }
 
         self.drain_channel(ctx);
+        self.duplicates.poll();
         self.queue_pending_thumbs(ctx);
     }
 
@@ -314,7 +933,7 @@ impl Browser {
         if !self.locations.is_empty() {
             ui.label(egui::RichText::new("LOCATIONS").weak().small());
             for (path, label) in &self.locations {
-                let is_current = *path == self.current_dir;
+                let is_current = *path == self.current_dir && self.browsing_folder();
                 if ui
                     .selectable_label(is_current, format!("\u{1F5C2} {}", label))
                     .clicked()
@@ -328,7 +947,7 @@ impl Browser {
         if !self.network_locations.is_empty() {
             ui.label(egui::RichText::new("NETWORK").weak().small());
             for (path, label) in &self.network_locations {
-                let is_current = *path == self.current_dir;
+                let is_current = *path == self.current_dir && self.browsing_folder();
                 if ui
                     .selectable_label(is_current, format!("\u{1F310} {}", label))
                     .clicked()
@@ -339,6 +958,9 @@ impl Browser {
             ui.add_space(8.0);
         }
 
+        self.show_collections(ui);
+        self.show_keywords(ui);
+
         ui.separator();
         ui.add_space(4.0);
 
@@ -392,6 +1014,249 @@ impl Browser {
         }
     }
 
+    /// Sidebar collection section: each collection (click to browse it,
+    /// right-click to change it), then entry for a new collection holding
+    /// the marked images and a button for a new smart collection.
+    fn show_collections(&mut self, ui: &mut egui::Ui) {
+        let targets = self.action_targets();
+        let mut open: Option<String> = None;
+        let mut delete: Option<String> = None;
+        let mut add: Option<String> = None;
+        let mut remove: Option<String> = None;
+
+        ui.label(egui::RichText::new("COLLECTIONS").weak().small());
+        for collection in &self.collections.collections {
+            let is_current = self.collection.as_ref() == Some(&collection.name);
+            let icon = if collection.smart.is_some() {
+                "\u{2699}"
+            } else {
+                "\u{1F4DA}"
+            };
+            let resp = ui.selectable_label(is_current, format!("{icon} {}", collection.name));
+            if resp.clicked() {
+                open = Some(collection.name.clone());
+            }
+            resp.context_menu(|ui| {
+                if let Some(rules) = &collection.smart {
+                    if ui.button("Edit rules\u{2026}").clicked() {
+                        self.smart_form = Some(SmartForm::edit(&collection.name, rules));
+                        ui.close();
+                    }
+                } else {
+                    if ui
+                        .add_enabled(
+                            !targets.is_empty(),
+                            egui::Button::new(format!("Add {} photo(s)", targets.len())),
+                        )
+                        .clicked()
+                    {
+                        add = Some(collection.name.clone());
+                        ui.close();
+                    }
+                    if is_current
+                        && ui
+                            .add_enabled(
+                                !targets.is_empty(),
+                                egui::Button::new(format!("Remove {} photo(s)", targets.len())),
+                            )
+                            .clicked()
+                    {
+                        remove = Some(collection.name.clone());
+                        ui.close();
+                    }
+                }
+                if ui.button("Delete collection").clicked() {
+                    delete = Some(collection.name.clone());
+                    ui.close();
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut self.new_collection_name)
+                    .hint_text("New collection")
+                    .desired_width(ui.available_width() - 60.0),
+            );
+            if resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                let name = self.new_collection_name.trim().to_string();
+                if !name.is_empty() {
+                    self.collections.insert(Collection {
+                        name: name.clone(),
+                        paths: targets.clone(),
+                        smart: None,
+                    });
+                    self.new_collection_name.clear();
+                    open = Some(name);
+                }
+            }
+            resp.on_hover_text("Press Enter to collect the marked photos");
+            if ui
+                .button("Smart\u{2026}")
+                .on_hover_text("Collection of photos matching rating, keyword and camera rules")
+                .clicked()
+            {
+                self.smart_form = Some(SmartForm {
+                    root: self.current_dir.display().to_string(),
+                    ..SmartForm::default()
+                });
+            }
+        });
+        ui.add_space(8.0);
+
+        if let Some(name) = add {
+            self.collections.add_paths(&name, &targets);
+        }
+        if let Some(name) = remove {
+            self.collections.remove_paths(&name, &targets);
+            self.marked.clear();
+            self.selected = None;
+            self.scan();
+        }
+        if let Some(name) = delete {
+            self.collections.remove(&name);
+            if self.collection.as_ref() == Some(&name) {
+                self.navigate(self.current_dir.clone());
+            }
+        }
+        if let Some(name) = open {
+            self.open_collection(name);
+        }
+    }
+
+    /// Renders the smart collection rules form while it is open.
+    pub fn show_smart_collection_window(&mut self, ctx: &egui::Context) {
+        let Some(form) = &mut self.smart_form else {
+            return;
+        };
+        let mut open = true;
+        let mut save = false;
+        let title = if form.editing.is_some() {
+            "Edit Smart Collection"
+        } else {
+            "New Smart Collection"
+        };
+        egui::Window::new(title)
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("smart_collection_rules")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut form.name);
+                        ui.end_row();
+                        ui.label("Folder");
+                        ui.text_edit_singleline(&mut form.root)
+                            .on_hover_text("Searched with its subfolders");
+                        ui.end_row();
+                        ui.label("Rating at least");
+                        ui.add(egui::Slider::new(&mut form.min_rating, 0..=5));
+                        ui.end_row();
+                        ui.label("Keyword");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut form.keyword)
+                                .hint_text("Any (or Places|Paris)"),
+                        );
+                        ui.end_row();
+                        ui.label("Camera");
+                        ui.add(egui::TextEdit::singleline(&mut form.camera).hint_text("Any"));
+                        ui.end_row();
+                    });
+                let valid =
+                    !form.name.trim().is_empty() && std::path::Path::new(form.root.trim()).is_dir();
+                if ui.add_enabled(valid, egui::Button::new("Save")).clicked() {
+                    save = true;
+                }
+            });
+
+        if save {
+            let name = form.name.trim().to_string();
+            let rules = form.rules();
+            if let Some(old) = form.editing.take()
+                && old != name
+            {
+                self.collections.remove(&old);
+            }
+            self.collections.insert(Collection {
+                name: name.clone(),
+                paths: Vec::new(),
+                smart: Some(rules),
+            });
+            self.smart_form = None;
+            self.open_collection(name);
+        } else if !open {
+            self.smart_form = None;
+        }
+    }
+
+    /// Sidebar keyword section: the folder's keyword tree (click to filter
+    /// the grid), keyword entry for the marked images or else the selected
+    /// one, and the selected image's keywords.
+    fn show_keywords(&mut self, ui: &mut egui::Ui) {
+        let targets = self.action_targets();
+        if self.keywords.is_empty() && targets.is_empty() {
+            return;
+        }
+
+        ui.label(egui::RichText::new("KEYWORDS").weak().small());
+        let tree = keywords::build_tree(self.keywords.values().map(Vec::as_slice));
+        let mut toggled: Option<String> = None;
+        keyword_tree_ui(ui, &tree, self.keyword_filter.as_deref(), &mut toggled);
+        if let Some(keyword) = toggled {
+            self.keyword_filter = if self.keyword_filter.as_ref() == Some(&keyword) {
+                None
+            } else {
+                Some(keyword)
+            };
+        }
+
+        if !targets.is_empty() {
+            let hint = if self.marked.is_empty() {
+                "Tag photo (Places|Paris)".to_string()
+            } else {
+                format!("Tag {} marked", targets.len())
+            };
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut self.keyword_input)
+                    .hint_text(hint)
+                    .desired_width(ui.available_width()),
+            );
+            let mut add: Option<String> = None;
+            if resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                add = keywords::normalize(&self.keyword_input);
+            }
+            for suggestion in keywords::suggest(&self.keyword_input, &self.known_keywords) {
+                if ui.small_button(&suggestion).clicked() {
+                    add = Some(suggestion);
+                }
+            }
+            if let Some(keyword) = add {
+                self.keyword_input.clear();
+                self.keyword_edits
+                    .push(KeywordEdit::Add(targets.clone(), keyword));
+                ui.ctx().request_repaint();
+            }
+        }
+
+        if let Some(path) = self.selected.clone() {
+            ui.horizontal_wrapped(|ui| {
+                for keyword in self.keywords(&path).to_vec() {
+                    if ui
+                        .small_button(format!("{} \u{2715}", keywords::leaf(&keyword)))
+                        .on_hover_text(format!("Remove {keyword}"))
+                        .clicked()
+                    {
+                        self.keyword_edits
+                            .push(KeywordEdit::Remove(path.clone(), keyword));
+                        ui.ctx().request_repaint();
+                    }
+                }
+            });
+        }
+        ui.add_space(8.0);
+    }
+
     /// Renders the thumbnail grid (Library mode central panel content).
     /// Plain click selects+opens a photo; Ctrl/Cmd-click toggles it as
     /// marked for batch export without changing the open photo.
@@ -403,14 +1268,90 @@ impl Browser {
             ui.centered_and_justified(|ui| {
                 ui.label(err.as_str());
             });
+        } else if self.collection_rx.is_some() {
+            ui.centered_and_justified(|ui| {
+                ui.spinner();
+            });
         } else if self.images.is_empty() && self.subdirs.is_empty() {
             ui.centered_and_justified(|ui| {
-                ui.label("No images in this directory");
+                ui.label(if self.collection.is_some() {
+                    "No images in this collection"
+                } else if self.date_group.is_some() {
+                    "No images on this date"
+                } else {
+                    "No images in this directory"
+                });
             });
         } else {
+            ui.horizontal(|ui| {
+                let source = self
+                    .collection
+                    .as_ref()
+                    .or(self.date_group.as_ref().map(|(label, _)| label))
+                    .cloned();
+                if let Some(name) = source {
+                    ui.strong(format!("{name} ({})", self.images.len()));
+                    if ui
+                        .button("\u{2715}")
+                        .on_hover_text("Back to the folder")
+                        .clicked()
+                    {
+                        self.navigate(self.current_dir.clone());
+                    }
+                    ui.separator();
+                }
+                ui.label("Sort:");
+                let mut sort = self.sort;
+                ui.selectable_value(&mut sort, SortOrder::Name, "Name");
+                ui.selectable_value(&mut sort, SortOrder::Sharpness, "Sharpness")
+                    .on_hover_text("Laplacian variance over the focus area or the center");
+                if sort != self.sort {
+                    self.sort = sort;
+                    self.sort_images();
+                }
+                if !self.pairs.is_empty() {
+                    ui.separator();
+                    ui.label("RAW+JPEG opens:");
+                    let mut primary = self.pair_primary;
+                    for option in [PairPrimary::Raw, PairPrimary::Jpeg] {
+                        ui.selectable_value(&mut primary, option, option.label());
+                    }
+                    self.set_pair_primary(primary);
+                }
+                ui.separator();
+                ui.checkbox(&mut self.stacking, "Stacks")
+                    .on_hover_text("Collapse bursts and near-duplicates to one cover image");
+                if ui
+                    .add_enabled(
+                        self.duplicates.rx.is_none(),
+                        egui::Button::new("Find duplicates\u{2026}"),
+                    )
+                    .on_hover_text("Search this folder and its subfolders")
+                    .clicked()
+                {
+                    self.duplicates.start(self.current_dir.clone(), ui.ctx());
+                }
+                let soft = self.images.iter().filter(|(p, _)| self.is_soft(p)).count();
+                if soft > 0 {
+                    ui.separator();
+                    ui.label(
+                        egui::RichText::new(format!("{soft} likely out of focus"))
+                            .color(SOFT_COLOR),
+                    );
+                }
+            });
+            ui.add_space(4.0);
+
             let avail_w = ui.available_width();
             let cols = ((avail_w / (CELL + 8.0)) as usize).max(1);
             let mut toggled_mark: Option<PathBuf> = None;
+            let mut toggled_stack: Option<PathBuf> = None;
+            let best_sharpness = self.best_sharpness();
+            let shown: Vec<&(PathBuf, String)> = self
+                .images
+                .iter()
+                .filter(|(p, _)| self.shown_in_grid(p))
+                .collect();
 
             egui::ScrollArea::vertical()
                 .auto_shrink([false, false])
@@ -419,7 +1360,7 @@ impl Browser {
                         .num_columns(cols)
                         .spacing([8.0, 8.0])
                         .show(ui, |ui| {
-                            for (i, (path, name)) in self.images.iter().enumerate() {
+                            for (i, (path, name)) in shown.iter().enumerate() {
                                 let is_sel = current_sel.as_ref() == Some(path);
                                 let is_marked = self.marked.contains(path);
                                 let thumb = match self.thumbnails.get(path) {
@@ -429,16 +1370,40 @@ impl Browser {
                                     _ => None,
                                 };
 
-                                let clicked = draw_thumb_cell(
+                                let stack = self.stack_of.get(path).filter(|_| self.stacking).map(
+                                    |&stack| {
+                                        let first = &self.stacks[stack][0];
+                                        (
+                                            self.stacks[stack].len(),
+                                            self.expanded_stacks.contains(first),
+                                        )
+                                    },
+                                );
+                                let resp = draw_thumb_cell(
                                     ui,
                                     name,
                                     thumb,
                                     is_sel,
-                                    is_marked,
+                                    ThumbBadges {
+                                        marked: is_marked,
+                                        rating: self.rating(path),
+                                        soft: self.sharpness(path).is_some_and(|score| {
+                                            focus::is_soft(score, best_sharpness)
+                                        }),
+                                        stack,
+                                        paired: self.pairs.contains_key(path),
+                                    },
                                     CELL,
                                     true,
                                 );
-                                if clicked {
+                                let on_stack_badge = stack.is_some()
+                                    && resp.interact_pointer_pos().is_some_and(|pos| {
+                                        stack_badge_rect(resp.rect, CELL).contains(pos)
+                                    });
+                                if resp.clicked() && on_stack_badge {
+                                    let stack = self.stack_of[path];
+                                    toggled_stack = Some(self.stacks[stack][0].clone());
+                                } else if resp.clicked() {
                                     let ctrl_held =
                                         ui.input(|i| i.modifiers.ctrl || i.modifiers.mac_cmd);
                                     if ctrl_held {
@@ -458,6 +1423,11 @@ impl Browser {
             if let Some(path) = toggled_mark {
                 self.toggle_mark(path);
             }
+            if let Some(first) = toggled_stack
+                && !self.expanded_stacks.remove(&first)
+            {
+                self.expanded_stacks.insert(first);
+            }
         }
 
         if let Some(sel) = new_sel {
@@ -465,10 +1435,201 @@ impl Browser {
         }
     }
 
+    /// Renders the "find duplicates" report while it is open. Clicking a
+    /// file opens its folder with the file selected.
+    pub fn show_duplicates_window(&mut self, ctx: &egui::Context) {
+        if !self.duplicates.open {
+            return;
+        }
+        let mut open = true;
+        let mut reveal: Option<PathBuf> = None;
+        let report = &self.duplicates;
+        egui::Window::new("Duplicates")
+            .open(&mut open)
+            .default_size([520.0, 420.0])
+            .show(ctx, |ui| {
+                ui.weak(report.root.display().to_string());
+                let Some(groups) = &report.groups else {
+                    let (done, total) = report.progress;
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("Comparing {done} of {total} images\u{2026}"));
+                    });
+                    return;
+                };
+                if groups.is_empty() {
+                    ui.label("No duplicates found");
+                    return;
+                }
+                let extra: usize = groups.iter().map(|g| g.len() - 1).sum();
+                ui.label(format!(
+                    "{} groups, {extra} possible duplicates",
+                    groups.len()
+                ));
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for group in groups {
+                            for path in group {
+                                let shown = path.strip_prefix(&report.root).unwrap_or(path);
+                                if ui.link(shown.display().to_string()).clicked() {
+                                    reveal = Some(path.clone());
+                                }
+                            }
+                            ui.separator();
+                        }
+                    });
+            });
+        self.duplicates.open = open;
+
+        if let Some(path) = reveal
+            && let Some(dir) = path.parent()
+        {
+            if dir == self.current_dir && self.browsing_folder() {
+                self.selected = Some(path);
+            } else {
+                self.navigate(dir.to_path_buf());
+                self.pending_select = Some(path);
+            }
+        }
+    }
+
+    /// Renders the marked images in one grid scaled to fill the panel
+    /// (Survey mode). A click focuses an image for rating, a double click
+    /// returns it to open in Detail, and the corner button unmarks it.
+    pub fn show_survey(
+        &mut self,
+        ui: &mut egui::Ui,
+        focus: &mut Option<PathBuf>,
+    ) -> Option<PathBuf> {
+        let paths = self.marked_in_order();
+        if paths.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.label("Mark images (Ctrl-click in the grid) to survey them");
+            });
+            return None;
+        }
+        if !focus.as_ref().is_some_and(|f| paths.contains(f)) {
+            *focus = paths.first().cloned();
+        }
+        for path in &paths {
+            self.queue_survey_preview(path, ui.ctx());
+        }
+
+        let (cols, cell) = survey_grid(paths.len(), ui.available_size(), SURVEY_SPACING);
+        let mut opened = None;
+        let mut unmark = None;
+        egui::Grid::new("survey_grid")
+            .num_columns(cols)
+            .spacing([SURVEY_SPACING, SURVEY_SPACING])
+            .show(ui, |ui| {
+                for (i, path) in paths.iter().enumerate() {
+                    let texture = [&self.survey_previews, &self.thumbnails]
+                        .into_iter()
+                        .find_map(|cache| match cache.get(path) {
+                            Some(ThumbState::Ready(tex)) => Some((tex.id(), tex.size_vec2())),
+                            _ => None,
+                        });
+                    let (resp, painter) = ui.allocate_painter(cell, egui::Sense::click());
+                    let rect = resp.rect;
+                    if focus.as_ref() == Some(path) {
+                        painter.rect_filled(rect, 4.0, ui.visuals().selection.bg_fill);
+                    }
+
+                    let img_area = rect.shrink(4.0).with_max_y(rect.max.y - 20.0);
+                    match texture {
+                        Some((tex_id, tex_size)) => {
+                            let scale =
+                                (img_area.width() / tex_size.x).min(img_area.height() / tex_size.y);
+                            let draw_rect =
+                                egui::Rect::from_center_size(img_area.center(), tex_size * scale);
+                            painter.image(
+                                tex_id,
+                                draw_rect,
+                                egui::Rect::from_min_max(
+                                    egui::pos2(0.0, 0.0),
+                                    egui::pos2(1.0, 1.0),
+                                ),
+                                egui::Color32::WHITE,
+                            );
+                        }
+                        None => {
+                            painter.rect_filled(img_area, 4.0, egui::Color32::from_gray(40));
+                        }
+                    }
+                    let rating = self.rating(path);
+                    if rating > 0 {
+                        draw_rating_badge(
+                            &painter,
+                            img_area.left_bottom() + egui::vec2(4.0, -4.0),
+                            rating,
+                            CELL,
+                        );
+                    }
+                    let name = path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    painter.text(
+                        egui::pos2(rect.center().x, rect.max.y - 10.0),
+                        egui::Align2::CENTER_CENTER,
+                        name,
+                        egui::FontId::proportional(11.0),
+                        ui.visuals().text_color(),
+                    );
+
+                    let close = egui::Rect::from_center_size(
+                        img_area.right_top() + egui::vec2(-12.0, 12.0),
+                        egui::vec2(18.0, 18.0),
+                    );
+                    let hover_close = resp.hover_pos().is_some_and(|p| close.contains(p));
+                    if resp.hovered() {
+                        painter.circle_filled(
+                            close.center(),
+                            9.0,
+                            egui::Color32::from_black_alpha(if hover_close { 220 } else { 150 }),
+                        );
+                        painter.text(
+                            close.center(),
+                            egui::Align2::CENTER_CENTER,
+                            "\u{2715}",
+                            egui::FontId::proportional(11.0),
+                            egui::Color32::WHITE,
+                        );
+                    }
+
+                    if resp.clicked() && hover_close {
+                        unmark = Some(path.clone());
+                    } else if resp.double_clicked() {
+                        opened = Some(path.clone());
+                    } else if resp.clicked() {
+                        *focus = Some(path.clone());
+                    }
+
+                    if (i + 1) % cols == 0 {
+                        ui.end_row();
+                    }
+                }
+            });
+
+        if let Some(path) = unmark {
+            self.marked.remove(&path);
+            if focus.as_ref() == Some(&path) {
+                *focus = None;
+            }
+        }
+        opened
+    }
+
     /// Renders a horizontal filmstrip of the current directory's images at a
     /// smaller size, reusing the same thumbnail cache as the grid. Returns
     /// the clicked path, if any, so the caller can switch the active photo.
-    pub fn show_filmstrip(&mut self, ui: &mut egui::Ui, active: Option<&std::path::Path>) -> Option<PathBuf> {
+    pub fn show_filmstrip(
+        &mut self,
+        ui: &mut egui::Ui,
+        active: Option<&std::path::Path>,
+    ) -> Option<PathBuf> {
         let mut clicked_path = None;
         egui::ScrollArea::horizontal()
             .auto_shrink([false, false])
@@ -486,10 +1647,18 @@ impl Browser {
                             name,
                             thumb,
                             is_active,
-                            is_marked,
+                            ThumbBadges {
+                                marked: is_marked,
+                                rating: self.rating(path),
+                                soft: self.is_soft(path),
+                                stack: None,
+                                paired: self.pairs.contains_key(path),
+                            },
                             FILMSTRIP_CELL,
                             false,
-                        ) {
+                        )
+                        .clicked()
+                        {
                             clicked_path = Some(path.clone());
                         }
                     }
@@ -499,15 +1668,46 @@ impl Browser {
     }
 }
 
+/// Column count and cell size that fit `n` cells into `avail` with the
+/// largest images, scored for the common 3:2 frame.
+fn survey_grid(n: usize, avail: egui::Vec2, spacing: f32) -> (usize, egui::Vec2) {
+    let mut best = (1, egui::Vec2::ZERO);
+    let mut best_side = f32::NEG_INFINITY;
+    for cols in 1..=n.max(1) {
+        let rows = n.div_ceil(cols).max(1);
+        let w = (avail.x - spacing * (cols - 1) as f32) / cols as f32;
+        let h = (avail.y - spacing * (rows - 1) as f32) / rows as f32;
+        let side = w.min(h * 1.5);
+        if side > best_side {
+            best_side = side;
+            best = (cols, egui::vec2(w, h).max(egui::Vec2::ZERO));
+        }
+    }
+    best
+}
+
+/// Status badges drawn over a thumbnail.
+#[derive(Clone, Copy)]
+struct ThumbBadges {
+    marked: bool,
+    rating: u8,
+    /// Flagged as likely out of focus.
+    soft: bool,
+    /// Size of the stack the image belongs to, and whether it is expanded.
+    stack: Option<(usize, bool)>,
+    /// Stands for a RAW+JPEG pair.
+    paired: bool,
+}
+
 fn draw_thumb_cell(
     ui: &mut egui::Ui,
     name: &str,
     thumb: Option<(egui::TextureId, egui::Vec2)>,
     selected: bool,
-    marked: bool,
+    badges: ThumbBadges,
     cell: f32,
     show_label: bool,
-) -> bool {
+) -> egui::Response {
     let cell_height = if show_label { cell + 22.0 } else { cell };
     let (resp, painter) = ui.allocate_painter(egui::vec2(cell, cell_height), egui::Sense::click());
     let rect = resp.rect;
@@ -547,7 +1747,7 @@ fn draw_thumb_cell(
     }
 
     // Marked-for-export badge
-    if marked {
+    if badges.marked {
         let badge_center = img_rect.right_top() + egui::vec2(-10.0, 10.0);
         painter.circle_filled(badge_center, 8.0, ui.visuals().selection.bg_fill);
         painter.text(
@@ -559,6 +1759,62 @@ fn draw_thumb_cell(
         );
     }
 
+    // Tags along the top-left corner
+    let tags = [
+        (
+            badges.paired,
+            "RAW+JPG",
+            egui::Color32::from_black_alpha(180),
+        ),
+        (badges.soft, "SOFT", SOFT_COLOR),
+    ];
+    let tag_size = if cell < CELL { 8.0 } else { 10.0 };
+    let mut tag_x = 4.0;
+    for (_, text, fill) in tags.into_iter().filter(|(shown, _, _)| *shown) {
+        let galley = painter.layout_no_wrap(
+            text.to_string(),
+            egui::FontId::proportional(tag_size),
+            egui::Color32::WHITE,
+        );
+        let badge = egui::Rect::from_min_size(
+            img_rect.left_top() + egui::vec2(tag_x, 4.0),
+            galley.size() + egui::vec2(6.0, 2.0),
+        );
+        painter.rect_filled(badge, 3.0, fill);
+        painter.galley(
+            badge.min + egui::vec2(3.0, 1.0),
+            galley,
+            egui::Color32::WHITE,
+        );
+        tag_x += badge.width() + 3.0;
+    }
+
+    if let Some((count, expanded)) = badges.stack {
+        let badge = stack_badge_rect(rect, cell);
+        let fill = if expanded {
+            ui.visuals().selection.bg_fill
+        } else {
+            egui::Color32::from_black_alpha(180)
+        };
+        painter.rect_filled(badge, 3.0, fill);
+        painter.text(
+            badge.center(),
+            egui::Align2::CENTER_CENTER,
+            format!("\u{29C9} {count}"),
+            egui::FontId::proportional(10.0),
+            egui::Color32::WHITE,
+        );
+    }
+
+    if badges.rating > 0 {
+        draw_rating_badge(
+            &painter,
+            img_rect.left_bottom() + egui::vec2(4.0, -4.0),
+            badges.rating,
+            cell,
+        );
+    }
+
     // Filename label
     if show_label {
         let label_pos = egui::pos2(rect.center().x, img_rect.max.y + 11.0);
@@ -572,26 +1828,170 @@ fn draw_thumb_cell(
         );
     }
 
-    resp.clicked()
+    resp
+}
+
+/// Clickable stack-size badge in the bottom-right corner of a cell's image.
+fn stack_badge_rect(cell_rect: egui::Rect, cell: f32) -> egui::Rect {
+    let corner = cell_rect.min + egui::vec2(cell - 4.0, cell - 4.0);
+    egui::Rect::from_min_max(corner - egui::vec2(34.0, 16.0), corner)
+}
+
+/// Grid entries for `paths`, named by file name.
+fn named_paths(paths: impl Iterator<Item = PathBuf>) -> Vec<(PathBuf, String)> {
+    paths
+        .map(|path| {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            (path, name)
+        })
+        .collect()
+}
+
+/// Keyword tree levels with their image counts; a click sets `toggled` to
+/// the keyword clicked.
+fn keyword_tree_ui(
+    ui: &mut egui::Ui,
+    nodes: &[KeywordNode],
+    filter: Option<&str>,
+    toggled: &mut Option<String>,
+) {
+    for node in nodes {
+        let label = format!("{} ({})", node.name, node.count);
+        let active = filter == Some(node.path.as_str());
+        if node.children.is_empty() {
+            if ui.selectable_label(active, label).clicked() {
+                *toggled = Some(node.path.clone());
+            }
+            continue;
+        }
+        let id = ui.make_persistent_id(("keyword", &node.path));
+        egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false)
+            .show_header(ui, |ui| {
+                if ui.selectable_label(active, label).clicked() {
+                    *toggled = Some(node.path.clone());
+                }
+            })
+            .body(|ui| keyword_tree_ui(ui, &node.children, filter, toggled));
+    }
+}
+
+/// Draws `rating` stars on a dark pill anchored at its bottom-left corner.
+fn draw_rating_badge(painter: &egui::Painter, anchor: egui::Pos2, rating: u8, cell: f32) {
+    let size = if cell < CELL { 8.0 } else { 11.0 };
+    let text = "\u{2605}".repeat(rating as usize);
+    let galley = painter.layout_no_wrap(
+        text,
+        egui::FontId::proportional(size),
+        egui::Color32::from_rgb(250, 200, 60),
+    );
+    let rect = egui::Rect::from_min_size(
+        anchor - egui::vec2(0.0, galley.size().y + 2.0),
+        galley.size() + egui::vec2(6.0, 2.0),
+    );
+    painter.rect_filled(rect, 3.0, egui::Color32::from_black_alpha(160));
+    painter.galley(
+        rect.min + egui::vec2(3.0, 1.0),
+        galley,
+        egui::Color32::WHITE,
+    );
 }
 
-fn generate_thumb(path: &PathBuf, cache_dir: &PathBuf) -> Option<(Vec<u8>, usize, usize)> {
-    let thumb_path = crate::thumbnail::cache_path(path, cache_dir);
+/// Collapses RAW+JPEG pairs (same stem, one RAW and one camera JPEG) to
+/// their `primary` half. Returns the remaining images and a map from each
+/// kept half to its hidden companion.
+fn pair_raw_jpeg(
+    images: Vec<(PathBuf, String)>,
+    primary: PairPrimary,
+) -> (Vec<(PathBuf, String)>, HashMap<PathBuf, PathBuf>) {
+    let mut hidden = HashSet::new();
+    let mut pairs = HashMap::new();
+    for (raw, jpeg) in crate::thumbnail::raw_jpeg_pairs(images.iter().map(|(p, _)| p.as_path())) {
+        let (keep, hide) = match primary {
+            PairPrimary::Raw => (raw, jpeg),
+            PairPrimary::Jpeg => (jpeg, raw),
+        };
+        hidden.insert(hide.to_path_buf());
+        pairs.insert(keep.to_path_buf(), hide.to_path_buf());
+    }
+    let images = images
+        .into_iter()
+        .filter(|(path, _)| !hidden.contains(path))
+        .collect();
+    (images, pairs)
+}
 
-    let img = if thumb_path.exists() {
-        image::open(&thumb_path).ok()?
-    } else {
-        let full = crate::thumbnail::open_image_for_preview(path).ok()?;
-        let t = full.thumbnail(crate::thumbnail::THUMB_SIZE, crate::thumbnail::THUMB_SIZE);
+/// Loads or builds the grid thumbnail and sharpness score of `path`,
+/// decoding the preview once when either is missing from the cache, and
+/// reads what stacking needs. `companion` is the hidden half of a RAW+JPEG
+/// pair, which shares the edits.
+fn generate_thumb(
+    path: PathBuf,
+    companion: Option<&std::path::Path>,
+    cache_dir: &std::path::Path,
+) -> ThumbResult {
+    let thumb_path = crate::thumbnail::cache_path(&path, cache_dir);
+    let score_path = sharpness_cache_path(&path, cache_dir);
+
+    let mut thumb = thumb_path
+        .exists()
+        .then(|| image::open(&thumb_path).ok())
+        .flatten();
+    let mut score = std::fs::read_to_string(&score_path)
+        .ok()
+        .and_then(|s| s.trim().parse::<f32>().ok());
+    if (thumb.is_none() || score.is_none())
+        && let Ok(full) = crate::thumbnail::open_image_for_preview(&path)
+    {
         let _ = std::fs::create_dir_all(cache_dir);
-        let _ = t.save(&thumb_path);
-        t
+        if thumb.is_none() {
+            let t = crate::thumbnail::display_thumbnail(&full);
+            let _ = t.save(&thumb_path);
+            thumb = Some(t);
+        }
+        if score.is_none() {
+            let s = focus::sharpness_score(&full, crate::metadata::focus_area(&path));
+            let _ = std::fs::write(&score_path, s.to_string());
+            score = Some(s);
+        }
+    }
+
+    let frame = FrameInfo {
+        captured: frame_capture_time(&path, companion),
+        hash: thumb.as_ref().map(stacks::dhash),
     };
+    let gps = crate::metadata::gps_position(&path);
+    let rgba = thumb.map(|img| {
+        let rgba = img.to_rgba8();
+        let w = rgba.width() as usize;
+        let h = rgba.height() as usize;
+        (rgba.into_raw(), w, h)
+    });
+    ThumbResult {
+        path,
+        rgba,
+        sharpness: score,
+        frame: Some(frame),
+        gps,
+        survey: false,
+    }
+}
 
-    let rgba = img.to_rgba8();
-    let w = rgba.width() as usize;
-    let h = rgba.height() as usize;
-    Some((rgba.into_raw(), w, h))
+/// Capture time stacks are grouped by: the corrected one saved with the
+/// edits, else EXIF.
+fn frame_capture_time(path: &std::path::Path, companion: Option<&std::path::Path>) -> Option<f64> {
+    EditState::load(path, companion)
+        .and_then(|state| state.info.capture_time)
+        .and_then(|time| crate::metadata::parse_datetime(&time))
+        .or_else(|| crate::metadata::capture_time(path))
+}
+
+/// Cached sharpness score next to the thumbnail of `source`.
+fn sharpness_cache_path(source: &std::path::Path, cache_dir: &std::path::Path) -> PathBuf {
+    let name = source.file_name().unwrap_or_default().to_string_lossy();
+    cache_dir.join(format!("{name}.sharpness"))
 }
 
 fn is_image(path: &std::path::Path) -> bool {
@@ -647,10 +2047,83 @@ fn unescape_mount_field(field: &str) -> String {
     String::from_utf8_lossy(&out).into_owned()
 }
 
-#[cfg(all(test, feature = "network-mounts"))]
+#[cfg(test)]
 mod tests {
     use super::*;
 
+    fn named(names: &[&str]) -> Vec<(PathBuf, String)> {
+        names
+            .iter()
+            .map(|n| (PathBuf::from("/shoot").join(n), n.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn raw_jpeg_pairs_collapse_to_the_primary() {
+        let files = named(&[
+            "DSC_0001.NEF",
+            "DSC_0001.JPG",
+            "DSC_0002.jpg",
+            "DSC_0003.nef",
+        ]);
+        let (images, pairs) = pair_raw_jpeg(files.clone(), PairPrimary::Raw);
+        let names: Vec<&str> = images.iter().map(|(_, n)| n.as_str()).collect();
+        assert_eq!(names, ["DSC_0001.NEF", "DSC_0002.jpg", "DSC_0003.nef"]);
+        assert_eq!(
+            pairs.get(&PathBuf::from("/shoot/DSC_0001.NEF")),
+            Some(&PathBuf::from("/shoot/DSC_0001.JPG"))
+        );
+
+        let (images, pairs) = pair_raw_jpeg(files, PairPrimary::Jpeg);
+        assert!(images.iter().any(|(_, n)| n == "DSC_0001.JPG"));
+        assert!(pairs.contains_key(&PathBuf::from("/shoot/DSC_0001.JPG")));
+    }
+
+    #[test]
+    fn ambiguous_stems_stay_unpaired() {
+        let files = named(&[
+            "IMG_1.CR2",
+            "IMG_1.JPG",
+            "IMG_1.jpeg",
+            "IMG_2.png",
+            "IMG_2.ARW",
+        ]);
+        let (images, pairs) = pair_raw_jpeg(files, PairPrimary::Raw);
+        assert_eq!(images.len(), 5);
+        assert!(pairs.is_empty());
+    }
+
+    #[test]
+    fn pairs_never_span_folders() {
+        // As in a collection mixing two shoots
+        let files = vec![
+            (PathBuf::from("/a/DSC_0001.NEF"), "DSC_0001.NEF".to_string()),
+            (PathBuf::from("/b/DSC_0001.JPG"), "DSC_0001.JPG".to_string()),
+        ];
+        let (images, pairs) = pair_raw_jpeg(files, PairPrimary::Raw);
+        assert_eq!(images.len(), 2);
+        assert!(pairs.is_empty());
+    }
+
+    #[test]
+    fn survey_grid_fits_one_image_to_the_panel() {
+        let (cols, cell) = survey_grid(1, egui::vec2(1200.0, 800.0), 8.0);
+        assert_eq!(cols, 1);
+        assert_eq!(cell, egui::vec2(1200.0, 800.0));
+    }
+
+    #[test]
+    fn survey_grid_picks_the_layout_with_the_largest_cells() {
+        // Four frames in a 16:9 panel make a 2×2 grid
+        let (cols, cell) = survey_grid(4, egui::vec2(1600.0, 900.0), 0.0);
+        assert_eq!(cols, 2);
+        assert_eq!(cell, egui::vec2(800.0, 450.0));
+        // Three in a wide strip sit side by side
+        let (cols, _) = survey_grid(3, egui::vec2(1800.0, 400.0), 0.0);
+        assert_eq!(cols, 3);
+    }
+
+    #[cfg(feature = "network-mounts")]
     #[test]
     fn friendly_gvfs_label_formats_smb_share() {
         assert_eq!(
@@ -659,6 +2132,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "network-mounts")]
     #[test]
     fn friendly_gvfs_label_formats_sftp_with_host_only() {
         assert_eq!(
@@ -667,16 +2141,19 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "network-mounts")]
     #[test]
     fn friendly_gvfs_label_falls_back_for_unknown_scheme() {
         assert_eq!(friendly_gvfs_label("unknown-thing"), "unknown-thing");
     }
 
+    #[cfg(feature = "network-mounts")]
     #[test]
     fn unescape_mount_field_decodes_octal_space() {
         assert_eq!(unescape_mount_field(r"/mnt/My\040Share"), "/mnt/My Share");
     }
 
+    #[cfg(feature = "network-mounts")]
     #[test]
     fn unescape_mount_field_passes_through_plain_path() {
         assert_eq!(unescape_mount_field("/mnt/nas"), "/mnt/nas");
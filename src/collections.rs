@@ -0,0 +1,218 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::state::EditState;
+
+/// User-defined sets of images that can span folders, persisted next to the
+/// config file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Collections {
+    #[serde(default)]
+    pub collections: Vec<Collection>,
+}
+
+/// A named collection: a fixed list of images, or the images under a folder
+/// matching smart rules.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Collection {
+    pub name: String,
+    /// Members of a manual collection, in the order they were added.
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+    /// Present for smart collections, whose members are found by rule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smart: Option<SmartRules>,
+}
+
+/// Rules of a smart collection; an image must match all that are set.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SmartRules {
+    /// Folder searched, with its subfolders.
+    pub root: PathBuf,
+    /// Lowest star rating; 0 accepts unrated images.
+    pub min_rating: u8,
+    /// Keyword the image must have, or one of its descendants.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyword: Option<String>,
+    /// Text the camera make or model must contain, ignoring case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<String>,
+}
+
+impl SmartRules {
+    /// Whether an image with these properties belongs to the collection.
+    pub fn matches(&self, rating: u8, keywords: &[String], camera: Option<&str>) -> bool {
+        if rating < self.min_rating {
+            return false;
+        }
+        if let Some(filter) = &self.keyword
+            && !keywords.iter().any(|k| crate::keywords::matches(k, filter))
+        {
+            return false;
+        }
+        match &self.camera {
+            Some(needle) => {
+                camera.is_some_and(|c| c.to_lowercase().contains(&needle.to_lowercase()))
+            }
+            None => true,
+        }
+    }
+
+    /// Images under `root` matching the rules, sorted by path.
+    pub fn evaluate(&self) -> Vec<PathBuf> {
+        let mut images = Vec::new();
+        crate::stacks::collect_images(&self.root, &mut images);
+        let companions = crate::thumbnail::companion_map(images.iter().map(PathBuf::as_path));
+        let mut found: Vec<PathBuf> = images
+            .iter()
+            .filter(|path| {
+                let companion = companions.get(*path).map(PathBuf::as_path);
+                let state = EditState::load(path, companion).unwrap_or_default();
+                let mut keywords = state.keywords;
+                if keywords.is_empty() && crate::xmp::uses_sidecar(path) {
+                    keywords = crate::xmp::read_sidecar_keywords(path);
+                }
+                // EXIF is only read when a camera rule needs it.
+                let camera = self.camera.as_ref().and_then(|_| camera_name(path));
+                self.matches(state.rating, &keywords, camera.as_deref())
+            })
+            .cloned()
+            .collect();
+        found.sort();
+        found
+    }
+}
+
+/// Camera make and model of `path`, as one string.
+fn camera_name(path: &Path) -> Option<String> {
+    let meta = crate::metadata::read(path).ok()?;
+    let name = [meta.camera_make, meta.camera_model]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!name.is_empty()).then_some(name)
+}
+
+impl Collections {
+    /// Returns the collections file path, if a config directory is available.
+    pub fn file_path() -> Option<PathBuf> {
+        crate::config::AppConfig::config_path().map(|p| p.with_file_name("collections.toml"))
+    }
+
+    /// Loads collections from disk, falling back to none on any error.
+    pub fn load() -> Self {
+        let Some(path) = Self::file_path() else {
+            return Self::default();
+        };
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        toml::from_str(&contents).unwrap_or_default()
+    }
+
+    /// Writes collections to disk, ignoring filesystem/serialization errors.
+    pub fn save(&self) {
+        let Some(path) = Self::file_path() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(s) = toml::to_string_pretty(self) {
+            let _ = std::fs::write(&path, s);
+        }
+    }
+
+    /// The collection named `name`.
+    pub fn get(&self, name: &str) -> Option<&Collection> {
+        self.collections.iter().find(|c| c.name == name)
+    }
+
+    /// Adds a collection, replacing any with the same name, and saves.
+    pub fn insert(&mut self, collection: Collection) {
+        self.collections.retain(|c| c.name != collection.name);
+        self.collections.push(collection);
+        self.collections.sort_by_key(|c| c.name.to_lowercase());
+        self.save();
+    }
+
+    /// Deletes the collection `name` and saves.
+    pub fn remove(&mut self, name: &str) {
+        self.collections.retain(|c| c.name != name);
+        self.save();
+    }
+
+    /// Adds `paths` missing from manual collection `name` and saves.
+    pub fn add_paths(&mut self, name: &str, paths: &[PathBuf]) {
+        let Some(collection) = self.collections.iter_mut().find(|c| c.name == name) else {
+            return;
+        };
+        for path in paths {
+            if !collection.paths.contains(path) {
+                collection.paths.push(path.clone());
+            }
+        }
+        self.save();
+    }
+
+    /// Removes `paths` from manual collection `name` and saves.
+    pub fn remove_paths(&mut self, name: &str, paths: &[PathBuf]) {
+        let Some(collection) = self.collections.iter_mut().find(|c| c.name == name) else {
+            return;
+        };
+        collection.paths.retain(|p| !paths.contains(p));
+        self.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{Collection, Collections, SmartRules};
+
+    #[test]
+    fn smart_rules_require_every_set_rule() {
+        let rules = SmartRules {
+            min_rating: 3,
+            keyword: Some("Places|France".to_string()),
+            camera: Some("x-t5".to_string()),
+            ..SmartRules::default()
+        };
+        let paris = vec!["Places|France|Paris".to_string()];
+        assert!(rules.matches(4, &paris, Some("FUJIFILM X-T5")));
+        assert!(!rules.matches(2, &paris, Some("FUJIFILM X-T5")));
+        assert!(!rules.matches(4, &[], Some("FUJIFILM X-T5")));
+        assert!(!rules.matches(4, &paris, None));
+        assert!(SmartRules::default().matches(0, &[], None));
+    }
+
+    #[test]
+    fn collections_round_trip_through_toml() {
+        let collections = Collections {
+            collections: vec![
+                Collection {
+                    name: "Portfolio".to_string(),
+                    paths: vec![PathBuf::from("/a/1.jpg"), PathBuf::from("/b/2.raf")],
+                    smart: None,
+                },
+                Collection {
+                    name: "Best of Paris".to_string(),
+                    paths: Vec::new(),
+                    smart: Some(SmartRules {
+                        root: PathBuf::from("/photos"),
+                        min_rating: 4,
+                        keyword: Some("Places|Paris".to_string()),
+                        camera: None,
+                    }),
+                },
+            ],
+        };
+        let text = toml::to_string_pretty(&collections).unwrap();
+        let parsed: Collections = toml::from_str(&text).unwrap();
+        assert_eq!(parsed.collections, collections.collections);
+    }
+}
@@ -9,6 +9,12 @@ pub struct AppConfig {
     pub window_height: Option<f32>,
     pub browse_path: Option<PathBuf>,
     pub preview_backend: Option<String>,
+    pub working_space: Option<String>,
+    pub monitor_profile: Option<String>,
+    /// Half of a RAW+JPEG pair that opens in the viewer: `raw` or `jpeg`.
+    pub raw_jpeg_primary: Option<String>,
+    /// Folder of offline map tiles laid out as `{z}/{x}/{y}.png`.
+    pub map_tile_dir: Option<PathBuf>,
 }
 
 impl AppConfig {
@@ -0,0 +1,148 @@
+/// Furthest a photo may be, in time, from the nearest track point (or the
+/// gap it falls into) and still be placed on the track.
+pub const MAX_MATCH_SECS: f64 = 300.0;
+
+/// A GPX track point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackPoint {
+    /// UTC seconds since 1970-01-01.
+    pub time: f64,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+/// Timed track points of every track in a GPX document, sorted by time.
+/// Points without a time are skipped since they cannot be matched.
+pub fn parse_gpx(xml: &str) -> anyhow::Result<Vec<TrackPoint>> {
+    let mut points = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<trkpt") {
+        rest = &rest[start..];
+        let head_end = rest.find('>').unwrap_or(rest.len());
+        let head = &rest[..head_end];
+        let end = if head.ends_with('/') {
+            head_end
+        } else {
+            rest.find("</trkpt>").unwrap_or(rest.len())
+        };
+        let body = &rest[head_end.min(end)..end];
+        let attr = |name: &str| -> Option<f64> {
+            let value = head.split(&format!("{name}=")).nth(1)?;
+            let quote = value.chars().next()?;
+            value[1..].split(quote).next()?.trim().parse().ok()
+        };
+        let time = body
+            .split("<time>")
+            .nth(1)
+            .and_then(|t| t.split("</time>").next())
+            .and_then(parse_iso_time);
+        if let (Some(lat), Some(lon), Some(time)) = (attr("lat"), attr("lon"), time) {
+            points.push(TrackPoint { time, lat, lon });
+        }
+        rest = &rest[end..];
+    }
+    anyhow::ensure!(!points.is_empty(), "no timed track points in the GPX file");
+    points.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(points)
+}
+
+/// UTC seconds for an ISO 8601 time (`2024-05-01T14:03:22.5Z`, or with a
+/// `+02:00` offset; no zone means UTC, as GPX requires).
+pub fn parse_iso_time(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (date, time) = text.split_once('T')?;
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next()??,
+    );
+
+    let (clock, offset) = match time.find(['Z', '+', '-']) {
+        Some(i) => (&time[..i], &time[i..]),
+        None => (time, ""),
+    };
+    let mut clock_parts = clock.splitn(3, ':');
+    let hour: i64 = clock_parts.next()?.parse().ok()?;
+    let minute: i64 = clock_parts.next()?.parse().ok()?;
+    let second: f64 = clock_parts.next().unwrap_or("0").parse().ok()?;
+    let offset_secs = match offset.as_bytes().first() {
+        Some(b'+' | b'-') => {
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (h, m) = offset[1..].split_once(':').unwrap_or((&offset[1..], "0"));
+            sign * (h.parse::<i64>().ok()? * 3600 + m.parse::<i64>().ok()? * 60)
+        }
+        _ => 0,
+    };
+    let seconds_of_day = hour * 3600 + minute * 60 - offset_secs;
+    Some(crate::metadata::timestamp(year, month, day, seconds_of_day) + second)
+}
+
+/// Position on `track` at UTC time `time`, interpolated between the points
+/// around it. `None` when the time is outside the track or inside a gap
+/// longer than [`MAX_MATCH_SECS`] on both sides.
+pub fn locate(track: &[TrackPoint], time: f64) -> Option<[f64; 2]> {
+    let after = track.partition_point(|p| p.time < time);
+    let next = track.get(after);
+    let prev = after.checked_sub(1).and_then(|i| track.get(i));
+    match (prev, next) {
+        (Some(a), Some(b)) if b.time - a.time <= MAX_MATCH_SECS * 2.0 => {
+            let span = b.time - a.time;
+            let t = if span > 0.0 {
+                (time - a.time) / span
+            } else {
+                0.0
+            };
+            Some([a.lat + (b.lat - a.lat) * t, a.lon + (b.lon - a.lon) * t])
+        }
+        (prev, next) => [prev, next]
+            .into_iter()
+            .flatten()
+            .filter(|p| (p.time - time).abs() <= MAX_MATCH_SECS)
+            .min_by(|a, b| (a.time - time).abs().total_cmp(&(b.time - time).abs()))
+            .map(|p| [p.lat, p.lon]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPX: &str = r#"<?xml version="1.0"?>
+        <gpx version="1.1"><trk><trkseg>
+          <trkpt lat="48.0" lon="2.0"><ele>35</ele><time>2024-05-01T10:00:00Z</time></trkpt>
+          <trkpt lat='48.1' lon='2.2'><time>2024-05-01T10:01:40Z</time></trkpt>
+          <trkpt lat="49.0" lon="3.0"/>
+          <trkpt lat="50.0" lon="4.0"><time>2024-05-01T12:00:00Z</time></trkpt>
+        </trkseg></trk></gpx>"#;
+
+    #[test]
+    fn gpx_points_need_a_time() {
+        let track = parse_gpx(GPX).unwrap();
+        assert_eq!(track.len(), 3);
+        assert_eq!((track[1].lat, track[1].lon), (48.1, 2.2));
+        assert!(parse_gpx("<gpx></gpx>").is_err());
+    }
+
+    #[test]
+    fn iso_times_honor_offsets() {
+        let utc = parse_iso_time("2024-05-01T10:00:00Z").unwrap();
+        assert_eq!(parse_iso_time("2024-05-01T12:00:00+02:00"), Some(utc));
+        assert_eq!(parse_iso_time("2024-05-01T10:00:00.5"), Some(utc + 0.5));
+        assert_eq!(parse_iso_time("2024-05-01"), None);
+    }
+
+    #[test]
+    fn photos_interpolate_between_points_and_skip_gaps() {
+        let track = parse_gpx(GPX).unwrap();
+        let start = track[0].time;
+
+        let [lat, lon] = locate(&track, start + 50.0).unwrap();
+        assert!((lat - 48.05).abs() < 1e-9 && (lon - 2.1).abs() < 1e-9);
+        // Shortly after the last point of the first stretch
+        assert_eq!(locate(&track, start + 200.0), Some([48.1, 2.2]));
+        // In the middle of the long gap, and before the track starts
+        assert_eq!(locate(&track, start + 3600.0), None);
+        assert_eq!(locate(&track, start - 600.0), None);
+    }
+}
@@ -0,0 +1,250 @@
+use std::path::{Path, PathBuf};
+
+use crate::metadata::{self, MetadataTag};
+use crate::state::PhotoInfo;
+
+/// A change made in the metadata window, saved by the app.
+pub enum InfoEdit {
+    Save(PathBuf, PhotoInfo),
+    /// Move the capture time of each image by this many seconds.
+    ShiftTimes(Vec<PathBuf>, i64),
+}
+
+/// Metadata window state: every tag of the photo in focus with a search
+/// filter, and the editable info fields.
+#[derive(Default)]
+pub struct MetadataInspector {
+    pub open: bool,
+    path: Option<PathBuf>,
+    tags: Vec<MetadataTag>,
+    /// EXIF capture time, shown as the default for the editable one.
+    original_time: Option<String>,
+    saved: PhotoInfo,
+    form: PhotoInfo,
+    /// Capture time field text; empty keeps the EXIF time.
+    time_text: String,
+    search: String,
+    shift: [i32; 4],
+    pub status: String,
+}
+
+impl MetadataInspector {
+    /// Shows `path` with its saved `info`, rereading the tags when the photo
+    /// changed.
+    pub fn load(&mut self, path: &Path, info: PhotoInfo) {
+        if !self.shows(path) {
+            self.tags = metadata::read_all(path);
+            self.original_time = metadata::original_datetime(path);
+            self.status.clear();
+        }
+        self.path = Some(path.to_path_buf());
+        self.saved = info;
+        self.form = self.saved.clone();
+        self.time_text = self.saved.capture_time.clone().unwrap_or_default();
+    }
+
+    /// Whether the window shows `path`.
+    pub fn shows(&self, path: &Path) -> bool {
+        self.path.as_deref() == Some(path)
+    }
+
+    /// Renders the window; `targets` are the photos a time shift applies to.
+    pub fn show(&mut self, ctx: &egui::Context, targets: &[PathBuf]) -> Option<InfoEdit> {
+        if !self.open {
+            return None;
+        }
+        let mut open = true;
+        let mut edit = None;
+        egui::Window::new("Metadata")
+            .open(&mut open)
+            .default_size([460.0, 560.0])
+            .show(ctx, |ui| {
+                let Some(path) = self.path.clone() else {
+                    ui.label("No photo selected");
+                    return;
+                };
+                if let Some(name) = path.file_name() {
+                    ui.strong(name.to_string_lossy());
+                }
+                ui.add_space(4.0);
+                egui::CollapsingHeader::new("Info")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        if let Some(save) = self.show_info_form(ui, &path) {
+                            edit = Some(save);
+                        }
+                    });
+                egui::CollapsingHeader::new("Shift capture time").show(ui, |ui| {
+                    if let Some(shift) = self.show_time_shift(ui, targets) {
+                        edit = Some(shift);
+                    }
+                });
+                if !self.status.is_empty() {
+                    ui.label(egui::RichText::new(&self.status).weak());
+                }
+                ui.separator();
+                self.show_tags(ui);
+            });
+        self.open = open;
+        edit
+    }
+
+    fn show_info_form(&mut self, ui: &mut egui::Ui, path: &Path) -> Option<InfoEdit> {
+        egui::Grid::new("info_form")
+            .num_columns(2)
+            .spacing([12.0, 4.0])
+            .show(ui, |ui| {
+                for (label, text) in [
+                    ("Title", &mut self.form.title),
+                    ("Creator", &mut self.form.creator),
+                    ("Copyright", &mut self.form.copyright),
+                ] {
+                    ui.label(egui::RichText::new(label).weak());
+                    ui.add(egui::TextEdit::singleline(text).desired_width(f32::INFINITY));
+                    ui.end_row();
+                }
+                ui.label(egui::RichText::new("Caption").weak());
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.form.caption)
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+                ui.end_row();
+                ui.label(egui::RichText::new("Capture time").weak());
+                let hint = self
+                    .original_time
+                    .as_deref()
+                    .unwrap_or("YYYY:MM:DD HH:MM:SS");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.time_text)
+                        .hint_text(hint)
+                        .font(egui::TextStyle::Monospace),
+                );
+                ui.end_row();
+                if let Some([lat, lon]) = self.form.location {
+                    ui.label(egui::RichText::new("Location").weak());
+                    ui.add(egui::Label::new(format!("{lat:.6}, {lon:.6}")).selectable(true))
+                        .on_hover_text("Set by geotagging in the Map view");
+                    ui.end_row();
+                }
+            });
+
+        let time = self.time_text.trim();
+        let valid = time.is_empty() || metadata::parse_datetime(time).is_some();
+        self.form.capture_time = (!time.is_empty()).then(|| time.to_string());
+        if !valid {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "Capture time must look like 2024:05:01 14:03:22",
+            );
+        }
+        let changed = self.form != self.saved;
+        let mut edit = None;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(valid && changed, egui::Button::new("Save"))
+                .on_hover_text("Written to the sidecar; RAW files also get an .xmp sidecar")
+                .clicked()
+            {
+                edit = Some(InfoEdit::Save(path.to_path_buf(), self.form.clone()));
+            }
+            if ui
+                .add_enabled(changed, egui::Button::new("Revert"))
+                .clicked()
+            {
+                self.form = self.saved.clone();
+                self.time_text = self.saved.capture_time.clone().unwrap_or_default();
+            }
+        });
+        edit
+    }
+
+    fn show_time_shift(&mut self, ui: &mut egui::Ui, targets: &[PathBuf]) -> Option<InfoEdit> {
+        ui.label(egui::RichText::new("Corrects a camera clock that was set wrong").weak());
+        ui.horizontal(|ui| {
+            for (value, unit) in self.shift.iter_mut().zip(["d", "h", "m", "s"]) {
+                ui.add(egui::DragValue::new(value).range(-9999..=9999).suffix(unit));
+            }
+        });
+        let [d, h, m, s] = self.shift.map(i64::from);
+        let delta = ((d * 24 + h) * 60 + m) * 60 + s;
+        let current = self
+            .saved
+            .capture_time
+            .clone()
+            .or_else(|| self.original_time.clone());
+        if let Some(shifted) = current
+            .as_deref()
+            .and_then(|t| metadata::shift_datetime(t, delta))
+        {
+            ui.weak(format!(
+                "{} \u{2192} {shifted}",
+                current.unwrap_or_default()
+            ));
+        }
+        let label = format!("Shift {} photo(s)", targets.len());
+        let clicked = ui
+            .add_enabled(delta != 0 && !targets.is_empty(), egui::Button::new(label))
+            .on_hover_text("Applies to the marked photos, or else the selected one")
+            .clicked();
+        if !clicked {
+            return None;
+        }
+        // Cleared so a second click cannot apply the shift twice
+        self.shift = [0; 4];
+        Some(InfoEdit::ShiftTimes(targets.to_vec(), delta))
+    }
+
+    /// All tags, grouped, filtered by the search text.
+    fn show_tags(&mut self, ui: &mut egui::Ui) {
+        ui.add(
+            egui::TextEdit::singleline(&mut self.search)
+                .hint_text("Search tags")
+                .desired_width(f32::INFINITY),
+        );
+        let needle = self.search.trim().to_lowercase();
+        let mut groups: Vec<&'static str> = Vec::new();
+        for tag in &self.tags {
+            if !groups.contains(&tag.group) {
+                groups.push(tag.group);
+            }
+        }
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for group in groups {
+                    let rows: Vec<&MetadataTag> = self
+                        .tags
+                        .iter()
+                        .filter(|t| t.group == group)
+                        .filter(|t| {
+                            needle.is_empty()
+                                || t.name.to_lowercase().contains(&needle)
+                                || t.value.to_lowercase().contains(&needle)
+                        })
+                        .collect();
+                    if rows.is_empty() {
+                        continue;
+                    }
+                    egui::CollapsingHeader::new(format!("{group} ({})", rows.len()))
+                        .id_salt(group)
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            egui::Grid::new(("metadata_tags", group))
+                                .num_columns(2)
+                                .spacing([12.0, 2.0])
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for tag in rows {
+                                        ui.label(egui::RichText::new(&tag.name).weak());
+                                        ui.add(
+                                            egui::Label::new(&tag.value).wrap().selectable(true),
+                                        );
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                }
+            });
+    }
+}
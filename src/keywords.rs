@@ -0,0 +1,157 @@
+/// Separator between levels of a stored keyword: keywords are kept as their
+/// full path (`Places|France|Paris`), as in XMP `lr:hierarchicalSubject`.
+pub const SEPARATOR: char = '|';
+/// Most autocomplete suggestions offered at once.
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Cleans typed input into a stored keyword: levels may be separated by `|`
+/// or `>`, surrounding whitespace and empty levels are dropped.
+pub fn normalize(input: &str) -> Option<String> {
+    let levels: Vec<&str> = input
+        .split(['|', '>'])
+        .map(str::trim)
+        .filter(|level| !level.is_empty())
+        .collect();
+    (!levels.is_empty()).then(|| levels.join("|"))
+}
+
+/// Last level of a keyword, as shown in the tree.
+pub fn leaf(keyword: &str) -> &str {
+    keyword.rsplit(SEPARATOR).next().unwrap_or(keyword)
+}
+
+/// Whether `keyword` is `filter` or one of its descendants.
+pub fn matches(keyword: &str, filter: &str) -> bool {
+    keyword
+        .strip_prefix(filter)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(SEPARATOR))
+}
+
+/// A level of the keyword tree with the number of images tagged with it or
+/// any of its descendants.
+#[derive(Debug, PartialEq)]
+pub struct KeywordNode {
+    pub name: String,
+    /// Full stored keyword of this level.
+    pub path: String,
+    pub count: usize,
+    pub children: Vec<KeywordNode>,
+}
+
+/// Builds the keyword tree from each image's keyword list, sorted by name.
+pub fn build_tree<'a>(images: impl IntoIterator<Item = &'a [String]>) -> Vec<KeywordNode> {
+    let mut roots: Vec<KeywordNode> = Vec::new();
+    for keywords in images {
+        // Each level counts an image once, however many of its keywords
+        // fall under it.
+        let mut counted: Vec<String> = Vec::new();
+        for keyword in keywords {
+            let mut level = &mut roots;
+            let mut path = String::new();
+            for name in keyword.split(SEPARATOR) {
+                if !path.is_empty() {
+                    path.push(SEPARATOR);
+                }
+                path.push_str(name);
+                let idx = match level.iter().position(|n| n.name == name) {
+                    Some(idx) => idx,
+                    None => {
+                        level.push(KeywordNode {
+                            name: name.to_string(),
+                            path: path.clone(),
+                            count: 0,
+                            children: Vec::new(),
+                        });
+                        level.len() - 1
+                    }
+                };
+                if !counted.contains(&path) {
+                    level[idx].count += 1;
+                    counted.push(path.clone());
+                }
+                level = &mut level[idx].children;
+            }
+        }
+    }
+    sort_tree(&mut roots);
+    roots
+}
+
+fn sort_tree(nodes: &mut [KeywordNode]) {
+    nodes.sort_by_key(|n| n.name.to_lowercase());
+    for node in nodes {
+        sort_tree(&mut node.children);
+    }
+}
+
+/// Known keywords matching typed `input`: those whose last level starts
+/// with it first, then any containing it.
+pub fn suggest<'a>(input: &str, known: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    let needle = input.trim().to_lowercase();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let mut prefix = Vec::new();
+    let mut contains = Vec::new();
+    for keyword in known {
+        let lower = keyword.to_lowercase();
+        if leaf(&lower).starts_with(&needle) || lower.starts_with(&needle) {
+            prefix.push(keyword.clone());
+        } else if lower.contains(&needle) {
+            contains.push(keyword.clone());
+        }
+    }
+    prefix.sort();
+    contains.sort();
+    prefix.extend(contains);
+    prefix.dedup();
+    prefix.truncate(MAX_SUGGESTIONS);
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(keywords: &[&str]) -> Vec<String> {
+        keywords.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn normalize_accepts_both_separators() {
+        assert_eq!(
+            normalize(" Places > France| Paris ").as_deref(),
+            Some("Places|France|Paris")
+        );
+        assert_eq!(normalize(" | "), None);
+    }
+
+    #[test]
+    fn filters_match_descendants_only() {
+        assert!(matches("Places|France|Paris", "Places|France"));
+        assert!(matches("Places", "Places"));
+        assert!(!matches("Placestwo", "Places"));
+    }
+
+    #[test]
+    fn tree_counts_each_image_once_per_level() {
+        let a = list(&["Places|France|Paris", "Places|France|Lyon"]);
+        let b = list(&["Places|Spain", "Birds"]);
+        let tree = build_tree([a.as_slice(), b.as_slice()]);
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!((tree[0].name.as_str(), tree[0].count), ("Birds", 1));
+        let places = &tree[1];
+        assert_eq!(places.count, 2);
+        let france = &places.children[0];
+        assert_eq!((france.path.as_str(), france.count), ("Places|France", 1));
+        assert_eq!(france.children.len(), 2);
+    }
+
+    #[test]
+    fn suggestions_prefer_leaf_prefixes() {
+        let known = list(&["Places|Paris", "Sports|Soccer", "Compass"]);
+        assert_eq!(suggest("pa", &known), list(&["Places|Paris", "Compass"]));
+        assert!(suggest("  ", &known).is_empty());
+    }
+}
@@ -1,12 +1,20 @@
 mod app;
 mod browser;
+mod collections;
 mod config;
 mod editor;
+mod geotag;
+mod inspector;
+mod keywords;
+mod map;
 mod metadata;
 mod processing;
+mod stacks;
 mod state;
 mod thumbnail;
+mod timeline;
 mod viewer;
+mod xmp;
 
 use app::PhotographApp;
 use config::AppConfig;
@@ -46,6 +54,14 @@ fn resolve_preview_backend(config: &AppConfig) -> PreviewBackend {
     PreviewBackend::Auto
 }
 
+fn resolve_working_space(config: &AppConfig) -> processing::icc::WorkingSpace {
+    std::env::var(processing::icc::WORKING_SPACE_ENV)
+        .ok()
+        .or_else(|| config.working_space.clone())
+        .and_then(|raw| processing::icc::WorkingSpace::parse(&raw))
+        .unwrap_or_default()
+}
+
 fn effective_preview_backend(
     requested: PreviewBackend,
     allow_debug_cpu_fallback: bool,
@@ -115,6 +131,7 @@ fn main() -> eframe::Result {
         .init();
 
     let config = AppConfig::load();
+    processing::icc::init_working_space(resolve_working_space(&config));
     let requested_preview_backend = resolve_preview_backend(&config);
     let allow_debug_cpu_fallback = processing::gpu_pipeline::allow_debug_cpu_fallback();
     let preview_backend =
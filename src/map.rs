@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::geotag::{self, TrackPoint};
+
+/// Edge of a basemap tile in screen points at its own zoom level.
+const TILE_SIZE: f64 = 256.0;
+const MAX_ZOOM: f64 = 19.0;
+/// Largest latitude Web Mercator can show.
+const MAX_LATITUDE: f64 = 85.051_128_78;
+/// How close, in points, a click must be to a photo to pick it.
+const PICK_RADIUS: f32 = 8.0;
+/// Decoded tiles kept before the cache is emptied.
+const TILE_CACHE_LIMIT: usize = 256;
+/// Tiles read from disk per frame, so panning never stalls the UI.
+const TILE_LOADS_PER_FRAME: usize = 8;
+
+/// Map panel state: a Web Mercator view over the geotagged photos, drawn on
+/// offline tiles when present and a latitude/longitude grid otherwise, plus
+/// the GPX geotagging window.
+pub struct MapView {
+    /// Point at the middle of the panel, in world units (0..1 both ways).
+    center: [f64; 2],
+    zoom: f64,
+    /// Photos the view was last fitted to; refitted when they change.
+    fitted_to: Option<(usize, Option<PathBuf>)>,
+    /// Folder of `{z}/{x}/{y}.png` tiles.
+    tile_dir: PathBuf,
+    tile_dir_text: String,
+    /// Loaded tiles; `None` for tiles missing from the folder.
+    tiles: HashMap<(u32, u32, u32), Option<egui::TextureHandle>>,
+    pub geotag_open: bool,
+    gpx_path: String,
+    track: Vec<TrackPoint>,
+    /// Camera clock minus UTC, in hours.
+    offset_hours: f64,
+    pub status: String,
+}
+
+impl Default for MapView {
+    fn default() -> Self {
+        let tile_dir = dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("photograph")
+            .join("tiles");
+        Self {
+            center: [0.5, 0.5],
+            zoom: 1.0,
+            fitted_to: None,
+            tile_dir_text: tile_dir.display().to_string(),
+            tile_dir,
+            tiles: HashMap::new(),
+            geotag_open: false,
+            gpx_path: String::new(),
+            track: Vec::new(),
+            offset_hours: 0.0,
+            status: String::new(),
+        }
+    }
+}
+
+impl MapView {
+    pub fn tile_dir(&self) -> &Path {
+        &self.tile_dir
+    }
+
+    pub fn set_tile_dir(&mut self, dir: PathBuf) {
+        self.tile_dir_text = dir.display().to_string();
+        self.tile_dir = dir;
+        self.tiles.clear();
+    }
+
+    /// Track loaded in the geotag window, sorted by time.
+    pub fn track(&self) -> &[TrackPoint] {
+        &self.track
+    }
+
+    /// Renders the map of `photos` with `selected` highlighted; returns the
+    /// photo that was clicked.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        photos: &[(PathBuf, [f64; 2])],
+        selected: Option<&Path>,
+    ) -> Option<PathBuf> {
+        let mut fit = false;
+        ui.horizontal(|ui| {
+            ui.label(format!("{} geotagged", photos.len()));
+            fit = ui
+                .add_enabled(!photos.is_empty(), egui::Button::new("Fit"))
+                .on_hover_text("Zoom to show every geotagged photo")
+                .clicked();
+            ui.menu_button("Basemap", |ui| {
+                ui.label("Offline tile folder ({z}/{x}/{y}.png)");
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.tile_dir_text)
+                        .desired_width(320.0)
+                        .font(egui::TextStyle::Monospace),
+                );
+                if response.lost_focus() || ui.button("Reload tiles").clicked() {
+                    let dir = crate::app::expand_home_prefix(self.tile_dir_text.trim());
+                    self.set_tile_dir(dir);
+                }
+                ui.label(
+                    egui::RichText::new("Without tiles a latitude/longitude grid is drawn").weak(),
+                );
+            });
+        });
+        ui.separator();
+
+        let (response, painter) =
+            ui.allocate_painter(ui.available_size(), egui::Sense::click_and_drag());
+        let rect = response.rect;
+        let key = (photos.len(), photos.first().map(|(p, _)| p.clone()));
+        if fit || self.fitted_to.as_ref() != Some(&key) {
+            let points: Vec<[f64; 2]> = photos.iter().map(|(_, p)| project(*p)).collect();
+            self.fit(&points, rect.size());
+            self.fitted_to = Some(key);
+        }
+        self.handle_input(ui, &response);
+
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+        if !self.paint_tiles(ui.ctx(), &painter, rect) {
+            self.paint_grid(ui, &painter, rect);
+        }
+
+        let scale = self.scale();
+        let to_screen = |world: [f64; 2]| {
+            rect.center()
+                + egui::vec2(
+                    ((world[0] - self.center[0]) * scale) as f32,
+                    ((world[1] - self.center[1]) * scale) as f32,
+                )
+        };
+        let accent = ui.visuals().selection.bg_fill;
+        if self.track.len() > 1 {
+            let line: Vec<egui::Pos2> = self
+                .track
+                .iter()
+                .map(|p| to_screen(project([p.lat, p.lon])))
+                .collect();
+            painter.add(egui::Shape::line(
+                line,
+                egui::Stroke::new(2.0, accent.gamma_multiply(0.6)),
+            ));
+        }
+
+        let pointer = response.hover_pos();
+        let mut nearest: Option<(f32, usize)> = None;
+        for (i, (path, position)) in photos.iter().enumerate() {
+            let pos = to_screen(project(*position));
+            if !rect.expand(PICK_RADIUS).contains(pos) {
+                continue;
+            }
+            let is_selected = selected == Some(path.as_path());
+            let radius = if is_selected { 7.0 } else { 5.0 };
+            painter.circle(
+                pos,
+                radius,
+                accent,
+                egui::Stroke::new(if is_selected { 2.0 } else { 1.0 }, egui::Color32::WHITE),
+            );
+            if let Some(pointer) = pointer {
+                let distance = pointer.distance(pos);
+                if distance <= PICK_RADIUS && nearest.is_none_or(|(d, _)| distance < d) {
+                    nearest = Some((distance, i));
+                }
+            }
+        }
+
+        let (_, index) = nearest?;
+        let path = &photos[index].0;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let clicked = response.clicked();
+        response.on_hover_text(name.to_string());
+        clicked.then(|| path.clone())
+    }
+
+    /// Drag to pan, scroll or pinch to zoom around the pointer.
+    fn handle_input(&mut self, ui: &egui::Ui, response: &egui::Response) {
+        let scale = self.scale();
+        if response.dragged() {
+            let delta = response.drag_delta();
+            self.center[0] -= f64::from(delta.x) / scale;
+            self.center[1] -= f64::from(delta.y) / scale;
+        }
+        if response.hovered() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            let pinch = ui.input(|i| i.zoom_delta());
+            let steps = if scroll != 0.0 {
+                f64::from(scroll) / 120.0
+            } else {
+                f64::from(pinch).log2()
+            };
+            if steps.abs() > 1e-4
+                && let Some(pointer) = response.hover_pos()
+            {
+                let offset = pointer - response.rect.center();
+                let anchor = [
+                    self.center[0] + f64::from(offset.x) / scale,
+                    self.center[1] + f64::from(offset.y) / scale,
+                ];
+                self.zoom = (self.zoom + steps).clamp(0.0, MAX_ZOOM);
+                let scale = self.scale();
+                self.center = [
+                    anchor[0] - f64::from(offset.x) / scale,
+                    anchor[1] - f64::from(offset.y) / scale,
+                ];
+            }
+        }
+        self.center = self.center.map(|c| c.clamp(0.0, 1.0));
+    }
+
+    /// Screen points per world unit.
+    fn scale(&self) -> f64 {
+        TILE_SIZE * self.zoom.exp2()
+    }
+
+    /// Centers and zooms the view on `points`, in world units.
+    fn fit(&mut self, points: &[[f64; 2]], size: egui::Vec2) {
+        let Some(first) = points.first() else {
+            return;
+        };
+        let (mut min, mut max) = (*first, *first);
+        for p in points {
+            min = [min[0].min(p[0]), min[1].min(p[1])];
+            max = [max[0].max(p[0]), max[1].max(p[1])];
+        }
+        self.center = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0];
+        let span = (max[0] - min[0]).max(max[1] - min[1]);
+        let view = f64::from(size.x.min(size.y)) * 0.8;
+        self.zoom = if span > 0.0 {
+            (view / (span * TILE_SIZE)).log2().clamp(1.0, 16.0)
+        } else {
+            14.0
+        };
+    }
+
+    /// Draws the tiles covering `rect`; returns whether any were found.
+    fn paint_tiles(
+        &mut self,
+        ctx: &egui::Context,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+    ) -> bool {
+        let z = self.zoom.round().clamp(0.0, MAX_ZOOM) as u32;
+        let count = 1u32 << z;
+        let scale = self.scale();
+        let tile_world = 1.0 / f64::from(count);
+        let half = [
+            f64::from(rect.width()) / 2.0 / scale,
+            f64::from(rect.height()) / 2.0 / scale,
+        ];
+        let range = |axis: usize| {
+            let first = ((self.center[axis] - half[axis]) / tile_world)
+                .floor()
+                .max(0.0) as u32;
+            let last = ((self.center[axis] + half[axis]) / tile_world)
+                .floor()
+                .min(f64::from(count - 1)) as u32;
+            first..=last
+        };
+        if self.tiles.len() > TILE_CACHE_LIMIT {
+            self.tiles.clear();
+        }
+        let mut loads = 0;
+        let mut drawn = false;
+        for y in range(1) {
+            for x in range(0) {
+                if !self.tiles.contains_key(&(z, x, y)) {
+                    if loads == TILE_LOADS_PER_FRAME {
+                        ctx.request_repaint();
+                        continue;
+                    }
+                    loads += 1;
+                    let texture = load_tile(ctx, &self.tile_dir, z, x, y);
+                    self.tiles.insert((z, x, y), texture);
+                }
+                let Some(Some(texture)) = self.tiles.get(&(z, x, y)) else {
+                    continue;
+                };
+                let corner = |tx: u32, ty: u32| {
+                    rect.center()
+                        + egui::vec2(
+                            ((f64::from(tx) * tile_world - self.center[0]) * scale) as f32,
+                            ((f64::from(ty) * tile_world - self.center[1]) * scale) as f32,
+                        )
+                };
+                painter.image(
+                    texture.id(),
+                    egui::Rect::from_min_max(corner(x, y), corner(x + 1, y + 1)),
+                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                    egui::Color32::WHITE,
+                );
+                drawn = true;
+            }
+        }
+        drawn
+    }
+
+    /// Vector basemap: meridians and parallels at a spacing suited to the
+    /// zoom, labelled along the panel edges.
+    fn paint_grid(&self, ui: &egui::Ui, painter: &egui::Painter, rect: egui::Rect) {
+        let scale = self.scale();
+        let degrees_across = f64::from(rect.width()) / scale * 360.0;
+        let step = [
+            30.0, 10.0, 5.0, 2.0, 1.0, 0.5, 0.2, 0.1, 0.05, 0.02, 0.01, 0.005, 0.002, 0.001,
+        ]
+        .into_iter()
+        .find(|step| degrees_across / step >= 4.0)
+        .unwrap_or(0.001);
+        let stroke = egui::Stroke::new(1.0, ui.visuals().weak_text_color().gamma_multiply(0.4));
+        let font = egui::FontId::proportional(10.0);
+        let color = ui.visuals().weak_text_color();
+        let painter = painter.with_clip_rect(rect);
+
+        let west = unproject([self.center[0] - f64::from(rect.width()) / 2.0 / scale, 0.5])[1];
+        let east = unproject([self.center[0] + f64::from(rect.width()) / 2.0 / scale, 0.5])[1];
+        let mut lon = (west / step).floor() * step;
+        while lon <= east {
+            let x = rect.center().x + ((project([0.0, lon])[0] - self.center[0]) * scale) as f32;
+            painter.vline(x, rect.y_range(), stroke);
+            painter.text(
+                egui::pos2(x + 3.0, rect.bottom() - 3.0),
+                egui::Align2::LEFT_BOTTOM,
+                format_degrees(lon, step, ['E', 'W']),
+                font.clone(),
+                color,
+            );
+            lon += step;
+        }
+
+        let north = unproject([0.5, self.center[1] - f64::from(rect.height()) / 2.0 / scale])[0];
+        let south = unproject([0.5, self.center[1] + f64::from(rect.height()) / 2.0 / scale])[0];
+        let mut lat = (south / step).floor() * step;
+        while lat <= north {
+            let y = rect.center().y + ((project([lat, 0.0])[1] - self.center[1]) * scale) as f32;
+            painter.hline(rect.x_range(), y, stroke);
+            painter.text(
+                egui::pos2(rect.left() + 3.0, y - 2.0),
+                egui::Align2::LEFT_BOTTOM,
+                format_degrees(lat, step, ['N', 'S']),
+                font.clone(),
+                color,
+            );
+            lat += step;
+        }
+    }
+
+    /// Renders the geotag window; returns the camera clock offset from UTC,
+    /// in seconds, when tagging was requested for `targets` photos.
+    pub fn show_geotag_window(&mut self, ctx: &egui::Context, targets: usize) -> Option<i64> {
+        if !self.geotag_open {
+            return None;
+        }
+        let mut open = true;
+        let mut offset = None;
+        egui::Window::new("Geotag from GPX")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label("GPX track");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.gpx_path)
+                            .hint_text("~/tracks/2024-05-01.gpx")
+                            .desired_width(300.0)
+                            .font(egui::TextStyle::Monospace),
+                    );
+                    if ui.button("Load").clicked() {
+                        self.load_track();
+                    }
+                });
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    ui.label("Camera clock");
+                    ui.add(
+                        egui::DragValue::new(&mut self.offset_hours)
+                            .range(-14.0..=14.0)
+                            .speed(0.25)
+                            .fixed_decimals(2)
+                            .prefix("UTC ")
+                            .suffix(" h"),
+                    )
+                    .on_hover_text("Time zone the camera clock was set to, e.g. 2 for UTC+2");
+                });
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Photos within {} minutes of the track are tagged; \
+                         corrected capture times are used",
+                        geotag::MAX_MATCH_SECS / 60.0
+                    ))
+                    .weak(),
+                );
+                ui.add_space(8.0);
+                let label = format!("Tag {targets} photo(s)");
+                if ui
+                    .add_enabled(
+                        !self.track.is_empty() && targets > 0,
+                        egui::Button::new(label),
+                    )
+                    .on_hover_text("Applies to the marked photos, or else every photo shown")
+                    .clicked()
+                {
+                    offset = Some((self.offset_hours * 3600.0).round() as i64);
+                }
+                if !self.status.is_empty() {
+                    ui.separator();
+                    ui.label(&self.status);
+                }
+            });
+        self.geotag_open = open;
+        offset
+    }
+
+    fn load_track(&mut self) {
+        let path = crate::app::expand_home_prefix(self.gpx_path.trim());
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|xml| geotag::parse_gpx(&xml));
+        match parsed {
+            Ok(track) => {
+                let hours = (track[track.len() - 1].time - track[0].time) / 3600.0;
+                self.status = format!("Loaded {} track points over {hours:.1} h", track.len());
+                self.track = track;
+            }
+            Err(err) => {
+                self.status = format!("Could not load {}: {err}", path.display());
+                self.track.clear();
+            }
+        }
+    }
+}
+
+/// Reads tile `z/x/y` from `dir` into a texture.
+fn load_tile(
+    ctx: &egui::Context,
+    dir: &Path,
+    z: u32,
+    x: u32,
+    y: u32,
+) -> Option<egui::TextureHandle> {
+    let path = dir
+        .join(z.to_string())
+        .join(x.to_string())
+        .join(format!("{y}.png"));
+    if !path.is_file() {
+        return None;
+    }
+    let rgba = match image::open(&path) {
+        Ok(img) => img.to_rgba8(),
+        Err(err) => {
+            eprintln!("photograph: unreadable map tile {}: {err}", path.display());
+            return None;
+        }
+    };
+    let size = [rgba.width() as usize, rgba.height() as usize];
+    let img = egui::ColorImage::from_rgba_unmultiplied(size, &rgba);
+    Some(ctx.load_texture(
+        format!("map_tile_{z}_{x}_{y}"),
+        img,
+        egui::TextureOptions::LINEAR,
+    ))
+}
+
+/// Web Mercator position of latitude/longitude degrees, in world units with
+/// the origin at the north-west corner.
+fn project([lat, lon]: [f64; 2]) -> [f64; 2] {
+    let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    [
+        (lon + 180.0) / 360.0,
+        (1.0 - lat.tan().asinh() / std::f64::consts::PI) / 2.0,
+    ]
+}
+
+/// Latitude/longitude degrees of a world position; the inverse of
+/// [`project`].
+fn unproject([x, y]: [f64; 2]) -> [f64; 2] {
+    let lat = (std::f64::consts::PI * (1.0 - 2.0 * y)).sinh().atan();
+    [lat.to_degrees(), x * 360.0 - 180.0]
+}
+
+/// Grid label such as `48.5°N`, with as many decimals as `step` needs.
+fn format_degrees(value: f64, step: f64, [positive, negative]: [char; 2]) -> String {
+    let decimals = (-step.log10()).ceil().max(0.0) as usize;
+    let hemisphere = if value < 0.0 { negative } else { positive };
+    format!("{:.decimals$}\u{b0}{hemisphere}", value.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mercator_round_trips() {
+        assert_eq!(project([0.0, 0.0]), [0.5, 0.5]);
+        assert!(project([MAX_LATITUDE, -180.0])[1].abs() < 1e-9);
+        let [lat, lon] = unproject(project([48.8584, 2.2945]));
+        assert!((lat - 48.8584).abs() < 1e-9 && (lon - 2.2945).abs() < 1e-9);
+        assert_eq!(format_degrees(-0.5, 0.5, ['N', 'S']), "0.5\u{b0}S");
+        assert_eq!(format_degrees(30.0, 10.0, ['E', 'W']), "30\u{b0}E");
+    }
+}
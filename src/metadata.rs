@@ -15,6 +15,48 @@ pub struct ImageMetadata {
     pub date_taken: Option<String>,
 }
 
+/// One property shown in the metadata inspector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataTag {
+    /// Section of the inspector: `File`, `EXIF`, `GPS`, `IPTC`, `XMP`, …
+    pub group: &'static str,
+    pub name: String,
+    pub value: String,
+}
+
+/// Longest value shown in the inspector; binary blobs such as maker notes
+/// are cut off.
+const MAX_VALUE_CHARS: usize = 300;
+
+/// How much of a non-JPEG file is searched for embedded IPTC and XMP.
+const HEADER_SCAN_BYTES: u64 = 4 << 20;
+
+/// IPTC-IIM application record (2:xx) dataset names.
+const IPTC_DATASETS: &[(u8, &str)] = &[
+    (5, "Object Name"),
+    (10, "Urgency"),
+    (15, "Category"),
+    (20, "Supplemental Category"),
+    (25, "Keywords"),
+    (40, "Special Instructions"),
+    (55, "Date Created"),
+    (60, "Time Created"),
+    (80, "By-line"),
+    (85, "By-line Title"),
+    (90, "City"),
+    (92, "Sub-location"),
+    (95, "Province/State"),
+    (100, "Country Code"),
+    (101, "Country"),
+    (103, "Original Transmission Reference"),
+    (105, "Headline"),
+    (110, "Credit"),
+    (115, "Source"),
+    (116, "Copyright Notice"),
+    (120, "Caption/Abstract"),
+    (122, "Writer/Editor"),
+];
+
 /// Reads EXIF metadata from an image file.
 pub fn read(path: &Path) -> anyhow::Result<ImageMetadata> {
     let file = std::fs::File::open(path)?;
@@ -26,7 +68,24 @@ pub fn read(path: &Path) -> anyhow::Result<ImageMetadata> {
             .map(|f| f.display_value().to_string())
     };
 
+    // Pixel dimensions are recorded by the camera; other files are asked
+    // directly (RAW headers describe their embedded previews instead).
+    let dimension = |tag| {
+        exif.get_field(tag, exif::In::PRIMARY)
+            .and_then(|f| f.value.get_uint(0))
+            .filter(|&v| v > 0)
+    };
+    let (width, height) = match (
+        dimension(exif::Tag::PixelXDimension),
+        dimension(exif::Tag::PixelYDimension),
+    ) {
+        (Some(w), Some(h)) => (Some(w), Some(h)),
+        _ => image::image_dimensions(path).map_or((None, None), |(w, h)| (Some(w), Some(h))),
+    };
+
     Ok(ImageMetadata {
+        width,
+        height,
         camera_make: field(exif::Tag::Make),
         camera_model: field(exif::Tag::Model),
         lens: field(exif::Tag::LensModel),
@@ -40,6 +99,482 @@ pub fn read(path: &Path) -> anyhow::Result<ImageMetadata> {
         aperture: field(exif::Tag::FNumber),
         focal_length: field(exif::Tag::FocalLength),
         date_taken: field(exif::Tag::DateTimeOriginal),
-        ..Default::default()
     })
 }
+
+/// Every EXIF, IPTC and XMP property of `path` (and its `.xmp` sidecar),
+/// grouped for the metadata inspector.
+pub fn read_all(path: &Path) -> Vec<MetadataTag> {
+    let mut tags = Vec::new();
+    let mut push = |group, name: &str, value: String| {
+        let value = match value.char_indices().nth(MAX_VALUE_CHARS) {
+            Some((cut, _)) => format!("{}\u{2026}", &value[..cut]),
+            None => value,
+        };
+        tags.push(MetadataTag {
+            group,
+            name: name.to_string(),
+            value,
+        });
+    };
+    if let Some(name) = path.file_name() {
+        push("File", "Name", name.to_string_lossy().into_owned());
+    }
+    if let Ok(meta) = std::fs::metadata(path) {
+        push("File", "Size", format!("{:.1} MB", meta.len() as f64 / 1e6));
+    }
+    if let Ok((w, h)) = image::image_dimensions(path) {
+        push("File", "Dimensions", format!("{w} \u{00D7} {h}"));
+    }
+
+    let exif = std::fs::File::open(path).ok().and_then(|file| {
+        let mut bufreader = std::io::BufReader::new(file);
+        exif::Reader::new().read_from_container(&mut bufreader).ok()
+    });
+    if let Some(exif) = exif {
+        for field in exif.fields() {
+            let group = match (field.ifd_num, field.tag.context()) {
+                (exif::In::THUMBNAIL, _) => "Thumbnail",
+                (_, exif::Context::Gps) => "GPS",
+                (_, exif::Context::Interop) => "Interoperability",
+                _ => "EXIF",
+            };
+            let value = field.display_value().with_unit(&exif).to_string();
+            push(group, &field.tag.to_string(), value);
+        }
+    }
+
+    let bytes = read_header(path).unwrap_or_default();
+    for (name, value) in iptc_properties(&bytes) {
+        push("IPTC", &name, value);
+    }
+    if let Some(xml) = crate::xmp::find_packet(&bytes) {
+        for (name, value) in crate::xmp::list_properties(&xml) {
+            push("XMP", &name, value);
+        }
+    }
+    if let Ok(xml) = std::fs::read_to_string(crate::xmp::sidecar_path(path)) {
+        for (name, value) in crate::xmp::list_properties(&xml) {
+            push("XMP sidecar", &name, value);
+        }
+    }
+    tags
+}
+
+/// The part of `path` that can hold embedded IPTC and XMP: every segment
+/// before the scan data of a JPEG, or the start of any other file.
+fn read_header(path: &Path) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut bytes = vec![0; 2];
+    reader.read_exact(&mut bytes)?;
+    if bytes == [0xFF, 0xD8] {
+        // A truncated file still yields the segments read so far
+        let _ = read_jpeg_segments(&mut reader, &mut bytes);
+    } else {
+        reader.take(HEADER_SCAN_BYTES).read_to_end(&mut bytes)?;
+    }
+    Ok(bytes)
+}
+
+/// Appends the JPEG segments of `reader` to `bytes`, up to the scan data.
+fn read_jpeg_segments(reader: &mut impl std::io::Read, bytes: &mut Vec<u8>) -> std::io::Result<()> {
+    use std::io::Read;
+    loop {
+        let mut marker = [0; 2];
+        reader.read_exact(&mut marker)?;
+        if marker[0] != 0xFF {
+            return Ok(());
+        }
+        // Fill bytes may pad the marker
+        while marker[1] == 0xFF {
+            reader.read_exact(&mut marker[1..])?;
+        }
+        if marker[1] == 0xDA || marker[1] == 0xD9 {
+            return Ok(());
+        }
+        let mut len = [0; 2];
+        reader.read_exact(&mut len)?;
+        let len = u16::from_be_bytes(len);
+        if len < 2 {
+            return Ok(());
+        }
+        bytes.extend_from_slice(&[0xFF, marker[1]]);
+        bytes.extend_from_slice(&len.to_be_bytes());
+        reader.take(len as u64 - 2).read_to_end(bytes)?;
+    }
+}
+
+/// IPTC-IIM datasets from the Photoshop (APP13) segment of a JPEG, with
+/// repeated datasets such as keywords joined into one value.
+fn iptc_properties(bytes: &[u8]) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = Vec::new();
+    let Some(iim) = jpeg_iptc_block(bytes) else {
+        return out;
+    };
+    let mut pos = 0;
+    while pos + 5 <= iim.len() && iim[pos] == 0x1C {
+        let (record, dataset) = (iim[pos + 1], iim[pos + 2]);
+        let len = u16::from_be_bytes([iim[pos + 3], iim[pos + 4]]) as usize;
+        // Extended-length datasets (high bit set) only hold binary data
+        if len & 0x8000 != 0 || pos + 5 + len > iim.len() {
+            break;
+        }
+        let value = String::from_utf8_lossy(&iim[pos + 5..pos + 5 + len]).into_owned();
+        pos += 5 + len;
+        if record == 2 && dataset == 0 {
+            continue;
+        }
+        let name = IPTC_DATASETS
+            .iter()
+            .find(|(id, _)| record == 2 && *id == dataset)
+            .map_or_else(|| format!("{record}:{dataset:03}"), |(_, n)| n.to_string());
+        if let Some((_, existing)) = out.iter_mut().find(|(n, _)| *n == name) {
+            existing.push_str("; ");
+            existing.push_str(&value);
+        } else {
+            out.push((name, value));
+        }
+    }
+    out
+}
+
+/// The IPTC-IIM resource (`8BIM` 0x0404) of a JPEG's APP13 segment.
+fn jpeg_iptc_block(bytes: &[u8]) -> Option<&[u8]> {
+    const PHOTOSHOP: &[u8] = b"Photoshop 3.0\0";
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        // Fill bytes may pad the marker
+        while bytes.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+        if pos + 4 > bytes.len() {
+            return None;
+        }
+        let marker = bytes[pos + 1];
+        // Metadata segments all come before the scan data
+        if marker == 0xDA {
+            return None;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        if len < 2 {
+            return None;
+        }
+        let segment = bytes.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xED
+            && let Some(mut resources) = segment.strip_prefix(PHOTOSHOP)
+        {
+            while resources.len() >= 12 && resources.starts_with(b"8BIM") {
+                let id = u16::from_be_bytes([resources[4], resources[5]]);
+                // Pascal-string name, padded to an even length
+                let name_len = (resources[6] as usize + 2) & !1;
+                let size_at = 6 + name_len;
+                let size = u32::from_be_bytes(resources.get(size_at..size_at + 4)?.try_into().ok()?)
+                    as usize;
+                let data = resources.get(size_at + 4..size_at + 4 + size)?;
+                if id == 0x0404 {
+                    return Some(data);
+                }
+                resources = resources.get(size_at + 4 + ((size + 1) & !1)..)?;
+            }
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+/// EXIF `DateTimeOriginal` of `path` as recorded (`YYYY:MM:DD HH:MM:SS`).
+pub fn original_datetime(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut bufreader = std::io::BufReader::new(file);
+    let exif = exif::Reader::new()
+        .read_from_container(&mut bufreader)
+        .ok()?;
+    match exif
+        .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?
+        .value
+    {
+        exif::Value::Ascii(ref v) => v
+            .first()
+            .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Seconds since 1970-01-01 for an EXIF date-time (`YYYY:MM:DD HH:MM:SS`),
+/// or `None` when it is malformed or out of range.
+pub fn parse_datetime(text: &str) -> Option<f64> {
+    let time = exif::DateTime::from_ascii(text.trim().as_bytes()).ok()?;
+    let valid = (1..=12).contains(&time.month)
+        && (1..=31).contains(&time.day)
+        && time.hour < 24
+        && time.minute < 60
+        && time.second < 61;
+    valid.then(|| {
+        timestamp(
+            time.year as i64,
+            time.month as i64,
+            time.day as i64,
+            time.hour as i64 * 3600 + time.minute as i64 * 60 + time.second as i64,
+        )
+    })
+}
+
+/// EXIF date-time (`YYYY:MM:DD HH:MM:SS`) for seconds since 1970-01-01.
+pub fn format_datetime(seconds: i64) -> String {
+    let (days, secs) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    // Civil from days (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}:{month:02}:{day:02} {:02}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// `text` (an EXIF date-time) moved by `delta` seconds.
+pub fn shift_datetime(text: &str, delta: i64) -> Option<String> {
+    parse_datetime(text).map(|t| format_datetime(t as i64 + delta))
+}
+
+/// Capture time from EXIF `DateTimeOriginal` (plus `SubSecTimeOriginal`),
+/// as seconds since 1970-01-01 in the camera's local time.
+pub fn capture_time(path: &Path) -> Option<f64> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut bufreader = std::io::BufReader::new(file);
+    let exif = exif::Reader::new()
+        .read_from_container(&mut bufreader)
+        .ok()?;
+
+    let ascii = |tag| match exif.get_field(tag, exif::In::PRIMARY) {
+        Some(exif::Field {
+            value: exif::Value::Ascii(v),
+            ..
+        }) => v.first().cloned(),
+        _ => None,
+    };
+    let mut time = exif::DateTime::from_ascii(&ascii(exif::Tag::DateTimeOriginal)?).ok()?;
+    if let Some(subsec) = ascii(exif::Tag::SubSecTimeOriginal) {
+        let _ = time.parse_subsec(&subsec);
+    }
+    Some(
+        timestamp(
+            time.year as i64,
+            time.month as i64,
+            time.day as i64,
+            time.hour as i64 * 3600 + time.minute as i64 * 60 + time.second as i64,
+        ) + time.nanosecond.unwrap_or(0) as f64 * 1e-9,
+    )
+}
+
+/// Seconds since 1970-01-01 for a proleptic Gregorian date and time of day.
+pub fn timestamp(year: i64, month: i64, day: i64, seconds_of_day: i64) -> f64 {
+    // Days from civil (Howard Hinnant's algorithm)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    (days * 86_400 + seconds_of_day) as f64
+}
+
+/// Position recorded by the camera's GPS, as latitude and longitude in
+/// degrees.
+pub fn gps_position(path: &Path) -> Option<[f64; 2]> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut bufreader = std::io::BufReader::new(file);
+    let exif = exif::Reader::new()
+        .read_from_container(&mut bufreader)
+        .ok()?;
+
+    let coordinate = |tag, reference, negative: &[u8]| {
+        let exif::Value::Rational(ref dms) = exif.get_field(tag, exif::In::PRIMARY)?.value else {
+            return None;
+        };
+        let degrees = dms
+            .iter()
+            .zip([1.0, 60.0, 3600.0])
+            .map(|(r, unit)| r.to_f64() / unit)
+            .sum::<f64>();
+        let sign = match exif.get_field(reference, exif::In::PRIMARY)?.value {
+            exif::Value::Ascii(ref v) if v.first().map(Vec::as_slice) == Some(negative) => -1.0,
+            _ => 1.0,
+        };
+        degrees.is_finite().then_some(sign * degrees)
+    };
+    Some([
+        coordinate(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, b"S")?,
+        coordinate(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, b"W")?,
+    ])
+}
+
+/// Focus area recorded by the camera (EXIF `SubjectArea`), normalized to
+/// x, y, width, height of the image. A point becomes a small square.
+pub fn focus_area(path: &Path) -> Option<[f32; 4]> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut bufreader = std::io::BufReader::new(file);
+    let exif = exif::Reader::new()
+        .read_from_container(&mut bufreader)
+        .ok()?;
+
+    let dimension = |tag| {
+        exif.get_field(tag, exif::In::PRIMARY)
+            .and_then(|f| f.value.get_uint(0))
+            .filter(|&v| v > 0)
+    };
+    let width = dimension(exif::Tag::PixelXDimension)? as f32;
+    let height = dimension(exif::Tag::PixelYDimension)? as f32;
+    let exif::Value::Short(ref area) = exif
+        .get_field(exif::Tag::SubjectArea, exif::In::PRIMARY)?
+        .value
+    else {
+        return None;
+    };
+    subject_area_rect(area, width, height)
+}
+
+/// Converts `SubjectArea` values (center point, circle diameter or
+/// rectangle size, in pixels) to a normalized rectangle.
+fn subject_area_rect(area: &[u16], width: f32, height: f32) -> Option<[f32; 4]> {
+    let (cx, cy) = (*area.first()? as f32, *area.get(1)? as f32);
+    let (w, h) = match area[2..] {
+        [] => (width * 0.1, width * 0.1),
+        [d] => (d as f32, d as f32),
+        [w, h, ..] => (w as f32, h as f32),
+    };
+    let w = (w / width).clamp(0.02, 1.0);
+    let h = (h / height).clamp(0.02, 1.0);
+    Some([
+        (cx / width - w / 2.0).clamp(0.0, 1.0 - w),
+        (cy / height - h / 2.0).clamp(0.0, 1.0 - h),
+        w,
+        h,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_datetime, iptc_properties, parse_datetime, read_all, shift_datetime,
+        subject_area_rect, timestamp,
+    };
+
+    #[test]
+    fn timestamp_counts_days_since_the_epoch() {
+        assert_eq!(timestamp(1970, 1, 1, 0), 0.0);
+        assert_eq!(timestamp(2000, 3, 1, 3600), 951_868_800.0 + 3600.0);
+    }
+
+    #[test]
+    fn datetimes_round_trip_and_shift_across_dates() {
+        let t = parse_datetime("2024:02:28 23:30:00").unwrap();
+        assert_eq!(format_datetime(t as i64), "2024:02:28 23:30:00");
+        assert_eq!(
+            shift_datetime("2024:02:28 23:30:00", 3600).as_deref(),
+            Some("2024:02:29 00:30:00")
+        );
+        assert_eq!(
+            shift_datetime("2000:01:01 00:00:10", -20).as_deref(),
+            Some("1999:12:31 23:59:50")
+        );
+        assert_eq!(parse_datetime("2024:13:01 00:00:00"), None);
+        assert_eq!(parse_datetime("yesterday"), None);
+    }
+
+    /// A JPEG whose APP13 segment holds the given IPTC datasets, with
+    /// `fill` padding bytes before its marker.
+    fn iptc_jpeg(datasets: &[(u8, &str)], fill: usize) -> Vec<u8> {
+        let mut iim = Vec::new();
+        for (dataset, value) in datasets {
+            iim.extend_from_slice(&[0x1C, 2, *dataset, 0, value.len() as u8]);
+            iim.extend_from_slice(value.as_bytes());
+        }
+        let mut resource = b"Photoshop 3.0\08BIM\x04\x04\0\0".to_vec();
+        resource.extend_from_slice(&(iim.len() as u32).to_be_bytes());
+        resource.extend_from_slice(&iim);
+        let mut jpeg = vec![0xFF, 0xD8];
+        jpeg.extend(std::iter::repeat_n(0xFF, fill));
+        jpeg.extend_from_slice(&[0xFF, 0xED]);
+        jpeg.extend_from_slice(&((resource.len() + 2) as u16).to_be_bytes());
+        jpeg.extend_from_slice(&resource);
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0, 2, 0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn iptc_datasets_are_read_from_app13() {
+        let jpeg = iptc_jpeg(
+            &[(5, "Title"), (25, "cat"), (25, "dog"), (116, "(c) Me")],
+            0,
+        );
+        let props = iptc_properties(&jpeg);
+        let get = |name: &str| {
+            props
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(get("Object Name"), Some("Title"));
+        assert_eq!(get("Keywords"), Some("cat; dog"));
+        assert_eq!(get("Copyright Notice"), Some("(c) Me"));
+    }
+
+    #[test]
+    fn iptc_segments_skip_fill_bytes_and_reject_short_lengths() {
+        let padded = iptc_jpeg(&[(5, "Title")], 3);
+        assert_eq!(
+            iptc_properties(&padded),
+            vec![("Object Name".to_string(), "Title".to_string())]
+        );
+
+        // A segment length below 2 would overlap its own marker
+        let broken = [0xFF, 0xD8, 0xFF, 0xE1, 0, 1, 0xFF, 0xED, 0, 2];
+        assert!(iptc_properties(&broken).is_empty());
+    }
+
+    #[test]
+    fn read_all_lists_size_and_iptc_from_the_file_header() {
+        let jpeg = iptc_jpeg(&[(120, "A caption")], 1);
+        let path = std::env::temp_dir().join(format!("photograph-iptc-{}.jpg", std::process::id()));
+        std::fs::write(&path, &jpeg).unwrap();
+        let tags = read_all(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let find = |group: &str, name: &str| {
+            tags.iter()
+                .find(|t| t.group == group && t.name == name)
+                .map(|t| t.value.as_str())
+        };
+        assert_eq!(find("File", "Size"), Some("0.0 MB"));
+        assert_eq!(find("IPTC", "Caption/Abstract"), Some("A caption"));
+    }
+
+    #[test]
+    fn subject_area_rectangle_is_normalized_around_its_center() {
+        let rect = subject_area_rect(&[3000, 1000, 600, 400], 6000.0, 4000.0).unwrap();
+        for (got, want) in rect.iter().zip([0.45, 0.2, 0.1, 0.1]) {
+            assert!((got - want).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn subject_area_point_stays_inside_the_image() {
+        let rect = subject_area_rect(&[0, 4000], 6000.0, 4000.0).unwrap();
+        assert_eq!(rect[0], 0.0);
+        assert!((rect[1] + rect[3] - 1.0).abs() < 1e-6);
+    }
+}
@@ -1,27 +1,51 @@
-use image::DynamicImage;
+use image::{DynamicImage, Rgba, RgbaImage};
 
-use crate::state::EditState;
+use crate::state::{ColorGrading, EditState, GradeWheel, HslAdjust, Monochrome};
 
 // red, orange, yellow, green, cyan, blue, purple, pink
-const SELECTIVE_CENTERS_DEG: [f32; 8] = [0.0, 30.0, 60.0, 120.0, 180.0, 240.0, 285.0, 330.0];
+pub const SELECTIVE_CENTERS_DEG: [f32; 8] = [0.0, 30.0, 60.0, 120.0, 180.0, 240.0, 285.0, 330.0];
 const SELECTIVE_HALF_WIDTH_DEG: f32 = 30.0;
+/// Limits on a selective color band's half-width, in degrees.
+pub const MIN_BAND_WIDTH_DEG: f32 = 5.0;
+pub const MAX_BAND_WIDTH_DEG: f32 = 90.0;
+/// Soft edge outside a band's luminance and saturation ranges.
+const RANGE_FEATHER: f32 = 0.1;
+/// Rec.709 luma weights used to split tonal ranges and keep tints neutral.
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+/// Full wheel saturation moves a channel at most this far from luma.
+const GRADE_TINT_SCALE: f32 = 0.3;
+/// Full wheel luminance offset.
+const GRADE_LUMINANCE_SCALE: f32 = 0.25;
+/// Brightness change of a fully saturated pixel at a black and white mix of 1.
+const MONO_MIX_SCALE: f32 = 0.5;
+/// Channel offset from gray at full toning.
+const MONO_TONE_SCALE: f32 = 0.25;
+/// Hue range (degrees) that vibrance treats as skin and boosts at half strength.
+const SKIN_CENTER_DEG: f32 = 25.0;
+const SKIN_HALF_WIDTH_DEG: f32 = 25.0;
 
-/// Applies white balance, global HSL, and selective color adjustments.
+/// Applies white balance, global HSL and vibrance, selective color, black and
+/// white conversion, and color grading.
 pub fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
-    let any_selective = state
-        .selective_color
-        .iter()
-        .any(|a| a.hue.abs() > 0.001 || a.saturation.abs() > 0.001 || a.lightness.abs() > 0.001);
+    let any_selective = state.selective_color.iter().any(HslAdjust::is_active);
     if state.temperature.abs() < 0.001
         && state.saturation.abs() < 0.001
+        && state.vibrance.abs() < 0.001
         && state.hue_shift.abs() < 0.001
         && !any_selective
+        && !state.monochrome.enabled
+        && !state.color_grading.is_active()
     {
         return img;
     }
+    let mono = state.monochrome;
+    let tone = mono_tone_offset(&mono);
+    let grading = state.color_grading;
+    let grade_offsets = grade_offsets(&grading);
 
     let temp = state.temperature.clamp(-1.0, 1.0);
     let sat_adjust = state.saturation.clamp(-1.0, 1.0);
+    let vibrance = state.vibrance.clamp(-1.0, 1.0);
     let hue_shift_unit = state.hue_shift / 360.0;
 
     let mut rgba = img.to_rgba8();
@@ -48,14 +72,16 @@ pub fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
         // Global HSL
         h = wrap_unit(h + hue_shift_unit);
         s = (s * (1.0 + sat_adjust)).clamp(0.0, 1.0);
+        if vibrance.abs() >= 0.001 {
+            s = (s * (1.0 + vibrance_amount(vibrance, h, s))).clamp(0.0, 1.0);
+        }
 
         // Selective color by hue ranges.
         for (idx, adj) in state.selective_color.iter().enumerate() {
-            if adj.hue.abs() < 0.001 && adj.saturation.abs() < 0.001 && adj.lightness.abs() < 0.001
-            {
+            if !adj.is_active() {
                 continue;
             }
-            let weight = selective_weight(h, SELECTIVE_CENTERS_DEG[idx], SELECTIVE_HALF_WIDTH_DEG);
+            let weight = band_weight(idx, adj, h, s, l);
             if weight <= 0.0 {
                 continue;
             }
@@ -64,7 +90,25 @@ pub fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
             l = (l + adj.lightness * weight).clamp(0.0, 1.0);
         }
 
-        let (r2, g2, b2) = hsl_to_rgb(h, s, l);
+        let (mut r2, mut g2, mut b2) = hsl_to_rgb(h, s, l);
+        if mono.enabled {
+            let gray = mono_gray(&mono, h, s, [r2, g2, b2]);
+            let toning = 4.0 * gray * (1.0 - gray);
+            r2 = (gray + tone[0] * toning).clamp(0.0, 1.0);
+            g2 = (gray + tone[1] * toning).clamp(0.0, 1.0);
+            b2 = (gray + tone[2] * toning).clamp(0.0, 1.0);
+        }
+        if grading.is_active() {
+            let weights = grade_weights(&grading, r2, g2, b2);
+            for (w, offset) in weights.iter().zip(&grade_offsets) {
+                r2 += w * offset[0];
+                g2 += w * offset[1];
+                b2 += w * offset[2];
+            }
+            r2 = r2.clamp(0.0, 1.0);
+            g2 = g2.clamp(0.0, 1.0);
+            b2 = b2.clamp(0.0, 1.0);
+        }
         px[0] = (r2 * 255.0).round() as u8;
         px[1] = (g2 * 255.0).round() as u8;
         px[2] = (b2 * 255.0).round() as u8;
@@ -73,6 +117,131 @@ pub fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
     DynamicImage::ImageRgba8(rgba)
 }
 
+/// Black and white level: luma plus the per-hue mix, scaled by saturation so
+/// neutral pixels keep their brightness.
+fn mono_gray(mono: &Monochrome, hue_unit: f32, saturation: f32, rgb: [f32; 3]) -> f32 {
+    let mut gray = LUMA[0] * rgb[0] + LUMA[1] * rgb[1] + LUMA[2] * rgb[2];
+    for (idx, &mix) in mono.mix.iter().enumerate() {
+        if mix.abs() < 0.001 {
+            continue;
+        }
+        let weight = selective_weight(
+            hue_unit,
+            SELECTIVE_CENTERS_DEG[idx],
+            SELECTIVE_HALF_WIDTH_DEG,
+        );
+        gray += mix.clamp(-1.0, 1.0) * weight * saturation * MONO_MIX_SCALE;
+    }
+    gray.clamp(0.0, 1.0)
+}
+
+/// Luma-neutral RGB offset for black and white toning, applied fully in the
+/// midtones and fading out toward black and white.
+pub fn mono_tone_offset(mono: &Monochrome) -> [f32; 3] {
+    let (r, g, b) = hsl_to_rgb(wrap_unit(mono.tone_hue / 360.0), 1.0, 0.5);
+    let y = LUMA[0] * r + LUMA[1] * g + LUMA[2] * b;
+    let amount = mono.tone_amount.clamp(0.0, 1.0) * MONO_TONE_SCALE;
+    [(r - y) * amount, (g - y) * amount, (b - y) * amount]
+}
+
+/// Saturation gain for vibrance: strongest on muted colors, halved on skin hues.
+fn vibrance_amount(vibrance: f32, hue_unit: f32, saturation: f32) -> f32 {
+    let skin = selective_weight(hue_unit, SKIN_CENTER_DEG, SKIN_HALF_WIDTH_DEG);
+    vibrance * (1.0 - saturation) * (1.0 - 0.5 * skin)
+}
+
+/// Per-wheel RGB offsets (shadows, midtones, highlights): a luma-neutral
+/// tint plus the luminance shift.
+pub fn grade_offsets(grading: &ColorGrading) -> [[f32; 3]; 3] {
+    let offset = |wheel: &GradeWheel| {
+        let (r, g, b) = hsl_to_rgb(wrap_unit(wheel.hue / 360.0), 1.0, 0.5);
+        let y = LUMA[0] * r + LUMA[1] * g + LUMA[2] * b;
+        let tint = wheel.saturation.clamp(0.0, 1.0) * GRADE_TINT_SCALE;
+        let lum = wheel.luminance.clamp(-1.0, 1.0) * GRADE_LUMINANCE_SCALE;
+        [
+            (r - y) * tint + lum,
+            (g - y) * tint + lum,
+            (b - y) * tint + lum,
+        ]
+    };
+    [
+        offset(&grading.shadows),
+        offset(&grading.midtones),
+        offset(&grading.highlights),
+    ]
+}
+
+/// How much each wheel (shadows, midtones, highlights) applies to a pixel.
+fn grade_weights(grading: &ColorGrading, r: f32, g: f32, b: f32) -> [f32; 3] {
+    let luma = LUMA[0] * r + LUMA[1] * g + LUMA[2] * b;
+    let l = (luma + grading.balance.clamp(-1.0, 1.0) * 0.25).clamp(0.0, 1.0);
+    let width = 0.1 + grading.blending.clamp(0.0, 1.0) * 0.3;
+    let shadows = 1.0 - smoothstep(1.0 / 3.0 - width, 1.0 / 3.0 + width, l);
+    let highlights = smoothstep(2.0 / 3.0 - width, 2.0 / 3.0 + width, l);
+    [shadows, (1.0 - shadows - highlights).max(0.0), highlights]
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Center and half-width in degrees of selective color band `idx`.
+pub fn band_hue(idx: usize, adj: &HslAdjust) -> (f32, f32) {
+    let center = (SELECTIVE_CENTERS_DEG[idx] + adj.center_shift).rem_euclid(360.0);
+    (
+        center,
+        adj.width.clamp(MIN_BAND_WIDTH_DEG, MAX_BAND_WIDTH_DEG),
+    )
+}
+
+/// How strongly band `idx` selects a pixel: its hue weight, narrowed by the
+/// luminance and saturation qualifiers. Mirrored in `COLOR_SHADER_SRC`.
+fn band_weight(idx: usize, adj: &HslAdjust, h: f32, s: f32, l: f32) -> f32 {
+    let (center, width) = band_hue(idx, adj);
+    let weight = selective_weight(h, center, width);
+    if weight <= 0.0 {
+        return 0.0;
+    }
+    weight * range_weight(l, adj.luminance_range) * range_weight(s, adj.saturation_range)
+}
+
+fn range_weight(v: f32, [lo, hi]: [f32; 2]) -> f32 {
+    let outside = (lo - v).max(v - hi).max(0.0);
+    (1.0 - outside / RANGE_FEATHER).max(0.0)
+}
+
+/// The selective color band whose hue best matches `rgb` as it reaches the
+/// selective color stage, for the target adjustment tool. Neutral colors
+/// have no band.
+pub fn band_at(state: &EditState, rgb: [u8; 3]) -> Option<usize> {
+    let probe = EditState {
+        temperature: state.temperature,
+        saturation: state.saturation,
+        vibrance: state.vibrance,
+        hue_shift: state.hue_shift,
+        ..EditState::default()
+    };
+    let px = RgbaImage::from_pixel(1, 1, Rgba([rgb[0], rgb[1], rgb[2], 255]));
+    let px = apply(DynamicImage::ImageRgba8(px), &probe).to_rgba8();
+    let [r, g, b, _] = px.get_pixel(0, 0).0.map(|v| v as f32 / 255.0);
+    let (h, s, _) = rgb_to_hsl(r, g, b);
+    if s < 0.05 {
+        return None;
+    }
+    state
+        .selective_color
+        .iter()
+        .enumerate()
+        .map(|(idx, adj)| {
+            let (center, width) = band_hue(idx, adj);
+            (idx, selective_weight(h, center, width))
+        })
+        .filter(|&(_, w)| w > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(idx, _)| idx)
+}
+
 fn selective_weight(hue_unit: f32, center_deg: f32, half_width_deg: f32) -> f32 {
     let hue_deg = wrap_unit(hue_unit) * 360.0;
     let dist = hue_distance_deg(hue_deg, center_deg);
@@ -158,7 +327,7 @@ mod tests {
 
     use crate::state::EditState;
 
-    use super::apply;
+    use super::{apply, band_at};
 
     fn one_pixel(rgb: [u8; 3]) -> DynamicImage {
         DynamicImage::ImageRgba8(ImageBuffer::from_pixel(
@@ -199,4 +368,130 @@ mod tests {
         let rgb = pixel_rgb(&out);
         assert!((rgb[0] as i32 - rgb[1] as i32).abs() < (255 - 32));
     }
+
+    #[test]
+    fn shifted_narrow_band_isolates_its_hue() {
+        let mut state = EditState::default();
+        // Orange band moved onto skin tones, between red and orange
+        let band = &mut state.selective_color[1];
+        band.saturation = -1.0;
+        band.center_shift = -16.0;
+        band.width = 10.0;
+        let skin = pixel_rgb(&apply(one_pixel([230, 115, 80]), &state));
+        assert!(skin[0] - skin[2] < 100, "{skin:?}");
+        let yellowish = [230, 192, 80];
+        assert_eq!(pixel_rgb(&apply(one_pixel(yellowish), &state)), yellowish);
+    }
+
+    #[test]
+    fn luminance_range_limits_the_selection() {
+        let mut state = EditState::default();
+        state.selective_color[0].saturation = -1.0;
+        state.selective_color[0].luminance_range = [0.0, 0.3];
+        let dark = pixel_rgb(&apply(one_pixel([120, 10, 10]), &state));
+        assert!(dark[0] - dark[1] < 20, "{dark:?}");
+        let bright = [255, 120, 120];
+        assert_eq!(pixel_rgb(&apply(one_pixel(bright), &state)), bright);
+    }
+
+    #[test]
+    fn band_at_picks_the_closest_band() {
+        let mut state = EditState::default();
+        assert_eq!(band_at(&state, [230, 140, 40]), Some(1));
+        assert_eq!(band_at(&state, [40, 60, 230]), Some(5));
+        assert_eq!(band_at(&state, [128, 128, 128]), None);
+        state.selective_color[1].center_shift = -25.0;
+        assert_eq!(band_at(&state, [230, 60, 40]), Some(1));
+    }
+
+    #[test]
+    fn vibrance_favors_muted_colors() {
+        let state = EditState {
+            vibrance: 1.0,
+            ..EditState::default()
+        };
+        let muted = pixel_rgb(&apply(one_pixel([100, 120, 140]), &state));
+        let vivid = pixel_rgb(&apply(one_pixel([20, 60, 235]), &state));
+        // Relative growth of the blue-red spread
+        let gain = |rgb: [u8; 3], before: f32| (rgb[2] as f32 - rgb[0] as f32) / before;
+        assert!(gain(muted, 40.0) > 1.5, "{muted:?}");
+        assert!(gain(vivid, 215.0) < 1.2, "{vivid:?}");
+    }
+
+    #[test]
+    fn vibrance_spares_skin_tones() {
+        let state = EditState {
+            vibrance: 1.0,
+            ..EditState::default()
+        };
+        let skin = pixel_rgb(&apply(one_pixel([200, 150, 120]), &state));
+        let sky = pixel_rgb(&apply(one_pixel([120, 150, 200]), &state));
+        assert!(skin[0] as i32 - skin[2] as i32 <= sky[2] as i32 - sky[0] as i32 - 4);
+    }
+
+    #[test]
+    fn monochrome_mix_brightens_its_hue_band() {
+        let mut state = EditState::default();
+        state.monochrome.enabled = true;
+        let plain = pixel_rgb(&apply(one_pixel([40, 60, 220]), &state));
+        assert!(plain[0] == plain[1] && plain[1] == plain[2], "{plain:?}");
+
+        state.monochrome.mix[5] = 1.0; // blue band
+        let lifted = pixel_rgb(&apply(one_pixel([40, 60, 220]), &state));
+        assert!(lifted[0] > plain[0] + 40, "{lifted:?}");
+        // Neutral grays ignore the mix
+        let gray = pixel_rgb(&apply(one_pixel([120, 120, 120]), &state));
+        assert_eq!(gray, [120, 120, 120]);
+    }
+
+    #[test]
+    fn monochrome_toning_tints_midtones_only() {
+        let mut state = EditState::default();
+        state.monochrome.enabled = true;
+        state.monochrome.tone_hue = 35.0;
+        state.monochrome.tone_amount = 1.0;
+        let mid = pixel_rgb(&apply(one_pixel([128, 128, 128]), &state));
+        assert!(mid[0] > mid[2] + 20, "{mid:?}");
+        assert_eq!(pixel_rgb(&apply(one_pixel([0, 0, 0]), &state)), [0, 0, 0]);
+    }
+
+    #[test]
+    fn grading_tints_only_the_targeted_range() {
+        let mut state = EditState::default();
+        state.color_grading.shadows.hue = 220.0;
+        state.color_grading.shadows.saturation = 1.0;
+        state.color_grading.blending = 0.0;
+
+        let dark = pixel_rgb(&apply(one_pixel([40, 40, 40]), &state));
+        assert!(dark[2] > dark[0] + 10, "{dark:?}");
+        let bright = pixel_rgb(&apply(one_pixel([230, 230, 230]), &state));
+        assert_eq!(bright, [230, 230, 230]);
+    }
+
+    #[test]
+    fn balance_moves_the_split() {
+        let mut state = EditState::default();
+        state.color_grading.highlights.hue = 40.0;
+        state.color_grading.highlights.saturation = 1.0;
+        let mid = one_pixel([128, 128, 128]);
+        let neutral = pixel_rgb(&apply(mid.clone(), &state));
+
+        state.color_grading.balance = 1.0;
+        let favored = pixel_rgb(&apply(mid, &state));
+        assert!(
+            favored[0] - favored[2] > neutral[0] - neutral[2],
+            "{neutral:?} vs {favored:?}"
+        );
+    }
+
+    #[test]
+    fn grading_luminance_brightens_midtones() {
+        let mut state = EditState::default();
+        state.color_grading.midtones.luminance = 0.5;
+        let out = pixel_rgb(&apply(one_pixel([128, 128, 128]), &state));
+        assert!(
+            out[0] > 140 && out[0] == out[1] && out[1] == out[2],
+            "{out:?}"
+        );
+    }
 }
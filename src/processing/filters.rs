@@ -1,58 +1,79 @@
 use image::DynamicImage;
 
-use crate::state::EditState;
+use crate::state::{EditState, GradFilter, MaskShape};
 
-/// Applies the graduated exposure filter, if configured.
-pub fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
-    let Some(grad) = state.graduated_filter.as_ref() else {
-        return img;
-    };
-    if grad.exposure.abs() < 0.001 {
-        return img;
-    }
+use super::local::{self, Tone};
+use super::transform::Region;
 
-    let top = grad.top.clamp(0.0, 1.0);
-    let bottom = grad.bottom.clamp(0.0, 1.0);
-    if bottom <= top + 0.0001 {
+/// Applies the graduated filters in order. Each filter is a linear mask from
+/// `start` (full strength) to `end` (no effect) at any angle, placed in the
+/// frame `region` places `img` in.
+pub fn apply(img: DynamicImage, state: &EditState, region: &Region) -> DynamicImage {
+    let active: Vec<&GradFilter> = state
+        .graduated_filters
+        .iter()
+        .filter(|grad| grad.is_active())
+        .collect();
+    if active.is_empty() {
         return img;
     }
 
-    let exposure = grad.exposure.clamp(-5.0, 5.0);
     let mut rgba = img.to_rgba8();
-    let h = rgba.height().max(1);
-    let h_denom = (h - 1).max(1) as f32;
-
-    for (y, row) in rgba.enumerate_rows_mut() {
-        let y_norm = y as f32 / h_denom;
-        let weight = if y_norm <= top {
-            1.0
-        } else if y_norm >= bottom {
-            0.0
-        } else {
-            (bottom - y_norm) / (bottom - top)
-        };
-        if weight <= 0.0 {
-            continue;
-        }
-        let gain = 2.0_f32.powf(exposure * weight);
-        for (_, _, px) in row {
-            for c in 0..3 {
-                let v = px[c] as f32 / 255.0;
-                px[c] = (v * gain).clamp(0.0, 1.0).mul_add(255.0, 0.0).round() as u8;
+    let filters: Vec<(local::ResolvedMask, Tone)> = active
+        .iter()
+        .map(|grad| {
+            let mask = MaskShape::Linear {
+                start: grad.start,
+                end: grad.end,
+            };
+            (local::resolve_in(&mask, region), Tone::from(*grad))
+        })
+        .collect();
+
+    for (x, y, px) in rgba.enumerate_pixels_mut() {
+        let mut rgb = [
+            px[0] as f32 / 255.0,
+            px[1] as f32 / 255.0,
+            px[2] as f32 / 255.0,
+        ];
+        for (mask, tone) in &filters {
+            let weight = local::weight_at(mask, x as f32 + 0.5, y as f32 + 0.5);
+            if weight > 0.0 {
+                rgb = local::apply_tone(rgb, tone, weight);
             }
         }
+        for c in 0..3 {
+            px[c] = (rgb[c] * 255.0).round() as u8;
+        }
     }
 
     DynamicImage::ImageRgba8(rgba)
 }
 
+impl From<&GradFilter> for Tone {
+    fn from(grad: &GradFilter) -> Self {
+        Self {
+            exposure: grad.exposure,
+            contrast: grad.contrast,
+            highlights: grad.highlights,
+            temperature: grad.temperature,
+            saturation: grad.saturation,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use image::{DynamicImage, ImageBuffer, Rgba};
 
-    use crate::state::{EditState, GradFilter};
+    use crate::processing::transform::Region;
+    use crate::state::{EditState, GradFilter, NormPoint};
 
-    use super::apply;
+    /// Runs the stage over the whole of `img`.
+    fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
+        let region = Region::whole(img.width(), img.height());
+        super::apply(img, state, &region)
+    }
 
     #[test]
     fn negative_exposure_darkens_top_more_than_bottom() {
@@ -60,13 +81,70 @@ mod tests {
             Rgba([200, 200, 200, 255])
         }));
         let mut state = EditState::default();
-        state.graduated_filter = Some(GradFilter {
-            top: 0.0,
-            bottom: 1.0,
-            exposure: -1.0,
-        });
+        let mut grad = GradFilter::new(NormPoint { x: 0.5, y: 0.0 }, NormPoint { x: 0.5, y: 1.0 });
+        grad.exposure = -1.0;
+        state.graduated_filters.push(grad);
 
         let out = apply(img, &state).to_rgba8();
         assert!(out.get_pixel(0, 0)[0] < out.get_pixel(0, 1)[0]);
     }
+
+    #[test]
+    fn rotated_filter_follows_its_angle() {
+        // Left-to-right gradient: left column darkened, right column untouched
+        let img =
+            DynamicImage::ImageRgba8(ImageBuffer::from_pixel(10, 10, Rgba([200, 200, 200, 255])));
+        let mut state = EditState::default();
+        let mut grad = GradFilter::new(NormPoint { x: 0.0, y: 0.5 }, NormPoint { x: 0.5, y: 0.5 });
+        grad.exposure = -1.0;
+        state.graduated_filters.push(grad);
+
+        let out = apply(img, &state).to_rgba8();
+        assert!(out.get_pixel(0, 5)[0] < 120);
+        assert_eq!(out.get_pixel(0, 0), out.get_pixel(0, 9));
+        assert_eq!(out.get_pixel(9, 5)[0], 200);
+    }
+
+    #[test]
+    fn filters_stack_and_carry_color_adjustments() {
+        let img =
+            DynamicImage::ImageRgba8(ImageBuffer::from_pixel(4, 4, Rgba([120, 120, 120, 255])));
+        let mut state = EditState::default();
+        // Gradient starts below the image, so the whole frame is at full strength
+        let full = || GradFilter::new(NormPoint { x: 0.5, y: 2.0 }, NormPoint { x: 0.5, y: 3.0 });
+        let mut warm = full();
+        warm.temperature = 1.0;
+        let mut desat = full();
+        desat.saturation = -1.0;
+        state.graduated_filters = vec![warm.clone()];
+        let warmed = apply(img.clone(), &state).to_rgba8();
+        assert!(warmed.get_pixel(1, 1)[0] > warmed.get_pixel(1, 1)[2]);
+
+        state.graduated_filters = vec![warm, desat];
+        let neutral = apply(img, &state).to_rgba8();
+        let px = neutral.get_pixel(1, 1);
+        assert_eq!(px[0], px[2]);
+    }
+
+    #[test]
+    fn highlights_only_touch_bright_pixels() {
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgba([40, 40, 40, 255])
+            } else {
+                Rgba([230, 230, 230, 255])
+            }
+        }));
+        let mut state = EditState::default();
+        let mut grad = GradFilter::new(NormPoint { x: 0.5, y: -1.0 }, NormPoint { x: 0.5, y: 0.0 });
+        grad.highlights = -1.0;
+        state.graduated_filters.push(grad);
+        // The mask ends above the image, so nothing changes...
+        assert_eq!(apply(img.clone(), &state).to_rgba8(), img.to_rgba8());
+
+        state.graduated_filters[0].end = NormPoint { x: 0.5, y: 5.0 };
+        let out = apply(img, &state).to_rgba8();
+        assert_eq!(out.get_pixel(0, 0)[0], 40);
+        assert!(out.get_pixel(1, 0)[0] < 230);
+    }
 }
@@ -0,0 +1,134 @@
+use image::{DynamicImage, GrayImage, RgbaImage};
+
+/// Longest edge the sharpness score is measured at, so scores from previews
+/// of different sizes stay comparable.
+pub const SCORE_EDGE: u32 = 1024;
+/// Laplacian magnitude (8-bit luma levels) above which focus peaking marks a
+/// pixel as in focus.
+pub const PEAKING_THRESHOLD: f32 = 28.0;
+/// Frames scoring below this fraction of the sharpest frame in the folder
+/// are flagged as likely out of focus.
+pub const SOFT_FRACTION: f32 = 0.3;
+/// Scores below this are flagged regardless of the other frames.
+pub const SOFT_FLOOR: f32 = 15.0;
+/// Measured region when the file records no focus area: the central 40%.
+const CENTER_REGION: [f32; 4] = [0.3, 0.3, 0.4, 0.4];
+/// Smallest measured region, in pixels per side.
+const MIN_REGION: u32 = 32;
+
+/// Sharpness of `img` as the variance of its luma Laplacian inside `region`
+/// (normalized x, y, width, height; the center when `None`). Higher is
+/// sharper; the value is only meaningful relative to other frames.
+pub fn sharpness_score(img: &DynamicImage, region: Option<[f32; 4]>) -> f32 {
+    let gray = if img.width().max(img.height()) > SCORE_EDGE {
+        img.thumbnail(SCORE_EDGE, SCORE_EDGE).to_luma8()
+    } else {
+        img.to_luma8()
+    };
+    let (w, h) = gray.dimensions();
+    if w < 3 || h < 3 {
+        return 0.0;
+    }
+
+    let [rx, ry, rw, rh] = region.unwrap_or(CENTER_REGION);
+    let side = |len: f32, size: u32| {
+        ((len * size as f32).round() as u32)
+            .max(MIN_REGION)
+            .min(size - 2)
+    };
+    let (rw, rh) = (side(rw, w), side(rh, h));
+    let x0 = ((rx * w as f32).round() as u32).clamp(1, w - 1 - rw);
+    let y0 = ((ry * h as f32).round() as u32).clamp(1, h - 1 - rh);
+
+    let mut sum = 0.0f64;
+    let mut sum_sq = 0.0f64;
+    for y in y0..y0 + rh {
+        for x in x0..x0 + rw {
+            let l = laplacian(&gray, x, y) as f64;
+            sum += l;
+            sum_sq += l * l;
+        }
+    }
+    let n = (rw * rh) as f64;
+    let mean = sum / n;
+    (sum_sq / n - mean * mean).max(0.0) as f32
+}
+
+/// Whether `score` marks a likely out-of-focus frame, given the best score
+/// among the frames it is culled against.
+pub fn is_soft(score: f32, best: f32) -> bool {
+    score < SOFT_FLOOR || score < best * SOFT_FRACTION
+}
+
+/// Focus-peaking overlay for `img`: `color` on pixels with strong fine
+/// detail, transparent elsewhere.
+pub fn peaking_overlay(img: &RgbaImage, color: [u8; 3]) -> RgbaImage {
+    let (w, h) = img.dimensions();
+    let gray = DynamicImage::ImageRgba8(img.clone()).to_luma8();
+    RgbaImage::from_fn(w, h, |x, y| {
+        let edge = x > 0 && y > 0 && x + 1 < w && y + 1 < h && {
+            laplacian(&gray, x, y).abs() > PEAKING_THRESHOLD
+        };
+        let alpha = if edge { 255 } else { 0 };
+        image::Rgba([color[0], color[1], color[2], alpha])
+    })
+}
+
+/// 4-neighbour Laplacian at an interior pixel.
+fn laplacian(gray: &GrayImage, x: u32, y: u32) -> f32 {
+    let at = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f32;
+    at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
+
+    use super::{is_soft, peaking_overlay, sharpness_score};
+
+    fn checkerboard(size: u32, block: u32) -> RgbaImage {
+        ImageBuffer::from_fn(size, size, |x, y| {
+            let v = if (x / block + y / block).is_multiple_of(2) {
+                40
+            } else {
+                220
+            };
+            Rgba([v, v, v, 255])
+        })
+    }
+
+    #[test]
+    fn blurred_frames_score_lower() {
+        let sharp = checkerboard(128, 4);
+        let soft = image::imageops::blur(&sharp, 2.5);
+        let sharp_score = sharpness_score(&DynamicImage::ImageRgba8(sharp), None);
+        let soft_score = sharpness_score(&DynamicImage::ImageRgba8(soft), None);
+        assert!(sharp_score > soft_score * 4.0);
+        assert!(is_soft(soft_score, sharp_score));
+        assert!(!is_soft(sharp_score, sharp_score));
+    }
+
+    #[test]
+    fn score_follows_the_focus_region() {
+        // Detail on the left, flat on the right
+        let mut img = checkerboard(128, 4);
+        for y in 0..128 {
+            for x in 64..128 {
+                img.put_pixel(x, y, Rgba([128, 128, 128, 255]));
+            }
+        }
+        let img = DynamicImage::ImageRgba8(img);
+        let left = sharpness_score(&img, Some([0.1, 0.3, 0.3, 0.4]));
+        let right = sharpness_score(&img, Some([0.6, 0.3, 0.3, 0.4]));
+        assert!(left > 100.0);
+        assert_eq!(right, 0.0);
+    }
+
+    #[test]
+    fn peaking_marks_edges_only() {
+        let overlay = peaking_overlay(&checkerboard(32, 8), [255, 0, 0]);
+        // On a block boundary vs inside a flat block
+        assert_eq!(overlay.get_pixel(8, 4)[3], 255);
+        assert_eq!(overlay.get_pixel(4, 4)[3], 0);
+    }
+}
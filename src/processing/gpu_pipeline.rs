@@ -6,7 +6,9 @@ use std::sync::{
 
 use image::{DynamicImage, RgbaImage};
 
-use crate::state::EditState;
+use crate::state::{EditState, HslAdjust, LutInterpolation};
+
+use super::{color, grain, icc, lut, presence, spots, transform};
 
 pub const DEBUG_ALLOW_CPU_FALLBACK_ENV: &str = "PHOTOGRAPH_DEBUG_ALLOW_CPU_FALLBACK";
 const STATE_EPS: f32 = 0.001;
@@ -24,6 +26,13 @@ struct GpuContext {
     geometry: OnceLock<PipelineBundle>,
     blur_h: OnceLock<PipelineBundle>,
     blur_v_usm: OnceLock<PipelineBundle>,
+    local: OnceLock<PipelineBundle>,
+    spots: OnceLock<PipelineBundle>,
+    dehaze_min: OnceLock<PipelineBundle>,
+    dehaze_airlight: OnceLock<PipelineBundle>,
+    dehaze: OnceLock<PipelineBundle>,
+    local_contrast: OnceLock<PipelineBundle>,
+    grain: OnceLock<PipelineBundle>,
     adapter_name: String,
     adapter_backend: String,
     adapter_driver: String,
@@ -97,6 +106,99 @@ impl GpuContext {
             )
         })
     }
+
+    fn local(&self) -> &PipelineBundle {
+        self.local.get_or_init(|| {
+            let [src, dst, params] = tex_storage_uniform_entries();
+            let entries = [
+                src,
+                dst,
+                params,
+                storage_entry(3),
+                storage_entry(4),
+                storage_entry(5),
+            ];
+            create_pipeline_bundle(&self.device, "gpu_local", LOCAL_SHADER_SRC, &entries)
+        })
+    }
+
+    fn spots(&self) -> &PipelineBundle {
+        self.spots.get_or_init(|| {
+            let [src, dst, params] = tex_storage_uniform_entries();
+            let entries = [src, dst, params, storage_entry(3), storage_entry(4)];
+            create_pipeline_bundle(&self.device, "gpu_spots", SPOTS_SHADER_SRC, &entries)
+        })
+    }
+
+    fn dehaze_min(&self) -> &PipelineBundle {
+        self.dehaze_min.get_or_init(|| {
+            let [src, dst, params] = tex_storage_uniform_entries();
+            let entries = [src, dst, params, storage_rw_entry(3)];
+            create_pipeline_bundle(
+                &self.device,
+                "gpu_dehaze_min",
+                DEHAZE_MIN_SHADER_SRC,
+                &entries,
+            )
+        })
+    }
+
+    fn dehaze_airlight(&self) -> &PipelineBundle {
+        self.dehaze_airlight.get_or_init(|| {
+            let entries = [
+                texture_entry(0),
+                texture_entry(1),
+                uniform_entry(2),
+                storage_rw_entry(3),
+            ];
+            create_pipeline_bundle(
+                &self.device,
+                "gpu_dehaze_airlight",
+                DEHAZE_AIRLIGHT_SHADER_SRC,
+                &entries,
+            )
+        })
+    }
+
+    fn dehaze(&self) -> &PipelineBundle {
+        self.dehaze.get_or_init(|| {
+            let [_, dst, _] = tex_storage_uniform_entries();
+            let entries = [
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry { binding: 2, ..dst },
+                uniform_entry(3),
+                storage_entry(4),
+            ];
+            create_pipeline_bundle(&self.device, "gpu_dehaze", DEHAZE_SHADER_SRC, &entries)
+        })
+    }
+
+    fn local_contrast(&self) -> &PipelineBundle {
+        self.local_contrast.get_or_init(|| {
+            let [_, dst, _] = tex_storage_uniform_entries();
+            let entries = [
+                texture_entry(0),
+                texture_entry(1),
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry { binding: 3, ..dst },
+                uniform_entry(4),
+            ];
+            create_pipeline_bundle(
+                &self.device,
+                "gpu_local_contrast",
+                LOCAL_CONTRAST_SHADER_SRC,
+                &entries,
+            )
+        })
+    }
+
+    fn grain(&self) -> &PipelineBundle {
+        self.grain.get_or_init(|| {
+            let entries = tex_storage_uniform_entries();
+            create_pipeline_bundle(&self.device, "gpu_grain", GRAIN_SHADER_SRC, &entries)
+        })
+    }
 }
 
 static GPU_CONTEXT: OnceLock<Option<GpuContext>> = OnceLock::new();
@@ -195,14 +297,7 @@ fn has_geometry(state: &EditState) -> bool {
 }
 
 fn has_gpu_adjustments(state: &EditState) -> bool {
-    let grad_active = state
-        .graduated_filter
-        .as_ref()
-        .map(|grad| grad.exposure.abs() > STATE_EPS && grad.bottom > grad.top + 0.0001)
-        .unwrap_or(false);
-    let selective_active = state.selective_color.iter().any(|a| {
-        a.hue.abs() > STATE_EPS || a.saturation.abs() > STATE_EPS || a.lightness.abs() > STATE_EPS
-    });
+    let selective_active = state.selective_color.iter().any(HslAdjust::is_active);
     state.exposure.abs() > STATE_EPS
         || state.contrast.abs() > STATE_EPS
         || state.highlights.abs() > STATE_EPS
@@ -210,34 +305,173 @@ fn has_gpu_adjustments(state: &EditState) -> bool {
         || state.temperature.abs() > STATE_EPS
         || state.saturation.abs() > STATE_EPS
         || state.hue_shift.abs() > STATE_EPS
-        || grad_active
         || selective_active
+        || state.vibrance.abs() > STATE_EPS
+        || state.vignette.is_active()
+        || state.monochrome.enabled
+        || state.color_grading.is_active()
+        || lut::active(state).is_some()
+        || has_presence(state)
         || state.sharpness > STATE_EPS
+        || state.grain.is_active()
+        || has_local_adjustments(state)
         || has_geometry(state)
+        || !state.spots.is_empty()
+}
+
+/// Dehaze, clarity and texture run as extra passes after the color pass.
+fn has_presence(state: &EditState) -> bool {
+    state.dehaze.abs() > STATE_EPS
+        || state.clarity.abs() > STATE_EPS
+        || state.texture.abs() > STATE_EPS
+}
+
+/// Graduated filters and masked local adjustments both run in the local pass.
+fn has_local_adjustments(state: &EditState) -> bool {
+    state.graduated_filters.iter().any(|grad| grad.is_active())
+        || state.local_adjustments.iter().any(|adj| adj.is_active())
+}
+
+/// Flattened storage-buffer contents for the local adjustment pass.
+struct LocalBuffers {
+    /// Four vec4s per filter/adjustment: shape, style, tone, detail (see `LOCAL_SHADER_SRC`).
+    adjustments: Vec<f32>,
+    /// One vec4 per brush stroke: first dab, dab count, erase, pad.
+    strokes: Vec<f32>,
+    /// One vec4 per dab: x, y, radius, feather (pixels).
+    dabs: Vec<f32>,
+}
+
+/// Packs graduated filters (as linear masks) followed by local adjustments,
+/// matching the CPU order `filters::apply` → `local::apply`.
+fn build_local_buffers(state: &EditState, w: u32, h: u32) -> LocalBuffers {
+    use super::local::{ResolvedMask, resolve};
+
+    let mut buffers = LocalBuffers {
+        adjustments: Vec::new(),
+        strokes: Vec::new(),
+        dabs: Vec::new(),
+    };
+    for grad in state
+        .graduated_filters
+        .iter()
+        .filter(|grad| grad.is_active())
+    {
+        let (wf, hf) = (w as f32, h as f32);
+        buffers.adjustments.extend_from_slice(&[
+            grad.start.x * wf,
+            grad.start.y * hf,
+            grad.end.x * wf,
+            grad.end.y * hf,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            grad.exposure.clamp(-5.0, 5.0),
+            grad.contrast.clamp(-1.0, 1.0),
+            grad.temperature.clamp(-1.0, 1.0),
+            grad.saturation.clamp(-1.0, 1.0),
+            0.0,
+            grad.highlights.clamp(-1.0, 1.0),
+            0.0,
+            0.0,
+        ]);
+    }
+    for adj in state.local_adjustments.iter().filter(|adj| adj.is_active()) {
+        let (kind, shape, style) = match resolve(&adj.mask, w, h) {
+            ResolvedMask::Linear { start, end } => {
+                (0.0, [start[0], start[1], end[0], end[1]], [0.0; 3])
+            }
+            ResolvedMask::Radial {
+                center,
+                radius,
+                angle,
+                feather,
+                invert,
+            } => (
+                1.0,
+                [center[0], center[1], radius[0], radius[1]],
+                [angle, feather, if invert { 1.0 } else { 0.0 }],
+            ),
+            ResolvedMask::Brush { strokes } => {
+                let first_stroke = (buffers.strokes.len() / 4) as f32;
+                for stroke in &strokes {
+                    let first_dab = (buffers.dabs.len() / 4) as f32;
+                    buffers.strokes.extend_from_slice(&[
+                        first_dab,
+                        stroke.dabs.len() as f32,
+                        if stroke.erase { 1.0 } else { 0.0 },
+                        0.0,
+                    ]);
+                    buffers.dabs.extend(stroke.dabs.iter().flatten());
+                }
+                (
+                    2.0,
+                    [first_stroke, strokes.len() as f32, 0.0, 0.0],
+                    [0.0; 3],
+                )
+            }
+        };
+        buffers.adjustments.extend_from_slice(&shape);
+        buffers
+            .adjustments
+            .extend_from_slice(&[kind, style[0], style[1], style[2]]);
+        buffers.adjustments.extend_from_slice(&[
+            adj.exposure.clamp(-5.0, 5.0),
+            adj.contrast.clamp(-1.0, 1.0),
+            adj.temperature.clamp(-1.0, 1.0),
+            adj.saturation.clamp(-1.0, 1.0),
+        ]);
+        buffers
+            .adjustments
+            .extend_from_slice(&[adj.sharpness.clamp(-1.0, 1.0), 0.0, 0.0, 0.0]);
+    }
+    buffers
+}
+
+/// Flattened storage-buffer contents for the spot pass.
+struct SpotBuffers {
+    /// Two vec4s per spot: center.xy, radius, feather | offset.xy, heal, pad.
+    spots: Vec<f32>,
+    /// `HEAL_RING_SAMPLES` vec4s per spot holding rim color differences (zero for clone spots).
+    rings: Vec<f32>,
 }
 
-/// Compute output dimensions after geometry transforms (rotation + crop).
-fn compute_geometry_output_dims(state: &EditState, src_w: u32, src_h: u32) -> (u32, u32) {
-    let (mut w, mut h) = match state.rotate.rem_euclid(360) {
-        90 | 270 => (src_h, src_w),
-        _ => (src_w, src_h),
+fn build_spot_buffers(src: &RgbaImage, state: &EditState) -> SpotBuffers {
+    let (w, h) = src.dimensions();
+    let mut buffers = SpotBuffers {
+        spots: Vec::new(),
+        rings: Vec::new(),
     };
-    if let Some(ref crop) = state.crop {
-        // Match CPU: (crop.x * w) as u32, then cw = (crop.width * w).min(w - cx)
-        let cx = (crop.x * w as f32) as u32;
-        let cy = (crop.y * h as f32) as u32;
-        let cw = (crop.width * w as f32).min(w as f32 - cx as f32) as u32;
-        let ch = (crop.height * h as f32).min(h as f32 - cy as f32) as u32;
-        if cw > 0 && ch > 0 {
-            w = cw;
-            h = ch;
+    for spot in &state.spots {
+        let resolved = spots::resolve(spot, w, h);
+        buffers.spots.extend_from_slice(&[
+            resolved.center[0],
+            resolved.center[1],
+            resolved.radius,
+            resolved.feather,
+            resolved.offset[0] as f32,
+            resolved.offset[1] as f32,
+            if resolved.heal { 1.0 } else { 0.0 },
+            0.0,
+        ]);
+        if resolved.heal {
+            for diff in spots::heal_ring(src, &resolved) {
+                buffers
+                    .rings
+                    .extend_from_slice(&[diff[0], diff[1], diff[2], 0.0]);
+            }
+        } else {
+            buffers
+                .rings
+                .extend(std::iter::repeat_n(0.0, spots::HEAL_RING_SAMPLES * 4));
         }
     }
-    (w.max(1), h.max(1))
+    buffers
 }
 
 /// Compute the 3×3 inverse perspective matrix for dst→src mapping.
-/// Replicates the control-point logic from transform.rs:apply_keystone.
+/// Uses the same control points as transform.rs:apply_keystone.
 /// Returns [f32; 9] in row-major order, or identity if no keystone.
 fn compute_perspective_matrix(state: &EditState, w: f32, h: f32) -> [f32; 9] {
     let v = state.keystone.vertical;
@@ -246,31 +480,11 @@ fn compute_perspective_matrix(state: &EditState, w: f32, h: f32) -> [f32; 9] {
         return [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
     }
 
-    // Source corners: TL, TR, BR, BL (same as CPU)
-    let src: [(f64, f64); 4] = [
-        (0.0, 0.0),
-        (w as f64, 0.0),
-        (w as f64, h as f64),
-        (0.0, h as f64),
-    ];
-
-    // Destination corners with perspective shifts
-    let v = v as f64;
-    let hz = hz as f64;
-    let w64 = w as f64;
-    let h64 = h as f64;
-    let dst: [(f64, f64); 4] = [
-        (v.max(0.0) * w64, hz.max(0.0) * h64),
-        (w64 - v.max(0.0) * w64, (-hz).max(0.0) * h64),
-        (w64 - (-v).max(0.0) * w64, h64 - (-hz).max(0.0) * h64),
-        ((-v).max(0.0) * w64, h64 - hz.max(0.0) * h64),
-    ];
-
     // imageproc's from_control_points(src, dst) creates P mapping input→output.
     // warp() internally inverts P to get output→input for sampling.
     // Our GPU shader applies the matrix directly to output coords to get input coords,
     // so we need the inverse: dst→src (output corners → input sample locations).
-    if let Some(mat) = compute_homography(&dst, &src) {
+    if let Some(mat) = transform::keystone_homography(&state.keystone, w as f64, h as f64, true) {
         [
             mat[0] as f32,
             mat[1] as f32,
@@ -287,68 +501,6 @@ fn compute_perspective_matrix(state: &EditState, w: f32, h: f32) -> [f32; 9] {
     }
 }
 
-/// Compute 3×3 homography mapping src corners → dst corners using DLT.
-/// Returns None if the system is degenerate.
-fn compute_homography(src: &[(f64, f64); 4], dst: &[(f64, f64); 4]) -> Option<[f64; 9]> {
-    // Build 8×8 system Ah = b where h = [h0..h7], h8 = 1
-    // For each point pair (sx,sy) → (dx,dy):
-    //   sx = (h0*dx + h1*dy + h2) / (h6*dx + h7*dy + 1)
-    //   sy = (h3*dx + h4*dy + h5) / (h6*dx + h7*dy + 1)
-    // Rearranged:
-    //   h0*dx + h1*dy + h2 - h6*dx*sx - h7*dy*sx = sx
-    //   h3*dx + h4*dy + h5 - h6*dx*sy - h7*dy*sy = sy
-    let mut a = [[0.0_f64; 8]; 8];
-    let mut b = [0.0_f64; 8];
-
-    for i in 0..4 {
-        let (dx, dy) = src[i]; // "from" coords
-        let (sx, sy) = dst[i]; // "to" coords
-        let row1 = i * 2;
-        let row2 = i * 2 + 1;
-        a[row1] = [dx, dy, 1.0, 0.0, 0.0, 0.0, -dx * sx, -dy * sx];
-        b[row1] = sx;
-        a[row2] = [0.0, 0.0, 0.0, dx, dy, 1.0, -dx * sy, -dy * sy];
-        b[row2] = sy;
-    }
-
-    // Gaussian elimination with partial pivoting
-    for col in 0..8 {
-        // Find pivot
-        let mut max_row = col;
-        let mut max_val = a[col][col].abs();
-        for row in (col + 1)..8 {
-            if a[row][col].abs() > max_val {
-                max_val = a[row][col].abs();
-                max_row = row;
-            }
-        }
-        if max_val < 1e-12 {
-            return None;
-        }
-        if max_row != col {
-            a.swap(col, max_row);
-            b.swap(col, max_row);
-        }
-        let pivot = a[col][col];
-        for j in col..8 {
-            a[col][j] /= pivot;
-        }
-        b[col] /= pivot;
-        for row in 0..8 {
-            if row == col {
-                continue;
-            }
-            let factor = a[row][col];
-            for j in col..8 {
-                a[row][j] -= factor * a[col][j];
-            }
-            b[row] -= factor * b[col];
-        }
-    }
-
-    Some([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7], 1.0])
-}
-
 fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
     let Some(ctx) = gpu_context() else {
         report_gpu_fallback_once();
@@ -363,10 +515,14 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
     };
     let needs_geometry = has_geometry(state);
     let needs_sharpness = state.sharpness > STATE_EPS;
+    let needs_local = has_local_adjustments(state);
+    let needs_spots = !state.spots.is_empty();
+    let needs_presence = has_presence(state);
+    let needs_grain = state.grain.is_active();
 
     // Compute output dimensions after geometry
     let (out_w, out_h) = if needs_geometry {
-        compute_geometry_output_dims(state, src_w, src_h)
+        transform::output_dims(state, src_w, src_h)
     } else {
         (src_w, src_h)
     };
@@ -404,7 +560,102 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
             label: Some("gpu_pipeline_encoder"),
         });
 
-    // The texture that feeds into the color pass — either geometry output or src
+    // Storage bindings must not be empty; pad unused buffers to one vec4.
+    let storage_buffer = |label: &str, values: &[f32]| {
+        let padded: Vec<f32> = if values.is_empty() {
+            vec![0.0; 4]
+        } else {
+            values.to_vec()
+        };
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: std::mem::size_of_val(padded.as_slice()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        ctx.queue.write_buffer(&buffer, 0, f32s_as_bytes(&padded));
+        buffer
+    };
+
+    // Spot retouching runs on the source, before geometry
+    let base_texture = if needs_spots {
+        let spot_out = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("gpu_pipeline_spot_out"),
+            size: src_extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let src_view = src_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let spot_out_view = spot_out.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let buffers = build_spot_buffers(src, state);
+        let spot_params: [f32; 4] = [
+            src_w as f32,
+            src_h as f32,
+            (buffers.spots.len() / 8) as f32,
+            0.0,
+        ];
+        let spot_params_buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu_pipeline_spot_params"),
+            size: std::mem::size_of_val(&spot_params) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        ctx.queue
+            .write_buffer(&spot_params_buffer, 0, f32s_as_bytes(&spot_params));
+        let spots_buffer = storage_buffer("gpu_pipeline_spots", &buffers.spots);
+        let rings_buffer = storage_buffer("gpu_pipeline_spot_rings", &buffers.rings);
+
+        let spots_bundle = ctx.spots();
+        let spots_bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gpu_pipeline_spots_bg"),
+            layout: &spots_bundle.bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&src_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&spot_out_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: spot_params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: spots_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: rings_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("gpu_pipeline_spots_pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&spots_bundle.pipeline);
+            pass.set_bind_group(0, &spots_bg, &[]);
+            pass.dispatch_workgroups(
+                src_w.div_ceil(WORKGROUP_SIZE),
+                src_h.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        spot_out
+    } else {
+        src_texture
+    };
+
+    // The texture that feeds into the color pass — either geometry output or the (retouched) source
     let color_input_texture;
     let color_input_view;
 
@@ -493,7 +744,7 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
         ctx.queue
             .write_buffer(&geo_params_buffer, 0, f32s_as_bytes(&geo_params));
 
-        let src_view = src_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let src_view = base_texture.create_view(&wgpu::TextureViewDescriptor::default());
         let geo_out_view = geo_out.create_view(&wgpu::TextureViewDescriptor::default());
 
         let geo_bundle = ctx.geometry();
@@ -532,14 +783,14 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
         color_input_view = geo_out_view;
         color_input_texture = geo_out;
     } else {
-        color_input_view = src_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        color_input_texture = src_texture;
+        color_input_view = base_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        color_input_texture = base_texture;
     };
     // Keep color_input_texture alive (it owns the GPU memory)
     let _color_input_texture = color_input_texture;
 
-    // Color output — needs TEXTURE_BINDING when sharpness follows
-    let color_out_usage = if needs_sharpness {
+    // Color output — needs TEXTURE_BINDING when any later pass reads it
+    let color_out_usage = if needs_presence || needs_local || needs_sharpness || needs_grain {
         wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING
     } else {
         wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC
@@ -556,21 +807,7 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
     });
 
     // Build color params uniform (uses output dimensions)
-    let (grad_enabled, grad_top, grad_bottom, grad_exposure) = state
-        .graduated_filter
-        .as_ref()
-        .and_then(|grad| {
-            let top = grad.top.clamp(0.0, 1.0);
-            let bottom = grad.bottom.clamp(0.0, 1.0);
-            if grad.exposure.abs() <= STATE_EPS || bottom <= top + 0.0001 {
-                None
-            } else {
-                Some((1.0_f32, top, bottom, grad.exposure.clamp(-5.0, 5.0)))
-            }
-        })
-        .unwrap_or((0.0, 0.0, 1.0, 0.0));
-
-    let mut params: [f32; 40] = [0.0; 40];
+    let mut params: [f32; 176] = [0.0; 176];
     params[0] = out_w as f32;
     params[1] = out_h as f32;
     params[2] = state.exposure;
@@ -580,14 +817,55 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
     params[6] = state.temperature;
     params[7] = state.saturation;
     params[8] = state.hue_shift;
-    params[9] = grad_enabled;
-    params[10] = grad_top;
-    params[11] = grad_bottom;
-    params[12] = grad_exposure;
+    params[9] = state.vibrance;
     for (i, adj) in state.selective_color.iter().enumerate() {
         params[16 + i * 3] = adj.hue;
         params[16 + i * 3 + 1] = adj.saturation;
         params[16 + i * 3 + 2] = adj.lightness;
+        let (center, width) = color::band_hue(i, adj);
+        let [lum_lo, lum_hi] = adj.luminance_range;
+        let [sat_lo, sat_hi] = adj.saturation_range;
+        params[112 + i * 8..118 + i * 8]
+            .copy_from_slice(&[center, width, lum_lo, lum_hi, sat_lo, sat_hi]);
+    }
+    if state.color_grading.is_active() {
+        for (i, offset) in color::grade_offsets(&state.color_grading)
+            .iter()
+            .enumerate()
+        {
+            params[76 + i * 4..79 + i * 4].copy_from_slice(offset);
+        }
+        params[88] = state.color_grading.balance;
+        params[89] = state.color_grading.blending;
+        params[90] = 1.0;
+    }
+    if state.monochrome.enabled {
+        params[92..100].copy_from_slice(&state.monochrome.mix);
+        params[100..103].copy_from_slice(&color::mono_tone_offset(&state.monochrome));
+        params[103] = 1.0;
+    }
+    if state.vignette.is_active() {
+        let v = &state.vignette;
+        params[104..108].copy_from_slice(&[v.amount, v.midpoint, v.roundness, v.feather]);
+        params[108..112].copy_from_slice(&transform::vignette_frame(state, src_w, src_h));
+    }
+    let active_lut = lut::active(state);
+    if let Some((lut, strength, interpolation)) = &active_lut {
+        params[40] = lut.size as f32;
+        params[41] = *strength;
+        params[42] = match interpolation {
+            LutInterpolation::Tetrahedral => 0.0,
+            LutInterpolation::Trilinear => 1.0,
+        };
+        params[43] = 1.0;
+        params[44..47].copy_from_slice(&lut.domain_min);
+        params[48..51].copy_from_slice(&lut.domain_max);
+        for (i, row) in icc::working_to_rec709().iter().enumerate() {
+            params[52 + i * 4..55 + i * 4].copy_from_slice(row);
+        }
+        for (i, row) in icc::rec709_to_working().iter().enumerate() {
+            params[64 + i * 4..67 + i * 4].copy_from_slice(row);
+        }
     }
     let color_params_buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
         label: Some("gpu_pipeline_color_params"),
@@ -598,6 +876,49 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
     ctx.queue
         .write_buffer(&color_params_buffer, 0, f32s_as_bytes(&params));
 
+    // Look LUT as a 3D texture; a 2³ placeholder keeps the binding valid when unused
+    let (lut_size, lut_texels) = match &active_lut {
+        Some((lut, ..)) => (
+            lut.size as u32,
+            lut.data
+                .iter()
+                .flat_map(|[r, g, b]| [*r, *g, *b, 1.0])
+                .collect(),
+        ),
+        None => (2, vec![0.0; 2 * 2 * 2 * 4]),
+    };
+    let lut_extent = wgpu::Extent3d {
+        width: lut_size,
+        height: lut_size,
+        depth_or_array_layers: lut_size,
+    };
+    let lut_texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("gpu_pipeline_lut"),
+        size: lut_extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D3,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    ctx.queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &lut_texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        f32s_as_bytes(&lut_texels),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(lut_size * 16),
+            rows_per_image: Some(lut_size),
+        },
+        lut_extent,
+    );
+    let lut_view = lut_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
     let color_out_view = color_out_texture.create_view(&wgpu::TextureViewDescriptor::default());
     let color_bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: Some("gpu_pipeline_color_bg"),
@@ -615,6 +936,10 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
                 binding: 2,
                 resource: color_params_buffer.as_entire_binding(),
             },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&lut_view),
+            },
         ],
     });
 
@@ -633,8 +958,108 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
         );
     }
 
+    // Dehaze and clarity/texture passes
+    let (graded_texture, graded_view) = if needs_presence {
+        encode_presence(ctx, &mut encoder, state, &color_out_view, out_w, out_h)
+    } else {
+        (color_out_texture, color_out_view)
+    };
+
+    // Local adjustment pass (masked tone + sharpness)
+    let (adjusted_texture, adjusted_view) = if needs_local {
+        let local_out_usage = if needs_sharpness || needs_grain {
+            wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING
+        } else {
+            wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC
+        };
+        let local_out_texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("gpu_pipeline_local_out"),
+            size: out_extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: local_out_usage,
+            view_formats: &[],
+        });
+        let local_out_view = local_out_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let buffers = build_local_buffers(state, out_w, out_h);
+        let local_params: [f32; 4] = [
+            out_w as f32,
+            out_h as f32,
+            (buffers.adjustments.len() / 16) as f32,
+            0.0,
+        ];
+        let local_params_buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu_pipeline_local_params"),
+            size: std::mem::size_of_val(&local_params) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        ctx.queue
+            .write_buffer(&local_params_buffer, 0, f32s_as_bytes(&local_params));
+        let adjustments_buffer =
+            storage_buffer("gpu_pipeline_local_adjustments", &buffers.adjustments);
+        let strokes_buffer = storage_buffer("gpu_pipeline_local_strokes", &buffers.strokes);
+        let dabs_buffer = storage_buffer("gpu_pipeline_local_dabs", &buffers.dabs);
+
+        let local_bundle = ctx.local();
+        let local_bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gpu_pipeline_local_bg"),
+            layout: &local_bundle.bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&graded_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&local_out_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: local_params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: adjustments_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: strokes_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: dabs_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("gpu_pipeline_local_pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&local_bundle.pipeline);
+            pass.set_bind_group(0, &local_bg, &[]);
+            pass.dispatch_workgroups(
+                out_w.div_ceil(WORKGROUP_SIZE),
+                out_h.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        (local_out_texture, local_out_view)
+    } else {
+        (graded_texture, graded_view)
+    };
+
     // Sharpness passes (using output dimensions)
-    let final_texture = if needs_sharpness {
+    let sharpened_texture = if needs_sharpness {
+        let usm_out_usage = if needs_grain {
+            wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING
+        } else {
+            wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC
+        };
         let blur_h_texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
             label: Some("gpu_pipeline_blur_h"),
             size: out_extent,
@@ -652,11 +1077,20 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            usage: usm_out_usage,
             view_formats: &[],
         });
 
-        let blur_params: [f32; 4] = [out_w as f32, out_h as f32, state.sharpness, 0.0];
+        let blur_params: [f32; 8] = [
+            out_w as f32,
+            out_h as f32,
+            state.sharpness,
+            state.sharpen_radius.max(super::sharpness::MIN_RADIUS),
+            state.sharpen_threshold.max(0.0),
+            state.sharpen_masking,
+            0.0,
+            0.0,
+        ];
         let blur_params_buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("gpu_pipeline_blur_params"),
             size: std::mem::size_of_val(&blur_params) as u64,
@@ -678,7 +1112,7 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&color_out_view),
+                    resource: wgpu::BindingResource::TextureView(&adjusted_view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
@@ -714,7 +1148,7 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&color_out_view),
+                    resource: wgpu::BindingResource::TextureView(&adjusted_view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 2,
@@ -742,7 +1176,77 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
 
         usm_out_texture
     } else {
-        color_out_texture
+        adjusted_texture
+    };
+
+    // Film grain pass, last so it sits on top of sharpening
+    let final_texture = if needs_grain {
+        let grain_out_texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("gpu_pipeline_grain_out"),
+            size: out_extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let grain_params: [f32; 8] = [
+            out_w as f32,
+            out_h as f32,
+            state.grain.amount.clamp(0.0, 1.0) * grain::GRAIN_STRENGTH,
+            grain::cell_size(&state.grain, out_w, out_h),
+            state.grain.roughness.clamp(0.0, 1.0),
+            0.0,
+            0.0,
+            0.0,
+        ];
+        let grain_params_buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu_pipeline_grain_params"),
+            size: std::mem::size_of_val(&grain_params) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        ctx.queue
+            .write_buffer(&grain_params_buffer, 0, f32s_as_bytes(&grain_params));
+
+        let sharpened_view = sharpened_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let grain_out_view = grain_out_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let grain_bundle = ctx.grain();
+        let grain_bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gpu_pipeline_grain_bg"),
+            layout: &grain_bundle.bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&sharpened_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&grain_out_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: grain_params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("gpu_pipeline_grain_pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&grain_bundle.pipeline);
+            pass.set_bind_group(0, &grain_bg, &[]);
+            pass.dispatch_workgroups(
+                out_w.div_ceil(WORKGROUP_SIZE),
+                out_h.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        grain_out_texture
+    } else {
+        sharpened_texture
     };
 
     // Readback
@@ -814,13 +1318,249 @@ fn apply_gpu(src: &RgbaImage, state: &EditState) -> Option<RgbaImage> {
     output
 }
 
-fn gpu_context() -> Option<&'static GpuContext> {
-    GPU_CONTEXT.get_or_init(init_gpu_context).as_ref()
-}
-
-fn create_pipeline_bundle(
-    device: &wgpu::Device,
-    label: &str,
+/// Encodes dehaze and then clarity/texture, mirroring `presence::apply`.
+/// Returns the last output texture and its view.
+fn encode_presence(
+    ctx: &GpuContext,
+    encoder: &mut wgpu::CommandEncoder,
+    state: &EditState,
+    input: &wgpu::TextureView,
+    out_w: u32,
+    out_h: u32,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let scales = presence::scales(out_w, out_h);
+    let target = |label: &str| {
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: out_w,
+                height: out_h,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    };
+    let uniform = |label: &str, values: &[f32]| {
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: std::mem::size_of_val(values) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        ctx.queue.write_buffer(&buffer, 0, f32s_as_bytes(values));
+        buffer
+    };
+    let bind = |label: &str, bundle: &PipelineBundle, resources: &[wgpu::BindingResource]| {
+        let entries: Vec<_> = resources
+            .iter()
+            .enumerate()
+            .map(|(binding, resource)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: resource.clone(),
+            })
+            .collect();
+        ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: &bundle.bgl,
+            entries: &entries,
+        })
+    };
+    let dispatch = |encoder: &mut wgpu::CommandEncoder,
+                    label: &str,
+                    bundle: &PipelineBundle,
+                    bind_group: &wgpu::BindGroup| {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some(label),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&bundle.pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.dispatch_workgroups(
+            out_w.div_ceil(WORKGROUP_SIZE),
+            out_h.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    };
+    let blur_h =
+        |encoder: &mut wgpu::CommandEncoder, label: &str, src: &wgpu::TextureView, sigma| {
+            let (texture, view) = target(label);
+            let params = uniform(
+                label,
+                &[out_w as f32, out_h as f32, 0.0, sigma, 0.0, 0.0, 0.0, 0.0],
+            );
+            let bundle = ctx.blur_h();
+            let bg = bind(
+                label,
+                bundle,
+                &[
+                    wgpu::BindingResource::TextureView(src),
+                    wgpu::BindingResource::TextureView(&view),
+                    params.as_entire_binding(),
+                ],
+            );
+            dispatch(encoder, label, bundle, &bg);
+            (texture, view)
+        };
+
+    let mut current: Option<(wgpu::Texture, wgpu::TextureView)> = None;
+
+    let dehaze = state.dehaze.clamp(-1.0, 1.0);
+    if dehaze.abs() > STATE_EPS {
+        // Histogram of the dark channel (256 bins), then the airlight (3 channels)
+        let haze_buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu_pipeline_haze"),
+            size: (259 * std::mem::size_of::<u32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let patch = scales.dehaze_patch as f32;
+        let min_bundle = ctx.dehaze_min();
+        let mut src = input.clone();
+        let mut dark = None;
+        for (axis, label) in [(0.0, "gpu_pipeline_dark_h"), (1.0, "gpu_pipeline_dark")] {
+            let (texture, view) = target(label);
+            let params = uniform(label, &[out_w as f32, out_h as f32, patch, axis]);
+            let bg = bind(
+                label,
+                min_bundle,
+                &[
+                    wgpu::BindingResource::TextureView(&src),
+                    wgpu::BindingResource::TextureView(&view),
+                    params.as_entire_binding(),
+                    haze_buffer.as_entire_binding(),
+                ],
+            );
+            dispatch(encoder, label, min_bundle, &bg);
+            src = view.clone();
+            dark = Some((texture, view));
+        }
+        let (_dark_texture, dark_view) = dark.expect("two min passes");
+
+        let airlight_bundle = ctx.dehaze_airlight();
+        let airlight_params = uniform(
+            "gpu_pipeline_airlight",
+            &[out_w as f32, out_h as f32, presence::AIRLIGHT_FRACTION, 0.0],
+        );
+        let airlight_bg = bind(
+            "gpu_pipeline_airlight",
+            airlight_bundle,
+            &[
+                wgpu::BindingResource::TextureView(input),
+                wgpu::BindingResource::TextureView(&dark_view),
+                airlight_params.as_entire_binding(),
+                haze_buffer.as_entire_binding(),
+            ],
+        );
+        dispatch(
+            encoder,
+            "gpu_pipeline_airlight",
+            airlight_bundle,
+            &airlight_bg,
+        );
+
+        let (_dark_blur_texture, dark_blur_view) = blur_h(
+            encoder,
+            "gpu_pipeline_dark_blur_h",
+            &dark_view,
+            scales.dehaze_sigma,
+        );
+        let (texture, view) = target("gpu_pipeline_dehaze_out");
+        let dehaze_params = uniform(
+            "gpu_pipeline_dehaze",
+            &[out_w as f32, out_h as f32, dehaze, scales.dehaze_sigma],
+        );
+        let dehaze_bundle = ctx.dehaze();
+        let dehaze_bg = bind(
+            "gpu_pipeline_dehaze",
+            dehaze_bundle,
+            &[
+                wgpu::BindingResource::TextureView(&dark_blur_view),
+                wgpu::BindingResource::TextureView(input),
+                wgpu::BindingResource::TextureView(&view),
+                dehaze_params.as_entire_binding(),
+                haze_buffer.as_entire_binding(),
+            ],
+        );
+        dispatch(encoder, "gpu_pipeline_dehaze", dehaze_bundle, &dehaze_bg);
+        current = Some((texture, view));
+    }
+
+    let clarity = state.clarity.clamp(-1.0, 1.0);
+    let texture_amount = state.texture.clamp(-1.0, 1.0);
+    if clarity.abs() > STATE_EPS || texture_amount.abs() > STATE_EPS {
+        let src = current
+            .as_ref()
+            .map(|(_, view)| view.clone())
+            .unwrap_or_else(|| input.clone());
+        // An inactive scale binds the source itself; the shader skips it
+        let coarse = (clarity.abs() > STATE_EPS).then(|| {
+            blur_h(
+                encoder,
+                "gpu_pipeline_clarity_h",
+                &src,
+                scales.clarity_sigma,
+            )
+        });
+        let fine = (texture_amount.abs() > STATE_EPS).then(|| {
+            blur_h(
+                encoder,
+                "gpu_pipeline_texture_h",
+                &src,
+                scales.texture_sigma,
+            )
+        });
+        let coarse_view = coarse.as_ref().map_or(&src, |(_, view)| view);
+        let fine_view = fine.as_ref().map_or(&src, |(_, view)| view);
+
+        let (texture, view) = target("gpu_pipeline_local_contrast_out");
+        let params = uniform(
+            "gpu_pipeline_local_contrast",
+            &[
+                out_w as f32,
+                out_h as f32,
+                clarity,
+                texture_amount,
+                scales.clarity_sigma,
+                scales.texture_sigma,
+                0.0,
+                0.0,
+            ],
+        );
+        let bundle = ctx.local_contrast();
+        let bg = bind(
+            "gpu_pipeline_local_contrast",
+            bundle,
+            &[
+                wgpu::BindingResource::TextureView(coarse_view),
+                wgpu::BindingResource::TextureView(fine_view),
+                wgpu::BindingResource::TextureView(&src),
+                wgpu::BindingResource::TextureView(&view),
+                params.as_entire_binding(),
+            ],
+        );
+        dispatch(encoder, "gpu_pipeline_local_contrast", bundle, &bg);
+        current = Some((texture, view));
+    }
+
+    current.expect("presence passes run only when one is active")
+}
+
+fn gpu_context() -> Option<&'static GpuContext> {
+    GPU_CONTEXT.get_or_init(init_gpu_context).as_ref()
+}
+
+fn create_pipeline_bundle(
+    device: &wgpu::Device,
+    label: &str,
     shader_src: &str,
     bgl_entries: &[wgpu::BindGroupLayoutEntry],
 ) -> PipelineBundle {
@@ -884,6 +1624,62 @@ fn tex_storage_uniform_entries() -> [wgpu::BindGroupLayoutEntry; 3] {
     ]
 }
 
+/// Unfilterable 2D texture layout entry at `binding`.
+fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+/// Uniform buffer layout entry at `binding`.
+fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// Read-write storage buffer layout entry at `binding`, for atomics.
+fn storage_rw_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// Read-only storage buffer layout entry at `binding`.
+fn storage_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
 fn adapter_type_priority(device_type: wgpu::DeviceType) -> Option<u8> {
     match device_type {
         wgpu::DeviceType::DiscreteGpu => Some(0),
@@ -940,8 +1736,19 @@ fn init_gpu_context() -> Option<GpuContext> {
     }))
     .ok()?;
 
-    let standard_entries = tex_storage_uniform_entries();
-    let color = create_pipeline_bundle(&device, "gpu_color", COLOR_SHADER_SRC, &standard_entries);
+    let [src, dst, params] = tex_storage_uniform_entries();
+    let lut_entry = wgpu::BindGroupLayoutEntry {
+        binding: 3,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D3,
+            multisampled: false,
+        },
+        count: None,
+    };
+    let color_entries = [src, dst, params, lut_entry];
+    let color = create_pipeline_bundle(&device, "gpu_color", COLOR_SHADER_SRC, &color_entries);
 
     Some(GpuContext {
         device,
@@ -950,6 +1757,13 @@ fn init_gpu_context() -> Option<GpuContext> {
         geometry: OnceLock::new(),
         blur_h: OnceLock::new(),
         blur_v_usm: OnceLock::new(),
+        local: OnceLock::new(),
+        spots: OnceLock::new(),
+        dehaze_min: OnceLock::new(),
+        dehaze_airlight: OnceLock::new(),
+        dehaze: OnceLock::new(),
+        local_contrast: OnceLock::new(),
+        grain: OnceLock::new(),
         adapter_name,
         adapter_backend,
         adapter_driver,
@@ -984,13 +1798,13 @@ struct Params {
     temperature: f32,
     saturation: f32,
     hue_shift: f32,
-    grad_enabled: f32,
-    grad_top: f32,
-    grad_bottom: f32,
-    grad_exposure: f32,
-    _pad0: f32,
+    vibrance: f32,
     _pad1: f32,
     _pad2: f32,
+    _pad3: f32,
+    _pad4: f32,
+    _pad5: f32,
+    _pad6: f32,
     // 8 selective color ranges × 3 (hue, saturation, lightness)
     sel_hue_0: f32, sel_sat_0: f32, sel_light_0: f32,
     sel_hue_1: f32, sel_sat_1: f32, sel_light_1: f32,
@@ -1000,6 +1814,29 @@ struct Params {
     sel_hue_5: f32, sel_sat_5: f32, sel_light_5: f32,
     sel_hue_6: f32, sel_sat_6: f32, sel_light_6: f32,
     sel_hue_7: f32, sel_sat_7: f32, sel_light_7: f32,
+    // Look LUT: size, strength, interpolation (0 tetrahedral, 1 trilinear), enabled
+    lut: vec4<f32>,
+    lut_min: vec4<f32>,
+    lut_max: vec4<f32>,
+    // Linear working → Rec.709 rows, then Rec.709 → working rows
+    to_lut_0: vec4<f32>, to_lut_1: vec4<f32>, to_lut_2: vec4<f32>,
+    from_lut_0: vec4<f32>, from_lut_1: vec4<f32>, from_lut_2: vec4<f32>,
+    // Color grading RGB offsets per range (see `color::grade_offsets`)
+    grade_shadows: vec4<f32>,
+    grade_midtones: vec4<f32>,
+    grade_highlights: vec4<f32>,
+    // balance, blending, enabled, pad
+    grade: vec4<f32>,
+    // Black and white per-hue mix, then toning RGB offset + enabled
+    mono_mix_0: vec4<f32>,
+    mono_mix_1: vec4<f32>,
+    mono: vec4<f32>,
+    // amount, midpoint, roundness, feather; then the frame (origin, size)
+    vignette: vec4<f32>,
+    vignette_frame: vec4<f32>,
+    // Per selective band: (center, half-width, luminance range),
+    // then (saturation range, pad)
+    sel_band: array<vec4<f32>, 16>,
 };
 
 @group(0) @binding(0)
@@ -1008,6 +1845,8 @@ var src_tex: texture_2d<f32>;
 var dst_tex: texture_storage_2d<rgba8unorm, write>;
 @group(0) @binding(2)
 var<uniform> params: Params;
+@group(0) @binding(3)
+var lut_tex: texture_3d<f32>;
 
 fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
     let t = clamp((x - edge0) / (edge1 - edge0), 0.0, 1.0);
@@ -1018,6 +1857,73 @@ fn wrap_unit(v: f32) -> f32 {
     return fract(v + 1000.0);
 }
 
+fn srgb_decode(v: vec3<f32>) -> vec3<f32> {
+    let lo = v / 12.92;
+    let hi = pow((max(v, vec3<f32>(0.0)) + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(hi, lo, v <= vec3<f32>(0.04045));
+}
+
+fn srgb_encode(v: vec3<f32>) -> vec3<f32> {
+    let lo = v * 12.92;
+    let hi = 1.055 * pow(max(v, vec3<f32>(0.0)), vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(hi, lo, v <= vec3<f32>(0.0031308));
+}
+
+// Moves sRGB-curve encoded values between primaries with a linear matrix.
+fn convert_primaries(rgb: vec3<f32>, r0: vec4<f32>, r1: vec4<f32>, r2: vec4<f32>) -> vec3<f32> {
+    let lin = srgb_decode(rgb);
+    let out = vec3<f32>(dot(r0.xyz, lin), dot(r1.xyz, lin), dot(r2.xyz, lin));
+    return srgb_encode(clamp(out, vec3<f32>(0.0), vec3<f32>(1.0)));
+}
+
+fn lut_at(base: vec3<i32>, offset: vec3<i32>) -> vec3<f32> {
+    return textureLoad(lut_tex, base + offset, 0).rgb;
+}
+
+// Mirrors `Lut3d::sample`.
+fn sample_lut(rgb: vec3<f32>) -> vec3<f32> {
+    let n = i32(params.lut.x);
+    let max_i = f32(n - 1);
+    let span = max(params.lut_max.xyz - params.lut_min.xyz, vec3<f32>(1e-6));
+    let t = clamp((rgb - params.lut_min.xyz) / span, vec3<f32>(0.0), vec3<f32>(1.0)) * max_i;
+    let base = min(vec3<i32>(floor(t)), vec3<i32>(n - 2));
+    let f = t - vec3<f32>(base);
+
+    if (params.lut.z > 0.5) {
+        let c00 = mix(lut_at(base, vec3<i32>(0, 0, 0)), lut_at(base, vec3<i32>(1, 0, 0)), f.x);
+        let c10 = mix(lut_at(base, vec3<i32>(0, 1, 0)), lut_at(base, vec3<i32>(1, 1, 0)), f.x);
+        let c01 = mix(lut_at(base, vec3<i32>(0, 0, 1)), lut_at(base, vec3<i32>(1, 0, 1)), f.x);
+        let c11 = mix(lut_at(base, vec3<i32>(0, 1, 1)), lut_at(base, vec3<i32>(1, 1, 1)), f.x);
+        return mix(mix(c00, c10, f.y), mix(c01, c11, f.y), f.z);
+    }
+
+    var w1: f32;
+    var w2: f32;
+    var w3: f32;
+    var o1: vec3<i32>;
+    var o2: vec3<i32>;
+    if (f.x > f.y) {
+        if (f.y > f.z) {
+            w1 = f.x; o1 = vec3<i32>(1, 0, 0); w2 = f.y; o2 = vec3<i32>(1, 1, 0); w3 = f.z;
+        } else if (f.x > f.z) {
+            w1 = f.x; o1 = vec3<i32>(1, 0, 0); w2 = f.z; o2 = vec3<i32>(1, 0, 1); w3 = f.y;
+        } else {
+            w1 = f.z; o1 = vec3<i32>(0, 0, 1); w2 = f.x; o2 = vec3<i32>(1, 0, 1); w3 = f.y;
+        }
+    } else if (f.z > f.y) {
+        w1 = f.z; o1 = vec3<i32>(0, 0, 1); w2 = f.y; o2 = vec3<i32>(0, 1, 1); w3 = f.x;
+    } else if (f.z > f.x) {
+        w1 = f.y; o1 = vec3<i32>(0, 1, 0); w2 = f.z; o2 = vec3<i32>(0, 1, 1); w3 = f.x;
+    } else {
+        w1 = f.y; o1 = vec3<i32>(0, 1, 0); w2 = f.x; o2 = vec3<i32>(1, 1, 0); w3 = f.z;
+    }
+    let c000 = lut_at(base, vec3<i32>(0, 0, 0));
+    let c111 = lut_at(base, vec3<i32>(1, 1, 1));
+    let p1 = lut_at(base, o1);
+    let p2 = lut_at(base, o2);
+    return c000 + w1 * (p1 - c000) + w2 * (p2 - p1) + w3 * (c111 - p2);
+}
+
 fn rgb_to_hsl(rgb: vec3<f32>) -> vec3<f32> {
     let max_c = max(rgb.r, max(rgb.g, rgb.b));
     let min_c = min(rgb.r, min(rgb.g, rgb.b));
@@ -1071,6 +1977,43 @@ fn selective_weight(hue_unit: f32, center_deg: f32, half_width: f32) -> f32 {
     return 1.0 - (dist / half_width);
 }
 
+fn range_weight(v: f32, range: vec2<f32>) -> f32 {
+    let outside = max(max(range.x - v, v - range.y), 0.0);
+    return max(1.0 - outside / 0.1, 0.0);
+}
+
+// Mirrors `color::band_weight`
+fn band_weight(band: u32, hsl: vec3<f32>) -> f32 {
+    let shape = params.sel_band[band * 2u];
+    let sat_range = params.sel_band[band * 2u + 1u].xy;
+    let w = selective_weight(hsl.x, shape.x, shape.y);
+    if (w <= 0.0) {
+        return 0.0;
+    }
+    return w * range_weight(hsl.z, shape.zw) * range_weight(hsl.y, sat_range);
+}
+
+fn vignette_norm(a: f32, b: f32, p: f32) -> f32 {
+    return pow(pow(max(abs(a), 1e-6), p) + pow(max(abs(b), 1e-6), p), 1.0 / p);
+}
+
+// Mirrors `vignette::weight`
+fn vignette_weight(pos: vec2<f32>) -> f32 {
+    let frame = params.vignette_frame;
+    let uv = (pos - frame.xy) / frame.zw * 2.0 - 1.0;
+    let roundness = clamp(params.vignette.z, -1.0, 1.0);
+    let long = max(frame.z, frame.w);
+    let scale = 1.0 + (frame.zw / long - 1.0) * max(roundness, 0.0);
+    let p = 2.0 + max(-roundness, 0.0) * 6.0;
+    let d = vignette_norm(uv.x * scale.x, uv.y * scale.y, p) / vignette_norm(scale.x, scale.y, p);
+    let midpoint = clamp(params.vignette.y, 0.0, 1.0);
+    let feather = clamp(params.vignette.w, 0.0, 1.0);
+    let inner = midpoint * (1.0 - feather);
+    let outer = max(midpoint + (1.0 - midpoint) * feather, inner + 0.001);
+    let t = clamp((d - inner) / (outer - inner), 0.0, 1.0);
+    return t * t * (3.0 - 2.0 * t);
+}
+
 fn hsl_to_rgb(hsl: vec3<f32>) -> vec3<f32> {
     let h = hsl.x;
     let s = hsl.y;
@@ -1100,6 +2043,22 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
     var g = px.g;
     var b = px.b;
 
+    // Vignette runs as its own CPU stage, so its 8-bit output is mirrored here
+    if (abs(params.vignette.x) > 0.001) {
+        let t = vignette_weight(vec2<f32>(gid.xy) + 0.5);
+        let amount = clamp(params.vignette.x, -1.0, 1.0);
+        var v = vec3<f32>(r, g, b);
+        if (amount < 0.0) {
+            v = v * (1.0 + amount * t);
+        } else {
+            v = v + (1.0 - v) * amount * t;
+        }
+        v = round(clamp(v, vec3<f32>(0.0), vec3<f32>(1.0)) * 255.0) / 255.0;
+        r = v.r;
+        g = v.g;
+        b = v.b;
+    }
+
     let exposure_gain = exp2(clamp(params.exposure, -5.0, 5.0));
     let contrast_gain = 1.0 + clamp(params.contrast, -1.0, 1.0);
     r = clamp((r * exposure_gain - 0.5) * contrast_gain + 0.5, 0.0, 1.0);
@@ -1151,8 +2110,16 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
     hsl.x = wrap_unit(hsl.x + hue_shift);
     hsl.y = clamp(hsl.y * (1.0 + sat_adjust), 0.0, 1.0);
 
-    // Selective color: 8 unrolled blocks matching CPU centers
-    // [0, 30, 60, 120, 180, 240, 285, 330] with half_width=30
+    // Vibrance, mirroring `color::vibrance_amount` (skin hues centered on 25°)
+    let vibrance = clamp(params.vibrance, -1.0, 1.0);
+    if (abs(vibrance) >= 0.001) {
+        let skin = selective_weight(hsl.x, 25.0, 25.0);
+        hsl.y = clamp(hsl.y * (1.0 + vibrance * (1.0 - hsl.y) * (1.0 - 0.5 * skin)), 0.0, 1.0);
+    }
+
+    // Selective color, mirroring `color::apply`: a loop over the 8 bands,
+    // weighted by `band_weight` (adjustable center and width plus the
+    // luminance/saturation qualifiers)
     {
         let sel_hues = array<f32, 8>(
             params.sel_hue_0, params.sel_hue_1, params.sel_hue_2, params.sel_hue_3,
@@ -1166,8 +2133,6 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
             params.sel_light_0, params.sel_light_1, params.sel_light_2, params.sel_light_3,
             params.sel_light_4, params.sel_light_5, params.sel_light_6, params.sel_light_7
         );
-        let centers = array<f32, 8>(0.0, 30.0, 60.0, 120.0, 180.0, 240.0, 285.0, 330.0);
-        let half_w = 30.0;
         for (var ci = 0u; ci < 8u; ci = ci + 1u) {
             let sh = sel_hues[ci];
             let ss = sel_sats[ci];
@@ -1175,7 +2140,7 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
             if (abs(sh) < 0.001 && abs(ss) < 0.001 && abs(sl) < 0.001) {
                 continue;
             }
-            let w = selective_weight(hsl.x, centers[ci], half_w);
+            let w = band_weight(ci, hsl);
             if (w <= 0.0) {
                 continue;
             }
@@ -1187,21 +2152,50 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
 
     var out_rgb = hsl_to_rgb(hsl);
 
-    if (params.grad_enabled > 0.5) {
-        let h_denom = max(params.height - 1.0, 1.0);
-        let y_norm = f32(gid.y) / h_denom;
-        var weight = 0.0;
-        if (y_norm <= params.grad_top) {
-            weight = 1.0;
-        } else if (y_norm >= params.grad_bottom) {
-            weight = 0.0;
-        } else {
-            weight = (params.grad_bottom - y_norm) / (params.grad_bottom - params.grad_top);
-        }
-        if (weight > 0.0) {
-            let gain = exp2(params.grad_exposure * weight);
-            out_rgb = clamp(out_rgb * vec3<f32>(gain, gain, gain), vec3<f32>(0.0), vec3<f32>(1.0));
+    // Black and white mix and toning, mirroring `color::mono_gray`
+    if (params.mono.w > 0.5) {
+        let mono_mix = array<f32, 8>(
+            params.mono_mix_0.x, params.mono_mix_0.y, params.mono_mix_0.z, params.mono_mix_0.w,
+            params.mono_mix_1.x, params.mono_mix_1.y, params.mono_mix_1.z, params.mono_mix_1.w
+        );
+        let mono_centers = array<f32, 8>(0.0, 30.0, 60.0, 120.0, 180.0, 240.0, 285.0, 330.0);
+        var gray = dot(out_rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        for (var mi = 0u; mi < 8u; mi = mi + 1u) {
+            let m = mono_mix[mi];
+            if (abs(m) < 0.001) {
+                continue;
+            }
+            let w = selective_weight(hsl.x, mono_centers[mi], 30.0);
+            gray = gray + clamp(m, -1.0, 1.0) * w * hsl.y * 0.5;
         }
+        gray = clamp(gray, 0.0, 1.0);
+        let toning = 4.0 * gray * (1.0 - gray);
+        out_rgb = clamp(vec3<f32>(gray) + params.mono.rgb * toning, vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+
+    // Color grading, mirroring `color::grade_weights`
+    if (params.grade.z > 0.5) {
+        let luma = dot(out_rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        let l = clamp(luma + clamp(params.grade.x, -1.0, 1.0) * 0.25, 0.0, 1.0);
+        let width = 0.1 + clamp(params.grade.y, 0.0, 1.0) * 0.3;
+        let w_shadows = 1.0 - smoothstep(1.0 / 3.0 - width, 1.0 / 3.0 + width, l);
+        let w_highlights = smoothstep(2.0 / 3.0 - width, 2.0 / 3.0 + width, l);
+        let w_midtones = max(1.0 - w_shadows - w_highlights, 0.0);
+        out_rgb = out_rgb
+            + w_shadows * params.grade_shadows.rgb
+            + w_midtones * params.grade_midtones.rgb
+            + w_highlights * params.grade_highlights.rgb;
+        out_rgb = clamp(out_rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+
+    if (params.lut.w > 0.5) {
+        let graded_709 = sample_lut(
+            convert_primaries(out_rgb, params.to_lut_0, params.to_lut_1, params.to_lut_2)
+        );
+        let graded = convert_primaries(
+            graded_709, params.from_lut_0, params.from_lut_1, params.from_lut_2
+        );
+        out_rgb = clamp(mix(out_rgb, graded, params.lut.y), vec3<f32>(0.0), vec3<f32>(1.0));
     }
 
     textureStore(dst_tex, coord, vec4<f32>(out_rgb, px.a));
@@ -1211,6 +2205,98 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
 // Geometry transform shader: inverse-maps each output pixel to source coordinates.
 // Pipeline order (CPU): straighten → keystone → rotate → flip → crop
 // Inverse order (GPU, per output pixel): undo crop → undo flip → undo rotate → undo keystone → undo straighten
+const SPOTS_SHADER_SRC: &str = r#"
+struct SpotParams {
+    width: f32,
+    height: f32,
+    count: f32,
+    _pad0: f32,
+};
+
+// geom:   center.xy, radius, feather (pixels)
+// source: whole-pixel offset.xy, heal flag, pad
+struct Spot {
+    geom: vec4<f32>,
+    source: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var src_tex: texture_2d<f32>;
+@group(0) @binding(1)
+var dst_tex: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2)
+var<uniform> params: SpotParams;
+@group(0) @binding(3)
+var<storage, read> spots: array<Spot>;
+// RING_SAMPLES rim color differences (rgb) per spot
+@group(0) @binding(4)
+var<storage, read> rings: array<vec4<f32>>;
+
+const RING_SAMPLES: u32 = 32u;
+const TAU: f32 = 6.2831855;
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = clamp((x - edge0) / (edge1 - edge0), 0.0, 1.0);
+    return t * t * (3.0 - 2.0 * t);
+}
+
+fn feather_falloff(r: f32, feather: f32) -> f32 {
+    if (feather < 0.001) {
+        return select(0.0, 1.0, r < 1.0);
+    }
+    return 1.0 - smoothstep(1.0 - feather, 1.0, r);
+}
+
+fn load_clamped(x: i32, y: i32, w: i32, h: i32) -> vec3<f32> {
+    return textureLoad(src_tex, vec2<i32>(clamp(x, 0, w - 1), clamp(y, 0, h - 1)), 0).rgb;
+}
+
+// Inverse squared distance interpolation of the rim differences.
+fn heal_delta(index: u32, center: vec2<f32>, radius: f32, p: vec2<f32>) -> vec3<f32> {
+    var sum = vec3<f32>(0.0);
+    var total = 0.0;
+    for (var k = 0u; k < RING_SAMPLES; k = k + 1u) {
+        let angle = f32(k) * TAU / f32(RING_SAMPLES);
+        let b = center + radius * vec2<f32>(cos(angle), sin(angle));
+        let d = p - b;
+        let weight = 1.0 / (d.x * d.x + d.y * d.y + 1.0);
+        sum = sum + rings[index * RING_SAMPLES + k].rgb * weight;
+        total = total + weight;
+    }
+    return sum / total;
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let w = i32(params.width + 0.5);
+    let h = i32(params.height + 0.5);
+    let x = i32(gid.x);
+    let y = i32(gid.y);
+    if (x >= w || y >= h) {
+        return;
+    }
+
+    var color = textureLoad(src_tex, vec2<i32>(x, y), 0);
+    let p = vec2<f32>(f32(x) + 0.5, f32(y) + 0.5);
+    let count = u32(params.count + 0.5);
+    for (var i = 0u; i < count; i = i + 1u) {
+        let spot = spots[i];
+        let d = p - spot.geom.xy;
+        let alpha = feather_falloff(sqrt(d.x * d.x + d.y * d.y) / spot.geom.z, spot.geom.w);
+        if (alpha <= 0.0) {
+            continue;
+        }
+        var fill = load_clamped(x + i32(spot.source.x), y + i32(spot.source.y), w, h);
+        if (spot.source.z > 0.5) {
+            fill = fill + heal_delta(i, spot.geom.xy, spot.geom.z, p);
+        }
+        color = vec4<f32>(color.rgb + (fill - color.rgb) * alpha, color.a);
+    }
+
+    textureStore(dst_tex, vec2<i32>(x, y), clamp(color, vec4<f32>(0.0), vec4<f32>(1.0)));
+}
+"#;
+
 const GEOMETRY_SHADER_SRC: &str = r#"
 struct GeoParams {
     src_width: f32,
@@ -1367,14 +2453,20 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
 }
 "#;
 
-// Horizontal separable Gaussian blur (sigma=1.5, radius=5, 11 taps)
-// Fully unrolled to avoid driver crashes from array+loop SPIR-V patterns.
+// Horizontal separable Gaussian blur. Kernel matches imageproc's
+// `gaussian_blur_f32`: radius ceil(2 * sigma), weights normalized to sum to 1.
+// Weights are computed per tap instead of indexed from an array to avoid
+// driver crashes from array+loop SPIR-V patterns.
 const BLUR_H_SHADER_SRC: &str = r#"
 struct BlurParams {
     width: f32,
     height: f32,
     sharpness: f32,
-    _pad: f32,
+    sigma: f32,
+    threshold: f32,
+    masking: f32,
+    _pad0: f32,
+    _pad1: f32,
 };
 
 @group(0) @binding(0)
@@ -1384,11 +2476,6 @@ var dst_tex: texture_storage_2d<rgba8unorm, write>;
 @group(0) @binding(2)
 var<uniform> params: BlurParams;
 
-fn sample_h(cx: i32, y: i32, offset: i32, w: i32) -> vec4<f32> {
-    let sx = clamp(cx + offset, 0, w - 1);
-    return textureLoad(src_tex, vec2<i32>(sx, y), 0);
-}
-
 @compute @workgroup_size(16, 16, 1)
 fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
     let w = i32(params.width + 0.5);
@@ -1397,32 +2484,34 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
         return;
     }
 
-    // Normalized Gaussian weights for sigma=1.5, radius=5
     let cx = i32(gid.x);
     let y = i32(gid.y);
-    var acc = sample_h(cx, y, -5, w) * 0.0010284
-            + sample_h(cx, y, -4, w) * 0.0075988
-            + sample_h(cx, y, -3, w) * 0.0360008
-            + sample_h(cx, y, -2, w) * 0.1093607
-            + sample_h(cx, y, -1, w) * 0.2130055
-            + sample_h(cx, y,  0, w) * 0.2660117
-            + sample_h(cx, y,  1, w) * 0.2130055
-            + sample_h(cx, y,  2, w) * 0.1093607
-            + sample_h(cx, y,  3, w) * 0.0360008
-            + sample_h(cx, y,  4, w) * 0.0075988
-            + sample_h(cx, y,  5, w) * 0.0010284;
-    textureStore(dst_tex, vec2<i32>(cx, y), acc);
+    let radius = i32(ceil(2.0 * params.sigma));
+    let inv_two_sigma_sq = 1.0 / (2.0 * params.sigma * params.sigma);
+    var acc = vec4<f32>(0.0);
+    var weight_sum = 0.0;
+    for (var i = -radius; i <= radius; i = i + 1) {
+        let wt = exp(-f32(i * i) * inv_two_sigma_sq);
+        let sx = clamp(cx + i, 0, w - 1);
+        acc = acc + textureLoad(src_tex, vec2<i32>(sx, y), 0) * wt;
+        weight_sum = weight_sum + wt;
+    }
+    textureStore(dst_tex, vec2<i32>(cx, y), acc / weight_sum);
 }
 "#;
 
-// Vertical Gaussian blur + USM blend in one pass.
-// Fully unrolled to avoid driver crashes from array+loop SPIR-V patterns.
+// Vertical Gaussian blur + USM blend in one pass, with the threshold and
+// Sobel edge mask from `sharpness::apply`.
 const BLUR_V_USM_SHADER_SRC: &str = r#"
 struct BlurParams {
     width: f32,
     height: f32,
     sharpness: f32,
-    _pad: f32,
+    sigma: f32,
+    threshold: f32,
+    masking: f32,
+    _pad0: f32,
+    _pad1: f32,
 };
 
 @group(0) @binding(0)
@@ -1434,9 +2523,34 @@ var dst_tex: texture_storage_2d<rgba8unorm, write>;
 @group(0) @binding(3)
 var<uniform> params: BlurParams;
 
-fn sample_v(x: i32, cy: i32, offset: i32, h: i32) -> vec3<f32> {
-    let sy = clamp(cy + offset, 0, h - 1);
-    return textureLoad(blur_h_tex, vec2<i32>(x, sy), 0).rgb;
+// Must match sharpness::MASK_EDGE_SCALE.
+const MASK_EDGE_SCALE: f32 = 0.3;
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = clamp((x - edge0) / (edge1 - edge0), 0.0, 1.0);
+    return t * t * (3.0 - 2.0 * t);
+}
+
+fn luma(rgb: vec3<f32>) -> f32 {
+    return 0.2126 * rgb.r + 0.7152 * rgb.g + 0.0722 * rgb.b;
+}
+
+fn orig_luma(x: i32, y: i32, w: i32, h: i32) -> f32 {
+    let c = vec2<i32>(clamp(x, 0, w - 1), clamp(y, 0, h - 1));
+    return luma(textureLoad(orig_tex, c, 0).rgb);
+}
+
+fn edge_mask(x: i32, y: i32, w: i32, h: i32) -> f32 {
+    if (params.masking < 0.001) {
+        return 1.0;
+    }
+    let gx = (orig_luma(x + 1, y - 1, w, h) + 2.0 * orig_luma(x + 1, y, w, h) + orig_luma(x + 1, y + 1, w, h))
+           - (orig_luma(x - 1, y - 1, w, h) + 2.0 * orig_luma(x - 1, y, w, h) + orig_luma(x - 1, y + 1, w, h));
+    let gy = (orig_luma(x - 1, y + 1, w, h) + 2.0 * orig_luma(x, y + 1, w, h) + orig_luma(x + 1, y + 1, w, h))
+           - (orig_luma(x - 1, y - 1, w, h) + 2.0 * orig_luma(x, y - 1, w, h) + orig_luma(x + 1, y - 1, w, h));
+    let edge = sqrt(gx * gx + gy * gy) * 0.25;
+    let hi = clamp(params.masking, 0.0, 1.0) * MASK_EDGE_SCALE;
+    return smoothstep(hi * 0.5, hi, edge);
 }
 
 @compute @workgroup_size(16, 16, 1)
@@ -1450,68 +2564,578 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
     let x = i32(gid.x);
     let cy = i32(gid.y);
 
-    // Vertical blur on the H-blurred texture (unrolled, same weights)
-    let blurred = sample_v(x, cy, -5, h) * 0.0010284
-                + sample_v(x, cy, -4, h) * 0.0075988
-                + sample_v(x, cy, -3, h) * 0.0360008
-                + sample_v(x, cy, -2, h) * 0.1093607
-                + sample_v(x, cy, -1, h) * 0.2130055
-                + sample_v(x, cy,  0, h) * 0.2660117
-                + sample_v(x, cy,  1, h) * 0.2130055
-                + sample_v(x, cy,  2, h) * 0.1093607
-                + sample_v(x, cy,  3, h) * 0.0360008
-                + sample_v(x, cy,  4, h) * 0.0075988
-                + sample_v(x, cy,  5, h) * 0.0010284;
-
-    // USM: sharp = orig + amount * (orig - blurred)
+    // Vertical blur on the H-blurred texture (same kernel as the H pass)
+    let radius = i32(ceil(2.0 * params.sigma));
+    let inv_two_sigma_sq = 1.0 / (2.0 * params.sigma * params.sigma);
+    var acc = vec3<f32>(0.0);
+    var weight_sum = 0.0;
+    for (var i = -radius; i <= radius; i = i + 1) {
+        let wt = exp(-f32(i * i) * inv_two_sigma_sq);
+        let sy = clamp(cy + i, 0, h - 1);
+        acc = acc + textureLoad(blur_h_tex, vec2<i32>(x, sy), 0).rgb * wt;
+        weight_sum = weight_sum + wt;
+    }
+    let blurred = acc / weight_sum;
+
+    // USM: sharp = orig + amount * weight * (orig - blurred)
     let coord = vec2<i32>(x, cy);
     let orig = textureLoad(orig_tex, coord, 0);
-    let sharp = clamp(orig.rgb + params.sharpness * (orig.rgb - blurred), vec3<f32>(0.0), vec3<f32>(1.0));
+    var weight = edge_mask(x, cy, w, h);
+    if (params.threshold >= 0.001) {
+        let detail = abs(luma(orig.rgb) - luma(blurred));
+        weight = weight * smoothstep(params.threshold * 0.5, params.threshold, detail);
+    }
+    let sharp = clamp(orig.rgb + params.sharpness * weight * (orig.rgb - blurred), vec3<f32>(0.0), vec3<f32>(1.0));
     textureStore(dst_tex, coord, vec4<f32>(sharp, orig.a));
 }
 "#;
 
-#[cfg(test)]
-mod tests {
-    use image::{DynamicImage, ImageBuffer, Rgba};
+// Dark channel for dehaze: the minimum over RGB, then a running minimum along
+// rows (axis 0) or columns (axis 1). The column pass also fills the histogram.
+const DEHAZE_MIN_SHADER_SRC: &str = r#"
+struct MinParams {
+    width: f32,
+    height: f32,
+    radius: f32,
+    axis: f32,
+};
 
-    use crate::state::{EditState, GradFilter, Rect};
+@group(0) @binding(0)
+var src_tex: texture_2d<f32>;
+@group(0) @binding(1)
+var dst_tex: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2)
+var<uniform> params: MinParams;
+// 256 dark channel bins, then the airlight RGB (0–255)
+@group(0) @binding(3)
+var<storage, read_write> haze: array<atomic<u32>>;
 
-    use super::{
-        NATIVE_BACKEND_FILTER, debug_fallback_truthy, has_gpu_adjustments,
-        is_gpu_state_supported, select_adapter_index, try_apply,
-    };
+@compute @workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let w = i32(params.width + 0.5);
+    let h = i32(params.height + 0.5);
+    if (i32(gid.x) >= w || i32(gid.y) >= h) {
+        return;
+    }
 
-    fn adapter_info(device_type: wgpu::DeviceType, backend: wgpu::Backend) -> wgpu::AdapterInfo {
-        wgpu::AdapterInfo {
-            name: format!("{device_type:?}"),
-            driver: "test-driver".to_string(),
-            driver_info: "test-driver-info".to_string(),
-            ..wgpu::AdapterInfo::new(device_type, backend)
+    let coord = vec2<i32>(gid.xy);
+    let radius = i32(params.radius + 0.5);
+    let vertical = params.axis > 0.5;
+    var m = 1.0;
+    for (var i = -radius; i <= radius; i = i + 1) {
+        var c = coord;
+        if (vertical) {
+            c.y = clamp(coord.y + i, 0, h - 1);
+        } else {
+            c.x = clamp(coord.x + i, 0, w - 1);
         }
+        let px = textureLoad(src_tex, c, 0).rgb;
+        m = min(m, min(px.r, min(px.g, px.b)));
     }
+    textureStore(dst_tex, coord, vec4<f32>(m, m, m, 1.0));
+    if (vertical) {
+        atomicAdd(&haze[u32(round(m * 255.0))], 1u);
+    }
+}
+"#;
 
-    #[test]
-    fn accepts_all_states() {
-        // GPU path now supports all states
-        let mut s = EditState::default();
-        s.rotate = 90;
-        assert!(is_gpu_state_supported(&s));
+// Airlight: the brightest RGB among pixels whose dark channel is in the top
+// `AIRLIGHT_FRACTION` of the histogram, as in `presence::airlight`.
+const DEHAZE_AIRLIGHT_SHADER_SRC: &str = r#"
+struct AirlightParams {
+    width: f32,
+    height: f32,
+    fraction: f32,
+    _pad0: f32,
+};
 
-        s = EditState::default();
-        s.straighten = 1.0;
-        assert!(is_gpu_state_supported(&s));
-    }
+@group(0) @binding(0)
+var color_tex: texture_2d<f32>;
+@group(0) @binding(1)
+var dark_tex: texture_2d<f32>;
+@group(0) @binding(2)
+var<uniform> params: AirlightParams;
+@group(0) @binding(3)
+var<storage, read_write> haze: array<atomic<u32>>;
 
-    #[test]
-    fn debug_fallback_truthy_parser_matches_expected_values() {
-        assert!(debug_fallback_truthy("1"));
-        assert!(debug_fallback_truthy(" true "));
-        assert!(debug_fallback_truthy("YES"));
-        assert!(debug_fallback_truthy("on"));
-        assert!(!debug_fallback_truthy("0"));
-        assert!(!debug_fallback_truthy("false"));
-        assert!(!debug_fallback_truthy("no"));
+var<workgroup> threshold: u32;
+
+@compute @workgroup_size(16, 16, 1)
+fn main(
+    @builtin(global_invocation_id) gid: vec3<u32>,
+    @builtin(local_invocation_index) lid: u32,
+) {
+    let w = i32(params.width + 0.5);
+    let h = i32(params.height + 0.5);
+    if (lid == 0u) {
+        let total = u32(w) * u32(h);
+        let target_count = max(u32(f32(total) * params.fraction), 1u);
+        var count = 0u;
+        var v = 255i;
+        loop {
+            count = count + atomicLoad(&haze[u32(v)]);
+            if (count >= target_count || v == 0) {
+                break;
+            }
+            v = v - 1;
+        }
+        threshold = u32(v);
+    }
+    workgroupBarrier();
+
+    if (i32(gid.x) >= w || i32(gid.y) >= h) {
+        return;
+    }
+    let coord = vec2<i32>(gid.xy);
+    let dark = u32(round(textureLoad(dark_tex, coord, 0).r * 255.0));
+    if (dark < threshold) {
+        return;
+    }
+    let rgb = vec3<u32>(round(textureLoad(color_tex, coord, 0).rgb * 255.0));
+    atomicMax(&haze[256], rgb.r);
+    atomicMax(&haze[257], rgb.g);
+    atomicMax(&haze[258], rgb.b);
+}
+"#;
+
+// Dehaze recovery: vertical blur of the H-blurred dark channel as the haze
+// estimate, then J = (I - A) / t + A (or a blend toward A for negative amounts).
+const DEHAZE_SHADER_SRC: &str = r#"
+struct DehazeParams {
+    width: f32,
+    height: f32,
+    amount: f32,
+    sigma: f32,
+};
+
+@group(0) @binding(0)
+var dark_h_tex: texture_2d<f32>;
+@group(0) @binding(1)
+var color_tex: texture_2d<f32>;
+@group(0) @binding(2)
+var dst_tex: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(3)
+var<uniform> params: DehazeParams;
+@group(0) @binding(4)
+var<storage, read> haze: array<u32>;
+
+// Must match presence::DEHAZE_OMEGA, DEHAZE_MIN_TRANSMISSION and ADD_HAZE.
+const OMEGA: f32 = 0.95;
+const MIN_TRANSMISSION: f32 = 0.1;
+const ADD_HAZE: f32 = 0.5;
+
+@compute @workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let w = i32(params.width + 0.5);
+    let h = i32(params.height + 0.5);
+    if (i32(gid.x) >= w || i32(gid.y) >= h) {
+        return;
+    }
+
+    let x = i32(gid.x);
+    let cy = i32(gid.y);
+    let radius = i32(ceil(2.0 * params.sigma));
+    let inv_two_sigma_sq = 1.0 / (2.0 * params.sigma * params.sigma);
+    var acc = 0.0;
+    var weight_sum = 0.0;
+    for (var i = -radius; i <= radius; i = i + 1) {
+        let wt = exp(-f32(i * i) * inv_two_sigma_sq);
+        let sy = clamp(cy + i, 0, h - 1);
+        acc = acc + textureLoad(dark_h_tex, vec2<i32>(x, sy), 0).r * wt;
+        weight_sum = weight_sum + wt;
+    }
+    let dark = acc / weight_sum;
+
+    let airlight = vec3<f32>(f32(haze[256]), f32(haze[257]), f32(haze[258])) / 255.0;
+    let a_max = max(max(airlight.r, max(airlight.g, airlight.b)), 1.0 / 255.0);
+    let coord = vec2<i32>(x, cy);
+    let px = textureLoad(color_tex, coord, 0);
+    var out: vec3<f32>;
+    if (params.amount > 0.0) {
+        let t = max(1.0 - OMEGA * params.amount * dark / a_max, MIN_TRANSMISSION);
+        out = (px.rgb - airlight) / t + airlight;
+    } else {
+        out = px.rgb + (airlight - px.rgb) * -params.amount * ADD_HAZE;
+    }
+    textureStore(dst_tex, coord, vec4<f32>(clamp(out, vec3<f32>(0.0), vec3<f32>(1.0)), px.a));
+}
+"#;
+
+// Clarity and texture: vertical blurs of the two H-blurred textures, then
+// luma detail added back as in `presence::apply_local_contrast`.
+const LOCAL_CONTRAST_SHADER_SRC: &str = r#"
+struct ContrastParams {
+    width: f32,
+    height: f32,
+    clarity: f32,
+    texture: f32,
+    clarity_sigma: f32,
+    texture_sigma: f32,
+    _pad0: f32,
+    _pad1: f32,
+};
+
+@group(0) @binding(0)
+var coarse_h_tex: texture_2d<f32>;
+@group(0) @binding(1)
+var fine_h_tex: texture_2d<f32>;
+@group(0) @binding(2)
+var orig_tex: texture_2d<f32>;
+@group(0) @binding(3)
+var dst_tex: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(4)
+var<uniform> params: ContrastParams;
+
+// Must match presence::CLARITY_GAIN and TEXTURE_GAIN.
+const CLARITY_GAIN: f32 = 0.6;
+const TEXTURE_GAIN: f32 = 0.8;
+
+fn luma(rgb: vec3<f32>) -> f32 {
+    return 0.2126 * rgb.r + 0.7152 * rgb.g + 0.0722 * rgb.b;
+}
+
+fn blurred_coarse(x: i32, cy: i32, h: i32) -> vec3<f32> {
+    let radius = i32(ceil(2.0 * params.clarity_sigma));
+    let inv_two_sigma_sq = 1.0 / (2.0 * params.clarity_sigma * params.clarity_sigma);
+    var acc = vec3<f32>(0.0);
+    var weight_sum = 0.0;
+    for (var i = -radius; i <= radius; i = i + 1) {
+        let wt = exp(-f32(i * i) * inv_two_sigma_sq);
+        let sy = clamp(cy + i, 0, h - 1);
+        acc = acc + textureLoad(coarse_h_tex, vec2<i32>(x, sy), 0).rgb * wt;
+        weight_sum = weight_sum + wt;
+    }
+    return acc / weight_sum;
+}
+
+fn blurred_fine(x: i32, cy: i32, h: i32) -> vec3<f32> {
+    let radius = i32(ceil(2.0 * params.texture_sigma));
+    let inv_two_sigma_sq = 1.0 / (2.0 * params.texture_sigma * params.texture_sigma);
+    var acc = vec3<f32>(0.0);
+    var weight_sum = 0.0;
+    for (var i = -radius; i <= radius; i = i + 1) {
+        let wt = exp(-f32(i * i) * inv_two_sigma_sq);
+        let sy = clamp(cy + i, 0, h - 1);
+        acc = acc + textureLoad(fine_h_tex, vec2<i32>(x, sy), 0).rgb * wt;
+        weight_sum = weight_sum + wt;
+    }
+    return acc / weight_sum;
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let w = i32(params.width + 0.5);
+    let h = i32(params.height + 0.5);
+    if (i32(gid.x) >= w || i32(gid.y) >= h) {
+        return;
+    }
+
+    let x = i32(gid.x);
+    let cy = i32(gid.y);
+    let coord = vec2<i32>(x, cy);
+    let px = textureLoad(orig_tex, coord, 0);
+    let l = luma(px.rgb);
+    var delta = 0.0;
+    if (abs(params.clarity) > 0.001) {
+        let midtones = 1.0 - (2.0 * l - 1.0) * (2.0 * l - 1.0);
+        delta = delta + params.clarity * CLARITY_GAIN * midtones * (l - luma(blurred_coarse(x, cy, h)));
+    }
+    if (abs(params.texture) > 0.001) {
+        delta = delta + params.texture * TEXTURE_GAIN * (l - luma(blurred_fine(x, cy, h)));
+    }
+    textureStore(dst_tex, coord, vec4<f32>(clamp(px.rgb + delta, vec3<f32>(0.0), vec3<f32>(1.0)), px.a));
+}
+"#;
+
+// Graduated filters and local adjustments: linear, radial and brush masks
+// evaluated per pixel, each applying masked exposure/contrast/highlights/
+// temperature/saturation and sharpness (against a sigma-1 Gaussian) as
+// `filters::apply` and `local::apply` do.
+const LOCAL_SHADER_SRC: &str = r#"
+struct LocalParams {
+    width: f32,
+    height: f32,
+    count: f32,
+    _pad0: f32,
+};
+
+// shape:  linear (start.xy, end.xy) | radial (center.xy, radius.xy) | brush (first stroke, stroke count)
+// style:  kind (0 linear, 1 radial, 2 brush), angle (rad), feather, invert
+// tone:   exposure, contrast, temperature, saturation
+// detail: sharpness, highlights
+struct LocalAdjustment {
+    shape: vec4<f32>,
+    style: vec4<f32>,
+    tone: vec4<f32>,
+    detail: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var src_tex: texture_2d<f32>;
+@group(0) @binding(1)
+var dst_tex: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2)
+var<uniform> params: LocalParams;
+@group(0) @binding(3)
+var<storage, read> adjustments: array<LocalAdjustment>;
+// first dab, dab count, erase
+@group(0) @binding(4)
+var<storage, read> strokes: array<vec4<f32>>;
+// x, y, radius, feather
+@group(0) @binding(5)
+var<storage, read> dabs: array<vec4<f32>>;
+
+const SHARPEN_SIGMA: f32 = 1.0;
+const SHARPEN_KERNEL_RADIUS: i32 = 2;
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = clamp((x - edge0) / (edge1 - edge0), 0.0, 1.0);
+    return t * t * (3.0 - 2.0 * t);
+}
+
+fn feather_falloff(r: f32, feather: f32) -> f32 {
+    if (feather < 0.001) {
+        return select(0.0, 1.0, r < 1.0);
+    }
+    return 1.0 - smoothstep(1.0 - feather, 1.0, r);
+}
+
+fn mask_weight(adj: LocalAdjustment, p: vec2<f32>) -> f32 {
+    let kind = u32(adj.style.x + 0.5);
+    if (kind == 0u) {
+        let d = adj.shape.zw - adj.shape.xy;
+        let len_sq = dot(d, d);
+        if (len_sq < 1e-6) {
+            return 0.0;
+        }
+        let t = dot(p - adj.shape.xy, d) / len_sq;
+        return clamp(1.0 - t, 0.0, 1.0);
+    }
+    if (kind == 1u) {
+        let rel = p - adj.shape.xy;
+        let s = sin(adj.style.y);
+        let c = cos(adj.style.y);
+        let q = vec2<f32>(rel.x * c + rel.y * s, -rel.x * s + rel.y * c);
+        let r = length(q / adj.shape.zw);
+        let w = feather_falloff(r, adj.style.z);
+        return select(w, 1.0 - w, adj.style.w > 0.5);
+    }
+
+    var w = 0.0;
+    let first_stroke = u32(adj.shape.x + 0.5);
+    let stroke_count = u32(adj.shape.y + 0.5);
+    for (var si = first_stroke; si < first_stroke + stroke_count; si = si + 1u) {
+        let stroke = strokes[si];
+        let first_dab = u32(stroke.x + 0.5);
+        let dab_count = u32(stroke.y + 0.5);
+        var sw = 0.0;
+        for (var di = first_dab; di < first_dab + dab_count; di = di + 1u) {
+            let dab = dabs[di];
+            let dist = length(p - dab.xy);
+            if (dist < dab.z) {
+                sw = max(sw, feather_falloff(dist / dab.z, dab.w));
+            }
+        }
+        if (stroke.z > 0.5) {
+            w = w * (1.0 - sw);
+        } else {
+            w = max(w, sw);
+        }
+    }
+    return w;
+}
+
+fn apply_tone(rgb_in: vec3<f32>, tone: vec4<f32>, highlights_in: f32, weight: f32) -> vec3<f32> {
+    let gain = exp2(tone.x * weight);
+    let contrast = 1.0 + tone.y * weight;
+    var rgb = clamp((rgb_in * gain - vec3<f32>(0.5)) * contrast + vec3<f32>(0.5), vec3<f32>(0.0), vec3<f32>(1.0));
+
+    let highlights = highlights_in * weight;
+    if (abs(highlights) > 0.001) {
+        let luma = 0.2126 * rgb.r + 0.7152 * rgb.g + 0.0722 * rgb.b;
+        let w = smoothstep(0.5, 1.0, luma);
+        var target_luma = luma * (1.0 + highlights * w);
+        if (highlights >= 0.0) {
+            target_luma = luma + (1.0 - luma) * highlights * w;
+        }
+        let scale = select(1.0, target_luma / luma, luma > 1e-5);
+        rgb = clamp(rgb * scale, vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+
+    let temp = tone.z * weight;
+    if (temp > 0.0) {
+        rgb.r = rgb.r + (1.0 - rgb.r) * temp * 0.25;
+        rgb.b = rgb.b * (1.0 - temp * 0.25);
+    } else if (temp < 0.0) {
+        let cool = -temp;
+        rgb.b = rgb.b + (1.0 - rgb.b) * cool * 0.25;
+        rgb.r = rgb.r * (1.0 - cool * 0.25);
+    }
+    rgb = clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    let sat = 1.0 + tone.w * weight;
+    let l = 0.2126 * rgb.r + 0.7152 * rgb.g + 0.0722 * rgb.b;
+    return clamp(vec3<f32>(l) + (rgb - vec3<f32>(l)) * sat, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn blurred_at(x: i32, y: i32, w: i32, h: i32) -> vec3<f32> {
+    let inv_two_sigma_sq = 1.0 / (2.0 * SHARPEN_SIGMA * SHARPEN_SIGMA);
+    var acc = vec3<f32>(0.0);
+    var weight_sum = 0.0;
+    for (var dy = -SHARPEN_KERNEL_RADIUS; dy <= SHARPEN_KERNEL_RADIUS; dy = dy + 1) {
+        for (var dx = -SHARPEN_KERNEL_RADIUS; dx <= SHARPEN_KERNEL_RADIUS; dx = dx + 1) {
+            let wt = exp(-f32(dx * dx + dy * dy) * inv_two_sigma_sq);
+            let sx = clamp(x + dx, 0, w - 1);
+            let sy = clamp(y + dy, 0, h - 1);
+            acc = acc + textureLoad(src_tex, vec2<i32>(sx, sy), 0).rgb * wt;
+            weight_sum = weight_sum + wt;
+        }
+    }
+    return acc / weight_sum;
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let w = i32(params.width + 0.5);
+    let h = i32(params.height + 0.5);
+    if (i32(gid.x) >= w || i32(gid.y) >= h) {
+        return;
+    }
+
+    let coord = vec2<i32>(i32(gid.x), i32(gid.y));
+    let px = textureLoad(src_tex, coord, 0);
+    let p = vec2<f32>(f32(gid.x) + 0.5, f32(gid.y) + 0.5);
+    var rgb = px.rgb;
+    var sharpen = 0.0;
+    let count = u32(params.count + 0.5);
+    for (var i = 0u; i < count; i = i + 1u) {
+        let adj = adjustments[i];
+        let weight = mask_weight(adj, p);
+        if (weight <= 0.0) {
+            continue;
+        }
+        rgb = apply_tone(rgb, adj.tone, adj.detail.y, weight);
+        sharpen = sharpen + adj.detail.x * weight;
+    }
+    if (abs(sharpen) > 0.0) {
+        let b = blurred_at(coord.x, coord.y, w, h);
+        rgb = rgb + sharpen * (px.rgb - b);
+    }
+
+    textureStore(dst_tex, coord, vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), px.a));
+}
+"#;
+
+// Film grain: two octaves of hashed value noise added to each channel,
+// mirroring `grain::apply`.
+const GRAIN_SHADER_SRC: &str = r#"
+struct GrainParams {
+    width: f32,
+    height: f32,
+    amount: f32,
+    cell: f32,
+    roughness: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+};
+
+@group(0) @binding(0)
+var src_tex: texture_2d<f32>;
+@group(0) @binding(1)
+var dst_tex: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2)
+var<uniform> params: GrainParams;
+
+fn lattice(x: u32, y: u32, octave: u32) -> f32 {
+    var h = (x * 0x8da6b343u) ^ (y * 0xd8163841u) ^ (octave * 0xcb1ab31fu);
+    h = h ^ (h >> 16u);
+    h = h * 0x7feb352du;
+    h = h ^ (h >> 15u);
+    h = h * 0x846ca68bu;
+    h = h ^ (h >> 16u);
+    return f32(h >> 8u) / 8388607.5 - 1.0;
+}
+
+fn value_noise(p: vec2<f32>, octave: u32) -> f32 {
+    let p0 = floor(p);
+    let f = p - p0;
+    let u = f * f * (3.0 - 2.0 * f);
+    let ix = u32(p0.x);
+    let iy = u32(p0.y);
+    let a = lattice(ix, iy, octave);
+    let b = lattice(ix + 1u, iy, octave);
+    let c = lattice(ix, iy + 1u, octave);
+    let d = lattice(ix + 1u, iy + 1u, octave);
+    let top = a + (b - a) * u.x;
+    let bottom = c + (d - c) * u.x;
+    return top + (bottom - top) * u.y;
+}
+
+@compute @workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let w = u32(params.width + 0.5);
+    let h = u32(params.height + 0.5);
+    if (gid.x >= w || gid.y >= h) {
+        return;
+    }
+
+    let coord = vec2<i32>(i32(gid.x), i32(gid.y));
+    let px = textureLoad(src_tex, coord, 0);
+    let l = dot(px.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let p = (vec2<f32>(gid.xy) + 0.5) / params.cell;
+    let n = (value_noise(p, 0u) + params.roughness * value_noise(p * 2.0, 1u))
+        / (1.0 + params.roughness);
+    let offset = n * params.amount * (1.0 - 0.75 * (2.0 * l - 1.0) * (2.0 * l - 1.0));
+    textureStore(dst_tex, coord, vec4<f32>(clamp(px.rgb + offset, vec3<f32>(0.0), vec3<f32>(1.0)), px.a));
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageBuffer, Rgba};
+
+    use crate::processing::lut;
+    use crate::state::{
+        BrushStroke, EditState, GradFilter, LocalAdjustment, LutInterpolation, LutRef, MaskShape,
+        NormPoint, Rect, Spot, SpotMode,
+    };
+
+    use super::{
+        NATIVE_BACKEND_FILTER, build_local_buffers, build_spot_buffers, debug_fallback_truthy,
+        has_gpu_adjustments, is_gpu_state_supported, select_adapter_index, try_apply,
+    };
+
+    fn adapter_info(device_type: wgpu::DeviceType, backend: wgpu::Backend) -> wgpu::AdapterInfo {
+        wgpu::AdapterInfo {
+            name: format!("{device_type:?}"),
+            driver: "test-driver".to_string(),
+            driver_info: "test-driver-info".to_string(),
+            ..wgpu::AdapterInfo::new(device_type, backend)
+        }
+    }
+
+    #[test]
+    fn accepts_all_states() {
+        // GPU path now supports all states
+        let mut s = EditState::default();
+        s.rotate = 90;
+        assert!(is_gpu_state_supported(&s));
+
+        s = EditState::default();
+        s.straighten = 1.0;
+        assert!(is_gpu_state_supported(&s));
+    }
+
+    #[test]
+    fn debug_fallback_truthy_parser_matches_expected_values() {
+        assert!(debug_fallback_truthy("1"));
+        assert!(debug_fallback_truthy(" true "));
+        assert!(debug_fallback_truthy("YES"));
+        assert!(debug_fallback_truthy("on"));
+        assert!(!debug_fallback_truthy("0"));
+        assert!(!debug_fallback_truthy("false"));
+        assert!(!debug_fallback_truthy("no"));
     }
 
     #[test]
@@ -1587,6 +3211,321 @@ mod tests {
         assert_rgba_close(&cpu, &gpu, 2);
     }
 
+    #[test]
+    fn parity_matches_cpu_for_selective_band_shape_and_ranges() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(32, 24, |x, y| {
+            Rgba([
+                ((x * 7 + y * 3) % 256) as u8,
+                ((x * 11 + y * 5) % 256) as u8,
+                ((x * 13 + y * 17) % 256) as u8,
+                255,
+            ])
+        }));
+        let mut state = EditState::default();
+        let band = &mut state.selective_color[1];
+        band.saturation = -0.6;
+        band.hue = 10.0;
+        band.center_shift = -12.0;
+        band.width = 18.0;
+        band.luminance_range = [0.2, 0.7];
+        let band = &mut state.selective_color[5];
+        band.lightness = 0.2;
+        band.width = 60.0;
+        band.saturation_range = [0.3, 1.0];
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for shaped selective bands")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 2);
+    }
+
+    #[test]
+    fn has_gpu_adjustments_includes_color_grading() {
+        let mut s = EditState::default();
+        s.color_grading.midtones.luminance = 0.2;
+        assert!(has_gpu_adjustments(&s));
+
+        let mut s2 = EditState::default();
+        s2.color_grading.balance = 0.5;
+        assert!(!has_gpu_adjustments(&s2));
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_color_grading() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(32, 24, |x, y| {
+            Rgba([
+                ((x * 7 + y * 3) % 256) as u8,
+                ((x * 11 + y * 5) % 256) as u8,
+                ((x * 13 + y * 17) % 256) as u8,
+                255,
+            ])
+        }));
+        let mut state = EditState {
+            saturation: 0.1,
+            ..EditState::default()
+        };
+        state.color_grading.shadows.hue = 210.0;
+        state.color_grading.shadows.saturation = 0.6;
+        state.color_grading.midtones.luminance = 0.15;
+        state.color_grading.highlights.hue = 40.0;
+        state.color_grading.highlights.saturation = 0.4;
+        state.color_grading.highlights.luminance = -0.1;
+        state.color_grading.balance = 0.3;
+        state.color_grading.blending = 0.7;
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for color grading state")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 2);
+    }
+
+    #[test]
+    fn has_gpu_adjustments_includes_vibrance_and_presence() {
+        let s = EditState {
+            vibrance: 0.3,
+            ..EditState::default()
+        };
+        assert!(has_gpu_adjustments(&s));
+
+        for setup in [
+            |s: &mut EditState| s.dehaze = 0.4,
+            |s: &mut EditState| s.clarity = -0.2,
+            |s: &mut EditState| s.texture = 0.5,
+        ] {
+            let mut s = EditState::default();
+            setup(&mut s);
+            assert!(has_gpu_adjustments(&s));
+        }
+    }
+
+    fn presence_test_image() -> DynamicImage {
+        // Gradient with a hazy bright band and hard edges
+        DynamicImage::ImageRgba8(ImageBuffer::from_fn(48, 32, |x, y| {
+            let base = if x > 30 {
+                210
+            } else {
+                ((x * 5 + y * 3) % 200) as u8
+            };
+            Rgba([base, base.saturating_add(12), base.saturating_add(25), 255])
+        }))
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_vibrance() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = presence_test_image();
+        let state = EditState {
+            vibrance: 0.7,
+            saturation: -0.1,
+            ..EditState::default()
+        };
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for vibrance state")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 2);
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_dehaze() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = presence_test_image();
+        for amount in [0.7, -0.6] {
+            let state = EditState {
+                dehaze: amount,
+                ..EditState::default()
+            };
+
+            let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+            let gpu = try_apply(&img, &state)
+                .expect("gpu apply should succeed for dehaze state")
+                .to_rgba8();
+            assert_rgba_close(&cpu, &gpu, 3);
+        }
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_clarity_and_texture() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = presence_test_image();
+        let state = EditState {
+            clarity: 0.8,
+            texture: -0.4,
+            dehaze: 0.3,
+            ..EditState::default()
+        };
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for clarity/texture state")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 3);
+    }
+
+    #[test]
+    fn has_gpu_adjustments_includes_monochrome_and_grain() {
+        let mut s = EditState::default();
+        s.monochrome.enabled = true;
+        assert!(has_gpu_adjustments(&s));
+
+        let mut s = EditState::default();
+        s.grain.amount = 0.4;
+        assert!(has_gpu_adjustments(&s));
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_monochrome() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = presence_test_image();
+        let mut state = EditState::default();
+        state.monochrome.enabled = true;
+        state.monochrome.mix = [0.5, 0.2, 0.0, -0.4, 0.3, 0.8, 0.0, -0.2];
+        state.monochrome.tone_amount = 0.6;
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for monochrome state")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 2);
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_grain() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = presence_test_image();
+        let mut state = EditState::default();
+        state.grain.amount = 0.8;
+        state.grain.roughness = 0.7;
+        state.sharpness = 0.5;
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for grain state")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 2);
+    }
+
+    #[test]
+    fn has_gpu_adjustments_includes_vignette() {
+        let mut s = EditState::default();
+        s.vignette.amount = -0.5;
+        assert!(has_gpu_adjustments(&s));
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_vignette() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = presence_test_image();
+        for uncropped in [false, true] {
+            let mut state = EditState {
+                crop: Some(Rect {
+                    x: 0.2,
+                    y: 0.1,
+                    width: 0.6,
+                    height: 0.7,
+                }),
+                rotate: 90,
+                exposure: 0.2,
+                ..EditState::default()
+            };
+            state.vignette.amount = -0.7;
+            state.vignette.roundness = -0.4;
+            state.vignette.uncropped = uncropped;
+
+            let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+            let gpu = try_apply(&img, &state)
+                .expect("gpu apply should succeed for vignette state")
+                .to_rgba8();
+            assert_rgba_close(&cpu, &gpu, 2);
+        }
+    }
+
+    /// Writes a non-linear look LUT to a temp `.cube` and references it.
+    fn lut_state(name: &str, interpolation: LutInterpolation) -> EditState {
+        let mut look = lut::Lut3d::identity(9);
+        for v in &mut look.data {
+            *v = [v[0].sqrt(), v[1] * v[1], 0.2 + v[2] * 0.6];
+        }
+        let path = std::env::temp_dir().join(format!(
+            "photograph-gpu-lut-{}-{name}.cube",
+            std::process::id()
+        ));
+        std::fs::write(&path, lut::to_cube(&look, name)).unwrap();
+        EditState {
+            lut: Some(LutRef {
+                path,
+                strength: 0.8,
+                interpolation,
+            }),
+            ..EditState::default()
+        }
+    }
+
+    #[test]
+    fn has_gpu_adjustments_includes_lut() {
+        let mut s = lut_state("active", LutInterpolation::Tetrahedral);
+        assert!(has_gpu_adjustments(&s));
+
+        s.lut.as_mut().unwrap().strength = 0.0;
+        assert!(!has_gpu_adjustments(&s));
+        let path = s.lut.take().unwrap().path;
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_lut() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(32, 24, |x, y| {
+            Rgba([
+                ((x * 7 + y * 3) % 256) as u8,
+                ((x * 11 + y * 5) % 256) as u8,
+                ((x * 13 + y * 17) % 256) as u8,
+                255,
+            ])
+        }));
+        for interpolation in [LutInterpolation::Tetrahedral, LutInterpolation::Trilinear] {
+            let state = lut_state(&format!("{interpolation:?}"), interpolation);
+            let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+            let gpu = try_apply(&img, &state)
+                .expect("gpu apply should succeed for lut state")
+                .to_rgba8();
+            assert_rgba_close(&cpu, &gpu, 3);
+            let _ = std::fs::remove_file(&state.lut.as_ref().unwrap().path);
+        }
+    }
+
     #[test]
     fn no_op_returns_image_without_gpu_requirement() {
         let img = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(2, 2, Rgba([10, 20, 30, 255])));
@@ -1641,11 +3580,9 @@ mod tests {
         let mut state = EditState::default();
         state.exposure = 0.25;
         state.saturation = 0.12;
-        state.graduated_filter = Some(GradFilter {
-            top: 0.1,
-            bottom: 0.9,
-            exposure: -0.8,
-        });
+        let mut grad = GradFilter::new(NormPoint { x: 0.5, y: 0.1 }, NormPoint { x: 0.5, y: 0.9 });
+        grad.exposure = -0.8;
+        state.graduated_filters.push(grad);
 
         let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
         let gpu = try_apply(&img, &state)
@@ -1654,6 +3591,49 @@ mod tests {
         assert_rgba_close(&cpu, &gpu, 2);
     }
 
+    #[test]
+    fn parity_matches_cpu_for_rotated_graduated_filters() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(32, 24, |x, y| {
+            Rgba([
+                ((x * 7 + y * 3) % 256) as u8,
+                ((x * 2 + y * 9) % 256) as u8,
+                ((x * 11 + y * 5) % 256) as u8,
+                255,
+            ])
+        }));
+        let mut state = EditState::default();
+        let mut sky = GradFilter::new(NormPoint { x: 0.3, y: 0.0 }, NormPoint { x: 0.5, y: 0.6 });
+        sky.exposure = -0.6;
+        sky.highlights = -0.8;
+        sky.temperature = -0.4;
+        let mut side = GradFilter::new(NormPoint { x: 1.0, y: 0.5 }, NormPoint { x: 0.4, y: 0.4 });
+        side.contrast = 0.3;
+        side.saturation = 0.5;
+        state.graduated_filters = vec![sky, side];
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for rotated graduated filters")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 2);
+    }
+
+    #[test]
+    fn has_gpu_adjustments_includes_graduated_filters() {
+        let mut s = EditState::default();
+        s.graduated_filters.push(GradFilter::new(
+            NormPoint { x: 0.5, y: 0.0 },
+            NormPoint { x: 0.5, y: 1.0 },
+        ));
+        assert!(!has_gpu_adjustments(&s));
+        s.graduated_filters[0].highlights = -0.5;
+        assert!(has_gpu_adjustments(&s));
+    }
+
     #[test]
     fn has_gpu_adjustments_includes_sharpness() {
         let mut s = EditState::default();
@@ -1661,6 +3641,161 @@ mod tests {
         assert!(has_gpu_adjustments(&s));
     }
 
+    fn local_adjustment_state() -> EditState {
+        let mut state = EditState::default();
+        let mut linear = LocalAdjustment::new(MaskShape::Linear {
+            start: NormPoint { x: 0.1, y: 0.2 },
+            end: NormPoint { x: 0.8, y: 0.7 },
+        });
+        linear.exposure = -0.8;
+        linear.temperature = 0.5;
+        let mut radial = LocalAdjustment::new(MaskShape::Radial {
+            center: NormPoint { x: 0.6, y: 0.4 },
+            radius_x: 0.3,
+            radius_y: 0.15,
+            angle: 30.0,
+            feather: 0.6,
+            invert: false,
+        });
+        radial.contrast = 0.4;
+        radial.saturation = -0.7;
+        radial.sharpness = 0.8;
+        let mut brush = LocalAdjustment::new(MaskShape::Brush {
+            strokes: vec![
+                BrushStroke {
+                    points: vec![NormPoint { x: 0.1, y: 0.8 }, NormPoint { x: 0.9, y: 0.5 }],
+                    radius: 0.1,
+                    feather: 0.5,
+                    erase: false,
+                },
+                BrushStroke {
+                    points: vec![NormPoint { x: 0.5, y: 0.3 }, NormPoint { x: 0.5, y: 0.9 }],
+                    radius: 0.05,
+                    feather: 0.0,
+                    erase: true,
+                },
+            ],
+        });
+        brush.exposure = 0.7;
+        brush.sharpness = -0.5;
+        state.local_adjustments = vec![linear, radial, brush];
+        state
+    }
+
+    #[test]
+    fn has_gpu_adjustments_includes_local_adjustments() {
+        let mut s = EditState::default();
+        s.local_adjustments
+            .push(LocalAdjustment::new(MaskShape::Brush {
+                strokes: Vec::new(),
+            }));
+        assert!(!has_gpu_adjustments(&s));
+        s.local_adjustments[0].saturation = 0.3;
+        assert!(has_gpu_adjustments(&s));
+    }
+
+    #[test]
+    fn local_buffers_index_brush_strokes_and_dabs() {
+        let state = local_adjustment_state();
+        let buffers = build_local_buffers(&state, 40, 30);
+        assert_eq!(buffers.adjustments.len(), 3 * 16);
+        assert_eq!(buffers.strokes.len(), 2 * 4);
+        // Brush adjustment points at both strokes; the erase stroke follows
+        // the paint stroke's dabs.
+        assert_eq!(&buffers.adjustments[32..34], &[0.0, 2.0]);
+        let first_dabs = buffers.strokes[1];
+        assert_eq!(buffers.strokes[4], first_dabs);
+        assert_eq!(buffers.strokes[6], 1.0);
+        assert_eq!(
+            buffers.dabs.len() as f32 / 4.0,
+            first_dabs + buffers.strokes[5]
+        );
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_local_adjustments() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(40, 30, |x, y| {
+            let v = if (x / 5 + y / 5) % 2 == 0 { 60 } else { 190 };
+            Rgba([v, (x * 6) as u8, (y * 8) as u8, 255])
+        }));
+        let state = local_adjustment_state();
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for local adjustments")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 3);
+    }
+
+    fn spot_state() -> EditState {
+        EditState {
+            spots: vec![
+                Spot {
+                    center: NormPoint { x: 0.3, y: 0.4 },
+                    source: NormPoint { x: 0.7, y: 0.5 },
+                    radius: 0.12,
+                    feather: 0.5,
+                    mode: SpotMode::Heal,
+                },
+                Spot {
+                    center: NormPoint { x: 0.35, y: 0.5 },
+                    source: NormPoint { x: 0.35, y: 0.1 },
+                    radius: 0.08,
+                    feather: 0.0,
+                    mode: SpotMode::Clone,
+                },
+            ],
+            ..EditState::default()
+        }
+    }
+
+    #[test]
+    fn has_gpu_adjustments_includes_spots() {
+        assert!(has_gpu_adjustments(&spot_state()));
+    }
+
+    #[test]
+    fn spot_buffers_pack_offsets_and_heal_rings() {
+        let img = ImageBuffer::from_fn(40, 30, |x, _| Rgba([(x * 6) as u8, 0, 0, 255]));
+        let buffers = build_spot_buffers(&img, &spot_state());
+        assert_eq!(buffers.spots.len(), 2 * 8);
+        assert_eq!(
+            buffers.rings.len(),
+            2 * crate::processing::spots::HEAL_RING_SAMPLES * 4
+        );
+        // Offsets are whole pixels; only the heal spot carries rim differences.
+        assert_eq!(&buffers.spots[4..7], &[16.0, 3.0, 1.0]);
+        assert_eq!(&buffers.spots[12..15], &[0.0, -12.0, 0.0]);
+        assert!(buffers.rings[..4].iter().any(|v| *v != 0.0));
+        let clone_rings = &buffers.rings[crate::processing::spots::HEAL_RING_SAMPLES * 4..];
+        assert!(clone_rings.iter().all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn parity_matches_cpu_for_spots() {
+        if !super::is_available() {
+            return;
+        }
+
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(40, 30, |x, y| {
+            let v = if (x / 4 + y / 4) % 2 == 0 { 50 } else { 180 };
+            Rgba([v, (x * 6) as u8, (y * 8) as u8, 255])
+        }));
+        let mut state = spot_state();
+        state.rotate = 90;
+        state.exposure = 0.2;
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for spots")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 3);
+    }
+
     #[test]
     fn parity_matches_cpu_for_sharpness() {
         if !super::is_available() {
@@ -1685,6 +3820,36 @@ mod tests {
         assert_rgba_close(&cpu, &gpu, 3);
     }
 
+    #[test]
+    fn parity_matches_cpu_for_sharpening_radius_threshold_and_masking() {
+        if !super::is_available() {
+            return;
+        }
+
+        // Noisy flat left half, bright right half: exercises threshold and edge mask
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(32, 24, |x, y| {
+            if x < 16 {
+                let v = if (x + y) % 2 == 0 { 60 } else { 68 };
+                Rgba([v, v + 4, v, 255])
+            } else {
+                Rgba([210, 200, 190, 255])
+            }
+        }));
+        let state = EditState {
+            sharpness: 1.2,
+            sharpen_radius: 2.5,
+            sharpen_threshold: 0.02,
+            sharpen_masking: 0.6,
+            ..EditState::default()
+        };
+
+        let cpu = crate::processing::transform::apply(&img, &state).to_rgba8();
+        let gpu = try_apply(&img, &state)
+            .expect("gpu apply should succeed for sharpening state")
+            .to_rgba8();
+        assert_rgba_close(&cpu, &gpu, 3);
+    }
+
     #[test]
     fn parity_matches_cpu_combined_color_and_sharpness() {
         if !super::is_available() {
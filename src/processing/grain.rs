@@ -0,0 +1,135 @@
+use image::DynamicImage;
+
+use crate::state::{EditState, Grain};
+
+use super::transform::Region;
+
+/// Luma deviation of a full-strength grain peak in the midtones.
+pub const GRAIN_STRENGTH: f32 = 0.12;
+
+/// Grain cell size in pixels: a fraction of the long edge, so the same grain
+/// pattern appears at preview and export resolution.
+pub fn cell_size(grain: &Grain, w: u32, h: u32) -> f32 {
+    let long = w.max(h) as f32;
+    (long * (0.0005 + 0.002 * grain.size.clamp(0.0, 1.0))).max(0.5)
+}
+
+/// Adds monochromatic film grain, strongest in the midtones. The pattern
+/// is laid over the frame `region` places `img` in.
+pub fn apply(img: DynamicImage, state: &EditState, region: &Region) -> DynamicImage {
+    let grain = state.grain;
+    if !grain.is_active() {
+        return img;
+    }
+
+    let mut rgba = img.to_rgba8();
+    let cell = cell_size(&grain, region.frame.0, region.frame.1);
+    let (ox, oy) = (region.origin.0 as f32, region.origin.1 as f32);
+    let amount = grain.amount.clamp(0.0, 1.0) * GRAIN_STRENGTH;
+    let roughness = grain.roughness.clamp(0.0, 1.0);
+    for (x, y, px) in rgba.enumerate_pixels_mut() {
+        let rgb = [px[0], px[1], px[2]].map(|v| v as f32 / 255.0);
+        let luma = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+        let (fx, fy) = (ox + x as f32 + 0.5, oy + y as f32 + 0.5);
+        let n = noise(fx / cell, fy / cell, roughness);
+        let offset = n * amount * (1.0 - 0.75 * (2.0 * luma - 1.0).powi(2));
+        for c in 0..3 {
+            px[c] = ((rgb[c] + offset).clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Two octaves of value noise in [-1, 1]; `roughness` weights the finer one.
+/// Mirrored by `GRAIN_SHADER_SRC`.
+fn noise(x: f32, y: f32, roughness: f32) -> f32 {
+    let coarse = value_noise(x, y, 0);
+    let fine = value_noise(x * 2.0, y * 2.0, 1);
+    (coarse + roughness * fine) / (1.0 + roughness)
+}
+
+fn value_noise(x: f32, y: f32, octave: u32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (ux, uy) = (fx * fx * (3.0 - 2.0 * fx), fy * fy * (3.0 - 2.0 * fy));
+    let (ix, iy) = (x0 as u32, y0 as u32);
+    let a = lattice(ix, iy, octave);
+    let b = lattice(ix.wrapping_add(1), iy, octave);
+    let c = lattice(ix, iy.wrapping_add(1), octave);
+    let d = lattice(ix.wrapping_add(1), iy.wrapping_add(1), octave);
+    let top = a + (b - a) * ux;
+    let bottom = c + (d - c) * ux;
+    top + (bottom - top) * uy
+}
+
+/// Hashes a lattice point to [-1, 1].
+fn lattice(x: u32, y: u32, octave: u32) -> f32 {
+    let mut h = x.wrapping_mul(0x8da6_b343)
+        ^ y.wrapping_mul(0xd816_3841)
+        ^ octave.wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    (h >> 8) as f32 / 8_388_607.5 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageBuffer, Rgba};
+
+    use crate::processing::transform::Region;
+    use crate::state::{EditState, Grain};
+
+    use super::{cell_size, noise};
+
+    /// Runs the stage over the whole of `img`.
+    fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
+        let region = Region::whole(img.width(), img.height());
+        super::apply(img, state, &region)
+    }
+
+    fn gray(w: u32, h: u32, v: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(ImageBuffer::from_pixel(w, h, Rgba([v, v, v, 255])))
+    }
+
+    #[test]
+    fn zero_amount_is_identity() {
+        let img = gray(16, 16, 128);
+        assert_eq!(
+            apply(img.clone(), &EditState::default()).to_rgba8(),
+            img.to_rgba8()
+        );
+    }
+
+    #[test]
+    fn grain_is_monochromatic_and_deterministic() {
+        let mut state = EditState::default();
+        state.grain.amount = 1.0;
+        let a = apply(gray(32, 32, 128), &state).to_rgba8();
+        let b = apply(gray(32, 32, 128), &state).to_rgba8();
+        assert_eq!(a, b);
+        assert!(a.pixels().all(|p| p[0] == p[1] && p[1] == p[2]));
+        assert!(a.pixels().any(|p| p[0] != 128));
+    }
+
+    #[test]
+    fn grain_scales_with_the_image() {
+        let grain = Grain {
+            amount: 1.0,
+            ..Grain::default()
+        };
+        let preview = cell_size(&grain, 1920, 1280);
+        let export = cell_size(&grain, 7680, 5120);
+        assert!((export / preview - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn noise_stays_in_range() {
+        for i in 0..500 {
+            let v = noise(i as f32 * 0.37, i as f32 * 0.91, 1.0);
+            assert!((-1.0..=1.0).contains(&v));
+        }
+    }
+}
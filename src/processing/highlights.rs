@@ -52,8 +52,7 @@ pub fn recover(pixels: &mut [[f32; 3]]) {
 
         // At least one channel is unclipped due to early-continue above.
         let target_luma = unclipped_luma_sum / unclipped_weight_sum;
-        let original_luma =
-            original[0] * LUMA[0] + original[1] * LUMA[1] + original[2] * LUMA[2];
+        let original_luma = original[0] * LUMA[0] + original[1] * LUMA[1] + original[2] * LUMA[2];
         let chroma = [
             original[0] - original_luma,
             original[1] - original_luma,
@@ -131,10 +130,7 @@ mod tests {
         let mut pixels = vec![[1.0, 1.0, 0.7]];
         recover(&mut pixels);
         // Both clipped channels should be reconstructed toward blue's value.
-        assert!(
-            pixels[0][0] < 1.0,
-            "red should be reconstructed below 1.0"
-        );
+        assert!(pixels[0][0] < 1.0, "red should be reconstructed below 1.0");
         assert!(
             pixels[0][1] < 1.0,
             "green should be reconstructed below 1.0"
@@ -0,0 +1,734 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use image::{DynamicImage, RgbaImage};
+use moxcms::{
+    ColorProfile, DataColorSpace, Layout, ProfileText, RenderingIntent, Transform8BitExecutor,
+    TransformOptions,
+};
+use rawler::RawImage;
+use rawler::imgop::develop::Intermediate;
+use rawler::imgop::matrix::{multiply, normalize, pseudo_inverse};
+use rawler::imgop::raw::clip_euclidean_norm_avg;
+use rawler::imgop::xyz::{Illuminant, SRGB_TO_XYZ_D65};
+use rawler::pixarray::RgbF32;
+use rayon::prelude::*;
+
+/// Environment override for the working space (`srgb`, `rec2020` or
+/// `prophoto`).
+pub const WORKING_SPACE_ENV: &str = "PHOTOGRAPH_WORKING_SPACE";
+
+/// Bradford adaptation from the D50 ICC connection space to D65, the white
+/// rawler's camera matrices are referenced to.
+const BRADFORD_D50_TO_D65: [[f32; 3]; 3] = [
+    [0.955_576_6, -0.023_039_3, 0.063_163_6],
+    [-0.028_289_5, 1.009_941_6, 0.021_007_7],
+    [0.012_298_2, -0.020_483_0, 1.329_909_8],
+];
+
+/// How far outside [0, 1] a linear output channel may land before the gamut
+/// warning flags it; absorbs 8-bit quantization of in-gamut colors.
+const GAMUT_TOLERANCE: f32 = 0.01;
+/// Color painted over out-of-gamut pixels while soft proofing.
+pub const GAMUT_WARNING_RGB: [u8; 3] = [255, 0, 255];
+
+static WORKING_SPACE: OnceLock<WorkingSpace> = OnceLock::new();
+static SRGB_DISPLAY: OnceLock<DisplayTransform> = OnceLock::new();
+
+/// RGB primaries the edit pipeline runs in.
+///
+/// Pixels keep the sRGB tone curve over these primaries so 8-bit preview
+/// textures keep their shadow precision; exposure and blending linearize
+/// through that curve exactly as they did for sRGB data.
+///
+/// sRGB is the default: untagged files, sRGB exports and RAW calibration
+/// then skip every conversion, so edits made before color management render
+/// unchanged. The wide spaces are opt-in; they avoid clipping saturated
+/// camera colors, but the same slider values land on different pixels and
+/// 8-bit steps cover a larger gamut, so saturated gradients band sooner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WorkingSpace {
+    #[default]
+    Srgb,
+    Rec2020,
+    ProPhoto,
+}
+
+impl WorkingSpace {
+    pub fn label(self) -> &'static str {
+        match self {
+            WorkingSpace::Srgb => "sRGB",
+            WorkingSpace::Rec2020 => "Rec.2020",
+            WorkingSpace::ProPhoto => "ProPhoto RGB",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "srgb" | "rec709" | "rec.709" => Some(WorkingSpace::Srgb),
+            "rec2020" | "bt2020" | "rec.2020" => Some(WorkingSpace::Rec2020),
+            "prophoto" | "prophoto_rgb" | "romm" => Some(WorkingSpace::ProPhoto),
+            _ => None,
+        }
+    }
+
+    pub fn profile(self) -> ColorProfile {
+        let mut profile = match self {
+            WorkingSpace::Srgb => return ColorProfile::new_srgb(),
+            WorkingSpace::Rec2020 => ColorProfile::new_bt2020(),
+            WorkingSpace::ProPhoto => ColorProfile::new_pro_photo_rgb(),
+        };
+        let srgb = ColorProfile::new_srgb();
+        profile.red_trc = srgb.red_trc;
+        profile.green_trc = srgb.green_trc;
+        profile.blue_trc = srgb.blue_trc;
+        profile.cicp = None;
+        profile
+    }
+
+    /// Linear working RGB to CIE XYZ relative to D65.
+    fn to_xyz_d65(self) -> [[f32; 3]; 3] {
+        // The matrix rawler's own `Calibrate` step uses
+        if self == WorkingSpace::Srgb {
+            return SRGB_TO_XYZ_D65;
+        }
+        let pcs = self
+            .profile()
+            .rgb_to_xyz_matrix()
+            .v
+            .map(|row| row.map(|v| v as f32));
+        multiply(&BRADFORD_D50_TO_D65, &pcs)
+    }
+}
+
+/// Sets the process-wide working space. Only the first call takes effect, so
+/// it must run before any image is opened.
+pub fn init_working_space(space: WorkingSpace) {
+    let _ = WORKING_SPACE.set(space);
+}
+
+pub fn working_space() -> WorkingSpace {
+    *WORKING_SPACE.get_or_init(WorkingSpace::default)
+}
+
+/// Profile embedded in rendered files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputProfile {
+    #[default]
+    Srgb,
+    DisplayP3,
+    AdobeRgb,
+}
+
+impl OutputProfile {
+    pub const ALL: [OutputProfile; 3] = [
+        OutputProfile::Srgb,
+        OutputProfile::DisplayP3,
+        OutputProfile::AdobeRgb,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OutputProfile::Srgb => "sRGB",
+            OutputProfile::DisplayP3 => "Display P3",
+            OutputProfile::AdobeRgb => "Adobe RGB (1998)",
+        }
+    }
+
+    pub fn profile(self) -> ColorProfile {
+        match self {
+            OutputProfile::Srgb => ColorProfile::new_srgb(),
+            OutputProfile::DisplayP3 => ColorProfile::new_display_p3(),
+            OutputProfile::AdobeRgb => ColorProfile::new_adobe_rgb(),
+        }
+    }
+
+    /// Serialized ICC profile for embedding in an encoded file.
+    pub fn icc_bytes(self) -> anyhow::Result<Vec<u8>> {
+        self.profile()
+            .encode()
+            .map_err(|err| anyhow::anyhow!("failed to encode {} profile: {err:?}", self.label()))
+    }
+}
+
+/// Converts a decoded image from its embedded ICC profile into the working
+/// space. Sources without a usable RGB profile are treated as sRGB.
+pub fn to_working(img: DynamicImage, icc: Option<&[u8]>) -> DynamicImage {
+    to_space(img, icc, working_space())
+}
+
+fn to_space(img: DynamicImage, icc: Option<&[u8]>, space: WorkingSpace) -> DynamicImage {
+    let source = icc
+        .and_then(|bytes| ColorProfile::new_from_slice(bytes).ok())
+        .filter(|profile| profile.color_space == DataColorSpace::Rgb);
+    if source.is_none() && space == WorkingSpace::Srgb {
+        return img;
+    }
+    let source = source.unwrap_or_else(ColorProfile::new_srgb);
+    convert(img, &source, &space.profile()).unwrap_or_else(|(img, _)| img)
+}
+
+/// Converts working-space pixels into `profile` for writing to disk.
+pub fn to_output(img: DynamicImage, profile: OutputProfile) -> anyhow::Result<DynamicImage> {
+    let space = working_space();
+    if space == WorkingSpace::Srgb && profile == OutputProfile::Srgb {
+        return Ok(img);
+    }
+    convert(img, &space.profile(), &profile.profile()).map_err(|(_, err)| err)
+}
+
+/// Rendering intent used when soft proofing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProofIntent {
+    #[default]
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+impl ProofIntent {
+    pub const ALL: [ProofIntent; 4] = [
+        ProofIntent::Perceptual,
+        ProofIntent::RelativeColorimetric,
+        ProofIntent::Saturation,
+        ProofIntent::AbsoluteColorimetric,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ProofIntent::Perceptual => "Perceptual",
+            ProofIntent::RelativeColorimetric => "Relative colorimetric",
+            ProofIntent::Saturation => "Saturation",
+            ProofIntent::AbsoluteColorimetric => "Absolute colorimetric",
+        }
+    }
+
+    fn rendering_intent(self) -> RenderingIntent {
+        match self {
+            ProofIntent::Perceptual => RenderingIntent::Perceptual,
+            ProofIntent::RelativeColorimetric => RenderingIntent::RelativeColorimetric,
+            ProofIntent::Saturation => RenderingIntent::Saturation,
+            ProofIntent::AbsoluteColorimetric => RenderingIntent::AbsoluteColorimetric,
+        }
+    }
+}
+
+/// Output simulated on screen while soft proofing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SoftProof {
+    pub profile: OutputProfile,
+    pub intent: ProofIntent,
+    /// Paint pixels the output profile cannot reproduce with
+    /// [`GAMUT_WARNING_RGB`].
+    pub gamut_warning: bool,
+}
+
+/// Where the monitor profile comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MonitorProfile {
+    /// Treat the display as sRGB.
+    #[default]
+    Srgb,
+    /// Ask colord for the default profile of the first display.
+    System,
+    File(PathBuf),
+}
+
+impl MonitorProfile {
+    /// Parses the `monitor_profile` config value: `srgb`, `colord`, or a path.
+    pub fn parse(raw: &str) -> Self {
+        match raw.trim() {
+            "" | "srgb" | "none" => MonitorProfile::Srgb,
+            "colord" | "system" => MonitorProfile::System,
+            path => MonitorProfile::File(PathBuf::from(path)),
+        }
+    }
+
+    pub fn config_value(&self) -> String {
+        match self {
+            MonitorProfile::Srgb => "srgb".to_string(),
+            MonitorProfile::System => "colord".to_string(),
+            MonitorProfile::File(path) => path.display().to_string(),
+        }
+    }
+
+    /// Loads the profile, or `None` for plain sRGB.
+    pub fn load(&self) -> anyhow::Result<Option<ColorProfile>> {
+        let path = match self {
+            MonitorProfile::Srgb => return Ok(None),
+            MonitorProfile::System => colord_display_profile()?,
+            MonitorProfile::File(path) => path.clone(),
+        };
+        load_profile(&path).map(Some)
+    }
+}
+
+/// Reads and validates an RGB ICC profile from disk.
+pub fn load_profile(path: &Path) -> anyhow::Result<ColorProfile> {
+    let bytes = std::fs::read(path)?;
+    let profile = ColorProfile::new_from_slice(&bytes)
+        .map_err(|err| anyhow::anyhow!("invalid ICC profile {}: {err:?}", path.display()))?;
+    if profile.color_space != DataColorSpace::Rgb {
+        anyhow::bail!("{} is not an RGB profile", path.display());
+    }
+    Ok(profile)
+}
+
+/// Human-readable name stored in a profile, if any.
+pub fn profile_description(profile: &ColorProfile) -> Option<String> {
+    let text = match profile.description.as_ref()? {
+        ProfileText::PlainString(s) => s.clone(),
+        ProfileText::Localizable(strings) => strings.first()?.value.clone(),
+        ProfileText::Description(d) => d.ascii_string.clone(),
+    };
+    let text = text.trim_end_matches('\0').trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Queries colord through `colormgr` for the first display's default profile.
+fn colord_display_profile() -> anyhow::Result<PathBuf> {
+    let devices = colormgr(&["get-devices-by-kind", "display"])?;
+    let device = colormgr_field(&devices, "Device ID")
+        .ok_or_else(|| anyhow::anyhow!("colord reports no display devices"))?;
+    let profile = colormgr(&["device-get-default-profile", &device])?;
+    colormgr_field(&profile, "Filename")
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("colord has no profile for display {device}"))
+}
+
+fn colormgr(args: &[&str]) -> anyhow::Result<String> {
+    let output = std::process::Command::new("colormgr").args(args).output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "colormgr {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// First `Key: value` field named `key` in `colormgr` output.
+fn colormgr_field(output: &str, key: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        (k.trim() == key).then(|| v.trim().to_string())
+    })
+}
+
+/// Converts working-space preview pixels for the screen, optionally
+/// simulating an output profile first, then into the monitor profile.
+pub struct DisplayTransform {
+    to_display: Option<Arc<Transform8BitExecutor>>,
+    proof: Option<ProofStage>,
+}
+
+struct ProofStage {
+    to_output: Arc<Transform8BitExecutor>,
+    output_to_display: Arc<Transform8BitExecutor>,
+    /// Linear working RGB to linear output RGB, when the gamut warning is on.
+    gamut: Option<[[f32; 3]; 3]>,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl DisplayTransform {
+    /// `monitor` of `None` means the display is treated as sRGB.
+    pub fn new(monitor: Option<&ColorProfile>, proof: Option<SoftProof>) -> Self {
+        Self::in_space(working_space(), monitor, proof)
+    }
+
+    fn in_space(
+        space: WorkingSpace,
+        monitor: Option<&ColorProfile>,
+        proof: Option<SoftProof>,
+    ) -> Self {
+        let working = space.profile();
+        let display = monitor.cloned().unwrap_or_else(ColorProfile::new_srgb);
+        let options = TransformOptions::default();
+        // sRGB pixels on an sRGB display are shown as they are
+        let to_display = (monitor.is_some() || space != WorkingSpace::Srgb)
+            .then(|| {
+                working
+                    .create_transform_8bit(Layout::Rgba, &display, Layout::Rgba, options)
+                    .ok()
+            })
+            .flatten();
+        let proof = proof.and_then(|proof| {
+            let output = proof.profile.profile();
+            let to_output = working
+                .create_transform_8bit(
+                    Layout::Rgba,
+                    &output,
+                    Layout::Rgba,
+                    TransformOptions {
+                        rendering_intent: proof.intent.rendering_intent(),
+                        ..options
+                    },
+                )
+                .ok()?;
+            let output_to_display = output
+                .create_transform_8bit(Layout::Rgba, &display, Layout::Rgba, options)
+                .ok()?;
+            let gamut = proof.gamut_warning.then(|| {
+                working
+                    .transform_matrix(&output)
+                    .v
+                    .map(|row| row.map(|v| v as f32))
+            });
+            Some(ProofStage {
+                to_output,
+                output_to_display,
+                gamut,
+            })
+        });
+        Self { to_display, proof }
+    }
+
+    pub fn apply(&self, img: &mut RgbaImage) {
+        let Some(proof) = &self.proof else {
+            if let Some(transform) = &self.to_display {
+                let src = img.as_raw().clone();
+                let _ = transform.transform(&src, img);
+            }
+            return;
+        };
+
+        let working = img.as_raw().clone();
+        let mut output = working.clone();
+        if proof.to_output.transform(&working, &mut output).is_err()
+            || proof.output_to_display.transform(&output, img).is_err()
+        {
+            return;
+        }
+        if let Some(m) = &proof.gamut {
+            let lut = srgb_to_linear_lut();
+            for (dst, src) in img.chunks_exact_mut(4).zip(working.chunks_exact(4)) {
+                let lin = [
+                    lut[src[0] as usize],
+                    lut[src[1] as usize],
+                    lut[src[2] as usize],
+                ];
+                let out_of_gamut = m.iter().any(|row| {
+                    let v = row[0] * lin[0] + row[1] * lin[1] + row[2] * lin[2];
+                    !(-GAMUT_TOLERANCE..=1.0 + GAMUT_TOLERANCE).contains(&v)
+                });
+                if out_of_gamut {
+                    dst[..3].copy_from_slice(&GAMUT_WARNING_RGB);
+                }
+            }
+        }
+    }
+}
+
+fn srgb_to_linear_lut() -> &'static [f32; 256] {
+    static LUT: OnceLock<[f32; 256]> = OnceLock::new();
+    LUT.get_or_init(|| std::array::from_fn(|i| srgb_decode(i as f32 / 255.0)))
+}
+
+/// sRGB tone curve, encoded value to linear light.
+pub fn srgb_decode(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// sRGB tone curve, linear light to encoded value.
+pub fn srgb_encode(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Linear working RGB to linear Rec.709 (sRGB primaries).
+pub fn working_to_rec709() -> [[f32; 3]; 3] {
+    working_space()
+        .profile()
+        .transform_matrix(&ColorProfile::new_srgb())
+        .v
+        .map(|row| row.map(|v| v as f32))
+}
+
+/// Linear Rec.709 (sRGB primaries) to linear working RGB.
+pub fn rec709_to_working() -> [[f32; 3]; 3] {
+    ColorProfile::new_srgb()
+        .transform_matrix(&working_space().profile())
+        .v
+        .map(|row| row.map(|v| v as f32))
+}
+
+/// Display-ready sRGB copy of a working-space image.
+pub fn to_display(img: &DynamicImage) -> DynamicImage {
+    let mut rgba = img.to_rgba8();
+    SRGB_DISPLAY
+        .get_or_init(DisplayTransform::default)
+        .apply(&mut rgba);
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Grayscale images pass through unchanged: every profile here shares the
+/// sRGB tone curve for neutrals, and relative rendering keeps them neutral.
+fn convert(
+    img: DynamicImage,
+    src: &ColorProfile,
+    dst: &ColorProfile,
+) -> Result<DynamicImage, (DynamicImage, anyhow::Error)> {
+    let options = TransformOptions::default();
+    match img {
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_) => Ok(img),
+        DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) => {
+            let transform =
+                match src.create_transform_8bit(Layout::Rgba, dst, Layout::Rgba, options) {
+                    Ok(transform) => transform,
+                    Err(err) => return Err((img, anyhow::anyhow!("color transform: {err:?}"))),
+                };
+            let src_px = img.to_rgba8();
+            let mut out = src_px.clone();
+            match transform.transform(&src_px, &mut out) {
+                Ok(()) => Ok(DynamicImage::ImageRgba8(out)),
+                Err(err) => Err((img, anyhow::anyhow!("color transform: {err:?}"))),
+            }
+        }
+        _ => {
+            let transform =
+                match src.create_transform_16bit(Layout::Rgba, dst, Layout::Rgba, options) {
+                    Ok(transform) => transform,
+                    Err(err) => return Err((img, anyhow::anyhow!("color transform: {err:?}"))),
+                };
+            let src_px = img.to_rgba16();
+            let mut out = src_px.clone();
+            match transform.transform(&src_px, &mut out) {
+                Ok(()) => Ok(DynamicImage::ImageRgba16(out)),
+                Err(err) => Err((img, anyhow::anyhow!("color transform: {err:?}"))),
+            }
+        }
+    }
+}
+
+/// Maps white-balanced camera RGB into linear working RGB using the camera
+/// matrix rawler's `Calibrate` step would use, without its detour through
+/// sRGB. Returns the intermediate unchanged when the camera has no matrix.
+pub fn calibrate_raw(raw: &RawImage, intermediate: Intermediate) -> Intermediate {
+    let matrix = raw
+        .color_matrix
+        .iter()
+        .find(|(illuminant, _)| **illuminant == Illuminant::D65)
+        .or_else(|| raw.color_matrix.iter().next())
+        .map(|(_, m)| m)
+        .filter(|m| !m.is_empty() && m.len() % 3 == 0 && m.len() <= 12);
+    let Some(matrix) = matrix else {
+        return intermediate;
+    };
+
+    let mut xyz2cam = [[0.0f32; 3]; 4];
+    for (row, chunk) in xyz2cam.iter_mut().zip(matrix.chunks_exact(3)) {
+        row.copy_from_slice(chunk);
+    }
+    let wb = if raw.wb_coeffs[0].is_nan() {
+        [1.0; 4]
+    } else {
+        raw.wb_coeffs
+    };
+    let to_xyz = working_space().to_xyz_d65();
+
+    match intermediate {
+        Intermediate::ThreeColor(pixels) => {
+            let xyz2cam = [xyz2cam[0], xyz2cam[1], xyz2cam[2]];
+            let cam2rgb = pseudo_inverse(normalize(multiply(&xyz2cam, &to_xyz)));
+            let data = pixels
+                .pixels()
+                .par_iter()
+                .map(|px| {
+                    let cam = [px[0] * wb[0], px[1] * wb[1], px[2] * wb[2]];
+                    clip_euclidean_norm_avg(&apply_matrix(&cam2rgb, &cam))
+                })
+                .collect();
+            Intermediate::ThreeColor(RgbF32::new_with(data, pixels.width, pixels.height))
+        }
+        Intermediate::FourColor(pixels) => {
+            let cam2rgb = pseudo_inverse(normalize(multiply(&xyz2cam, &to_xyz)));
+            let data = pixels
+                .pixels()
+                .par_iter()
+                .map(|px| {
+                    let cam = [px[0] * wb[0], px[1] * wb[1], px[2] * wb[2], px[3] * wb[3]];
+                    clip_euclidean_norm_avg(&apply_matrix(&cam2rgb, &cam))
+                })
+                .collect();
+            Intermediate::ThreeColor(RgbF32::new_with(data, pixels.width, pixels.height))
+        }
+        Intermediate::Monochrome(_) => intermediate,
+    }
+}
+
+fn apply_matrix<const N: usize>(m: &[[f32; N]; 3], px: &[f32; N]) -> [f32; 3] {
+    m.map(|row| row.iter().zip(px).map(|(a, b)| a * b).sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn srgb_working_space_leaves_untagged_pixels_alone() {
+        assert_eq!(working_space(), WorkingSpace::Srgb);
+        let img = RgbaImage::from_fn(8, 1, |x, _| Rgba([x as u8 * 30, 200, 255 - x as u8, 255]));
+        let working = to_working(DynamicImage::ImageRgba8(img.clone()), None);
+        assert_eq!(working.to_rgba8(), img);
+        let out = to_output(working.clone(), OutputProfile::Srgb).unwrap();
+        assert_eq!(out.to_rgba8(), img);
+        assert_eq!(to_display(&working).to_rgba8(), img);
+    }
+
+    #[test]
+    fn srgb_primaries_move_inward_in_working_space() {
+        let img = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 255]));
+        let out = to_space(DynamicImage::ImageRgba8(img), None, WorkingSpace::Rec2020).to_rgba8();
+        let px = out.get_pixel(0, 0);
+        assert!(
+            px[0] < 255 && px[1] > 0,
+            "sRGB red in working space: {px:?}"
+        );
+        assert_eq!(px[3], 255);
+    }
+
+    #[test]
+    fn neutrals_survive_round_trip() {
+        let img = RgbaImage::from_fn(4, 1, |x, _| {
+            let v = (x * 80) as u8;
+            Rgba([v, v, v, 255])
+        });
+        let working = to_working(DynamicImage::ImageRgba8(img.clone()), None);
+        let out = to_output(working, OutputProfile::Srgb).unwrap().to_rgba8();
+        for (a, b) in img.pixels().zip(out.pixels()) {
+            for c in 0..3 {
+                assert!((a[c] as i32 - b[c] as i32).abs() <= 1, "{a:?} -> {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn embedded_profile_is_honored() {
+        // Display P3 red lies outside sRGB, so it must land further out in the
+        // working space than sRGB red does.
+        let red = || DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 255])));
+        let p3 = OutputProfile::DisplayP3.icc_bytes().unwrap();
+        let from_srgb = to_space(red(), None, WorkingSpace::Rec2020).to_rgba8();
+        let from_p3 = to_space(red(), Some(&p3), WorkingSpace::Rec2020).to_rgba8();
+        assert!(from_p3.get_pixel(0, 0)[0] > from_srgb.get_pixel(0, 0)[0]);
+        assert!(from_p3.get_pixel(0, 0)[1] <= from_srgb.get_pixel(0, 0)[1]);
+    }
+
+    #[test]
+    fn output_profiles_encode() {
+        for profile in OutputProfile::ALL {
+            let bytes = profile.icc_bytes().unwrap();
+            assert!(
+                ColorProfile::new_from_slice(&bytes).is_ok(),
+                "{}",
+                profile.label()
+            );
+        }
+    }
+
+    #[test]
+    fn working_space_white_maps_to_d65() {
+        for space in [
+            WorkingSpace::Srgb,
+            WorkingSpace::Rec2020,
+            WorkingSpace::ProPhoto,
+        ] {
+            let m = space.to_xyz_d65();
+            let white: Vec<f32> = m.iter().map(|row| row.iter().sum()).collect();
+            assert!(
+                (white[0] - 0.9505).abs() < 0.005,
+                "{}: {white:?}",
+                space.label()
+            );
+            assert!(
+                (white[1] - 1.0).abs() < 0.005,
+                "{}: {white:?}",
+                space.label()
+            );
+            assert!(
+                (white[2] - 1.089).abs() < 0.005,
+                "{}: {white:?}",
+                space.label()
+            );
+        }
+    }
+
+    #[test]
+    fn gamut_warning_flags_only_colors_outside_output() {
+        let working = |rgb: [u8; 3]| {
+            let img = RgbaImage::from_pixel(1, 1, Rgba([rgb[0], rgb[1], rgb[2], 255]));
+            to_space(DynamicImage::ImageRgba8(img), None, WorkingSpace::Rec2020).to_rgba8()
+        };
+        let proof = SoftProof {
+            profile: OutputProfile::Srgb,
+            intent: ProofIntent::RelativeColorimetric,
+            gamut_warning: true,
+        };
+        let display = DisplayTransform::in_space(WorkingSpace::Rec2020, None, Some(proof));
+
+        let mut in_gamut = working([200, 120, 60]);
+        display.apply(&mut in_gamut);
+        assert_ne!(in_gamut.get_pixel(0, 0).0[..3], GAMUT_WARNING_RGB);
+
+        // Pure working-space green is far outside sRGB.
+        let mut out_of_gamut = RgbaImage::from_pixel(1, 1, Rgba([0, 255, 0, 255]));
+        display.apply(&mut out_of_gamut);
+        assert_eq!(out_of_gamut.get_pixel(0, 0).0[..3], GAMUT_WARNING_RGB);
+    }
+
+    #[test]
+    fn srgb_display_round_trips_srgb_sources() {
+        let img = RgbaImage::from_pixel(1, 1, Rgba([200, 120, 60, 255]));
+        let working = to_working(DynamicImage::ImageRgba8(img.clone()), None);
+        let shown = to_display(&working).to_rgba8();
+        for c in 0..3 {
+            let d = shown.get_pixel(0, 0)[c] as i32 - img.get_pixel(0, 0)[c] as i32;
+            assert!(d.abs() <= 2, "{:?}", shown.get_pixel(0, 0));
+        }
+    }
+
+    #[test]
+    fn reads_colormgr_fields() {
+        let output = "Object Path:   /org/freedesktop/ColorManager/devices/xrandr\n\
+                      Device ID:     xrandr-Dell-U2720Q\n\
+                      Filename:      /home/me/.local/share/icc/dell.icc\n";
+        assert_eq!(
+            colormgr_field(output, "Device ID").as_deref(),
+            Some("xrandr-Dell-U2720Q")
+        );
+        assert_eq!(
+            colormgr_field(output, "Filename").as_deref(),
+            Some("/home/me/.local/share/icc/dell.icc")
+        );
+        assert_eq!(MonitorProfile::parse("colord"), MonitorProfile::System);
+    }
+
+    #[test]
+    fn parses_config_spellings() {
+        assert_eq!(
+            WorkingSpace::parse("ProPhoto"),
+            Some(WorkingSpace::ProPhoto)
+        );
+        assert_eq!(WorkingSpace::parse("rec2020"), Some(WorkingSpace::Rec2020));
+        assert_eq!(WorkingSpace::parse("sRGB"), Some(WorkingSpace::Srgb));
+        assert_eq!(WorkingSpace::parse("adobe"), None);
+    }
+}
@@ -0,0 +1,555 @@
+use image::{DynamicImage, RgbaImage};
+
+use crate::state::{EditState, LocalAdjustment, MaskShape};
+
+use super::transform::Region;
+
+/// Brush dabs are placed along a stroke every `DAB_SPACING * radius` pixels.
+const DAB_SPACING: f32 = 0.25;
+/// Gaussian sigma (px) of the blur that local sharpness pushes away from.
+pub const SHARPEN_SIGMA: f32 = 1.0;
+/// Smallest sharpening sigma on downscaled frames, below which the blur
+/// no longer reaches the neighboring pixels.
+const MIN_SHARPEN_SIGMA: f32 = 0.3;
+
+/// Mask geometry resolved to pixel units for one output size.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedMask {
+    Linear {
+        start: [f32; 2],
+        end: [f32; 2],
+    },
+    Radial {
+        center: [f32; 2],
+        radius: [f32; 2],
+        /// Rotation in radians.
+        angle: f32,
+        feather: f32,
+        invert: bool,
+    },
+    Brush {
+        strokes: Vec<ResolvedStroke>,
+    },
+}
+
+/// A brush stroke flattened into circular dabs (`[x, y, radius, feather]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedStroke {
+    pub dabs: Vec<[f32; 4]>,
+    pub erase: bool,
+}
+
+/// Applies the masked exposure, contrast, white balance, saturation and
+/// sharpness of every active local adjustment, with masks placed in the
+/// frame `region` places `img` in and the sharpening blur scaled to it.
+pub fn apply(img: DynamicImage, state: &EditState, region: &Region) -> DynamicImage {
+    let active: Vec<&LocalAdjustment> = state
+        .local_adjustments
+        .iter()
+        .filter(|adj| adj.is_active())
+        .collect();
+    if active.is_empty() {
+        return img;
+    }
+
+    let src = img.to_rgba8();
+    let (w, h) = src.dimensions();
+    let weights: Vec<Vec<f32>> = active
+        .iter()
+        .map(|adj| mask_weights(&resolve_in(&adj.mask, region), w, h))
+        .collect();
+    let any_sharpen = active.iter().any(|adj| adj.sharpness.abs() > 0.001);
+    let sigma = sharpen_sigma(region.scale);
+
+    let mut out = src.clone();
+    for (x, y, px) in out.enumerate_pixels_mut() {
+        let i = (y * w + x) as usize;
+        let s = [
+            px[0] as f32 / 255.0,
+            px[1] as f32 / 255.0,
+            px[2] as f32 / 255.0,
+        ];
+        let mut rgb = s;
+        let mut sharpen = 0.0;
+        for (adj, mask) in active.iter().zip(&weights) {
+            let weight = mask[i];
+            if weight <= 0.0 {
+                continue;
+            }
+            rgb = apply_tone(rgb, &Tone::from(*adj), weight);
+            sharpen += adj.sharpness.clamp(-1.0, 1.0) * weight;
+        }
+        if any_sharpen && sharpen.abs() > 0.0 {
+            let b = blurred_at(&src, x as i32, y as i32, sigma);
+            for c in 0..3 {
+                rgb[c] += sharpen * (s[c] - b[c]);
+            }
+        }
+        for c in 0..3 {
+            px[c] = (rgb[c].clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+
+    DynamicImage::ImageRgba8(out)
+}
+
+/// Pixels around each output pixel that [`apply`] reads at
+/// [`Region::scale`] `scale`.
+pub fn reach(state: &EditState, scale: f32) -> u32 {
+    let sharpens = state
+        .local_adjustments
+        .iter()
+        .any(|adj| adj.is_active() && adj.sharpness.abs() > 0.001);
+    if sharpens {
+        kernel_radius(sharpen_sigma(scale)) as u32
+    } else {
+        0
+    }
+}
+
+/// Sharpening sigma for a frame at [`Region::scale`] `scale`.
+fn sharpen_sigma(scale: f32) -> f32 {
+    (SHARPEN_SIGMA * scale).max(MIN_SHARPEN_SIGMA)
+}
+
+/// Radius of the sharpening kernel for `sigma`, `ceil(2 * sigma)`.
+fn kernel_radius(sigma: f32) -> i32 {
+    (2.0 * sigma).ceil() as i32
+}
+
+/// Converts a normalized mask into pixel units of an image that is `region`
+/// of its frame.
+pub fn resolve_in(mask: &MaskShape, region: &Region) -> ResolvedMask {
+    let (x, y) = (region.origin.0 as f32, region.origin.1 as f32);
+    let shift = |p: [f32; 2]| [p[0] - x, p[1] - y];
+    match resolve(mask, region.frame.0, region.frame.1) {
+        ResolvedMask::Linear { start, end } => ResolvedMask::Linear {
+            start: shift(start),
+            end: shift(end),
+        },
+        ResolvedMask::Radial {
+            center,
+            radius,
+            angle,
+            feather,
+            invert,
+        } => ResolvedMask::Radial {
+            center: shift(center),
+            radius,
+            angle,
+            feather,
+            invert,
+        },
+        ResolvedMask::Brush { mut strokes } => {
+            for dab in strokes.iter_mut().flat_map(|stroke| &mut stroke.dabs) {
+                [dab[0], dab[1]] = shift([dab[0], dab[1]]);
+            }
+            ResolvedMask::Brush { strokes }
+        }
+    }
+}
+
+/// Converts a normalized mask into pixel units for a `w`×`h` image.
+pub fn resolve(mask: &MaskShape, w: u32, h: u32) -> ResolvedMask {
+    let (wf, hf) = (w as f32, h as f32);
+    let long_edge = wf.max(hf);
+    match mask {
+        MaskShape::Linear { start, end } => ResolvedMask::Linear {
+            start: [start.x * wf, start.y * hf],
+            end: [end.x * wf, end.y * hf],
+        },
+        MaskShape::Radial {
+            center,
+            radius_x,
+            radius_y,
+            angle,
+            feather,
+            invert,
+        } => ResolvedMask::Radial {
+            center: [center.x * wf, center.y * hf],
+            radius: [
+                (radius_x * long_edge).max(0.5),
+                (radius_y * long_edge).max(0.5),
+            ],
+            angle: angle.to_radians(),
+            feather: feather.clamp(0.0, 1.0),
+            invert: *invert,
+        },
+        MaskShape::Brush { strokes } => ResolvedMask::Brush {
+            strokes: strokes
+                .iter()
+                .map(|stroke| {
+                    let radius = (stroke.radius * long_edge).max(0.5);
+                    let feather = stroke.feather.clamp(0.0, 1.0);
+                    let points: Vec<[f32; 2]> =
+                        stroke.points.iter().map(|p| [p.x * wf, p.y * hf]).collect();
+                    ResolvedStroke {
+                        dabs: stroke_dabs(&points, radius, feather),
+                        erase: stroke.erase,
+                    }
+                })
+                .collect(),
+        },
+    }
+}
+
+/// Mask coverage (0–1) at pixel-space position `(px, py)`.
+pub fn weight_at(mask: &ResolvedMask, px: f32, py: f32) -> f32 {
+    match mask {
+        ResolvedMask::Linear { start, end } => {
+            let d = [end[0] - start[0], end[1] - start[1]];
+            let len_sq = d[0] * d[0] + d[1] * d[1];
+            if len_sq < 1e-6 {
+                return 0.0;
+            }
+            let t = ((px - start[0]) * d[0] + (py - start[1]) * d[1]) / len_sq;
+            (1.0 - t).clamp(0.0, 1.0)
+        }
+        ResolvedMask::Radial {
+            center,
+            radius,
+            angle,
+            feather,
+            invert,
+        } => {
+            let (rx, ry) = (px - center[0], py - center[1]);
+            let (s, c) = angle.sin_cos();
+            let qx = rx * c + ry * s;
+            let qy = -rx * s + ry * c;
+            let r = ((qx / radius[0]).powi(2) + (qy / radius[1]).powi(2)).sqrt();
+            let w = feather_falloff(r, *feather);
+            if *invert { 1.0 - w } else { w }
+        }
+        ResolvedMask::Brush { strokes } => {
+            let mut w = 0.0_f32;
+            for stroke in strokes {
+                let mut sw = 0.0_f32;
+                for dab in &stroke.dabs {
+                    let dist = ((px - dab[0]).powi(2) + (py - dab[1]).powi(2)).sqrt();
+                    if dist < dab[2] {
+                        sw = sw.max(feather_falloff(dist / dab[2], dab[3]));
+                    }
+                }
+                w = combine_stroke(w, sw, stroke.erase);
+            }
+            w
+        }
+    }
+}
+
+/// Per-pixel mask coverage in row-major order, sampled at pixel centers.
+///
+/// Brush masks are rasterized dab by dab within each dab's bounding box,
+/// which gives the same result as [`weight_at`] without visiting every dab
+/// for every pixel.
+pub fn mask_weights(mask: &ResolvedMask, w: u32, h: u32) -> Vec<f32> {
+    let len = (w as usize) * (h as usize);
+    let ResolvedMask::Brush { strokes } = mask else {
+        let mut out = Vec::with_capacity(len);
+        for y in 0..h {
+            for x in 0..w {
+                out.push(weight_at(mask, x as f32 + 0.5, y as f32 + 0.5));
+            }
+        }
+        return out;
+    };
+
+    let mut out = vec![0.0_f32; len];
+    let mut stroke_cov = vec![0.0_f32; len];
+    for stroke in strokes {
+        stroke_cov.iter_mut().for_each(|v| *v = 0.0);
+        for dab in &stroke.dabs {
+            let x0 = (dab[0] - dab[2]).floor().max(0.0) as u32;
+            let y0 = (dab[1] - dab[2]).floor().max(0.0) as u32;
+            let x1 = ((dab[0] + dab[2]).ceil().max(0.0) as u32).min(w);
+            let y1 = ((dab[1] + dab[2]).ceil().max(0.0) as u32).min(h);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let dx = x as f32 + 0.5 - dab[0];
+                    let dy = y as f32 + 0.5 - dab[1];
+                    let dist = (dx * dx + dy * dy).sqrt();
+                    if dist < dab[2] {
+                        let i = (y * w + x) as usize;
+                        stroke_cov[i] = stroke_cov[i].max(feather_falloff(dist / dab[2], dab[3]));
+                    }
+                }
+            }
+        }
+        for (o, sw) in out.iter_mut().zip(&stroke_cov) {
+            *o = combine_stroke(*o, *sw, stroke.erase);
+        }
+    }
+    out
+}
+
+fn combine_stroke(coverage: f32, stroke: f32, erase: bool) -> f32 {
+    if erase {
+        coverage * (1.0 - stroke)
+    } else {
+        coverage.max(stroke)
+    }
+}
+
+/// Places dabs along a polyline so the stroke renders without gaps.
+fn stroke_dabs(points: &[[f32; 2]], radius: f32, feather: f32) -> Vec<[f32; 4]> {
+    let Some(first) = points.first() else {
+        return Vec::new();
+    };
+    let step = (radius * DAB_SPACING).max(1.0);
+    let mut dabs = vec![[first[0], first[1], radius, feather]];
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let len = ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+        let n = (len / step).ceil().max(1.0) as u32;
+        for k in 1..=n {
+            let t = k as f32 / n as f32;
+            dabs.push([
+                a[0] + (b[0] - a[0]) * t,
+                a[1] + (b[1] - a[1]) * t,
+                radius,
+                feather,
+            ]);
+        }
+    }
+    dabs
+}
+
+/// Radial falloff: 1 inside `1 - feather`, easing to 0 at `r = 1`.
+pub(crate) fn feather_falloff(r: f32, feather: f32) -> f32 {
+    if feather < 0.001 {
+        if r < 1.0 { 1.0 } else { 0.0 }
+    } else {
+        1.0 - smoothstep(1.0 - feather, 1.0, r)
+    }
+}
+
+/// Masked tone settings shared by local adjustments and graduated filters.
+pub(crate) struct Tone {
+    pub exposure: f32,
+    pub contrast: f32,
+    pub highlights: f32,
+    pub temperature: f32,
+    pub saturation: f32,
+}
+
+impl From<&LocalAdjustment> for Tone {
+    fn from(adj: &LocalAdjustment) -> Self {
+        Self {
+            exposure: adj.exposure,
+            contrast: adj.contrast,
+            highlights: 0.0,
+            temperature: adj.temperature,
+            saturation: adj.saturation,
+        }
+    }
+}
+
+/// Applies `tone` scaled by the mask `weight`: exposure, contrast,
+/// highlights, white balance, then saturation.
+pub(crate) fn apply_tone(rgb: [f32; 3], tone: &Tone, weight: f32) -> [f32; 3] {
+    let gain = 2.0_f32.powf(tone.exposure.clamp(-5.0, 5.0) * weight);
+    let contrast = 1.0 + tone.contrast.clamp(-1.0, 1.0) * weight;
+    let [mut r, mut g, mut b] = rgb.map(|c| ((c * gain - 0.5) * contrast + 0.5).clamp(0.0, 1.0));
+
+    let highlights = tone.highlights.clamp(-1.0, 1.0) * weight;
+    if highlights.abs() > 0.001 {
+        let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let w = smoothstep(0.5, 1.0, luma);
+        let target = if highlights >= 0.0 {
+            luma + (1.0 - luma) * highlights * w
+        } else {
+            luma * (1.0 + highlights * w)
+        };
+        let scale = if luma > 1e-5 { target / luma } else { 1.0 };
+        r = (r * scale).clamp(0.0, 1.0);
+        g = (g * scale).clamp(0.0, 1.0);
+        b = (b * scale).clamp(0.0, 1.0);
+    }
+
+    let temp = tone.temperature.clamp(-1.0, 1.0) * weight;
+    if temp > 0.0 {
+        r += (1.0 - r) * temp * 0.25;
+        b *= 1.0 - temp * 0.25;
+    } else if temp < 0.0 {
+        let cool = -temp;
+        b += (1.0 - b) * cool * 0.25;
+        r *= 1.0 - cool * 0.25;
+    }
+    r = r.clamp(0.0, 1.0);
+    g = g.clamp(0.0, 1.0);
+    b = b.clamp(0.0, 1.0);
+
+    let sat = 1.0 + tone.saturation.clamp(-1.0, 1.0) * weight;
+    let l = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    [r, g, b].map(|c| (l + (c - l) * sat).clamp(0.0, 1.0))
+}
+
+/// Gaussian blur of a single pixel with `sigma`, clamping at borders.
+fn blurred_at(src: &RgbaImage, x: i32, y: i32, sigma: f32) -> [f32; 3] {
+    let (w, h) = (src.width() as i32, src.height() as i32);
+    let inv_two_sigma_sq = 1.0 / (2.0 * sigma * sigma);
+    let radius = kernel_radius(sigma);
+    let mut acc = [0.0_f32; 3];
+    let mut weight_sum = 0.0;
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let wt = (-((dx * dx + dy * dy) as f32) * inv_two_sigma_sq).exp();
+            let p = src.get_pixel(
+                (x + dx).clamp(0, w - 1) as u32,
+                (y + dy).clamp(0, h - 1) as u32,
+            );
+            for c in 0..3 {
+                acc[c] += p[c] as f32 / 255.0 * wt;
+            }
+            weight_sum += wt;
+        }
+    }
+    acc.map(|v| v / weight_sum)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageBuffer, Rgba};
+
+    use crate::processing::transform::Region;
+    use crate::state::{BrushStroke, EditState, LocalAdjustment, MaskShape, NormPoint};
+
+    use super::{mask_weights, resolve, weight_at};
+
+    /// Runs the stage over the whole of `img`.
+    fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
+        let region = Region::whole(img.width(), img.height());
+        super::apply(img, state, &region)
+    }
+
+    fn gray(w: u32, h: u32, v: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(ImageBuffer::from_pixel(w, h, Rgba([v, v, v, 255])))
+    }
+
+    fn radial(x: f32, y: f32, r: f32) -> MaskShape {
+        MaskShape::Radial {
+            center: NormPoint { x, y },
+            radius_x: r,
+            radius_y: r,
+            angle: 0.0,
+            feather: 0.0,
+            invert: false,
+        }
+    }
+
+    #[test]
+    fn inactive_adjustments_are_identity() {
+        let img = gray(8, 8, 100);
+        let mut state = EditState::default();
+        state
+            .local_adjustments
+            .push(LocalAdjustment::new(radial(0.5, 0.5, 0.3)));
+        assert_eq!(apply(img.clone(), &state).to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn linear_mask_fades_from_start_to_end() {
+        let mask = resolve(
+            &MaskShape::Linear {
+                start: NormPoint { x: 0.0, y: 0.0 },
+                end: NormPoint { x: 1.0, y: 0.0 },
+            },
+            100,
+            10,
+        );
+        assert_eq!(weight_at(&mask, 0.0, 5.0), 1.0);
+        assert!((weight_at(&mask, 50.0, 5.0) - 0.5).abs() < 1e-4);
+        assert_eq!(weight_at(&mask, 100.0, 5.0), 0.0);
+    }
+
+    #[test]
+    fn radial_exposure_brightens_only_inside() {
+        let img = gray(20, 20, 100);
+        let mut state = EditState::default();
+        let mut adj = LocalAdjustment::new(radial(0.5, 0.5, 0.2));
+        adj.exposure = 1.0;
+        state.local_adjustments.push(adj);
+
+        let out = apply(img, &state).to_rgba8();
+        assert_eq!(out.get_pixel(10, 10)[0], 200);
+        assert_eq!(out.get_pixel(0, 0)[0], 100);
+    }
+
+    #[test]
+    fn inverted_radial_affects_outside() {
+        let mask = MaskShape::Radial {
+            center: NormPoint { x: 0.5, y: 0.5 },
+            radius_x: 0.2,
+            radius_y: 0.2,
+            angle: 0.0,
+            feather: 0.0,
+            invert: true,
+        };
+        let weights = mask_weights(&resolve(&mask, 20, 20), 20, 20);
+        assert_eq!(weights[10 * 20 + 10], 0.0);
+        assert_eq!(weights[0], 1.0);
+    }
+
+    #[test]
+    fn erase_stroke_removes_painted_coverage() {
+        let stroke = |erase, radius| BrushStroke {
+            points: vec![NormPoint { x: 0.2, y: 0.5 }, NormPoint { x: 0.8, y: 0.5 }],
+            radius,
+            feather: 0.0,
+            erase,
+        };
+        let mask = MaskShape::Brush {
+            strokes: vec![stroke(false, 0.1), stroke(true, 0.05)],
+        };
+        let resolved = resolve(&mask, 40, 40);
+        let weights = mask_weights(&resolved, 40, 40);
+        // Centre line erased, band edge still painted, far away untouched.
+        assert_eq!(weights[20 * 40 + 20], 0.0);
+        assert_eq!(weights[17 * 40 + 20], 1.0);
+        assert_eq!(weights[5 * 40 + 20], 0.0);
+    }
+
+    #[test]
+    fn rasterized_brush_matches_per_pixel_weights() {
+        let mask = MaskShape::Brush {
+            strokes: vec![BrushStroke {
+                points: vec![NormPoint { x: 0.1, y: 0.1 }, NormPoint { x: 0.7, y: 0.9 }],
+                radius: 0.15,
+                feather: 0.5,
+                erase: false,
+            }],
+        };
+        let resolved = resolve(&mask, 32, 24);
+        let weights = mask_weights(&resolved, 32, 24);
+        for y in 0..24 {
+            for x in 0..32 {
+                let expected = weight_at(&resolved, x as f32 + 0.5, y as f32 + 0.5);
+                assert!((weights[(y * 32 + x) as usize] - expected).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn negative_sharpness_softens_edges() {
+        let mut buf = ImageBuffer::from_pixel(8, 8, Rgba([40u8, 40, 40, 255]));
+        for y in 0..8 {
+            for x in 4..8 {
+                buf.put_pixel(x, y, Rgba([220, 220, 220, 255]));
+            }
+        }
+        let img = DynamicImage::ImageRgba8(buf);
+        let mut state = EditState::default();
+        let mut adj = LocalAdjustment::new(radial(0.5, 0.5, 2.0));
+        adj.sharpness = -1.0;
+        state.local_adjustments.push(adj);
+
+        let out = apply(img, &state).to_rgba8();
+        assert!(out.get_pixel(3, 4)[0] > 40);
+        assert!(out.get_pixel(4, 4)[0] < 220);
+    }
+}
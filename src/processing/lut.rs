@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::state::{EditState, LutInterpolation};
+
+use super::{color, exposure, icc};
+
+/// Grid size used when baking the current edits to a `.cube`.
+pub const EXPORT_SIZE: usize = 33;
+/// Largest grid accepted from a `.cube` file.
+const MAX_SIZE: usize = 256;
+
+type Cache = Mutex<HashMap<PathBuf, (Option<SystemTime>, Arc<Lut3d>)>>;
+static CACHE: OnceLock<Cache> = OnceLock::new();
+
+/// A 3D LUT in `.cube` layout: red varies fastest, then green, then blue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut3d {
+    pub size: usize,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    pub data: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    pub fn identity(size: usize) -> Self {
+        let step = 1.0 / (size - 1) as f32;
+        let data = (0..size * size * size)
+            .map(|i| {
+                let (r, g, b) = (i % size, (i / size) % size, i / (size * size));
+                [r as f32 * step, g as f32 * step, b as f32 * step]
+            })
+            .collect();
+        Self {
+            size,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            data,
+        }
+    }
+
+    fn at(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.data[r + g * self.size + b * self.size * self.size]
+    }
+
+    /// Looks up `rgb` (in the LUT's input domain).
+    pub fn sample(&self, rgb: [f32; 3], interpolation: LutInterpolation) -> [f32; 3] {
+        let n = self.size;
+        let max = (n - 1) as f32;
+        let mut base = [0usize; 3];
+        let mut frac = [0.0f32; 3];
+        for c in 0..3 {
+            let span = (self.domain_max[c] - self.domain_min[c]).max(1e-6);
+            let t = ((rgb[c] - self.domain_min[c]) / span).clamp(0.0, 1.0) * max;
+            let i = (t.floor() as usize).min(n - 2);
+            base[c] = i;
+            frac[c] = t - i as f32;
+        }
+        let [r0, g0, b0] = base;
+        let [fr, fg, fb] = frac;
+        let c = |dr: usize, dg: usize, db: usize| self.at(r0 + dr, g0 + dg, b0 + db);
+        let lerp = |a: [f32; 3], b: [f32; 3], t: f32| {
+            [
+                a[0] + (b[0] - a[0]) * t,
+                a[1] + (b[1] - a[1]) * t,
+                a[2] + (b[2] - a[2]) * t,
+            ]
+        };
+
+        match interpolation {
+            LutInterpolation::Trilinear => {
+                let c00 = lerp(c(0, 0, 0), c(1, 0, 0), fr);
+                let c10 = lerp(c(0, 1, 0), c(1, 1, 0), fr);
+                let c01 = lerp(c(0, 0, 1), c(1, 0, 1), fr);
+                let c11 = lerp(c(0, 1, 1), c(1, 1, 1), fr);
+                lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+            }
+            LutInterpolation::Tetrahedral => {
+                // Walk from c000 to c111 along the edges of the tetrahedron
+                // containing the point, largest fraction first.
+                let (c000, c111) = (c(0, 0, 0), c(1, 1, 1));
+                let (w1, p1, w2, p2, w3) = if fr > fg {
+                    if fg > fb {
+                        (fr, c(1, 0, 0), fg, c(1, 1, 0), fb)
+                    } else if fr > fb {
+                        (fr, c(1, 0, 0), fb, c(1, 0, 1), fg)
+                    } else {
+                        (fb, c(0, 0, 1), fr, c(1, 0, 1), fg)
+                    }
+                } else if fb > fg {
+                    (fb, c(0, 0, 1), fg, c(0, 1, 1), fr)
+                } else if fb > fr {
+                    (fg, c(0, 1, 0), fb, c(0, 1, 1), fr)
+                } else {
+                    (fg, c(0, 1, 0), fr, c(1, 1, 0), fb)
+                };
+                std::array::from_fn(|k| {
+                    c000[k] + w1 * (p1[k] - c000[k]) + w2 * (p2[k] - p1[k]) + w3 * (c111[k] - p2[k])
+                })
+            }
+        }
+    }
+}
+
+/// Parses an Adobe/Resolve `.cube` 3D LUT.
+pub fn parse_cube(text: &str) -> anyhow::Result<Lut3d> {
+    let mut size = None;
+    let mut domain_min = [0.0; 3];
+    let mut domain_max = [1.0; 3];
+    let mut data = Vec::new();
+
+    let triple = |parts: &[&str]| -> anyhow::Result<[f32; 3]> {
+        if parts.len() != 3 {
+            anyhow::bail!("expected three values, found {}", parts.len());
+        }
+        Ok([parts[0].parse()?, parts[1].parse()?, parts[2].parse()?])
+    };
+
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let result = match parts[0] {
+            "TITLE" => Ok(()),
+            "LUT_1D_SIZE" => Err(anyhow::anyhow!("1D LUTs are not supported")),
+            "LUT_3D_SIZE" => parts
+                .get(1)
+                .ok_or_else(|| anyhow::anyhow!("missing size"))
+                .and_then(|v| Ok(v.parse::<usize>()?))
+                .map(|n| size = Some(n)),
+            "DOMAIN_MIN" => triple(&parts[1..]).map(|v| domain_min = v),
+            "DOMAIN_MAX" => triple(&parts[1..]).map(|v| domain_max = v),
+            keyword if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => Ok(()),
+            _ => triple(&parts).map(|v| data.push(v)),
+        };
+        result.map_err(|err| anyhow::anyhow!("line {}: {err}", lineno + 1))?;
+    }
+
+    let size = size.ok_or_else(|| anyhow::anyhow!("missing LUT_3D_SIZE"))?;
+    if !(2..=MAX_SIZE).contains(&size) {
+        anyhow::bail!("unsupported LUT_3D_SIZE {size}");
+    }
+    if data.len() != size * size * size {
+        anyhow::bail!(
+            "expected {} entries for size {size}, found {}",
+            size * size * size,
+            data.len()
+        );
+    }
+    Ok(Lut3d {
+        size,
+        domain_min,
+        domain_max,
+        data,
+    })
+}
+
+/// Serializes `lut` in `.cube` format.
+pub fn to_cube(lut: &Lut3d, title: &str) -> String {
+    let mut out = format!("TITLE \"{title}\"\nLUT_3D_SIZE {}\n", lut.size);
+    if lut.domain_min != [0.0; 3] || lut.domain_max != [1.0; 3] {
+        let [a, b, c] = lut.domain_min;
+        out.push_str(&format!("DOMAIN_MIN {a:.6} {b:.6} {c:.6}\n"));
+        let [a, b, c] = lut.domain_max;
+        out.push_str(&format!("DOMAIN_MAX {a:.6} {b:.6} {c:.6}\n"));
+    }
+    for [r, g, b] in &lut.data {
+        out.push_str(&format!("{r:.6} {g:.6} {b:.6}\n"));
+    }
+    out
+}
+
+/// Loads a `.cube` file, reusing the parsed LUT until the file changes.
+pub fn load(path: &Path) -> anyhow::Result<Arc<Lut3d>> {
+    let modified = std::fs::metadata(path)?.modified().ok();
+    let cache = CACHE.get_or_init(Default::default);
+    if let Ok(cache) = cache.lock()
+        && let Some((stamp, lut)) = cache.get(path)
+        && *stamp == modified
+    {
+        return Ok(Arc::clone(lut));
+    }
+
+    let lut = Arc::new(parse_cube(&std::fs::read_to_string(path)?)?);
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(path.to_path_buf(), (modified, Arc::clone(&lut)));
+    }
+    Ok(lut)
+}
+
+/// The LUT `state` applies, if any, loaded and with a visible strength.
+pub fn active(state: &EditState) -> Option<(Arc<Lut3d>, f32, LutInterpolation)> {
+    let lut = state.lut.as_ref()?;
+    if lut.strength <= 0.001 {
+        return None;
+    }
+    let loaded = load(&lut.path).ok()?;
+    Some((loaded, lut.strength.clamp(0.0, 1.0), lut.interpolation))
+}
+
+/// Grades the image through the edit's LUT. LUT looks are built for
+/// Rec.709, so pixels are converted out of the working space for the
+/// lookup and back again; colors outside Rec.709 are clipped to it.
+pub fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
+    let Some((lut, strength, interpolation)) = active(state) else {
+        return img;
+    };
+    let to_709 = icc::working_to_rec709();
+    let from_709 = icc::rec709_to_working();
+
+    let mut rgba = img.to_rgba8();
+    for px in rgba.pixels_mut() {
+        let rgb = [
+            px[0] as f32 / 255.0,
+            px[1] as f32 / 255.0,
+            px[2] as f32 / 255.0,
+        ];
+        let graded = convert(&from_709, lut.sample(convert(&to_709, rgb), interpolation));
+        for c in 0..3 {
+            let v = rgb[c] + (graded[c] - rgb[c]) * strength;
+            px[c] = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Moves sRGB-curve encoded values between primaries with linear matrix `m`.
+fn convert(m: &[[f32; 3]; 3], rgb: [f32; 3]) -> [f32; 3] {
+    let lin = rgb.map(icc::srgb_decode);
+    m.map(|row| {
+        icc::srgb_encode((row[0] * lin[0] + row[1] * lin[1] + row[2] * lin[2]).clamp(0.0, 1.0))
+    })
+}
+
+/// Bakes the global tone and color edits (including any LUT) into a
+/// Rec.709 LUT of `size`³ entries, for use in other tools.
+pub fn bake(state: &EditState, size: usize) -> Lut3d {
+    let mut lut = Lut3d::identity(size);
+    let to_709 = icc::working_to_rec709();
+    let from_709 = icc::rec709_to_working();
+
+    let count = lut.data.len() as u32;
+    let grid = RgbaImage::from_fn(count, 1, |i, _| {
+        let rgb = convert(&from_709, lut.data[i as usize]);
+        Rgba([
+            (rgb[0] * 255.0).round() as u8,
+            (rgb[1] * 255.0).round() as u8,
+            (rgb[2] * 255.0).round() as u8,
+            255,
+        ])
+    });
+    let mut graded = DynamicImage::ImageRgba8(grid);
+    graded = exposure::apply(graded, state);
+    graded = color::apply(graded, state);
+    graded = apply(graded, state);
+
+    for (entry, px) in lut.data.iter_mut().zip(graded.to_rgba8().pixels()) {
+        let rgb = [
+            px[0] as f32 / 255.0,
+            px[1] as f32 / 255.0,
+            px[2] as f32 / 255.0,
+        ];
+        *entry = convert(&to_709, rgb);
+    }
+    lut
+}
+
+/// Writes the baked global edits to `path` as a `.cube`.
+pub fn export_cube(state: &EditState, path: &Path) -> anyhow::Result<()> {
+    let title = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Photograph look");
+    std::fs::write(path, to_cube(&bake(state, EXPORT_SIZE), title))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Swaps red and blue: a LUT whose output is easy to predict.
+    fn swap_rb(size: usize) -> Lut3d {
+        let mut lut = Lut3d::identity(size);
+        for v in &mut lut.data {
+            v.swap(0, 2);
+        }
+        lut
+    }
+
+    #[test]
+    fn parses_cube_files() {
+        let text = "# comment\nTITLE \"test\"\nLUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 1 1 1\n\
+                    0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+        let lut = parse_cube(text).unwrap();
+        assert_eq!(lut, Lut3d::identity(2));
+        assert!(parse_cube("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(parse_cube("LUT_1D_SIZE 4\n").is_err());
+    }
+
+    #[test]
+    fn cube_round_trips() {
+        let lut = swap_rb(3);
+        assert_eq!(parse_cube(&to_cube(&lut, "swap")).unwrap(), lut);
+    }
+
+    #[test]
+    fn interpolation_is_exact_for_linear_luts() {
+        let lut = swap_rb(5);
+        let rgb = [0.13, 0.71, 0.42];
+        for interpolation in [LutInterpolation::Tetrahedral, LutInterpolation::Trilinear] {
+            let out = lut.sample(rgb, interpolation);
+            for (a, b) in out.iter().zip([0.42, 0.71, 0.13]) {
+                assert!((a - b).abs() < 1e-5, "{interpolation:?}: {out:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn tetrahedral_differs_from_trilinear_on_nonlinear_luts() {
+        let mut lut = Lut3d::identity(2);
+        lut.data[7] = [0.5, 0.5, 0.5];
+        let rgb = [0.6, 0.3, 0.8];
+        let tetra = lut.sample(rgb, LutInterpolation::Tetrahedral);
+        let tri = lut.sample(rgb, LutInterpolation::Trilinear);
+        assert!((tetra[0] - tri[0]).abs() > 1e-3);
+        // Neutral axis stays on the c000–c111 diagonal for tetrahedral.
+        let gray = lut.sample([0.5; 3], LutInterpolation::Tetrahedral);
+        assert!((gray[0] - 0.25).abs() < 1e-5, "{gray:?}");
+    }
+
+    #[test]
+    fn baked_identity_state_is_identity() {
+        let lut = bake(&EditState::default(), 5);
+        for (a, b) in lut.data.iter().zip(&Lut3d::identity(5).data) {
+            for c in 0..3 {
+                // The grid passes through 8-bit working-space pixels; compare
+                // in linear light, where that stays within about one code.
+                let err = icc::srgb_decode(a[c]) - icc::srgb_decode(b[c]);
+                assert!(err.abs() < 0.01, "{a:?} vs {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn applies_lut_with_strength() {
+        let dir = std::env::temp_dir().join(format!("photograph-lut-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("swap.cube");
+        std::fs::write(&path, to_cube(&swap_rb(9), "swap")).unwrap();
+
+        // Neutral-free color that stays inside Rec.709 in the working space.
+        let src = icc::to_working(
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([200, 120, 60, 255]))),
+            None,
+        );
+        let mut state = EditState::default();
+        state.lut = Some(crate::state::LutRef {
+            path: path.clone(),
+            strength: 1.0,
+            interpolation: LutInterpolation::Tetrahedral,
+        });
+        let full = icc::to_display(&apply(src.clone(), &state)).to_rgba8();
+        let px = full.get_pixel(0, 0);
+        assert!(
+            (px[0] as i32 - 60).abs() <= 2 && (px[2] as i32 - 200).abs() <= 2,
+            "{px:?}"
+        );
+
+        state.lut.as_mut().unwrap().strength = 0.0;
+        assert_eq!(apply(src.clone(), &state).to_rgba8(), src.to_rgba8());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,7 +1,16 @@
 pub mod color;
 pub mod exposure;
 pub mod filters;
+pub mod focus;
 pub mod gpu_pipeline;
+pub mod grain;
 pub mod highlights;
+pub mod icc;
+pub mod local;
+pub mod lut;
+pub mod presence;
 pub mod sharpness;
+pub mod spots;
 pub mod transform;
+pub mod upright;
+pub mod vignette;
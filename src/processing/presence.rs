@@ -0,0 +1,383 @@
+use image::{DynamicImage, RgbaImage};
+
+use crate::state::EditState;
+
+use super::transform::Region;
+
+/// Haze removed by a dehaze of 1 (the ω of the dark channel prior).
+pub const DEHAZE_OMEGA: f32 = 0.95;
+/// Lower bound on the transmission estimate, so dense haze isn't stretched into noise.
+pub const DEHAZE_MIN_TRANSMISSION: f32 = 0.1;
+/// Share of pixels, by haziest dark channel, that the airlight is taken from.
+pub const AIRLIGHT_FRACTION: f32 = 0.001;
+/// How far a dehaze of -1 blends toward the airlight.
+pub const ADD_HAZE: f32 = 0.5;
+/// Luma detail gain at clarity 1, before the midtone weighting.
+pub const CLARITY_GAIN: f32 = 0.6;
+/// Luma detail gain at texture 1.
+pub const TEXTURE_GAIN: f32 = 0.8;
+
+/// Filter sizes in pixels, proportional to the image so the preview and a
+/// full-size render look alike.
+#[derive(Debug, Clone, Copy)]
+pub struct Scales {
+    /// Half-width of the dark channel's minimum filter.
+    pub dehaze_patch: u32,
+    /// Gaussian sigma smoothing the dark channel into a transmission map.
+    pub dehaze_sigma: f32,
+    pub clarity_sigma: f32,
+    pub texture_sigma: f32,
+}
+
+pub fn scales(w: u32, h: u32) -> Scales {
+    let long = w.max(h) as f32;
+    let patch = ((long * 0.005).round() as u32).max(1);
+    Scales {
+        dehaze_patch: patch,
+        dehaze_sigma: patch as f32 * 2.0,
+        clarity_sigma: (long * 0.01).max(2.0),
+        texture_sigma: (long * 0.0015).max(1.0),
+    }
+}
+
+/// Applies dehaze, then clarity and texture.
+///
+/// The blurs follow the GPU passes: a horizontal Gaussian stored at 8 bits,
+/// then a vertical Gaussian evaluated per pixel. Filter sizes follow the
+/// frame `region` places `img` in, and dehaze uses the frame's airlight when
+/// it is given.
+pub fn apply(img: DynamicImage, state: &EditState, region: &Region) -> DynamicImage {
+    let dehaze = state.dehaze.clamp(-1.0, 1.0);
+    let clarity = state.clarity.clamp(-1.0, 1.0);
+    let texture = state.texture.clamp(-1.0, 1.0);
+    if dehaze.abs() < 0.001 && clarity.abs() < 0.001 && texture.abs() < 0.001 {
+        return img;
+    }
+
+    let mut rgba = img.to_rgba8();
+    let scales = scales(region.frame.0, region.frame.1);
+    if dehaze.abs() >= 0.001 {
+        rgba = apply_dehaze(&rgba, dehaze, &scales, region.airlight);
+    }
+    if clarity.abs() >= 0.001 || texture.abs() >= 0.001 {
+        rgba = apply_local_contrast(&rgba, clarity, texture, &scales);
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Pixels around each output pixel that [`apply`] reads in a `w`×`h` frame.
+pub fn reach(state: &EditState, w: u32, h: u32) -> u32 {
+    let scales = scales(w, h);
+    let radius = |sigma: f32| gaussian_kernel(sigma).radius as u32;
+    let mut reach = 0;
+    if state.dehaze.clamp(-1.0, 1.0).abs() >= 0.001 {
+        reach += scales.dehaze_patch + radius(scales.dehaze_sigma);
+    }
+    let mut contrast = 0;
+    if state.clarity.clamp(-1.0, 1.0).abs() >= 0.001 {
+        contrast = radius(scales.clarity_sigma);
+    }
+    if state.texture.clamp(-1.0, 1.0).abs() >= 0.001 {
+        contrast = contrast.max(radius(scales.texture_sigma));
+    }
+    reach + contrast
+}
+
+/// The airlight dehaze estimates for `src` on its own.
+pub fn estimate_airlight(src: &RgbaImage) -> [u8; 3] {
+    let (w, h) = src.dimensions();
+    airlight(src, &dark_channel(src, &scales(w, h)))
+}
+
+/// Minimum channel value over a square patch around each pixel.
+fn dark_channel(src: &RgbaImage, scales: &Scales) -> Vec<u8> {
+    let (w, h) = src.dimensions();
+    let min_rgb: Vec<u8> = src.pixels().map(|p| p[0].min(p[1]).min(p[2])).collect();
+    // The minimum over a square patch is the minimum of its row minima
+    let patch = scales.dehaze_patch as i64;
+    min_filter(&min_filter(&min_rgb, w, h, patch, true), w, h, patch, false)
+}
+
+fn apply_dehaze(
+    src: &RgbaImage,
+    amount: f32,
+    scales: &Scales,
+    airlight: Option<[u8; 3]>,
+) -> RgbaImage {
+    let (w, h) = src.dimensions();
+    let dark = dark_channel(src, scales);
+    let airlight = airlight
+        .unwrap_or_else(|| self::airlight(src, &dark))
+        .map(|v| v as f32 / 255.0);
+    let a_max = airlight[0]
+        .max(airlight[1])
+        .max(airlight[2])
+        .max(1.0 / 255.0);
+
+    let sigma = scales.dehaze_sigma;
+    let kernel = gaussian_kernel(sigma);
+    let dark_h = blur_h(&dark, w, h, 1, &kernel);
+
+    let mut out = src.clone();
+    for (x, y, px) in out.enumerate_pixels_mut() {
+        let haze = dark_h.blur_v_at(x, y, 0, &kernel);
+        let transmission =
+            (1.0 - DEHAZE_OMEGA * amount * haze / a_max).max(DEHAZE_MIN_TRANSMISSION);
+        for c in 0..3 {
+            let v = px[c] as f32 / 255.0;
+            let a = airlight[c];
+            let out = if amount > 0.0 {
+                (v - a) / transmission + a
+            } else {
+                v + (a - v) * -amount * ADD_HAZE
+            };
+            px[c] = (out.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+    out
+}
+
+/// Brightest value per channel among the pixels with the haziest dark channel.
+fn airlight(src: &RgbaImage, dark: &[u8]) -> [u8; 3] {
+    let mut histogram = [0usize; 256];
+    for &d in dark {
+        histogram[d as usize] += 1;
+    }
+    let target = ((dark.len() as f32 * AIRLIGHT_FRACTION) as usize).max(1);
+    let mut threshold = 0;
+    let mut count = 0;
+    for v in (0..256).rev() {
+        count += histogram[v];
+        if count >= target {
+            threshold = v as u8;
+            break;
+        }
+    }
+
+    let mut airlight = [0u8; 3];
+    for (px, &d) in src.pixels().zip(dark) {
+        if d >= threshold {
+            for c in 0..3 {
+                airlight[c] = airlight[c].max(px[c]);
+            }
+        }
+    }
+    airlight
+}
+
+/// Running minimum over `radius` pixels along rows (`horizontal`) or columns.
+fn min_filter(src: &[u8], w: u32, h: u32, radius: i64, horizontal: bool) -> Vec<u8> {
+    let (w, h) = (w as i64, h as i64);
+    let mut out = vec![0u8; src.len()];
+    for y in 0..h {
+        for x in 0..w {
+            let mut m = u8::MAX;
+            for i in -radius..=radius {
+                let (sx, sy) = if horizontal {
+                    ((x + i).clamp(0, w - 1), y)
+                } else {
+                    (x, (y + i).clamp(0, h - 1))
+                };
+                m = m.min(src[(sy * w + sx) as usize]);
+            }
+            out[(y * w + x) as usize] = m;
+        }
+    }
+    out
+}
+
+fn apply_local_contrast(src: &RgbaImage, clarity: f32, texture: f32, scales: &Scales) -> RgbaImage {
+    let (w, h) = src.dimensions();
+    let raw = src.as_raw();
+    let coarse_kernel = gaussian_kernel(scales.clarity_sigma);
+    let fine_kernel = gaussian_kernel(scales.texture_sigma);
+    let coarse = (clarity.abs() >= 0.001).then(|| blur_h(raw, w, h, 4, &coarse_kernel));
+    let fine = (texture.abs() >= 0.001).then(|| blur_h(raw, w, h, 4, &fine_kernel));
+    let blurred_luma = |plane: &Plane, kernel: &Kernel, x: u32, y: u32| {
+        let rgb: [f32; 3] = std::array::from_fn(|c| plane.blur_v_at(x, y, c, kernel) / 255.0);
+        luma(rgb)
+    };
+
+    let mut out = src.clone();
+    for (x, y, px) in out.enumerate_pixels_mut() {
+        let rgb = [px[0], px[1], px[2]].map(|v| v as f32 / 255.0);
+        let l = luma(rgb);
+        let mut delta = 0.0;
+        if let Some(coarse) = &coarse {
+            let midtones = 1.0 - (2.0 * l - 1.0).powi(2);
+            delta += clarity
+                * CLARITY_GAIN
+                * midtones
+                * (l - blurred_luma(coarse, &coarse_kernel, x, y));
+        }
+        if let Some(fine) = &fine {
+            delta += texture * TEXTURE_GAIN * (l - blurred_luma(fine, &fine_kernel, x, y));
+        }
+        for c in 0..3 {
+            px[c] = ((rgb[c] + delta).clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+    out
+}
+
+/// Gaussian weights for offsets `-radius..=radius`, `radius = ceil(2σ)`,
+/// matching `BLUR_H_SHADER_SRC`.
+struct Kernel {
+    radius: i64,
+    weights: Vec<f32>,
+    sum: f32,
+}
+
+fn gaussian_kernel(sigma: f32) -> Kernel {
+    let radius = (2.0 * sigma).ceil() as i64;
+    let inv_two_sigma_sq = 1.0 / (2.0 * sigma * sigma);
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|i| (-((i * i) as f32) * inv_two_sigma_sq).exp())
+        .collect();
+    let sum = weights.iter().sum();
+    Kernel {
+        radius,
+        weights,
+        sum,
+    }
+}
+
+/// Horizontal blur of the first (up to three) channels of an interleaved
+/// plane, rounded back to 8 bits like the GPU's intermediate texture.
+fn blur_h(src: &[u8], w: u32, h: u32, stride: usize, kernel: &Kernel) -> Plane {
+    let mut out = src.to_vec();
+    let (wi, hi) = (w as i64, h as i64);
+    for y in 0..hi {
+        for x in 0..wi {
+            let at = |sx: i64| (y * wi + sx.clamp(0, wi - 1)) as usize * stride;
+            for c in 0..stride.min(3) {
+                let acc: f32 = (-kernel.radius..=kernel.radius)
+                    .zip(&kernel.weights)
+                    .map(|(i, wt)| src[at(x + i) + c] as f32 * wt)
+                    .sum();
+                out[at(x) + c] = (acc / kernel.sum).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    Plane {
+        data: out,
+        w,
+        h,
+        stride,
+    }
+}
+
+/// Interleaved 8-bit image data with the dimensions needed to filter it.
+struct Plane {
+    data: Vec<u8>,
+    w: u32,
+    h: u32,
+    stride: usize,
+}
+
+impl Plane {
+    /// Vertical blur of channel `c` at one pixel, unrounded (0–255).
+    fn blur_v_at(&self, x: u32, y: u32, c: usize, kernel: &Kernel) -> f32 {
+        let (w, h) = (self.w as i64, self.h as i64);
+        let acc: f32 = (-kernel.radius..=kernel.radius)
+            .zip(&kernel.weights)
+            .map(|(i, wt)| {
+                let sy = (y as i64 + i).clamp(0, h - 1);
+                self.data[(sy * w + x as i64) as usize * self.stride + c] as f32 * wt
+            })
+            .sum();
+        acc / kernel.sum
+    }
+}
+
+fn luma(rgb: [f32; 3]) -> f32 {
+    0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2]
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageBuffer, Rgba};
+
+    use crate::processing::transform::Region;
+    use crate::state::EditState;
+
+    /// Runs the stage over the whole of `img`.
+    fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
+        let region = Region::whole(img.width(), img.height());
+        super::apply(img, state, &region)
+    }
+
+    /// Dark subject on the left, bright hazy veil on the right.
+    fn hazy_image() -> DynamicImage {
+        DynamicImage::ImageRgba8(ImageBuffer::from_fn(40, 20, |x, y| {
+            let v = if x < 20 { 90 + (y % 4) as u8 * 10 } else { 200 };
+            Rgba([v, v + 10, v + 20, 255])
+        }))
+    }
+
+    fn mean_luma(img: &DynamicImage) -> f32 {
+        let rgba = img.to_rgba8();
+        let sum: f32 = rgba
+            .pixels()
+            .map(|p| 0.2126 * p[0] as f32 + 0.7152 * p[1] as f32 + 0.0722 * p[2] as f32)
+            .sum();
+        sum / (rgba.width() * rgba.height()) as f32
+    }
+
+    fn luma_spread(img: &DynamicImage) -> i32 {
+        let rgba = img.to_rgba8();
+        let values: Vec<i32> = rgba.pixels().map(|p| p[1] as i32).collect();
+        values.iter().max().unwrap() - values.iter().min().unwrap()
+    }
+
+    #[test]
+    fn zero_amounts_are_identity() {
+        let img = hazy_image();
+        let out = apply(img.clone(), &EditState::default());
+        assert_eq!(out.to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn dehaze_deepens_shadows_and_negative_adds_haze() {
+        let img = hazy_image();
+        let mut state = EditState {
+            dehaze: 0.8,
+            ..EditState::default()
+        };
+        let clear = apply(img.clone(), &state);
+        assert!(mean_luma(&clear) < mean_luma(&img) - 5.0);
+        assert!(luma_spread(&clear) > luma_spread(&img));
+
+        state.dehaze = -1.0;
+        let hazy = apply(img.clone(), &state);
+        assert!(luma_spread(&hazy) < luma_spread(&img));
+    }
+
+    #[test]
+    fn clarity_and_texture_raise_local_contrast() {
+        let img = hazy_image();
+        for setup in [
+            |s: &mut EditState| s.clarity = 1.0,
+            |s: &mut EditState| s.texture = 1.0,
+        ] {
+            let mut state = EditState::default();
+            setup(&mut state);
+            let out = apply(img.clone(), &state).to_rgba8();
+            let src = img.to_rgba8();
+            // Across the step, the dark side darkens and the bright side brightens
+            assert!(out.get_pixel(19, 10)[1] < src.get_pixel(19, 10)[1]);
+            assert!(out.get_pixel(20, 10)[1] > src.get_pixel(20, 10)[1]);
+        }
+    }
+
+    #[test]
+    fn negative_clarity_softens() {
+        let img = hazy_image();
+        let state = EditState {
+            clarity: -1.0,
+            ..EditState::default()
+        };
+        let out = apply(img.clone(), &state).to_rgba8();
+        assert!(out.get_pixel(19, 10)[1] > img.to_rgba8().get_pixel(19, 10)[1]);
+    }
+}
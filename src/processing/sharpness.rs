@@ -1,24 +1,49 @@
-use image::DynamicImage;
+use image::{DynamicImage, GrayImage, Luma, RgbaImage};
 use imageproc::filter::gaussian_blur_f32;
 
 use crate::state::EditState;
 
+use super::transform::Region;
+
+/// Edge strength (Sobel magnitude / 4) that a masking value of 1.0 requires
+/// before sharpening reaches full strength.
+pub const MASK_EDGE_SCALE: f32 = 0.3;
+/// Smallest blur radius accepted; `gaussian_blur_f32` panics on sigma <= 0.
+pub const MIN_RADIUS: f32 = 0.3;
+
 /// Applies an unsharp-mask style sharpening pass.
-pub fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
+///
+/// `sharpen_radius` sets the Gaussian sigma, `sharpen_threshold` suppresses
+/// low-contrast detail (noise, flat sky) and `sharpen_masking` restricts the
+/// effect to strong edges found with a Sobel pass on the unsharpened input.
+/// The radius is scaled to the frame `region` renders.
+pub fn apply(img: DynamicImage, state: &EditState, region: &Region) -> DynamicImage {
     if state.sharpness < 0.001 {
         return img;
     }
 
     let amount = state.sharpness;
-    let sigma = 1.5_f32;
+    let sigma = (state.sharpen_radius * region.scale).max(MIN_RADIUS);
+    let threshold = state.sharpen_threshold.max(0.0);
 
     let rgba = img.to_rgba8();
     let blurred = gaussian_blur_f32(&rgba, sigma);
+    let mask = edge_mask(&rgba, state.sharpen_masking);
 
     let mut out = rgba.clone();
-    for (o, (s, b)) in out.pixels_mut().zip(rgba.pixels().zip(blurred.pixels())) {
+    for (i, (o, (s, b))) in out
+        .pixels_mut()
+        .zip(rgba.pixels().zip(blurred.pixels()))
+        .enumerate()
+    {
+        let detail = luma(s.0) - luma(b.0);
+        let weight =
+            mask.as_ref().map(|m| m[i]).unwrap_or(1.0) * threshold_weight(detail, threshold);
+        if weight <= 0.0 {
+            continue;
+        }
         for c in 0..3 {
-            let sharp = s[c] as f32 + amount * (s[c] as f32 - b[c] as f32);
+            let sharp = s[c] as f32 + amount * weight * (s[c] as f32 - b[c] as f32);
             o[c] = sharp.round().clamp(0.0, 255.0) as u8;
         }
         // preserve alpha
@@ -27,13 +52,106 @@ pub fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
     DynamicImage::ImageRgba8(out)
 }
 
+/// Pixels around each output pixel that [`apply`] reads at
+/// [`Region::scale`] `scale`.
+pub fn reach(state: &EditState, scale: f32) -> u32 {
+    if state.sharpness < 0.001 {
+        return 0;
+    }
+    // The Gaussian kernel spans ceil(2σ), the Sobel mask one pixel
+    (2.0 * (state.sharpen_radius * scale).max(MIN_RADIUS)).ceil() as u32
+}
+
+/// Renders the edge mask used by [`apply`] as a grayscale image, white where
+/// sharpening is applied at full strength. Used for the alt-drag preview of
+/// the masking slider.
+pub fn mask_visualization(img: &DynamicImage, state: &EditState) -> DynamicImage {
+    let rgba = img.to_rgba8();
+    let (w, h) = rgba.dimensions();
+    let mask = edge_mask(&rgba, state.sharpen_masking);
+    let gray = GrayImage::from_fn(w, h, |x, y| {
+        let v = mask
+            .as_ref()
+            .map(|m| m[(y * w + x) as usize])
+            .unwrap_or(1.0);
+        Luma([(v * 255.0).round() as u8])
+    });
+    DynamicImage::ImageLuma8(gray)
+}
+
+/// Per-pixel edge mask weights in row-major order, or `None` when masking is
+/// off. Weights ramp from 0 to 1 as the Sobel edge strength rises through
+/// `[0.5, 1.0] * masking * MASK_EDGE_SCALE`.
+fn edge_mask(rgba: &RgbaImage, masking: f32) -> Option<Vec<f32>> {
+    if masking < 0.001 {
+        return None;
+    }
+    let (w, h) = rgba.dimensions();
+    let lum: Vec<f32> = rgba.pixels().map(|p| luma(p.0)).collect();
+    let at = |x: i64, y: i64| -> f32 {
+        let cx = x.clamp(0, w as i64 - 1) as usize;
+        let cy = y.clamp(0, h as i64 - 1) as usize;
+        lum[cy * w as usize + cx]
+    };
+
+    let hi = masking.clamp(0.0, 1.0) * MASK_EDGE_SCALE;
+    let lo = hi * 0.5;
+    let mut mask = Vec::with_capacity(lum.len());
+    for y in 0..h as i64 {
+        for x in 0..w as i64 {
+            let gx = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+            let gy = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+            let edge = (gx * gx + gy * gy).sqrt() * 0.25;
+            mask.push(smoothstep(lo, hi, edge));
+        }
+    }
+    Some(mask)
+}
+
+/// Soft threshold on the high-pass luma difference: details smaller than
+/// half the threshold are left untouched.
+fn threshold_weight(detail: f32, threshold: f32) -> f32 {
+    if threshold < 0.001 {
+        1.0
+    } else {
+        smoothstep(threshold * 0.5, threshold, detail.abs())
+    }
+}
+
+fn luma(px: [u8; 4]) -> f32 {
+    (0.2126 * px[0] as f32 + 0.7152 * px[1] as f32 + 0.0722 * px[2] as f32) / 255.0
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
 #[cfg(test)]
 mod tests {
     use image::{DynamicImage, ImageBuffer, Rgba};
 
+    use crate::processing::transform::Region;
     use crate::state::EditState;
 
-    use super::apply;
+    use super::{mask_visualization, reach};
+
+    /// Runs the stage over the whole of `img`.
+    fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
+        let region = Region::whole(img.width(), img.height());
+        super::apply(img, state, &region)
+    }
+
+    fn edge_image() -> DynamicImage {
+        // Left half dark, right half bright
+        let mut buf = ImageBuffer::from_pixel(8, 1, Rgba([200u8, 200, 200, 255]));
+        for x in 0..4 {
+            buf.put_pixel(x, 0, Rgba([50, 50, 50, 255]));
+        }
+        DynamicImage::ImageRgba8(buf)
+    }
 
     #[test]
     fn zero_sharpness_is_identity() {
@@ -46,16 +164,89 @@ mod tests {
 
     #[test]
     fn positive_sharpness_changes_pixels() {
-        // Create image with an edge: left half dark, right half bright
-        let mut buf = ImageBuffer::from_pixel(8, 1, Rgba([200u8, 200, 200, 255]));
-        for x in 0..4 {
-            buf.put_pixel(x, 0, Rgba([50, 50, 50, 255]));
-        }
-        let img = DynamicImage::ImageRgba8(buf);
+        let img = edge_image();
         let mut state = EditState::default();
         state.sharpness = 1.0;
         let out = apply(img.clone(), &state);
         // The edge pixels should differ from the original
         assert_ne!(img.to_rgba8(), out.to_rgba8());
     }
+
+    #[test]
+    fn threshold_leaves_low_contrast_noise_untouched() {
+        // +-3 level checkerboard noise on mid gray
+        let img = DynamicImage::ImageRgba8(ImageBuffer::from_fn(8, 8, |x, y| {
+            let v = if (x + y) % 2 == 0 { 125 } else { 131 };
+            Rgba([v, v, v, 255])
+        }));
+        let mut state = EditState {
+            sharpness: 2.0,
+            ..EditState::default()
+        };
+        assert_ne!(apply(img.clone(), &state).to_rgba8(), img.to_rgba8());
+
+        state.sharpen_threshold = 0.1;
+        assert_eq!(apply(img.clone(), &state).to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn full_masking_skips_flat_areas_but_keeps_edges() {
+        let mut buf = ImageBuffer::from_fn(16, 4, |x, y| {
+            let v = if (x + y) % 2 == 0 { 110 } else { 118 };
+            Rgba([v, v, v, 255])
+        });
+        for y in 0..4 {
+            for x in 8..16 {
+                buf.put_pixel(x, y, Rgba([230, 230, 230, 255]));
+            }
+        }
+        let img = DynamicImage::ImageRgba8(buf);
+        let state = EditState {
+            sharpness: 1.5,
+            sharpen_masking: 1.0,
+            ..EditState::default()
+        };
+        let out = apply(img.clone(), &state).to_rgba8();
+        let src = img.to_rgba8();
+
+        // Flat noisy region far from the edge is untouched...
+        assert_eq!(out.get_pixel(1, 1), src.get_pixel(1, 1));
+        // ...while the step edge is still sharpened.
+        assert!(out.get_pixel(8, 1)[0] > src.get_pixel(8, 1)[0]);
+    }
+
+    #[test]
+    fn radius_is_measured_in_full_resolution_pixels() {
+        let img = edge_image();
+        let state = |sharpen_radius| EditState {
+            sharpness: 1.0,
+            sharpen_radius,
+            ..EditState::default()
+        };
+        let mut half = Region::whole(img.width(), img.height());
+        half.scale = 0.5;
+        assert_eq!(
+            super::apply(img.clone(), &state(4.0), &half).to_rgba8(),
+            apply(img.clone(), &state(2.0)).to_rgba8()
+        );
+        assert_eq!(reach(&state(4.0), 0.5), reach(&state(2.0), 1.0));
+    }
+
+    #[test]
+    fn mask_visualization_is_white_without_masking() {
+        let state = EditState::default();
+        let mask = mask_visualization(&edge_image(), &state).to_luma8();
+        assert!(mask.pixels().all(|p| p[0] == 255));
+    }
+
+    #[test]
+    fn mask_visualization_highlights_edges() {
+        let state = EditState {
+            sharpen_masking: 1.0,
+            ..EditState::default()
+        };
+        let mask = mask_visualization(&edge_image(), &state).to_luma8();
+        assert_eq!(mask.get_pixel(0, 0)[0], 0);
+        assert_eq!(mask.get_pixel(4, 0)[0], 255);
+    }
 }
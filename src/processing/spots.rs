@@ -0,0 +1,334 @@
+use image::{DynamicImage, RgbaImage};
+
+use crate::state::{EditState, NormPoint, Spot, SpotMode};
+
+use super::local::feather_falloff;
+
+/// Boundary samples used to match the tone of a heal spot to its surroundings.
+pub const HEAL_RING_SAMPLES: usize = 32;
+/// Source candidates tried by [`auto_source`], as multiples of the spot radius.
+const AUTO_SOURCE_DISTANCES: [f32; 3] = [2.2, 3.0, 4.0];
+const AUTO_SOURCE_DIRECTIONS: usize = 16;
+
+/// A spot resolved to pixel units for one source size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolvedSpot {
+    pub center: [f32; 2],
+    /// Whole-pixel offset from the destination to the source area, so source
+    /// pixels are copied without resampling.
+    pub offset: [i32; 2],
+    pub radius: f32,
+    pub feather: f32,
+    pub heal: bool,
+}
+
+/// Replaces every spot area with pixels from its source. All spots sample the
+/// unretouched image and are composited in order.
+pub fn apply(img: DynamicImage, state: &EditState) -> DynamicImage {
+    if state.spots.is_empty() {
+        return img;
+    }
+
+    let src = img.to_rgba8();
+    let (w, h) = src.dimensions();
+    let spots: Vec<(ResolvedSpot, Vec<[f32; 3]>)> = state
+        .spots
+        .iter()
+        .map(|spot| {
+            let resolved = resolve(spot, w, h);
+            let ring = if resolved.heal {
+                heal_ring(&src, &resolved)
+            } else {
+                Vec::new()
+            };
+            (resolved, ring)
+        })
+        .collect();
+
+    let mut out = src.clone();
+    for (x, y, px) in out.enumerate_pixels_mut() {
+        let p = [x as f32 + 0.5, y as f32 + 0.5];
+        let mut rgb = [
+            px[0] as f32 / 255.0,
+            px[1] as f32 / 255.0,
+            px[2] as f32 / 255.0,
+        ];
+        let mut touched = false;
+        for (spot, ring) in &spots {
+            let alpha = coverage(spot, p);
+            if alpha <= 0.0 {
+                continue;
+            }
+            let mut fill = load(&src, x as i32 + spot.offset[0], y as i32 + spot.offset[1]);
+            if spot.heal {
+                let delta = heal_delta(spot, ring, p);
+                for c in 0..3 {
+                    fill[c] += delta[c];
+                }
+            }
+            for c in 0..3 {
+                rgb[c] += (fill[c] - rgb[c]) * alpha;
+            }
+            touched = true;
+        }
+        if touched {
+            for c in 0..3 {
+                px[c] = (rgb[c].clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+    }
+
+    DynamicImage::ImageRgba8(out)
+}
+
+/// Converts a normalized spot into pixel units for a `w`×`h` source image.
+pub fn resolve(spot: &Spot, w: u32, h: u32) -> ResolvedSpot {
+    let (wf, hf) = (w as f32, h as f32);
+    ResolvedSpot {
+        center: [spot.center.x * wf, spot.center.y * hf],
+        offset: [
+            ((spot.source.x - spot.center.x) * wf).round() as i32,
+            ((spot.source.y - spot.center.y) * hf).round() as i32,
+        ],
+        radius: (spot.radius * wf.max(hf)).max(0.5),
+        feather: spot.feather.clamp(0.0, 1.0),
+        heal: spot.mode == SpotMode::Heal,
+    }
+}
+
+/// Opacity of `spot` at pixel position `p`.
+pub fn coverage(spot: &ResolvedSpot, p: [f32; 2]) -> f32 {
+    let dx = p[0] - spot.center[0];
+    let dy = p[1] - spot.center[1];
+    feather_falloff((dx * dx + dy * dy).sqrt() / spot.radius, spot.feather)
+}
+
+/// Position of boundary sample `k` on the rim of `spot`.
+pub fn ring_point(spot: &ResolvedSpot, k: usize) -> [f32; 2] {
+    let angle = k as f32 * std::f32::consts::TAU / HEAL_RING_SAMPLES as f32;
+    [
+        spot.center[0] + spot.radius * angle.cos(),
+        spot.center[1] + spot.radius * angle.sin(),
+    ]
+}
+
+/// Destination-minus-source color at each rim sample of a heal spot.
+pub fn heal_ring(img: &RgbaImage, spot: &ResolvedSpot) -> Vec<[f32; 3]> {
+    (0..HEAL_RING_SAMPLES)
+        .map(|k| {
+            let [bx, by] = ring_point(spot, k);
+            let (ix, iy) = (bx.floor() as i32, by.floor() as i32);
+            let dst = load(img, ix, iy);
+            let src = load(img, ix + spot.offset[0], iy + spot.offset[1]);
+            [dst[0] - src[0], dst[1] - src[1], dst[2] - src[2]]
+        })
+        .collect()
+}
+
+/// Smooth color correction inside a heal spot, interpolated from the rim
+/// differences with inverse squared distance weights.
+fn heal_delta(spot: &ResolvedSpot, ring: &[[f32; 3]], p: [f32; 2]) -> [f32; 3] {
+    let mut sum = [0.0; 3];
+    let mut total = 0.0;
+    for (k, diff) in ring.iter().enumerate() {
+        let [bx, by] = ring_point(spot, k);
+        let (dx, dy) = (p[0] - bx, p[1] - by);
+        let weight = 1.0 / (dx * dx + dy * dy + 1.0);
+        for c in 0..3 {
+            sum[c] += diff[c] * weight;
+        }
+        total += weight;
+    }
+    sum.map(|v| v / total)
+}
+
+/// Picks a nearby source for a spot whose surroundings best match the spot's
+/// own rim. Falls back to a point beside the spot when no candidate fits.
+pub fn auto_source(img: &RgbaImage, center: NormPoint, radius: f32) -> NormPoint {
+    let (w, h) = img.dimensions();
+    let (wf, hf) = (w as f32, h as f32);
+    let spot = Spot {
+        center,
+        source: center,
+        radius,
+        feather: 0.0,
+        mode: SpotMode::Clone,
+    };
+    let resolved = resolve(&spot, w, h);
+    let r = resolved.radius;
+
+    let mut best: Option<(f32, [i32; 2])> = None;
+    for distance in AUTO_SOURCE_DISTANCES {
+        for k in 0..AUTO_SOURCE_DIRECTIONS {
+            let angle = k as f32 * std::f32::consts::TAU / AUTO_SOURCE_DIRECTIONS as f32;
+            let offset = [
+                (angle.cos() * distance * r).round() as i32,
+                (angle.sin() * distance * r).round() as i32,
+            ];
+            let sx = resolved.center[0] + offset[0] as f32;
+            let sy = resolved.center[1] + offset[1] as f32;
+            if sx - r < 0.0 || sy - r < 0.0 || sx + r > wf || sy + r > hf {
+                continue;
+            }
+            let candidate = ResolvedSpot { offset, ..resolved };
+            let score: f32 = heal_ring(img, &candidate)
+                .iter()
+                .map(|d| d[0] * d[0] + d[1] * d[1] + d[2] * d[2])
+                .sum();
+            if best.is_none_or(|(s, _)| score < s) {
+                best = Some((score, offset));
+            }
+        }
+    }
+
+    let offset = best.map_or([(2.2 * r).round() as i32, 0], |(_, offset)| offset);
+    NormPoint {
+        x: ((resolved.center[0] + offset[0] as f32) / wf).clamp(0.0, 1.0),
+        y: ((resolved.center[1] + offset[1] as f32) / hf).clamp(0.0, 1.0),
+    }
+}
+
+/// Nearest texel load with edge clamping, as `textureLoad` on clamped coords.
+fn load(img: &RgbaImage, x: i32, y: i32) -> [f32; 3] {
+    let x = x.clamp(0, img.width() as i32 - 1) as u32;
+    let y = y.clamp(0, img.height() as i32 - 1) as u32;
+    let px = img.get_pixel(x, y);
+    [
+        px[0] as f32 / 255.0,
+        px[1] as f32 / 255.0,
+        px[2] as f32 / 255.0,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn spot(center: (f32, f32), source: (f32, f32), mode: SpotMode) -> Spot {
+        Spot {
+            center: NormPoint {
+                x: center.0,
+                y: center.1,
+            },
+            source: NormPoint {
+                x: source.0,
+                y: source.1,
+            },
+            radius: 0.1,
+            feather: 0.0,
+            mode,
+        }
+    }
+
+    /// Horizontal gradient with a dark blemish centered at (20, 20).
+    fn blemished() -> RgbaImage {
+        RgbaImage::from_fn(64, 64, |x, y| {
+            let dx = x as f32 + 0.5 - 20.0;
+            let dy = y as f32 + 0.5 - 20.0;
+            if dx * dx + dy * dy < 9.0 {
+                Rgba([10, 10, 10, 255])
+            } else {
+                let v = 100 + x as u8;
+                Rgba([v, v, v, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn clone_copies_source_pixels() {
+        let img = blemished();
+        let mut state = EditState::default();
+        state.spots.push(spot(
+            (20.0 / 64.0, 20.0 / 64.0),
+            (40.0 / 64.0, 20.0 / 64.0),
+            SpotMode::Clone,
+        ));
+
+        let out = apply(DynamicImage::ImageRgba8(img.clone()), &state).to_rgba8();
+        assert_eq!(out.get_pixel(20, 20), img.get_pixel(40, 20));
+        assert_eq!(out.get_pixel(2, 2), img.get_pixel(2, 2));
+    }
+
+    #[test]
+    fn heal_matches_surrounding_tone() {
+        let img = blemished();
+        let mut state = EditState::default();
+        state.spots.push(spot(
+            (20.0 / 64.0, 20.0 / 64.0),
+            (20.0 / 64.0, 44.0 / 64.0),
+            SpotMode::Heal,
+        ));
+
+        let out = apply(DynamicImage::ImageRgba8(img), &state).to_rgba8();
+        // Source column matches, so heal reproduces the gradient under the blemish.
+        let v = out.get_pixel(20, 20)[0] as i32;
+        assert!((v - 120).abs() <= 2, "healed value {v}");
+    }
+
+    #[test]
+    fn heal_corrects_source_brightness() {
+        // Source half is uniformly darker; healing should lift it to match.
+        let img = RgbaImage::from_fn(64, 64, |_, y| {
+            if y < 32 {
+                Rgba([200, 150, 100, 255])
+            } else {
+                Rgba([100, 75, 50, 255])
+            }
+        });
+        let mut state = EditState::default();
+        state
+            .spots
+            .push(spot((0.5, 0.2), (0.5, 0.75), SpotMode::Heal));
+
+        let out = apply(DynamicImage::ImageRgba8(img), &state).to_rgba8();
+        let px = out.get_pixel(32, 13);
+        assert_eq!(px, &Rgba([200, 150, 100, 255]));
+    }
+
+    #[test]
+    fn feather_blends_toward_edge() {
+        let img = RgbaImage::from_fn(64, 64, |x, _| {
+            if x < 32 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let mut s = spot((0.25, 0.5), (0.75, 0.5), SpotMode::Clone);
+        s.radius = 0.2;
+        s.feather = 1.0;
+        let mut state = EditState::default();
+        state.spots.push(s);
+
+        let out = apply(DynamicImage::ImageRgba8(img), &state).to_rgba8();
+        let center = out.get_pixel(16, 32)[0];
+        let edge = out.get_pixel(16 + 10, 32)[0];
+        assert!(center > 240, "center {center}");
+        assert!(edge > 0 && edge < center, "edge {edge}");
+    }
+
+    #[test]
+    fn auto_source_prefers_matching_surroundings() {
+        // Left half flat gray (with the blemish), right half noisy.
+        let img = RgbaImage::from_fn(96, 64, |x, y| {
+            let dx = x as f32 + 0.5 - 24.0;
+            let dy = y as f32 + 0.5 - 32.0;
+            if dx * dx + dy * dy < 9.0 {
+                Rgba([0, 0, 0, 255])
+            } else if x < 48 {
+                Rgba([128, 128, 128, 255])
+            } else {
+                let v = ((x * 37 + y * 91) % 256) as u8;
+                Rgba([v, v, v, 255])
+            }
+        });
+        let center = NormPoint {
+            x: 24.0 / 96.0,
+            y: 0.5,
+        };
+        let source = auto_source(&img, center, 0.06);
+        assert!(source.x * 96.0 < 48.0 - 5.0, "picked {source:?}");
+        assert_ne!(source, center);
+    }
+}
@@ -1,16 +1,89 @@
-use image::{DynamicImage, Rgba};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
 use imageproc::geometric_transformations::{
     Border, Interpolation, Projection, rotate_about_center, warp,
 };
 
-use crate::state::{EditState, Keystone};
+use crate::state::{EditState, Keystone, Rect};
 
-use super::{color, exposure, filters, sharpness};
+use super::{color, exposure, filters, grain, local, lut, presence, sharpness, spots, vignette};
 
 /// Apply all geometry transforms from `state` to `img`.
-/// Order: straighten → keystone → orthogonal rotate → flip → crop.
+/// Order: spots → straighten → keystone → orthogonal rotate → flip → crop →
+/// vignette, then the tone and color stages.
 pub fn apply(img: &DynamicImage, state: &EditState) -> DynamicImage {
-    let mut out = img.clone();
+    let out = geometry(img, state);
+    let region = Region::whole(out.width(), out.height());
+    effects(
+        out,
+        state,
+        vignette_frame(state, img.width(), img.height()),
+        &region,
+    )
+}
+
+/// Where an image sits in the output frame when it is processed as one tile
+/// of it. Stages that depend on pixel position or frame size read it, so a
+/// tile matches the same pixels of a whole-frame render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    /// Offset of the image's top-left pixel in the frame.
+    pub origin: (u32, u32),
+    /// Size of the whole output frame.
+    pub frame: (u32, u32),
+    /// Dehaze airlight of the whole frame; `None` estimates it from the image.
+    pub airlight: Option<[u8; 3]>,
+    /// Frame pixels per full-resolution pixel. Sharpening radii are set in
+    /// full-resolution pixels and shrink with it, so a downscaled frame
+    /// matches the full-resolution render downscaled.
+    pub scale: f32,
+}
+
+impl Region {
+    /// The whole `w`×`h` frame.
+    pub fn whole(w: u32, h: u32) -> Self {
+        Self {
+            origin: (0, 0),
+            frame: (w, h),
+            airlight: None,
+            scale: 1.0,
+        }
+    }
+}
+
+/// The geometry stages of [`apply`]: spots, straighten, keystone, rotate,
+/// flip and crop.
+pub fn geometry(img: &DynamicImage, state: &EditState) -> DynamicImage {
+    let out = uncropped_geometry(img, state);
+    match crop_bounds(state, out.width(), out.height()) {
+        Some((x, y, w, h)) => out.crop_imm(x, y, w, h),
+        None => out,
+    }
+}
+
+/// Whether [`geometry`] does more than crop.
+pub fn has_warp(state: &EditState) -> bool {
+    !state.spots.is_empty()
+        || state.straighten.abs() > 0.01
+        || has_keystone(&state.keystone)
+        || state.rotate.rem_euclid(360) != 0
+        || state.flip_h
+        || state.flip_v
+}
+
+/// Whether `a` and `b` give the same [`uncropped_geometry`].
+pub fn same_warp(a: &EditState, b: &EditState) -> bool {
+    a.spots == b.spots
+        && a.straighten == b.straighten
+        && a.keystone == b.keystone
+        && a.rotate.rem_euclid(360) == b.rotate.rem_euclid(360)
+        && a.flip_h == b.flip_h
+        && a.flip_v == b.flip_v
+}
+
+/// [`geometry`] up to the crop.
+pub fn uncropped_geometry(img: &DynamicImage, state: &EditState) -> DynamicImage {
+    // Retouching spots are anchored to the source image
+    let mut out = spots::apply(img.clone(), state);
 
     // Straighten — arbitrary angle, bilinear interpolation
     if state.straighten.abs() > 0.01 {
@@ -25,7 +98,7 @@ pub fn apply(img: &DynamicImage, state: &EditState) -> DynamicImage {
     }
 
     // Keystone (perspective) correction
-    if state.keystone.vertical.abs() > 0.001 || state.keystone.horizontal.abs() > 0.001 {
+    if has_keystone(&state.keystone) {
         out = apply_keystone(out, &state.keystone);
     }
 
@@ -44,28 +117,422 @@ pub fn apply(img: &DynamicImage, state: &EditState) -> DynamicImage {
     if state.flip_v {
         out = out.flipv();
     }
+    out
+}
+
+/// The crop of a `w`×`h` uncropped frame in whole pixels (x, y, width,
+/// height), or `None` when the frame is not cropped.
+pub fn crop_bounds(state: &EditState, w: u32, h: u32) -> Option<(u32, u32, u32, u32)> {
+    // Crop is in normalized 0.0–1.0 coordinates
+    let crop = state.crop.as_ref()?;
+    let (w, h) = (w as f32, h as f32);
+    let cx = (crop.x * w) as u32;
+    let cy = (crop.y * h) as u32;
+    let cw = (crop.width * w).min(w - cx as f32) as u32;
+    let ch = (crop.height * h).min(h - cy as f32) as u32;
+    (cw > 0 && ch > 0).then_some((cx, cy, cw, ch))
+}
+
+/// The tone and effect stages of [`apply`], run on `img` as `region` of the
+/// geometry output. `vignette` is the [`vignette_frame`] in frame pixels.
+pub fn effects(
+    img: DynamicImage,
+    state: &EditState,
+    vignette: [f32; 4],
+    region: &Region,
+) -> DynamicImage {
+    let [fx, fy, fw, fh] = vignette;
+    let (ox, oy) = (region.origin.0 as f32, region.origin.1 as f32);
+    let mut out = vignette::apply(img, state, [fx - ox, fy - oy, fw, fh]);
+    out = exposure::apply(out, state);
+    out = color::apply(out, state);
+    out = lut::apply(out, state);
+    out = presence::apply(out, state, region);
+    out = filters::apply(out, state, region);
+    out = local::apply(out, state, region);
+    out = sharpness::apply(out, state, region);
+    out = grain::apply(out, state, region);
+
+    out
+}
+
+/// Pixels of context [`effects`] reads around each output pixel of a `w`×`h`
+/// frame at [`Region::scale`] `scale`; a tile rendered with this margin
+/// matches the whole-frame render.
+pub fn effect_padding(state: &EditState, w: u32, h: u32, scale: f32) -> u32 {
+    presence::reach(state, w, h) + local::reach(state, scale) + sharpness::reach(state, scale)
+}
+
+/// Dehaze airlight for rendering `frame`, a full-resolution geometry output,
+/// in tiles: the estimate [`apply`] makes for the whole frame. `None` when
+/// dehaze is off.
+pub fn frame_airlight<I>(frame: &I, state: &EditState, vignette: [f32; 4]) -> Option<[u8; 3]>
+where
+    I: GenericImageView<Pixel = Rgba<u8>>,
+{
+    if state.dehaze.clamp(-1.0, 1.0).abs() < 0.001 {
+        return None;
+    }
+    let (w, h) = frame.dimensions();
+    let whole = RgbaImage::from_fn(w, h, |x, y| frame.get_pixel(x, y));
+    let mut out = vignette::apply(DynamicImage::ImageRgba8(whole), state, vignette);
+    out = exposure::apply(out, state);
+    out = color::apply(out, state);
+    out = lut::apply(out, state);
+    Some(presence::estimate_airlight(&out.to_rgba8()))
+}
 
-    // Crop (applied last, in normalized 0.0–1.0 coordinates)
+pub fn output_dims(state: &EditState, src_w: u32, src_h: u32) -> (u32, u32) {
+    let (mut w, mut h) = match state.rotate.rem_euclid(360) {
+        90 | 270 => (src_h, src_w),
+        _ => (src_w, src_h),
+    };
     if let Some(ref crop) = state.crop {
-        let w = out.width() as f32;
-        let h = out.height() as f32;
-        let cx = (crop.x * w) as u32;
-        let cy = (crop.y * h) as u32;
-        let cw = (crop.width * w).min(w - cx as f32) as u32;
-        let ch = (crop.height * h).min(h - cy as f32) as u32;
+        // Match CPU: (crop.x * w) as u32, then cw = (crop.width * w).min(w - cx)
+        let cx = (crop.x * w as f32) as u32;
+        let cy = (crop.y * h as f32) as u32;
+        let cw = (crop.width * w as f32).min(w as f32 - cx as f32) as u32;
+        let ch = (crop.height * h as f32).min(h as f32 - cy as f32) as u32;
         if cw > 0 && ch > 0 {
-            out = out.crop_imm(cx, cy, cw, ch);
+            w = cw;
+            h = ch;
         }
     }
+    (w.max(1), h.max(1))
+}
 
-    out = exposure::apply(out, state);
-    out = color::apply(out, state);
-    out = filters::apply(out, state);
-    out = sharpness::apply(out, state);
+/// The frame the vignette is fitted to, as origin x, origin y, width and
+/// height in output pixels: the crop, or the whole image when the vignette
+/// follows the uncropped frame.
+pub fn vignette_frame(state: &EditState, src_w: u32, src_h: u32) -> [f32; 4] {
+    if !state.vignette.uncropped {
+        let (w, h) = output_dims(state, src_w, src_h);
+        return [0.0, 0.0, w as f32, h as f32];
+    }
+    let (rw, rh) = match state.rotate.rem_euclid(360) {
+        90 | 270 => (src_h as f64, src_w as f64),
+        _ => (src_w as f64, src_h as f64),
+    };
+    let (cx, cy) = crop_origin(state, rw, rh);
+    [-cx as f32, -cy as f32, rw as f32, rh as f32]
+}
+
+/// Maps a point in source-image pixels to output pixels, following the
+/// geometry order of [`apply`]. Points may land outside the output frame.
+pub fn source_to_output(state: &EditState, src_w: u32, src_h: u32, p: (f32, f32)) -> (f32, f32) {
+    let (w, h) = (src_w as f64, src_h as f64);
+    let (mut x, mut y) = (p.0 as f64, p.1 as f64);
+
+    if state.straighten.abs() > 0.01 {
+        (x, y) = rotate_point(
+            (x, y),
+            (w * 0.5, h * 0.5),
+            (state.straighten as f64).to_radians(),
+        );
+    }
+    if has_keystone(&state.keystone)
+        && let Some(m) = keystone_homography(&state.keystone, w, h, false)
+    {
+        (x, y) = project(&m, (x, y));
+    }
+
+    let rot = state.rotate.rem_euclid(360);
+    (x, y) = match rot {
+        90 => (h - y, x),
+        180 => (w - x, h - y),
+        270 => (y, w - x),
+        _ => (x, y),
+    };
+    let (rw, rh) = if matches!(rot, 90 | 270) {
+        (h, w)
+    } else {
+        (w, h)
+    };
+    if state.flip_h {
+        x = rw - x;
+    }
+    if state.flip_v {
+        y = rh - y;
+    }
+
+    let (cx, cy) = crop_origin(state, rw, rh);
+    ((x - cx) as f32, (y - cy) as f32)
+}
+
+/// Maps a point in output pixels back to source-image pixels; the inverse of
+/// [`source_to_output`].
+pub fn output_to_source(state: &EditState, src_w: u32, src_h: u32, p: (f32, f32)) -> (f32, f32) {
+    let (w, h) = (src_w as f64, src_h as f64);
+    let rot = state.rotate.rem_euclid(360);
+    let (rw, rh) = if matches!(rot, 90 | 270) {
+        (h, w)
+    } else {
+        (w, h)
+    };
+
+    let (cx, cy) = crop_origin(state, rw, rh);
+    let (mut x, mut y) = (p.0 as f64 + cx, p.1 as f64 + cy);
+    if state.flip_h {
+        x = rw - x;
+    }
+    if state.flip_v {
+        y = rh - y;
+    }
+    (x, y) = match rot {
+        90 => (y, h - x),
+        180 => (w - x, h - y),
+        270 => (w - y, x),
+        _ => (x, y),
+    };
+
+    if has_keystone(&state.keystone)
+        && let Some(m) = keystone_homography(&state.keystone, w, h, true)
+    {
+        (x, y) = project(&m, (x, y));
+    }
+    if state.straighten.abs() > 0.01 {
+        (x, y) = rotate_point(
+            (x, y),
+            (w * 0.5, h * 0.5),
+            -(state.straighten as f64).to_radians(),
+        );
+    }
+    (x as f32, y as f32)
+}
+
+/// Largest axis-aligned rectangle, normalized to the uncropped frame, that
+/// holds no border exposed by straighten or keystone. `aspect` (width over
+/// height, in pixels) fixes its shape; otherwise the area is maximized.
+pub fn inscribed_rect(state: &EditState, src_w: u32, src_h: u32, aspect: Option<f32>) -> Rect {
+    let (w, h) = (src_w as f64, src_h as f64);
+    let (rw, rh) = match state.rotate.rem_euclid(360) {
+        90 | 270 => (h, w),
+        _ => (w, h),
+    };
+    let uncropped = EditState {
+        crop: None,
+        ..state.clone()
+    };
+    let map = |s: &EditState, (x, y): (f64, f64)| {
+        let (x, y) = source_to_output(s, src_w, src_h, (x as f32, y as f32));
+        (x as f64, y as f64)
+    };
+    let corners = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+
+    // Straighten keeps the canvas size, so the valid area is the rotated
+    // source, clipped to the canvas as keystone warps it, clipped to the frame.
+    let mut valid: Vec<(f64, f64)> = corners.iter().map(|&p| map(&uncropped, p)).collect();
+    let unstraightened = EditState {
+        straighten: 0.0,
+        ..uncropped.clone()
+    };
+    let canvas: Vec<(f64, f64)> = corners.iter().map(|&p| map(&unstraightened, p)).collect();
+    valid = clip_convex(&valid, &canvas);
+    valid = clip_convex(&valid, &[(0.0, 0.0), (rw, 0.0), (rw, rh), (0.0, rh)]);
+
+    let full = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+    let edges = half_planes(&valid);
+    if edges.is_empty() {
+        return full;
+    }
+    let (cx, cy, half_w, half_h) = match aspect {
+        Some(ratio) => largest_box(&edges, &valid, ratio.max(1e-3) as f64),
+        None => {
+            // Area is unimodal in practice: scan ratios, then refine the best.
+            let area = |ratio: f64| {
+                let (_, _, hw, hh) = largest_box(&edges, &valid, ratio);
+                hw * hh
+            };
+            let steps = 24;
+            let log_ratio = |i: f64| (-2.5 + 5.0 * i / steps as f64).exp();
+            let best = (0..=steps)
+                .max_by(|&a, &b| area(log_ratio(a as f64)).total_cmp(&area(log_ratio(b as f64))))
+                .unwrap_or(steps / 2) as f64;
+            let (mut lo, mut hi) = (log_ratio(best - 1.0).ln(), log_ratio(best + 1.0).ln());
+            for _ in 0..24 {
+                let m1 = lo + (hi - lo) / 3.0;
+                let m2 = hi - (hi - lo) / 3.0;
+                if area(m1.exp()) < area(m2.exp()) {
+                    lo = m1;
+                } else {
+                    hi = m2;
+                }
+            }
+            largest_box(&edges, &valid, ((lo + hi) * 0.5).exp())
+        }
+    };
+    if half_w <= 0.0 || half_h <= 0.0 {
+        return full;
+    }
+    Rect {
+        x: ((cx - half_w) / rw).clamp(0.0, 1.0) as f32,
+        y: ((cy - half_h) / rh).clamp(0.0, 1.0) as f32,
+        width: (2.0 * half_w / rw).min(1.0) as f32,
+        height: (2.0 * half_h / rh).min(1.0) as f32,
+    }
+}
+
+/// Limits `crop` to `bounds`; a crop entirely outside falls back to `bounds`.
+pub fn constrain_crop(crop: &Rect, bounds: &Rect) -> Rect {
+    let x1 = crop.x.max(bounds.x);
+    let y1 = crop.y.max(bounds.y);
+    let x2 = (crop.x + crop.width).min(bounds.x + bounds.width);
+    let y2 = (crop.y + crop.height).min(bounds.y + bounds.height);
+    if x2 - x1 < 0.01 || y2 - y1 < 0.01 {
+        return bounds.clone();
+    }
+    Rect {
+        x: x1,
+        y: y1,
+        width: x2 - x1,
+        height: y2 - y1,
+    }
+}
+
+/// Pixels kept between an inscribed crop and the exposed border, so bilinear
+/// sampling never blends in the fill color.
+const INSCRIBED_MARGIN: f64 = 1.0;
 
+/// Outward unit normals `n` and offsets `d` with `n·p <= d` inside `poly`.
+fn half_planes(poly: &[(f64, f64)]) -> Vec<((f64, f64), f64)> {
+    if poly.len() < 3 {
+        return Vec::new();
+    }
+    let n = poly.len() as f64;
+    let centroid = poly
+        .iter()
+        .fold((0.0, 0.0), |acc, p| (acc.0 + p.0 / n, acc.1 + p.1 / n));
+    poly.iter()
+        .zip(poly.iter().cycle().skip(1))
+        .filter_map(|(&a, &b)| {
+            let (ex, ey) = (b.0 - a.0, b.1 - a.1);
+            let len = ex.hypot(ey);
+            if len < 1e-9 {
+                return None;
+            }
+            let mut normal = (ey / len, -ex / len);
+            if normal.0 * (centroid.0 - a.0) + normal.1 * (centroid.1 - a.1) > 0.0 {
+                normal = (-normal.0, -normal.1);
+            }
+            Some((normal, normal.0 * a.0 + normal.1 * a.1 - INSCRIBED_MARGIN))
+        })
+        .collect()
+}
+
+/// Largest box of the given aspect inside the half-planes, as center and
+/// half-extents. The feasible half-height is concave in the center, so
+/// nested ternary searches find its maximum.
+fn largest_box(
+    edges: &[((f64, f64), f64)],
+    poly: &[(f64, f64)],
+    ratio: f64,
+) -> (f64, f64, f64, f64) {
+    let half_h = |cx: f64, cy: f64| {
+        edges
+            .iter()
+            .map(|&((nx, ny), d)| (d - nx * cx - ny * cy) / (nx.abs() * ratio + ny.abs()))
+            .fold(f64::INFINITY, f64::min)
+    };
+    let (min_x, max_x) = poly
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+            (lo.min(p.0), hi.max(p.0))
+        });
+    let (min_y, max_y) = poly
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+            (lo.min(p.1), hi.max(p.1))
+        });
+    let ternary = |mut lo: f64, mut hi: f64, f: &dyn Fn(f64) -> f64| {
+        for _ in 0..32 {
+            let m1 = lo + (hi - lo) / 3.0;
+            let m2 = hi - (hi - lo) / 3.0;
+            if f(m1) < f(m2) {
+                lo = m1;
+            } else {
+                hi = m2;
+            }
+        }
+        (lo + hi) * 0.5
+    };
+    let best_cy = |cx: f64| ternary(min_y, max_y, &|cy| half_h(cx, cy));
+    let cx = ternary(min_x, max_x, &|cx| half_h(cx, best_cy(cx)));
+    let cy = best_cy(cx);
+    let hh = half_h(cx, cy);
+    (cx, cy, hh * ratio, hh)
+}
+
+/// Sutherland–Hodgman clip of convex `subject` against convex `clip`.
+fn clip_convex(subject: &[(f64, f64)], clip: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let orientation = signed_area(clip).signum();
+    let mut out = subject.to_vec();
+    for (&a, &b) in clip.iter().zip(clip.iter().cycle().skip(1)) {
+        if out.is_empty() {
+            break;
+        }
+        let side =
+            |p: (f64, f64)| ((b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)) * orientation;
+        let input = std::mem::take(&mut out);
+        for (&p, &q) in input.iter().zip(input.iter().cycle().skip(1)) {
+            let (sp, sq) = (side(p), side(q));
+            if sp >= 0.0 {
+                out.push(p);
+            }
+            if (sp >= 0.0) != (sq >= 0.0) {
+                let t = sp / (sp - sq);
+                out.push((p.0 + (q.0 - p.0) * t, p.1 + (q.1 - p.1) * t));
+            }
+        }
+    }
     out
 }
 
+fn signed_area(poly: &[(f64, f64)]) -> f64 {
+    poly.iter()
+        .zip(poly.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum::<f64>()
+        * 0.5
+}
+
+/// Top-left of the crop rectangle in post-rotation pixels, truncated like [`apply`].
+fn crop_origin(state: &EditState, rw: f64, rh: f64) -> (f64, f64) {
+    let Some(ref crop) = state.crop else {
+        return (0.0, 0.0);
+    };
+    let cx = (crop.x * rw as f32) as u32;
+    let cy = (crop.y * rh as f32) as u32;
+    let cw = (crop.width * rw as f32).min(rw as f32 - cx as f32) as u32;
+    let ch = (crop.height * rh as f32).min(rh as f32 - cy as f32) as u32;
+    if cw > 0 && ch > 0 {
+        (cx as f64, cy as f64)
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+fn rotate_point((x, y): (f64, f64), (cx, cy): (f64, f64), angle: f64) -> (f64, f64) {
+    let (sin, cos) = angle.sin_cos();
+    let (dx, dy) = (x - cx, y - cy);
+    (dx * cos - dy * sin + cx, dx * sin + dy * cos + cy)
+}
+
+fn project(m: &[f64; 9], (x, y): (f64, f64)) -> (f64, f64) {
+    let denom = m[6] * x + m[7] * y + m[8];
+    if denom.abs() <= 1e-8 {
+        return (x, y);
+    }
+    (
+        (m[0] * x + m[1] * y + m[2]) / denom,
+        (m[3] * x + m[4] * y + m[5]) / denom,
+    )
+}
+
 /// Apply keystone (perspective) correction.
 ///
 /// `vertical` shifts top corners inward (positive) or bottom corners inward (negative).
@@ -76,14 +543,32 @@ fn apply_keystone(img: DynamicImage, keystone: &Keystone) -> DynamicImage {
     let w = rgba.width() as f32;
     let h = rgba.height() as f32;
 
-    let v = keystone.vertical;
-    let hz = keystone.horizontal;
+    let (src, dst) = keystone_corners(keystone, w as f64, h as f64);
+    let to_f32 = |pts: [(f64, f64); 4]| pts.map(|(x, y)| (x as f32, y as f32));
 
-    // Source corners: top-left, top-right, bottom-right, bottom-left
-    let src: [(f32, f32); 4] = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+    let Some(projection) = Projection::from_control_points(to_f32(src), to_f32(dst)) else {
+        return img;
+    };
+
+    let warped = warp(
+        &rgba,
+        projection,
+        Interpolation::Bilinear,
+        Border::Constant(Rgba([0, 0, 0, 255])),
+    );
+    DynamicImage::ImageRgba8(warped)
+}
 
-    // Destination corners with perspective shifts applied
-    let dst: [(f32, f32); 4] = [
+/// Quad corners ordered top-left, top-right, bottom-right, bottom-left.
+type Corners = [(f64, f64); 4];
+
+/// Keystone control points as `(source, destination)` corners.
+fn keystone_corners(keystone: &Keystone, w: f64, h: f64) -> (Corners, Corners) {
+    let v = keystone.vertical as f64;
+    let hz = keystone.horizontal as f64;
+
+    let src = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+    let dst = [
         // top-left: shift right for +v, shift down for +h
         (v.max(0.0) * w, hz.max(0.0) * h),
         // top-right: shift left for +v, shift down for -h
@@ -93,16 +578,288 @@ fn apply_keystone(img: DynamicImage, keystone: &Keystone) -> DynamicImage {
         // bottom-left: shift right for -v, shift up for +h
         ((-v).max(0.0) * w, h - hz.max(0.0) * h),
     ];
+    (src, dst)
+}
 
-    let Some(projection) = Projection::from_control_points(src, dst) else {
-        return img;
-    };
+/// Homography mapping keystone output coordinates back to source coordinates
+/// (`inverse = true`) or source to output (`inverse = false`).
+pub(crate) fn keystone_homography(
+    keystone: &Keystone,
+    w: f64,
+    h: f64,
+    inverse: bool,
+) -> Option<[f64; 9]> {
+    let (src, dst) = keystone_corners(keystone, w, h);
+    if inverse {
+        compute_homography(&dst, &src)
+    } else {
+        compute_homography(&src, &dst)
+    }
+}
 
-    let warped = warp(
-        &rgba,
-        projection,
-        Interpolation::Bilinear,
-        Border::Constant(Rgba([0, 0, 0, 255])),
-    );
-    DynamicImage::ImageRgba8(warped)
+fn has_keystone(keystone: &Keystone) -> bool {
+    keystone.vertical.abs() > 0.001 || keystone.horizontal.abs() > 0.001
+}
+
+/// Compute 3×3 homography mapping src corners → dst corners using DLT.
+/// Returns None if the system is degenerate.
+pub(crate) fn compute_homography(src: &Corners, dst: &Corners) -> Option<[f64; 9]> {
+    // Build 8×8 system Ah = b where h = [h0..h7], h8 = 1
+    // For each point pair (sx,sy) → (dx,dy):
+    //   sx = (h0*dx + h1*dy + h2) / (h6*dx + h7*dy + 1)
+    //   sy = (h3*dx + h4*dy + h5) / (h6*dx + h7*dy + 1)
+    // Rearranged:
+    //   h0*dx + h1*dy + h2 - h6*dx*sx - h7*dy*sx = sx
+    //   h3*dx + h4*dy + h5 - h6*dx*sy - h7*dy*sy = sy
+    let mut a = [[0.0_f64; 8]; 8];
+    let mut b = [0.0_f64; 8];
+
+    for i in 0..4 {
+        let (dx, dy) = src[i]; // "from" coords
+        let (sx, sy) = dst[i]; // "to" coords
+        let row1 = i * 2;
+        let row2 = i * 2 + 1;
+        a[row1] = [dx, dy, 1.0, 0.0, 0.0, 0.0, -dx * sx, -dy * sx];
+        b[row1] = sx;
+        a[row2] = [0.0, 0.0, 0.0, dx, dy, 1.0, -dx * sy, -dy * sy];
+        b[row2] = sy;
+    }
+
+    // Gaussian elimination with partial pivoting
+    for col in 0..8 {
+        // Find pivot
+        let mut max_row = col;
+        let mut max_val = a[col][col].abs();
+        for (row, r) in a.iter().enumerate().skip(col + 1) {
+            if r[col].abs() > max_val {
+                max_val = r[col].abs();
+                max_row = row;
+            }
+        }
+        if max_val < 1e-12 {
+            return None;
+        }
+        if max_row != col {
+            a.swap(col, max_row);
+            b.swap(col, max_row);
+        }
+        let pivot = a[col][col];
+        for v in &mut a[col][col..] {
+            *v /= pivot;
+        }
+        b[col] /= pivot;
+        let pivot_row = a[col];
+        let pivot_b = b[col];
+        for (row, (r, rb)) in a.iter_mut().zip(b.iter_mut()).enumerate() {
+            if row == col {
+                continue;
+            }
+            let factor = r[col];
+            for (v, p) in r[col..].iter_mut().zip(&pivot_row[col..]) {
+                *v -= factor * p;
+            }
+            *rb -= factor * pivot_b;
+        }
+    }
+
+    Some([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7], 1.0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_mapping_round_trips_through_geometry() {
+        let mut state = EditState {
+            straighten: 4.0,
+            rotate: 90,
+            flip_h: true,
+            crop: Some(Rect {
+                x: 0.1,
+                y: 0.2,
+                width: 0.6,
+                height: 0.5,
+            }),
+            ..EditState::default()
+        };
+        state.keystone.vertical = 0.1;
+
+        for p in [(10.0, 12.0), (150.0, 40.0), (75.5, 99.25)] {
+            let out = source_to_output(&state, 160, 120, p);
+            let back = output_to_source(&state, 160, 120, out);
+            assert!((back.0 - p.0).abs() < 0.01 && (back.1 - p.1).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn source_to_output_follows_rotation_and_crop() {
+        let state = EditState {
+            rotate: 90,
+            crop: Some(Rect {
+                x: 0.25,
+                y: 0.0,
+                width: 0.5,
+                height: 1.0,
+            }),
+            ..EditState::default()
+        };
+        // 40×20 source rotates to 20×40; the crop starts at x = 5.
+        let (x, y) = source_to_output(&state, 40, 20, (10.0, 4.0));
+        assert!((x - 11.0).abs() < 1e-4 && (y - 10.0).abs() < 1e-4);
+        assert_eq!(output_dims(&state, 40, 20), (10, 40));
+    }
+
+    fn assert_inside_source(state: &EditState, rect: &Rect, src: (u32, u32)) {
+        let (rw, rh) = output_dims(
+            &EditState {
+                crop: None,
+                ..state.clone()
+            },
+            src.0,
+            src.1,
+        );
+        let (x0, y0) = (rect.x * rw as f32, rect.y * rh as f32);
+        let (x1, y1) = (x0 + rect.width * rw as f32, y0 + rect.height * rh as f32);
+        let uncropped = EditState {
+            crop: None,
+            ..state.clone()
+        };
+        for p in [(x0, y0), (x1, y0), (x1, y1), (x0, y1)] {
+            let (sx, sy) = output_to_source(&uncropped, src.0, src.1, p);
+            assert!(
+                (-0.01..=src.0 as f32 + 0.01).contains(&sx)
+                    && (-0.01..=src.1 as f32 + 0.01).contains(&sy),
+                "{p:?} maps outside the source to {:?}",
+                (sx, sy)
+            );
+        }
+    }
+
+    #[test]
+    fn inscribed_rect_is_full_frame_without_warps() {
+        let r = inscribed_rect(&EditState::default(), 300, 200, None);
+        assert!(r.x < 0.01 && r.y < 0.01 && r.width > 0.98 && r.height > 0.98);
+    }
+
+    #[test]
+    fn inscribed_rect_avoids_straighten_and_keystone_borders() {
+        let mut state = EditState {
+            straighten: 8.0,
+            rotate: 90,
+            ..EditState::default()
+        };
+        state.keystone.vertical = 0.15;
+        let r = inscribed_rect(&state, 300, 200, None);
+        assert_inside_source(&state, &r, (300, 200));
+        assert!(r.width * r.height > 0.4, "{r:?}");
+    }
+
+    #[test]
+    fn inscribed_rect_keeps_the_aspect() {
+        let state = EditState {
+            straighten: -6.0,
+            ..EditState::default()
+        };
+        let r = inscribed_rect(&state, 300, 200, Some(1.0));
+        assert_inside_source(&state, &r, (300, 200));
+        let pixel_ratio = r.width * 300.0 / (r.height * 200.0);
+        assert!((pixel_ratio - 1.0).abs() < 0.01, "{pixel_ratio}");
+    }
+
+    #[test]
+    fn inscribed_crop_has_no_black_corners() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            120,
+            80,
+            Rgba([255, 255, 255, 255]),
+        ));
+        let mut state = EditState::default();
+        state.straighten = 10.0;
+        state.crop = Some(inscribed_rect(&state, 120, 80, None));
+        let out = apply(&img, &state).to_rgba8();
+        assert!(
+            out.pixels().all(|p| p[0] > 240),
+            "border leaked into the crop"
+        );
+    }
+
+    #[test]
+    fn constrain_crop_clips_to_bounds() {
+        let bounds = Rect {
+            x: 0.1,
+            y: 0.1,
+            width: 0.8,
+            height: 0.8,
+        };
+        let crop = Rect {
+            x: 0.0,
+            y: 0.5,
+            width: 0.5,
+            height: 0.5,
+        };
+        let c = constrain_crop(&crop, &bounds);
+        assert!((c.x - 0.1).abs() < 1e-6 && (c.y - 0.5).abs() < 1e-6);
+        assert!((c.width - 0.4).abs() < 1e-6 && (c.height - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn padded_tiles_match_the_whole_frame_render() {
+        use crate::state::{GradFilter, LocalAdjustment, MaskShape, NormPoint};
+
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(96, 64, |x, y| {
+            let v = ((x * 7 + y * 13) % 97) as u8;
+            Rgba([v * 2, 255 - v, (x * 2 + y) as u8, 255])
+        }));
+        let mut state = EditState {
+            crop: Some(Rect {
+                x: 0.1,
+                y: 0.05,
+                width: 0.8,
+                height: 0.9,
+            }),
+            dehaze: 0.5,
+            clarity: 0.7,
+            sharpness: 0.8,
+            ..EditState::default()
+        };
+        state.vignette.amount = -0.6;
+        state.grain.amount = 0.5;
+        let mut grad = GradFilter::new(NormPoint { x: 0.5, y: 0.0 }, NormPoint { x: 0.5, y: 1.0 });
+        grad.exposure = -0.5;
+        state.graduated_filters.push(grad);
+        let mut local = LocalAdjustment::new(MaskShape::Radial {
+            center: NormPoint { x: 0.4, y: 0.6 },
+            radius_x: 0.2,
+            radius_y: 0.15,
+            angle: 20.0,
+            feather: 0.5,
+            invert: false,
+        });
+        local.sharpness = 0.6;
+        state.local_adjustments.push(local);
+
+        let whole = apply(&img, &state).to_rgba8();
+        let frame = geometry(&img, &state);
+        let (fw, fh) = (frame.width(), frame.height());
+        let vignette = vignette_frame(&state, img.width(), img.height());
+        let pad = effect_padding(&state, fw, fh, 1.0);
+        let (x, y, w, h) = (30, 20, 16, 12);
+        let (px, py) = (x - pad.min(x), y - pad.min(y));
+        let padded = frame.crop_imm(
+            px,
+            py,
+            (x + w + pad).min(fw) - px,
+            (y + h + pad).min(fh) - py,
+        );
+        let region = Region {
+            origin: (px, py),
+            frame: (fw, fh),
+            airlight: frame_airlight(&frame.to_rgba8(), &state, vignette),
+            scale: 1.0,
+        };
+        let tile = effects(padded, &state, vignette, &region).crop_imm(x - px, y - py, w, h);
+        let expected = DynamicImage::ImageRgba8(whole).crop_imm(x, y, w, h);
+        assert_eq!(tile.to_rgba8(), expected.to_rgba8());
+    }
 }
@@ -0,0 +1,433 @@
+use image::{DynamicImage, imageops::FilterType};
+use imageproc::{
+    edges::canny,
+    hough::{LineDetectionOptions, detect_lines},
+};
+
+use crate::state::{EditState, Keystone};
+
+use super::transform;
+
+/// Longest edge of the downscaled copy used for line detection.
+const ANALYSIS_MAX: u32 = 640;
+const CANNY_LOW: f32 = 30.0;
+const CANNY_HIGH: f32 = 80.0;
+/// Edge pixels within this distance (px) of a Hough line belong to it.
+const LINE_TOLERANCE: f32 = 1.5;
+/// Largest gap (px) between edge pixels of one segment.
+const MAX_GAP: f32 = 6.0;
+/// Segments shorter than this fraction of the short edge are ignored.
+const MIN_SEGMENT: f32 = 0.08;
+/// Only lines within this many degrees of vertical/horizontal are used.
+const MAX_TILT_DEG: f32 = 30.0;
+/// Scale (degrees) of the robust loss used for detected lines; outliers much
+/// further off than this stop pulling on the fit.
+const ROBUST_SCALE_DEG: f32 = 3.0;
+const STRAIGHTEN_LIMIT: f32 = 15.0;
+const KEYSTONE_LIMIT: f32 = 0.45;
+
+/// Which way a line should run once corrected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Vertical,
+    Horizontal,
+}
+
+/// A line in source-image pixels that should end up vertical or horizontal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GuideLine {
+    pub a: [f32; 2],
+    pub b: [f32; 2],
+    pub axis: Axis,
+}
+
+/// Proposed straighten angle and keystone correction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub straighten: f32,
+    pub keystone: Keystone,
+    /// Number of lines the proposal is based on.
+    pub lines: usize,
+}
+
+/// Detects dominant lines in `img` and proposes straighten and keystone
+/// values that make them vertical or horizontal in the output.
+pub fn auto(img: &DynamicImage, state: &EditState) -> Option<Suggestion> {
+    let segments = detect_segments(img);
+    let lines = classify(state, img.width(), img.height(), &segments);
+    solve(state, img.width(), img.height(), &lines, true)
+}
+
+/// Straight edge segments in `img`, as `[start, end]` in image pixels.
+pub fn detect_segments(img: &DynamicImage) -> Vec<[[f32; 2]; 2]> {
+    let long_edge = img.width().max(img.height()).max(1);
+    let scale = (ANALYSIS_MAX as f32 / long_edge as f32).min(1.0);
+    let small = if scale < 1.0 {
+        img.resize(
+            ((img.width() as f32 * scale).round() as u32).max(1),
+            ((img.height() as f32 * scale).round() as u32).max(1),
+            FilterType::Triangle,
+        )
+    } else {
+        img.clone()
+    };
+    let gray = small.to_luma8();
+    let edges = canny(&gray, CANNY_LOW, CANNY_HIGH);
+    let short_edge = gray.width().min(gray.height()) as f32;
+
+    let points: Vec<[f32; 2]> = edges
+        .enumerate_pixels()
+        .filter(|(_, _, p)| p[0] > 0)
+        .map(|(x, y, _)| [x as f32, y as f32])
+        .collect();
+    let options = LineDetectionOptions {
+        vote_threshold: ((short_edge * MIN_SEGMENT) as u32).max(8),
+        suppression_radius: 6,
+    };
+
+    let mut segments: Vec<[[f32; 2]; 2]> = detect_lines(&edges, options)
+        .into_iter()
+        .filter_map(|line| {
+            let (sin, cos) = (line.angle_in_degrees as f32).to_radians().sin_cos();
+            let near: Vec<[f32; 2]> = points
+                .iter()
+                .copied()
+                .filter(|p| (p[0] * cos + p[1] * sin - line.r).abs() <= LINE_TOLERANCE)
+                .collect();
+            fit_segment(&near, short_edge * MIN_SEGMENT)
+        })
+        .collect();
+
+    // Back to source pixels (pixel centers)
+    for seg in &mut segments {
+        for p in seg.iter_mut() {
+            *p = [(p[0] + 0.5) / scale, (p[1] + 0.5) / scale];
+        }
+    }
+    segments.sort_by(|a, b| length(b).total_cmp(&length(a)));
+    segments.truncate(40);
+    segments
+}
+
+/// Fits a line through `points` and returns its longest run without gaps,
+/// if that run is at least `min_len` long and densely covered.
+fn fit_segment(points: &[[f32; 2]], min_len: f32) -> Option<[[f32; 2]; 2]> {
+    let (center, dir) = principal_axis(points)?;
+    let mut ts: Vec<f32> = points
+        .iter()
+        .map(|p| (p[0] - center[0]) * dir[0] + (p[1] - center[1]) * dir[1])
+        .collect();
+    ts.sort_by(f32::total_cmp);
+
+    let (mut best, mut start) = ((0, 0), 0);
+    for i in 1..=ts.len() {
+        if i == ts.len() || ts[i] - ts[i - 1] > MAX_GAP {
+            if ts[i - 1] - ts[start] > ts[best.1] - ts[best.0] {
+                best = (start, i - 1);
+            }
+            start = i;
+        }
+    }
+    let (t0, t1) = (ts[best.0], ts[best.1]);
+    let count = best.1 - best.0 + 1;
+    if t1 - t0 < min_len || (count as f32) < (t1 - t0) * 0.5 {
+        return None;
+    }
+
+    // Refit on the run only so stray collinear pixels don't tilt it.
+    let run: Vec<[f32; 2]> = points
+        .iter()
+        .copied()
+        .filter(|p| {
+            let t = (p[0] - center[0]) * dir[0] + (p[1] - center[1]) * dir[1];
+            t >= t0 && t <= t1
+        })
+        .collect();
+    let (center, dir) = principal_axis(&run)?;
+    let ts = run
+        .iter()
+        .map(|p| (p[0] - center[0]) * dir[0] + (p[1] - center[1]) * dir[1]);
+    let (lo, hi) = ts.fold((f32::MAX, f32::MIN), |(lo, hi), t| (lo.min(t), hi.max(t)));
+    Some([
+        [center[0] + dir[0] * lo, center[1] + dir[1] * lo],
+        [center[0] + dir[0] * hi, center[1] + dir[1] * hi],
+    ])
+}
+
+/// Centroid and unit direction of least squares line through `points`.
+fn principal_axis(points: &[[f32; 2]]) -> Option<([f32; 2], [f32; 2])> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f32;
+    let cx = points.iter().map(|p| p[0]).sum::<f32>() / n;
+    let cy = points.iter().map(|p| p[1]).sum::<f32>() / n;
+    let (mut sxx, mut syy, mut sxy) = (0.0, 0.0, 0.0);
+    for p in points {
+        let (dx, dy) = (p[0] - cx, p[1] - cy);
+        sxx += dx * dx;
+        syy += dy * dy;
+        sxy += dx * dy;
+    }
+    let angle = 0.5 * (2.0 * sxy).atan2(sxx - syy);
+    Some(([cx, cy], [angle.cos(), angle.sin()]))
+}
+
+/// Sorts segments into the lines that should become vertical or horizontal
+/// in the output under the current geometry. Strongly tilted ones are dropped.
+pub fn classify(
+    state: &EditState,
+    src_w: u32,
+    src_h: u32,
+    segments: &[[[f32; 2]; 2]],
+) -> Vec<GuideLine> {
+    segments
+        .iter()
+        .filter_map(|&[a, b]| {
+            let (oa, ob) = (
+                transform::source_to_output(state, src_w, src_h, (a[0], a[1])),
+                transform::source_to_output(state, src_w, src_h, (b[0], b[1])),
+            );
+            let (dx, dy) = ((ob.0 - oa.0).abs(), (ob.1 - oa.1).abs());
+            let tilt = dx.min(dy).atan2(dx.max(dy)).to_degrees();
+            (tilt <= MAX_TILT_DEG).then_some(GuideLine {
+                a,
+                b,
+                axis: if dy > dx {
+                    Axis::Vertical
+                } else {
+                    Axis::Horizontal
+                },
+            })
+        })
+        .collect()
+}
+
+/// Finds straighten and keystone values that make `lines` vertical or
+/// horizontal in the output. Keystone is only fitted along an axis with at
+/// least two well separated lines. `robust` down-weights lines that cannot be
+/// aligned, as detected lines include some that are not truly straight edges.
+pub fn solve(
+    state: &EditState,
+    src_w: u32,
+    src_h: u32,
+    lines: &[GuideLine],
+    robust: bool,
+) -> Option<Suggestion> {
+    if lines.is_empty() {
+        return None;
+    }
+    let mut trial = state.clone();
+    trial.crop = None;
+    trial.straighten = 0.0;
+    trial.keystone = Keystone::default();
+
+    // Keystone acts in the source frame; an orthogonal rotation swaps which
+    // source axis ends up vertical.
+    let swapped = matches!(state.rotate.rem_euclid(360), 90 | 270);
+    let spread = |axis: Axis| {
+        let along: Vec<f32> = lines
+            .iter()
+            .filter(|l| l.axis == axis)
+            .map(|l| {
+                let mid = [(l.a[0] + l.b[0]) * 0.5, (l.a[1] + l.b[1]) * 0.5];
+                let o = transform::source_to_output(&trial, src_w, src_h, (mid[0], mid[1]));
+                match axis {
+                    Axis::Vertical => o.0,
+                    Axis::Horizontal => o.1,
+                }
+            })
+            .collect();
+        let (out_w, out_h) = transform::output_dims(&trial, src_w, src_h);
+        let extent = match axis {
+            Axis::Vertical => out_w,
+            Axis::Horizontal => out_h,
+        } as f32;
+        let lo = along.iter().copied().fold(f32::MAX, f32::min);
+        let hi = along.iter().copied().fold(f32::MIN, f32::max);
+        along.len() >= 2 && hi - lo >= extent * 0.2
+    };
+    let (fit_out_v, fit_out_h) = (spread(Axis::Vertical), spread(Axis::Horizontal));
+    let (fit_vertical, fit_horizontal) = if swapped {
+        (fit_out_h, fit_out_v)
+    } else {
+        (fit_out_v, fit_out_h)
+    };
+
+    let cost = |s: &EditState| -> f32 {
+        lines
+            .iter()
+            .map(|l| {
+                let oa = transform::source_to_output(s, src_w, src_h, (l.a[0], l.a[1]));
+                let ob = transform::source_to_output(s, src_w, src_h, (l.b[0], l.b[1]));
+                let (dx, dy) = (ob.0 - oa.0, ob.1 - oa.1);
+                let len = (dx * dx + dy * dy).sqrt();
+                let dev = match l.axis {
+                    Axis::Vertical => dx.atan2(dy.abs()),
+                    Axis::Horizontal => dy.atan2(dx.abs()),
+                }
+                .to_degrees();
+                let err = dev * dev;
+                let scale = ROBUST_SCALE_DEG * ROBUST_SCALE_DEG;
+                len * if robust { err / (err + scale) } else { err }
+            })
+            .sum()
+    };
+
+    for _ in 0..3 {
+        trial.straighten = minimize(-STRAIGHTEN_LIMIT, STRAIGHTEN_LIMIT, 0.25, |v| {
+            let mut s = trial.clone();
+            s.straighten = v;
+            cost(&s)
+        });
+        if fit_vertical {
+            trial.keystone.vertical = minimize(-KEYSTONE_LIMIT, KEYSTONE_LIMIT, 0.01, |v| {
+                let mut s = trial.clone();
+                s.keystone.vertical = v;
+                cost(&s)
+            });
+        }
+        if fit_horizontal {
+            trial.keystone.horizontal = minimize(-KEYSTONE_LIMIT, KEYSTONE_LIMIT, 0.01, |v| {
+                let mut s = trial.clone();
+                s.keystone.horizontal = v;
+                cost(&s)
+            });
+        }
+        if !fit_vertical && !fit_horizontal {
+            break;
+        }
+    }
+
+    Some(Suggestion {
+        straighten: (trial.straighten * 10.0).round() / 10.0,
+        keystone: Keystone {
+            vertical: (trial.keystone.vertical * 1000.0).round() / 1000.0,
+            horizontal: (trial.keystone.horizontal * 1000.0).round() / 1000.0,
+        },
+        lines: lines.len(),
+    })
+}
+
+/// Grid search over `[lo, hi]` at `step`, refined around the best sample.
+fn minimize(lo: f32, hi: f32, step: f32, f: impl Fn(f32) -> f32) -> f32 {
+    let search = |lo: f32, hi: f32, step: f32| {
+        let n = ((hi - lo) / step).round() as i32;
+        (0..=n)
+            .map(|i| (lo + i as f32 * step).clamp(lo, hi))
+            .map(|v| (v, f(v)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(0.0, |(v, _)| v)
+    };
+    let coarse = search(lo, hi, step);
+    let fine = search(
+        (coarse - step).max(lo),
+        (coarse + step).min(hi),
+        step / 10.0,
+    );
+    search(
+        (fine - step / 10.0).max(lo),
+        (fine + step / 10.0).min(hi),
+        step / 100.0,
+    )
+}
+
+fn length(seg: &[[f32; 2]; 2]) -> f32 {
+    let (dx, dy) = (seg[1][0] - seg[0][0], seg[1][1] - seg[0][1]);
+    (dx * dx + dy * dy).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    /// Dark background with bright bars tilted by `deg` degrees around the center.
+    fn tilted_bars(deg: f32) -> DynamicImage {
+        let (w, h) = (320u32, 240u32);
+        let (sin, cos) = deg.to_radians().sin_cos();
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(w, h, |x, y| {
+            let (dx, dy) = (x as f32 + 0.5 - 160.0, y as f32 + 0.5 - 120.0);
+            // Coordinates in the untilted frame
+            let u = dx * cos + dy * sin;
+            let v = -dx * sin + dy * cos;
+            let bar = (u.rem_euclid(80.0) < 12.0 && v.abs() < 90.0)
+                || (v.rem_euclid(70.0) < 10.0 && u.abs() < 120.0);
+            if bar {
+                Rgba([230, 230, 230, 255])
+            } else {
+                Rgba([30, 30, 30, 255])
+            }
+        }))
+    }
+
+    #[test]
+    fn auto_levels_tilted_image() {
+        let img = tilted_bars(4.0);
+        let suggestion = auto(&img, &EditState::default()).expect("lines detected");
+        assert!(
+            (suggestion.straighten + 4.0).abs() < 0.5,
+            "straighten {}",
+            suggestion.straighten
+        );
+        assert!(suggestion.lines >= 4);
+    }
+
+    #[test]
+    fn auto_is_neutral_on_level_image() {
+        let img = tilted_bars(0.0);
+        let suggestion = auto(&img, &EditState::default()).expect("lines detected");
+        assert!(suggestion.straighten.abs() < 0.3);
+        assert!(suggestion.keystone.vertical.abs() < 0.02);
+        assert!(suggestion.keystone.horizontal.abs() < 0.02);
+    }
+
+    #[test]
+    fn guide_lines_straighten_single_line() {
+        let line = GuideLine {
+            a: [100.0, 20.0],
+            b: [110.0, 180.0],
+            axis: Axis::Vertical,
+        };
+        let suggestion = solve(&EditState::default(), 320, 240, &[line], false).unwrap();
+        // A line leaning right at the bottom needs a clockwise correction.
+        let expected = (10.0_f32).atan2(160.0).to_degrees();
+        assert!((suggestion.straighten - expected).abs() < 0.1);
+        assert_eq!(suggestion.keystone, Keystone::default());
+    }
+
+    #[test]
+    fn guide_lines_recover_keystone() {
+        // Apply a known vertical keystone to two truly vertical lines, then
+        // ask the solver to undo it.
+        let mut warped = EditState::default();
+        warped.keystone.vertical = -0.12;
+        let (w, h) = (320, 240);
+        let lines: Vec<GuideLine> = [60.0, 260.0]
+            .iter()
+            .map(|&x| {
+                let a = transform::output_to_source(&warped, w, h, (x, 10.0));
+                let b = transform::output_to_source(&warped, w, h, (x, 230.0));
+                GuideLine {
+                    a: [a.0, a.1],
+                    b: [b.0, b.1],
+                    axis: Axis::Vertical,
+                }
+            })
+            .collect();
+        // Lines converge in the source; the solver should find the keystone
+        // that makes them vertical again.
+        let suggestion = solve(&EditState::default(), w, h, &lines, false).unwrap();
+        let fixed = EditState {
+            straighten: suggestion.straighten,
+            keystone: suggestion.keystone.clone(),
+            ..EditState::default()
+        };
+        for l in &lines {
+            let a = transform::source_to_output(&fixed, w, h, (l.a[0], l.a[1]));
+            let b = transform::source_to_output(&fixed, w, h, (l.b[0], l.b[1]));
+            assert!((a.0 - b.0).abs() < 1.0, "{a:?} {b:?}");
+        }
+        assert!(suggestion.keystone.vertical.abs() > 0.05);
+    }
+}
@@ -0,0 +1,111 @@
+use image::DynamicImage;
+
+use crate::state::{EditState, Vignette};
+
+/// Darkens or lightens toward the edges of `frame` (origin x, origin y,
+/// width, height in output pixels; see `transform::vignette_frame`).
+pub fn apply(img: DynamicImage, state: &EditState, frame: [f32; 4]) -> DynamicImage {
+    let vignette = state.vignette;
+    if !vignette.is_active() {
+        return img;
+    }
+
+    let amount = vignette.amount.clamp(-1.0, 1.0);
+    let mut rgba = img.to_rgba8();
+    for (x, y, px) in rgba.enumerate_pixels_mut() {
+        let t = weight(&vignette, frame, x as f32 + 0.5, y as f32 + 0.5);
+        for c in 0..3 {
+            let v = px[c] as f32 / 255.0;
+            let v = if amount < 0.0 {
+                v * (1.0 + amount * t)
+            } else {
+                v + (1.0 - v) * amount * t
+            };
+            px[c] = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Vignette strength (0–1) at a pixel center. Mirrored in `COLOR_SHADER_SRC`.
+fn weight(vignette: &Vignette, frame: [f32; 4], x: f32, y: f32) -> f32 {
+    let [ox, oy, fw, fh] = frame;
+    let u = (x - ox) / fw * 2.0 - 1.0;
+    let v = (y - oy) / fh * 2.0 - 1.0;
+
+    // Positive roundness pulls the frame's ellipse toward a circle, negative
+    // toward a rounded rectangle.
+    let roundness = vignette.roundness.clamp(-1.0, 1.0);
+    let long = fw.max(fh);
+    let ax = 1.0 + (fw / long - 1.0) * roundness.max(0.0);
+    let ay = 1.0 + (fh / long - 1.0) * roundness.max(0.0);
+    let p = 2.0 + (-roundness).max(0.0) * 6.0;
+    let norm =
+        |a: f32, b: f32| (a.abs().max(1e-6).powf(p) + b.abs().max(1e-6).powf(p)).powf(1.0 / p);
+    // 1 at the frame corners
+    let d = norm(u * ax, v * ay) / norm(ax, ay);
+
+    let midpoint = vignette.midpoint.clamp(0.0, 1.0);
+    let feather = vignette.feather.clamp(0.0, 1.0);
+    let inner = midpoint * (1.0 - feather);
+    let outer = (midpoint + (1.0 - midpoint) * feather).max(inner + 0.001);
+    let t = ((d - inner) / (outer - inner)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageBuffer, Rgba};
+
+    use crate::state::{EditState, Vignette};
+
+    use super::{apply, weight};
+
+    fn gray(w: u32, h: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(ImageBuffer::from_pixel(w, h, Rgba([128, 128, 128, 255])))
+    }
+
+    #[test]
+    fn darkens_corners_and_keeps_center() {
+        let mut state = EditState::default();
+        state.vignette.amount = -0.8;
+        let out = apply(gray(41, 31), &state, [0.0, 0.0, 41.0, 31.0]).to_rgba8();
+        assert_eq!(out.get_pixel(20, 15)[0], 128);
+        assert!(out.get_pixel(0, 0)[0] < 60);
+    }
+
+    #[test]
+    fn positive_amount_lightens_edges() {
+        let mut state = EditState::default();
+        state.vignette.amount = 0.8;
+        let out = apply(gray(41, 31), &state, [0.0, 0.0, 41.0, 31.0]).to_rgba8();
+        assert!(out.get_pixel(40, 30)[0] > 200);
+    }
+
+    #[test]
+    fn full_roundness_is_circular() {
+        let vignette = Vignette {
+            amount: -1.0,
+            roundness: 1.0,
+            ..Vignette::default()
+        };
+        let frame = [0.0, 0.0, 200.0, 100.0];
+        // Same pixel distance from the center along each axis
+        let along_x = weight(&vignette, frame, 140.0, 50.0);
+        let along_y = weight(&vignette, frame, 100.0, 90.0);
+        assert!((along_x - along_y).abs() < 1e-4);
+    }
+
+    #[test]
+    fn uncropped_frame_follows_the_full_image() {
+        let mut state = EditState::default();
+        state.vignette.amount = -1.0;
+        // The right half of a 200×100 image: its left edge is the full
+        // frame's center.
+        let cropped = apply(gray(100, 100), &state, [0.0, 0.0, 100.0, 100.0]).to_rgba8();
+        let uncropped = apply(gray(100, 100), &state, [-100.0, 0.0, 200.0, 100.0]).to_rgba8();
+        assert!(cropped.get_pixel(0, 50)[0] < 128);
+        assert_eq!(uncropped.get_pixel(0, 50)[0], 128);
+        assert!(uncropped.get_pixel(99, 0)[0] < 60);
+    }
+}
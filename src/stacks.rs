@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use image::DynamicImage;
+use image::imageops::FilterType;
+use rayon::prelude::*;
+
+/// Longest gap between consecutive frames of one burst.
+pub const BURST_GAP_SECS: f64 = 2.0;
+/// Largest hash distance between consecutive frames of one burst.
+pub const STACK_HASH_DISTANCE: u32 = 12;
+/// Largest hash distance for frames to count as duplicates, used when the
+/// capture time is unknown and for the cross-folder report.
+pub const DUPLICATE_HASH_DISTANCE: u32 = 4;
+
+/// What stacking knows about a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameInfo {
+    /// Capture time in seconds (see `metadata::capture_time`).
+    pub captured: Option<f64>,
+    /// Perceptual hash of the thumbnail (see [`dhash`]).
+    pub hash: Option<u64>,
+}
+
+/// 64-bit difference hash: one bit per horizontally adjacent pair of a 9×8
+/// grayscale reduction, set where brightness falls to the right.
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let bit = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | bit as u64;
+        }
+    }
+    hash
+}
+
+/// Number of differing bits between two hashes.
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Whether two consecutive frames belong to the same stack.
+fn same_stack(a: &FrameInfo, b: &FrameInfo) -> bool {
+    let (Some(ha), Some(hb)) = (a.hash, b.hash) else {
+        return false;
+    };
+    let distance = hash_distance(ha, hb);
+    match (a.captured, b.captured) {
+        (Some(ta), Some(tb)) => {
+            (ta - tb).abs() <= BURST_GAP_SECS && distance <= STACK_HASH_DISTANCE
+        }
+        _ => distance <= DUPLICATE_HASH_DISTANCE,
+    }
+}
+
+/// Indexes of `frames`, given with their file names, in shooting order: by
+/// capture time, then name. Frames without a capture time follow in name
+/// order. Names alone are no guide across counter rollovers, camera bodies
+/// or folders.
+pub fn shooting_order(frames: &[(&str, FrameInfo)]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..frames.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (name_a, frame_a) = &frames[a];
+        let (name_b, frame_b) = &frames[b];
+        match (frame_a.captured, frame_b.captured) {
+            (Some(ta), Some(tb)) => ta.total_cmp(&tb).then_with(|| name_a.cmp(name_b)),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => name_a.cmp(name_b),
+        }
+    });
+    order
+}
+
+/// Groups runs of consecutive similar frames (in shooting order) into
+/// stacks of two or more, as index lists into `frames`.
+pub fn group_stacks(frames: &[FrameInfo]) -> Vec<Vec<usize>> {
+    let mut stacks = Vec::new();
+    let mut run = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        if let Some(&prev) = run.last()
+            && !same_stack(&frames[prev], frame)
+        {
+            if run.len() > 1 {
+                stacks.push(std::mem::take(&mut run));
+            }
+            run.clear();
+        }
+        run.push(i);
+    }
+    if run.len() > 1 {
+        stacks.push(run);
+    }
+    stacks
+}
+
+/// Groups of near-identical images anywhere under `root`, compared by the
+/// hashes of their cached thumbnails (generated when missing). A RAW+JPEG
+/// pair is one image, not a duplicate. `progress` is called with
+/// (hashed, total) as images are processed.
+pub fn find_duplicates(root: &Path, progress: impl Fn(usize, usize) + Sync) -> Vec<Vec<PathBuf>> {
+    let mut images = Vec::new();
+    collect_images(root, &mut images);
+    let images = crate::thumbnail::without_raw_companions(images);
+    let total = images.len();
+    let done = std::sync::atomic::AtomicUsize::new(0);
+    let hashed: Vec<(PathBuf, u64)> = images
+        .into_par_iter()
+        .filter_map(|path| {
+            let hash = thumbnail_hash(&path);
+            let n = done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+            progress(n, total);
+            hash.map(|hash| (path, hash))
+        })
+        .collect();
+    duplicate_groups(&hashed)
+}
+
+/// Groups paths whose hashes are within [`DUPLICATE_HASH_DISTANCE`] of each
+/// other, directly or through other members.
+///
+/// Split into `DUPLICATE_HASH_DISTANCE + 1` bands, two such hashes agree on
+/// at least one band, so only hashes sharing a band are compared.
+fn duplicate_groups(hashed: &[(PathBuf, u64)]) -> Vec<Vec<PathBuf>> {
+    let mut parent: Vec<usize> = (0..hashed.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let bands = DUPLICATE_HASH_DISTANCE + 1;
+    let band_bits = u64::BITS.div_ceil(bands);
+    let mut buckets: HashMap<(u32, u64), Vec<usize>> = HashMap::new();
+    for (i, (_, hash)) in hashed.iter().enumerate() {
+        for band in 0..bands {
+            let value = (hash >> (band * band_bits)) & ((1 << band_bits) - 1);
+            buckets.entry((band, value)).or_default().push(i);
+        }
+    }
+    for members in buckets.values() {
+        for (k, &i) in members.iter().enumerate() {
+            for &j in &members[k + 1..] {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                if a != b && hash_distance(hashed[i].1, hashed[j].1) <= DUPLICATE_HASH_DISTANCE {
+                    parent[b] = a;
+                }
+            }
+        }
+    }
+
+    let mut groups: std::collections::BTreeMap<usize, Vec<PathBuf>> = Default::default();
+    for (i, (path, _)) in hashed.iter().enumerate() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(path.clone());
+    }
+    let mut groups: Vec<Vec<PathBuf>> = groups.into_values().filter(|g| g.len() > 1).collect();
+    for group in &mut groups {
+        group.sort();
+    }
+    groups.sort();
+    groups
+}
+
+pub fn collect_images(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_images(&path, out);
+        } else if crate::thumbnail::is_supported_image(&path) {
+            out.push(path);
+        }
+    }
+}
+
+/// Hash of the cached thumbnail of `path`, generating the thumbnail first
+/// when the folder has none yet.
+fn thumbnail_hash(path: &Path) -> Option<u64> {
+    let cache_dir = path.parent()?.join(".thumbnails");
+    let thumb_path = crate::thumbnail::cache_path(path, &cache_dir);
+    if !thumb_path.exists() {
+        crate::thumbnail::generate(path, &thumb_path).ok()?;
+    }
+    image::open(&thumb_path).ok().map(|img| dhash(&img))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use image::{DynamicImage, ImageBuffer, Rgba};
+
+    use super::*;
+
+    fn gradient(flip: bool) -> DynamicImage {
+        DynamicImage::ImageRgba8(ImageBuffer::from_fn(64, 48, |x, _| {
+            let v = (x * 4) as u8;
+            let v = if flip { 255 - v } else { v };
+            Rgba([v, v, v, 255])
+        }))
+    }
+
+    fn frame(captured: f64, hash: u64) -> FrameInfo {
+        FrameInfo {
+            captured: Some(captured),
+            hash: Some(hash),
+        }
+    }
+
+    #[test]
+    fn dhash_separates_different_images() {
+        assert_eq!(dhash(&gradient(false)), dhash(&gradient(false)));
+        assert_eq!(
+            hash_distance(dhash(&gradient(false)), dhash(&gradient(true))),
+            64
+        );
+    }
+
+    #[test]
+    fn bursts_split_on_time_gaps_and_content_changes() {
+        let frames = [
+            frame(0.0, 0),
+            frame(0.1, 0b111),
+            frame(0.2, 0b11),
+            // Long pause
+            frame(10.0, 0b11),
+            frame(10.5, 0b11),
+            // Different scene
+            frame(10.6, u64::MAX),
+        ];
+        assert_eq!(group_stacks(&frames), vec![vec![0, 1, 2], vec![3, 4]]);
+    }
+
+    #[test]
+    fn stacks_follow_capture_time_not_names() {
+        let frames = [
+            // Counter rollover in the middle of a burst
+            ("IMG_0001.JPG", frame(100.5, 0)),
+            ("IMG_9999.JPG", frame(100.0, 0)),
+            // A second body shooting in between, in name order
+            ("DSC_0500.JPG", frame(50.0, 0)),
+            ("IMG_5000.JPG", frame(0.0, 0)),
+            ("UNTIMED.JPG", FrameInfo::default()),
+        ];
+        let order = shooting_order(&frames);
+        assert_eq!(order, vec![3, 2, 1, 0, 4]);
+        let ordered: Vec<FrameInfo> = order.iter().map(|&i| frames[i].1).collect();
+        let stacks: Vec<Vec<usize>> = group_stacks(&ordered)
+            .into_iter()
+            .map(|stack| stack.into_iter().map(|i| order[i]).collect())
+            .collect();
+        assert_eq!(stacks, vec![vec![1, 0]]);
+    }
+
+    #[test]
+    fn frames_without_capture_time_need_near_identical_hashes() {
+        let untimed = |hash| FrameInfo {
+            captured: None,
+            hash: Some(hash),
+        };
+        let frames = [untimed(0), untimed(0b1), untimed(0xFFF)];
+        assert_eq!(group_stacks(&frames), vec![vec![0, 1]]);
+        // Unhashed frames never stack
+        assert!(group_stacks(&[FrameInfo::default(), FrameInfo::default()]).is_empty());
+    }
+
+    #[test]
+    fn duplicate_groups_chain_through_members() {
+        let p = |s: &str| PathBuf::from(s);
+        let hashed = [
+            (p("/a/1.jpg"), 0),
+            (p("/b/2.jpg"), 0b1111),
+            (p("/c/3.jpg"), 0b1111_1111),
+            (p("/c/4.jpg"), u64::MAX),
+        ];
+        assert_eq!(
+            duplicate_groups(&hashed),
+            vec![vec![p("/a/1.jpg"), p("/b/2.jpg"), p("/c/3.jpg")]]
+        );
+    }
+
+    #[test]
+    fn duplicate_groups_find_differences_spread_over_bands() {
+        let p = |s: &str| PathBuf::from(s);
+        // Four flipped bits in four different bands; the fifth band matches
+        let spread = (1 << 3) | (1 << 16) | (1 << 29) | (1 << 42);
+        let hashed = [
+            (p("/a/1.jpg"), 0x0123_4567_89ab_cdef),
+            (p("/b/2.jpg"), 0x0123_4567_89ab_cdef ^ spread),
+            (p("/c/3.jpg"), 0x0123_4567_89ab_cdef ^ (0x1f << 48)),
+        ];
+        assert_eq!(
+            duplicate_groups(&hashed),
+            vec![vec![p("/a/1.jpg"), p("/b/2.jpg")]]
+        );
+    }
+}
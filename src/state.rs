@@ -1,12 +1,21 @@
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 /// Per-hue HSL adjustment used for selective color controls.
 pub struct HslAdjust {
     pub hue: f32,
     pub saturation: f32,
     pub lightness: f32,
+    /// Offset of the band center from its default hue, in degrees.
+    pub center_shift: f32,
+    /// Half-width of the hue band in degrees.
+    pub width: f32,
+    /// Luminance range (0–1) the adjustment is limited to.
+    pub luminance_range: [f32; 2],
+    /// Saturation range (0–1) the adjustment is limited to.
+    pub saturation_range: [f32; 2],
 }
 
 impl Default for HslAdjust {
@@ -15,11 +24,22 @@ impl Default for HslAdjust {
             hue: 0.0,
             saturation: 0.0,
             lightness: 0.0,
+            center_shift: 0.0,
+            width: 30.0,
+            luminance_range: [0.0, 1.0],
+            saturation_range: [0.0, 1.0],
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl HslAdjust {
+    /// Whether the band changes anything; its shape alone does not.
+    pub fn is_active(&self) -> bool {
+        self.hue.abs() > 0.001 || self.saturation.abs() > 0.001 || self.lightness.abs() > 0.001
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Keystone perspective correction parameters.
 pub struct Keystone {
     pub vertical: f32,
@@ -35,7 +55,7 @@ impl Default for Keystone {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// Normalized rectangle in image coordinates.
 pub struct Rect {
     pub x: f32,
@@ -44,12 +64,322 @@ pub struct Rect {
     pub height: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-/// Graduated filter parameters applied from top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// Point in normalized (0–1) image coordinates.
+pub struct NormPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Linear graduated filter: full strength at `start`, fading out at `end`,
+/// in normalized coordinates of the processed (post-crop) frame.
 pub struct GradFilter {
-    pub top: f32,
-    pub bottom: f32,
+    pub start: NormPoint,
+    pub end: NormPoint,
+    #[serde(default)]
+    pub exposure: f32,
+    #[serde(default)]
+    pub contrast: f32,
+    #[serde(default)]
+    pub highlights: f32,
+    #[serde(default)]
+    pub temperature: f32,
+    #[serde(default)]
+    pub saturation: f32,
+}
+
+impl GradFilter {
+    /// Creates a filter between `start` and `end` with neutral settings.
+    pub fn new(start: NormPoint, end: NormPoint) -> Self {
+        Self {
+            start,
+            end,
+            exposure: 0.0,
+            contrast: 0.0,
+            highlights: 0.0,
+            temperature: 0.0,
+            saturation: 0.0,
+        }
+    }
+
+    /// Returns true when the filter changes the image at all.
+    pub fn is_active(&self) -> bool {
+        let span = (self.end.x - self.start.x).abs() + (self.end.y - self.start.y).abs();
+        span > 0.0001
+            && (self.exposure.abs() > 0.001
+                || self.contrast.abs() > 0.001
+                || self.highlights.abs() > 0.001
+                || self.temperature.abs() > 0.001
+                || self.saturation.abs() > 0.001)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// One stroke of a painted brush mask.
+pub struct BrushStroke {
+    pub points: Vec<NormPoint>,
+    /// Brush radius as a fraction of the image's long edge.
+    pub radius: f32,
+    /// Soft edge width as a fraction of the radius (0 = hard edge).
+    pub feather: f32,
+    /// Erase strokes remove coverage painted by earlier strokes.
+    pub erase: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+/// Mask shape of a local adjustment, in normalized coordinates of the
+/// processed (post-crop) frame. Lengths are fractions of the long edge.
+pub enum MaskShape {
+    /// Full effect at `start`, fading linearly to none at `end`.
+    Linear { start: NormPoint, end: NormPoint },
+    /// Ellipse around `center`, rotated by `angle` degrees.
+    Radial {
+        center: NormPoint,
+        radius_x: f32,
+        radius_y: f32,
+        angle: f32,
+        feather: f32,
+        invert: bool,
+    },
+    /// Painted coverage built from brush strokes, applied in order.
+    Brush { strokes: Vec<BrushStroke> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Masked adjustment layered on top of the global edits.
+pub struct LocalAdjustment {
+    pub mask: MaskShape,
+    #[serde(default)]
     pub exposure: f32,
+    #[serde(default)]
+    pub contrast: f32,
+    #[serde(default)]
+    pub temperature: f32,
+    #[serde(default)]
+    pub saturation: f32,
+    /// Positive values sharpen, negative values soften.
+    #[serde(default)]
+    pub sharpness: f32,
+}
+
+impl LocalAdjustment {
+    /// Creates an adjustment with neutral settings for `mask`.
+    pub fn new(mask: MaskShape) -> Self {
+        Self {
+            mask,
+            exposure: 0.0,
+            contrast: 0.0,
+            temperature: 0.0,
+            saturation: 0.0,
+            sharpness: 0.0,
+        }
+    }
+
+    /// Returns true when any of the adjustment sliders is away from neutral.
+    pub fn is_active(&self) -> bool {
+        self.exposure.abs() > 0.001
+            || self.contrast.abs() > 0.001
+            || self.temperature.abs() > 0.001
+            || self.saturation.abs() > 0.001
+            || self.sharpness.abs() > 0.001
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How a retouching spot fills its area from the source.
+pub enum SpotMode {
+    /// Copies source texture and blends its tone into the surrounding area.
+    #[default]
+    Heal,
+    /// Copies source pixels verbatim.
+    Clone,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Circular heal/clone spot in normalized coordinates of the source image
+/// (before geometry), so it stays attached to the content under crop and rotation.
+pub struct Spot {
+    pub center: NormPoint,
+    /// Where the replacement pixels are sampled from.
+    pub source: NormPoint,
+    /// Radius as a fraction of the image's long edge.
+    pub radius: f32,
+    /// Fraction of the radius (0–1) used for the soft edge.
+    pub feather: f32,
+    #[serde(default)]
+    pub mode: SpotMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+/// One color grading wheel: a tint and a brightness offset for its tonal range.
+pub struct GradeWheel {
+    /// Tint hue in degrees.
+    pub hue: f32,
+    /// Tint strength (0–1).
+    pub saturation: f32,
+    /// Brightness offset (-1 to 1).
+    pub luminance: f32,
+}
+
+impl GradeWheel {
+    pub fn is_active(&self) -> bool {
+        self.saturation > 0.001 || self.luminance.abs() > 0.001
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+/// Three-way color grading applied after the HSL edits.
+pub struct ColorGrading {
+    pub shadows: GradeWheel,
+    pub midtones: GradeWheel,
+    pub highlights: GradeWheel,
+    /// Moves the shadow/highlight split (-1 to 1); positive widens the highlights.
+    pub balance: f32,
+    /// Overlap between the tonal ranges (0–1).
+    pub blending: f32,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self {
+            shadows: GradeWheel::default(),
+            midtones: GradeWheel::default(),
+            highlights: GradeWheel::default(),
+            balance: 0.0,
+            blending: 0.5,
+        }
+    }
+}
+
+impl ColorGrading {
+    pub fn is_active(&self) -> bool {
+        self.shadows.is_active() || self.midtones.is_active() || self.highlights.is_active()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+/// Black and white conversion with a per-hue brightness mix and toning.
+pub struct Monochrome {
+    pub enabled: bool,
+    /// Brightness offset per selective color hue band (-1 to 1).
+    pub mix: [f32; 8],
+    /// Toning hue in degrees.
+    pub tone_hue: f32,
+    /// Toning strength (0–1); 0 keeps a neutral gray.
+    pub tone_amount: f32,
+}
+
+impl Default for Monochrome {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mix: [0.0; 8],
+            tone_hue: 35.0,
+            tone_amount: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+/// Film grain, sized relative to the image so previews match the export.
+pub struct Grain {
+    /// Strength (0–1); 0 disables grain.
+    pub amount: f32,
+    /// Grain size (0–1), from fine to coarse.
+    pub size: f32,
+    /// How much finer noise breaks up the grain (0–1).
+    pub roughness: f32,
+}
+
+impl Default for Grain {
+    fn default() -> Self {
+        Self {
+            amount: 0.0,
+            size: 0.25,
+            roughness: 0.5,
+        }
+    }
+}
+
+impl Grain {
+    pub fn is_active(&self) -> bool {
+        self.amount > 0.001
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+/// Edge darkening or lightening, fitted to the cropped frame by default.
+pub struct Vignette {
+    /// Strength (-1 to 1); negative darkens the edges, positive lightens them.
+    pub amount: f32,
+    /// Where the falloff is centered (0–1), from the middle to the corners.
+    pub midpoint: f32,
+    /// Shape (-1 to 1): squarer below 0, the frame's ellipse at 0, a circle at 1.
+    pub roundness: f32,
+    /// Softness of the falloff (0–1).
+    pub feather: f32,
+    /// Fit to the uncropped frame instead of the crop.
+    pub uncropped: bool,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            amount: 0.0,
+            midpoint: 0.5,
+            roundness: 0.0,
+            feather: 0.5,
+            uncropped: false,
+        }
+    }
+}
+
+impl Vignette {
+    pub fn is_active(&self) -> bool {
+        self.amount.abs() > 0.001
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How a 3D LUT is interpolated between grid points.
+pub enum LutInterpolation {
+    #[default]
+    Tetrahedral,
+    Trilinear,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A `.cube` 3D LUT applied after the global color edits.
+pub struct LutRef {
+    pub path: PathBuf,
+    /// Blend between the unmodified (0) and fully graded (1) image.
+    pub strength: f32,
+    #[serde(default)]
+    pub interpolation: LutInterpolation,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+/// Descriptive metadata edited in the metadata window.
+pub struct PhotoInfo {
+    pub title: String,
+    pub caption: String,
+    pub creator: String,
+    pub copyright: String,
+    /// Corrected capture time (`YYYY:MM:DD HH:MM:SS`), overriding EXIF
+    /// `DateTimeOriginal` when set.
+    pub capture_time: Option<String>,
+    /// Latitude and longitude in degrees, from geotagging.
+    pub location: Option<[f64; 2]>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,17 +392,57 @@ pub struct EditState {
     pub crop: Option<Rect>,
     pub straighten: f32,
     pub keystone: Keystone,
+    /// Keep the crop inside the image area left by straighten and keystone.
+    pub constrain_crop: bool,
+    /// Vignette, applied right after crop.
+    pub vignette: Vignette,
     pub exposure: f32,
     pub contrast: f32,
     pub highlights: f32,
     pub shadows: f32,
     pub temperature: f32,
     pub saturation: f32,
+    /// Saturation boost weighted toward muted colors, sparing skin tones.
+    pub vibrance: f32,
     pub hue_shift: f32,
     // red, orange, yellow, green, cyan, blue, purple, pink
     pub selective_color: [HslAdjust; 8],
-    pub graduated_filter: Option<GradFilter>,
+    /// Black and white conversion, applied after selective color.
+    pub monochrome: Monochrome,
+    /// Shadow/midtone/highlight tints, applied after selective color and
+    /// black and white conversion.
+    pub color_grading: ColorGrading,
+    /// Local contrast on fine detail (-1 to 1).
+    pub texture: f32,
+    /// Midtone local contrast over large features (-1 to 1).
+    pub clarity: f32,
+    /// Dark-channel-prior haze removal (-1 to 1); negative adds haze.
+    pub dehaze: f32,
+    /// Look LUT, applied after the global color edits.
+    pub lut: Option<LutRef>,
+    /// Graduated filters, applied in order after the global color edits.
+    pub graduated_filters: Vec<GradFilter>,
+    /// Unsharp-mask amount; 0 disables sharpening.
     pub sharpness: f32,
+    /// Gaussian sigma of the unsharp-mask blur, in pixels.
+    pub sharpen_radius: f32,
+    /// Minimum local luma contrast (0–1) before sharpening kicks in.
+    pub sharpen_threshold: f32,
+    /// Edge mask strength (0–1); higher values confine sharpening to strong edges.
+    pub sharpen_masking: f32,
+    /// Film grain, added after sharpening.
+    pub grain: Grain,
+    /// Masked local adjustments, applied in order after the global color edits.
+    pub local_adjustments: Vec<LocalAdjustment>,
+    /// Heal/clone spots, applied in order before geometry.
+    pub spots: Vec<Spot>,
+    /// Star rating (0–5) given while culling; not part of the rendered look.
+    pub rating: u8,
+    /// Hierarchical keywords (see `keywords::SEPARATOR`); not part of the
+    /// rendered look.
+    pub keywords: Vec<String>,
+    /// Title, caption and the like; not part of the rendered look.
+    pub info: PhotoInfo,
 }
 
 impl Default for EditState {
@@ -84,40 +454,96 @@ impl Default for EditState {
             crop: None,
             straighten: 0.0,
             keystone: Keystone::default(),
+            constrain_crop: false,
+            vignette: Vignette::default(),
             exposure: 0.0,
             contrast: 0.0,
             highlights: 0.0,
             shadows: 0.0,
             temperature: 0.0,
             saturation: 0.0,
+            vibrance: 0.0,
             hue_shift: 0.0,
             selective_color: Default::default(),
-            graduated_filter: None,
+            monochrome: Monochrome::default(),
+            color_grading: ColorGrading::default(),
+            texture: 0.0,
+            clarity: 0.0,
+            dehaze: 0.0,
+            lut: None,
+            graduated_filters: Vec::new(),
             sharpness: 0.0,
+            sharpen_radius: 1.5,
+            sharpen_threshold: 0.0,
+            sharpen_masking: 0.0,
+            grain: Grain::default(),
+            local_adjustments: Vec::new(),
+            spots: Vec::new(),
+            rating: 0,
+            keywords: Vec::new(),
+            info: PhotoInfo::default(),
         }
     }
 }
 
 impl EditState {
     /// Loads edit state from the image sidecar JSON, if present and valid.
-    pub fn load(image_path: &Path) -> Option<Self> {
-        let sidecar = sidecar_path(image_path);
-        let json = std::fs::read_to_string(sidecar).ok()?;
-        serde_json::from_str(&json).ok()
+    /// The halves of a RAW+JPEG pair share edits, so a missing sidecar falls
+    /// back to the sidecar of `companion`, the other half.
+    pub fn load(image_path: &Path, companion: Option<&Path>) -> Option<Self> {
+        let json = std::fs::read_to_string(sidecar_path(image_path))
+            .ok()
+            .or_else(|| std::fs::read_to_string(sidecar_path(companion?)).ok())?;
+        let mut value: serde_json::Value = serde_json::from_str(&json).ok()?;
+        migrate_legacy_fields(&mut value);
+        serde_json::from_value(value).ok()
     }
 
-    /// Saves the current edit state to the image sidecar JSON.
-    pub fn save(&self, image_path: &Path) -> anyhow::Result<()> {
-        let sidecar = sidecar_path(image_path);
-        if let Some(parent) = sidecar.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+    /// Saves the current edit state to the image sidecar JSON, and to the
+    /// sidecar of `companion`, the other half of a RAW+JPEG pair.
+    pub fn save(&self, image_path: &Path, companion: Option<&Path>) -> anyhow::Result<()> {
         let json = serde_json::to_string_pretty(self)?;
-        std::fs::write(sidecar, json)?;
+        for path in std::iter::once(image_path).chain(companion) {
+            let sidecar = sidecar_path(path);
+            if let Some(parent) = sidecar.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(sidecar, &json)?;
+        }
         Ok(())
     }
 }
 
+/// Rewrites sidecar fields from older versions into their current form.
+///
+/// `graduated_filter` was a single top-to-bottom exposure ramp
+/// (`{ top, bottom, exposure }`); it becomes one vertical entry in
+/// `graduated_filters`.
+fn migrate_legacy_fields(value: &mut serde_json::Value) {
+    let Some(obj) = value.as_object_mut() else {
+        return;
+    };
+    let Some(legacy) = obj.remove("graduated_filter") else {
+        return;
+    };
+    if obj.contains_key("graduated_filters") {
+        return;
+    }
+    let field = |name: &str| legacy.get(name).and_then(|v| v.as_f64()).map(|v| v as f32);
+    if let (Some(top), Some(bottom), Some(exposure)) =
+        (field("top"), field("bottom"), field("exposure"))
+    {
+        let mut grad = GradFilter::new(
+            NormPoint { x: 0.5, y: top },
+            NormPoint { x: 0.5, y: bottom },
+        );
+        grad.exposure = exposure;
+        if let Ok(grad) = serde_json::to_value(vec![grad]) {
+            obj.insert("graduated_filters".to_string(), grad);
+        }
+    }
+}
+
 fn sidecar_path(image_path: &Path) -> std::path::PathBuf {
     let dir = image_path.parent().unwrap_or(Path::new("."));
     let filename = image_path.file_name().unwrap().to_string_lossy();
@@ -134,4 +560,87 @@ mod tests {
         let p = sidecar_path(Path::new("/photos/IMG_001.RAF"));
         assert_eq!(p, PathBuf::from("/photos/.edits/IMG_001.RAF.json"));
     }
+
+    #[test]
+    fn raw_jpeg_pair_shares_edits() {
+        let dir = std::env::temp_dir().join(format!("photograph-pair-{}", std::process::id()));
+        let raw = dir.join("DSC_0001.NEF");
+        let jpeg = dir.join("DSC_0001.JPG");
+
+        let state = EditState {
+            exposure: 0.5,
+            rating: 4,
+            ..EditState::default()
+        };
+        state.save(&jpeg, None).unwrap();
+        assert!(EditState::load(&raw, None).is_none());
+        let loaded = EditState::load(&raw, Some(&jpeg)).unwrap();
+        assert_eq!(loaded.exposure, 0.5);
+        assert_eq!(loaded.rating, 4);
+
+        // Saving mirrors the edits into the companion's sidecar
+        state.save(&raw, Some(&jpeg)).unwrap();
+        assert!(sidecar_path(&raw).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn ambiguous_pairs_do_not_share_edits() {
+        let dir = std::env::temp_dir().join(format!("photograph-ambiguous-{}", std::process::id()));
+        let paths: Vec<PathBuf> = [
+            "IMG_1.CR2",
+            "IMG_1.JPG",
+            "IMG_1.jpeg",
+            "IMG_2.nef",
+            "IMG_2.Jpg",
+        ]
+        .iter()
+        .map(|name| dir.join(name))
+        .collect();
+        let companions = crate::thumbnail::companion_map(paths.iter().map(PathBuf::as_path));
+        let companion = |name: &str| companions.get(&dir.join(name)).map(PathBuf::as_path);
+
+        let state = EditState {
+            rating: 3,
+            ..EditState::default()
+        };
+        // Two camera files for one RAW: nothing is mirrored
+        assert_eq!(companion("IMG_1.CR2"), None);
+        state
+            .save(&dir.join("IMG_1.CR2"), companion("IMG_1.CR2"))
+            .unwrap();
+        assert!(!sidecar_path(&dir.join("IMG_1.JPG")).exists());
+        assert!(EditState::load(&dir.join("IMG_1.JPG"), companion("IMG_1.JPG")).is_none());
+        // Mixed-case extensions still pair
+        state
+            .save(&dir.join("IMG_2.Jpg"), companion("IMG_2.Jpg"))
+            .unwrap();
+        assert!(sidecar_path(&dir.join("IMG_2.nef")).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn legacy_graduated_filter_becomes_vertical_filter() {
+        let mut value = serde_json::json!({
+            "exposure": 0.5,
+            "graduated_filter": { "top": 0.1, "bottom": 0.6, "exposure": -0.7 }
+        });
+        migrate_legacy_fields(&mut value);
+        let state: EditState = serde_json::from_value(value).unwrap();
+
+        assert_eq!(state.exposure, 0.5);
+        assert_eq!(state.graduated_filters.len(), 1);
+        let grad = &state.graduated_filters[0];
+        assert_eq!(grad.start, NormPoint { x: 0.5, y: 0.1 });
+        assert_eq!(grad.end, NormPoint { x: 0.5, y: 0.6 });
+        assert_eq!(grad.exposure, -0.7);
+    }
+
+    #[test]
+    fn disabled_legacy_graduated_filter_is_dropped() {
+        let mut value = serde_json::json!({ "graduated_filter": null });
+        migrate_legacy_fields(&mut value);
+        let state: EditState = serde_json::from_value(value).unwrap();
+        assert!(state.graduated_filters.is_empty());
+    }
 }
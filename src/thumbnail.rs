@@ -1,13 +1,16 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-use image::{DynamicImage, ImageBuffer};
+use image::{DynamicImage, ImageBuffer, ImageDecoder, ImageReader};
 use rawler::imgop::develop::{Intermediate, ProcessingStep, RawDevelop};
 
-use crate::processing::highlights;
+use crate::processing::{highlights, icc};
 
 pub const THUMB_SIZE: u32 = 300;
 
 static RAW_EXTS: &[&str] = &["raf", "dng", "nef", "cr2", "arw"];
+/// Camera-rendered formats written next to a RAW in RAW+JPEG mode.
+static RAW_COMPANION_EXTS: &[&str] = &["jpg", "jpeg", "heic"];
 static SUPPORTED_IMAGE_EXTS: &[&str] = &[
     "jpg", "jpeg", "png", "tiff", "tif", "webp", "bmp", "raf", "dng", "nef", "cr2", "arw", "heic",
     "avif",
@@ -24,6 +27,68 @@ pub fn is_raw_image(path: &Path) -> bool {
     has_extension(path, RAW_EXTS)
 }
 
+/// Returns `true` for formats a camera writes alongside a RAW file.
+pub fn is_raw_companion_image(path: &Path) -> bool {
+    has_extension(path, RAW_COMPANION_EXTS)
+}
+
+/// Folder and stem of `path`, ignoring case: what the halves of a RAW+JPEG
+/// pair have in common.
+fn pair_key(path: &Path) -> String {
+    path.with_extension("").to_string_lossy().to_lowercase()
+}
+
+/// RAW+JPEG pairs among `paths`, as (RAW, companion). Files pair only when
+/// their folder and stem (ignoring case) are shared by exactly one RAW and
+/// one camera-rendered file; anything ambiguous stays unpaired.
+pub fn raw_jpeg_pairs<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Vec<(&'a Path, &'a Path)> {
+    let mut by_stem: HashMap<String, (Vec<&Path>, Vec<&Path>)> = HashMap::new();
+    for path in paths {
+        if is_raw_image(path) {
+            by_stem.entry(pair_key(path)).or_default().0.push(path);
+        } else if is_raw_companion_image(path) {
+            by_stem.entry(pair_key(path)).or_default().1.push(path);
+        }
+    }
+    by_stem
+        .into_values()
+        .filter_map(
+            |(raws, companions)| match (raws.as_slice(), companions.as_slice()) {
+                (&[raw], &[companion]) => Some((raw, companion)),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// `paths` with the camera-rendered half of each RAW+JPEG pair removed, so
+/// a pair counts once.
+pub fn without_raw_companions(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let companions: std::collections::HashSet<PathBuf> =
+        raw_jpeg_pairs(paths.iter().map(PathBuf::as_path))
+            .into_iter()
+            .map(|(_, companion)| companion.to_path_buf())
+            .collect();
+    paths
+        .into_iter()
+        .filter(|path| !companions.contains(path))
+        .collect()
+}
+
+/// The other half of each RAW+JPEG pair among `paths`, in both directions.
+/// `paths` must list whole folders for the pairing to be reliable.
+pub fn companion_map<'a>(paths: impl IntoIterator<Item = &'a Path>) -> HashMap<PathBuf, PathBuf> {
+    raw_jpeg_pairs(paths)
+        .into_iter()
+        .flat_map(|(raw, companion)| {
+            [
+                (raw.to_path_buf(), companion.to_path_buf()),
+                (companion.to_path_buf(), raw.to_path_buf()),
+            ]
+        })
+        .collect()
+}
+
 /// Returns `true` if the path has a supported image extension.
 pub fn is_supported_image(path: &Path) -> bool {
     has_extension(path, SUPPORTED_IMAGE_EXTS)
@@ -35,11 +100,12 @@ pub fn cache_path(source: &Path, cache_dir: &Path) -> PathBuf {
     cache_dir.join(format!("{}.webp", stem))
 }
 
-/// Open an image, falling back to raw decoding for RAW extensions.
+/// Open an image in the working space, falling back to raw decoding for RAW
+/// extensions.
 pub fn open_image(path: &Path) -> anyhow::Result<DynamicImage> {
     // Fast path: try the standard image crate first.
-    if let Ok(img) = image::open(path) {
-        return Ok(img);
+    if let Ok((img, icc_profile)) = decode_with_profile(path) {
+        return Ok(icc::to_working(img, icc_profile.as_deref()));
     }
 
     // Fallback: try raw decode for known raw extensions.
@@ -54,13 +120,23 @@ pub fn open_image(path: &Path) -> anyhow::Result<DynamicImage> {
     develop_raw_with_recovery(&raw)
 }
 
+/// Decodes with the `image` crate, keeping any embedded ICC profile.
+fn decode_with_profile(path: &Path) -> anyhow::Result<(DynamicImage, Option<Vec<u8>>)> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let icc_profile = decoder.icc_profile().ok().flatten();
+    Ok((DynamicImage::from_decoder(decoder)?, icc_profile))
+}
+
 /// Develop a RAW image with highlight recovery.
 ///
-/// Uses a custom pipeline that skips rawler's sRGB gamma step so we can
-/// operate on linear f32 data. After development:
-/// 1. Apply highlight reconstruction on the linear RGB channels.
-/// 2. Apply sRGB gamma.
-/// 3. Convert to a standard `DynamicImage`.
+/// Uses a custom pipeline that skips rawler's calibration and sRGB gamma
+/// steps so we can operate on linear f32 data. After development:
+/// 1. Map camera RGB straight into the working space (see [`icc::calibrate_raw`]).
+/// 2. Apply highlight reconstruction on the linear RGB channels.
+/// 3. Apply the sRGB tone curve the working space is stored with.
+/// 4. Convert to a standard `DynamicImage`.
 fn develop_raw_with_recovery(raw: &rawler::RawImage) -> anyhow::Result<DynamicImage> {
     let develop = RawDevelop {
         steps: vec![
@@ -68,13 +144,14 @@ fn develop_raw_with_recovery(raw: &rawler::RawImage) -> anyhow::Result<DynamicIm
             ProcessingStep::Demosaic,
             ProcessingStep::CropActiveArea,
             ProcessingStep::WhiteBalance,
-            ProcessingStep::Calibrate,
+            // Calibrate omitted — it bakes in sRGB primaries, so the camera
+            // matrix is applied below in the working space instead.
             ProcessingStep::CropDefault,
             // SRgb intentionally omitted — applied after highlight recovery.
         ],
     };
 
-    let intermediate = develop.develop_intermediate(raw)?;
+    let intermediate = icc::calibrate_raw(raw, develop.develop_intermediate(raw)?);
 
     match intermediate {
         Intermediate::ThreeColor(mut pixels) => {
@@ -156,23 +233,26 @@ fn open_embedded_raw_preview(path: &Path) -> anyhow::Result<Option<DynamicImage>
     let decoder = rawler::get_decoder(&source)?;
     let params = rawler::decoders::RawDecodeParams::default();
 
-    if let Some(img) = decoder.preview_image(&source, &params)? {
-        return Ok(Some(img));
-    }
-    if let Some(img) = decoder.thumbnail_image(&source, &params)? {
-        return Ok(Some(img));
-    }
-    if let Some(img) = decoder.full_image(&source, &params)? {
-        return Ok(Some(img));
-    }
+    // Embedded previews are camera-rendered sRGB.
+    let img = match decoder.preview_image(&source, &params)? {
+        Some(img) => Some(img),
+        None => match decoder.thumbnail_image(&source, &params)? {
+            Some(img) => Some(img),
+            None => decoder.full_image(&source, &params)?,
+        },
+    };
+    Ok(img.map(|img| icc::to_working(img, None)))
+}
 
-    Ok(None)
+/// Downscales a working-space image to an sRGB thumbnail.
+pub fn display_thumbnail(img: &DynamicImage) -> DynamicImage {
+    icc::to_display(&img.thumbnail(THUMB_SIZE, THUMB_SIZE))
 }
 
 /// Generate a thumbnail for `source` and write it to `dest`.
 pub fn generate(source: &Path, dest: &Path) -> anyhow::Result<()> {
     let img = open_image_for_preview(source)?;
-    let thumb = img.thumbnail(THUMB_SIZE, THUMB_SIZE);
+    let thumb = display_thumbnail(&img);
     std::fs::create_dir_all(dest.parent().unwrap())?;
     thumb.save(dest)?;
     Ok(())
@@ -185,12 +265,30 @@ mod tests {
 
     use image::{DynamicImage, ImageBuffer, Rgba};
 
-    use super::{PreviewSource, is_raw_image, open_image_for_preview_with_hooks};
+    use super::{
+        PreviewSource, is_raw_image, open_image_for_preview_with_hooks, without_raw_companions,
+    };
 
     fn img(px: [u8; 4]) -> DynamicImage {
         DynamicImage::ImageRgba8(ImageBuffer::from_pixel(1, 1, Rgba(px)))
     }
 
+    #[test]
+    fn pairs_count_once_but_ambiguous_stems_do_not_pair() {
+        let paths = [
+            "/a/DSC_1.NEF",
+            "/a/dsc_1.Jpg",
+            "/b/DSC_1.JPG",
+            "/a/IMG_2.CR2",
+            "/a/IMG_2.JPG",
+            "/a/IMG_2.jpeg",
+        ]
+        .map(std::path::PathBuf::from);
+        let kept = without_raw_companions(paths.to_vec());
+        assert_eq!(kept.len(), 5);
+        assert!(!kept.contains(&paths[1]));
+    }
+
     #[test]
     fn raw_preview_prefers_embedded_and_skips_full_decode() {
         let embedded_calls = Cell::new(0);
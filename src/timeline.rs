@@ -0,0 +1,385 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+    mpsc,
+};
+
+use rayon::prelude::*;
+
+use crate::state::EditState;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+/// Height of the header pinned to the top of the scrolled timeline.
+const STICKY_HEIGHT: f32 = 26.0;
+
+/// Calendar day a photo was taken, in the camera's local time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Date part of an EXIF date-time, as recorded (`2024:05:01 14:03:22`) or
+    /// as displayed (`2024-05-01 14:03:22`).
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().get(..10)?.split([':', '-']);
+        let date = Self {
+            year: parts.next()?.parse().ok()?,
+            month: parts.next()?.parse().ok()?,
+            day: parts.next()?.parse().ok()?,
+        };
+        // Cameras with an unset clock record zeros
+        let valid = date.year > 0 && (1..=12).contains(&date.month) && (1..=31).contains(&date.day);
+        valid.then_some(date)
+    }
+
+    fn month_name(self) -> &'static str {
+        MONTHS[self.month as usize - 1]
+    }
+
+    fn weekday(self) -> &'static str {
+        let days =
+            crate::metadata::timestamp(self.year, i64::from(self.month), i64::from(self.day), 0)
+                as i64
+                / 86_400;
+        // 1970-01-01 was a Thursday
+        WEEKDAYS[(days + 4).rem_euclid(7) as usize]
+    }
+}
+
+/// Photos under a folder grouped by capture day.
+#[derive(Debug, Default)]
+pub struct TimelineIndex {
+    pub root: PathBuf,
+    days: BTreeMap<Date, Vec<PathBuf>>,
+    /// Grid cells per day: a RAW+JPEG pair counts once, as the grid shows it.
+    counts: BTreeMap<Date, usize>,
+    /// Photos without a readable capture date.
+    undated: Vec<PathBuf>,
+}
+
+/// Number of grid cells `paths` take, with RAW+JPEG pairs collapsed.
+fn cell_count(paths: &[PathBuf]) -> usize {
+    paths.len() - crate::thumbnail::raw_jpeg_pairs(paths.iter().map(PathBuf::as_path)).len()
+}
+
+impl TimelineIndex {
+    /// Groups `photos` by their capture dates.
+    pub fn new(root: PathBuf, photos: Vec<(PathBuf, Option<Date>)>) -> Self {
+        let mut index = Self {
+            root,
+            ..Self::default()
+        };
+        for (path, date) in photos {
+            match date {
+                Some(date) => index.days.entry(date).or_default().push(path),
+                None => index.undated.push(path),
+            }
+        }
+        for (date, paths) in &mut index.days {
+            paths.sort();
+            index.counts.insert(*date, cell_count(paths));
+        }
+        index.undated.sort();
+        index
+    }
+
+    /// Photos taken on the days for which `within` holds, by day.
+    pub fn paths(&self, within: impl Fn(&Date) -> bool) -> Vec<PathBuf> {
+        self.days
+            .iter()
+            .filter(|(date, _)| within(date))
+            .flat_map(|(_, paths)| paths.iter().cloned())
+            .collect()
+    }
+
+    /// Number of photos taken on the days for which `within` holds, with
+    /// RAW+JPEG pairs counted once.
+    pub fn count(&self, within: impl Fn(&Date) -> bool) -> usize {
+        self.counts
+            .iter()
+            .filter(|(date, _)| within(date))
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Years, newest first, each with its months, newest first.
+    fn years(&self) -> Vec<(i64, Vec<u32>)> {
+        let mut years: Vec<(i64, Vec<u32>)> = Vec::new();
+        for date in self.days.keys().rev() {
+            match years.last_mut() {
+                Some((year, months)) if *year == date.year => {
+                    if months.last() != Some(&date.month) {
+                        months.push(date.month);
+                    }
+                }
+                _ => years.push((date.year, vec![date.month])),
+            }
+        }
+        years
+    }
+}
+
+/// Capture date of `path`: the corrected capture time saved with its edits
+/// (or those of `companion`, the other half of its RAW+JPEG pair), else
+/// EXIF `DateTimeOriginal`.
+fn capture_date(path: &Path, companion: Option<&Path>) -> Option<Date> {
+    let corrected = EditState::load(path, companion).and_then(|s| s.info.capture_time);
+    let text = corrected.or_else(|| crate::metadata::read(path).ok()?.date_taken)?;
+    Date::parse(&text)
+}
+
+/// Timeline view state: the date index of a folder tree, built in the
+/// background, shown as years, months and days with photo counts.
+#[derive(Default)]
+pub struct Timeline {
+    index: Option<TimelineIndex>,
+    /// Folder being indexed, with the photos read so far and in total.
+    pending: Option<(PathBuf, Arc<AtomicUsize>, Arc<AtomicUsize>)>,
+    rx: Option<mpsc::Receiver<TimelineIndex>>,
+}
+
+impl Timeline {
+    /// Folder the timeline covers, or is being built for.
+    pub fn root(&self) -> Option<&Path> {
+        match &self.pending {
+            Some((root, ..)) => Some(root),
+            None => self.index.as_ref().map(|i| i.root.as_path()),
+        }
+    }
+
+    /// Starts indexing every photo under `root` on a background thread.
+    pub fn scan(&mut self, root: PathBuf) {
+        let (tx, rx) = mpsc::channel();
+        let done = Arc::new(AtomicUsize::new(0));
+        let total = Arc::new(AtomicUsize::new(0));
+        self.pending = Some((root.clone(), done.clone(), total.clone()));
+        self.rx = Some(rx);
+        std::thread::spawn(move || {
+            let mut images = Vec::new();
+            crate::stacks::collect_images(&root, &mut images);
+            total.store(images.len(), Ordering::Relaxed);
+            let companions = crate::thumbnail::companion_map(images.iter().map(PathBuf::as_path));
+            let photos = images
+                .into_par_iter()
+                .map(|path| {
+                    let date = capture_date(&path, companions.get(&path).map(PathBuf::as_path));
+                    done.fetch_add(1, Ordering::Relaxed);
+                    (path, date)
+                })
+                .collect();
+            let _ = tx.send(TimelineIndex::new(root, photos));
+        });
+    }
+
+    fn poll(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.rx else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(index) => {
+                self.index = Some(index);
+                self.pending = None;
+                self.rx = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {
+                ctx.request_repaint_after(std::time::Duration::from_millis(100));
+            }
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.pending = None;
+                self.rx = None;
+            }
+        }
+    }
+
+    /// Renders the timeline; returns the label and photos of the year, month
+    /// or day that was clicked.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<(String, Vec<PathBuf>)> {
+        self.poll(ui.ctx());
+        if let Some((root, done, total)) = &self.pending {
+            ui.centered_and_justified(|ui| {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!(
+                        "Reading capture dates under {}: {} of {}",
+                        root.display(),
+                        done.load(Ordering::Relaxed),
+                        total.load(Ordering::Relaxed)
+                    ));
+                });
+            });
+            return None;
+        }
+        let index = self.index.as_ref()?;
+        if index.days.is_empty() && index.undated.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.label("No images in this folder or its subfolders");
+            });
+            return None;
+        }
+
+        let mut picked = None;
+        let output = egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                let top = ui.min_rect().top();
+                // Month headers by their offset in the scrolled content
+                let mut headers: Vec<(f32, String)> = Vec::new();
+                for (year, months) in index.years() {
+                    let total = index.count(|d| d.year == year);
+                    headers.push((ui.cursor().top() - top, year.to_string()));
+                    let heading = egui::RichText::new(format!("{year}  \u{b7}  {total}")).heading();
+                    if ui.add(egui::Button::new(heading).frame(false)).clicked() {
+                        picked = Some((year.to_string(), index.paths(|d| d.year == year)));
+                    }
+                    for month in months {
+                        let in_month = |d: &Date| d.year == year && d.month == month;
+                        let label = format!("{} {year}", MONTHS[month as usize - 1]);
+                        headers.push((ui.cursor().top() - top, label.clone()));
+                        ui.add_space(4.0);
+                        let text = egui::RichText::new(format!(
+                            "{}  ({})",
+                            MONTHS[month as usize - 1],
+                            index.count(in_month)
+                        ))
+                        .strong();
+                        if ui.add(egui::Button::new(text).frame(false)).clicked() {
+                            picked = Some((label, index.paths(in_month)));
+                        }
+                        ui.horizontal_wrapped(|ui| {
+                            for (date, paths) in index.days.range(
+                                Date {
+                                    year,
+                                    month,
+                                    day: 0,
+                                }..=Date {
+                                    year,
+                                    month,
+                                    day: 31,
+                                },
+                            ) {
+                                let chip = format!(
+                                    "{} {}  \u{b7}  {}",
+                                    date.weekday(),
+                                    date.day,
+                                    index.counts[date]
+                                );
+                                if ui.button(chip).clicked() {
+                                    picked = Some((
+                                        format!("{} {} {}", date.day, date.month_name(), date.year),
+                                        paths.clone(),
+                                    ));
+                                }
+                            }
+                        });
+                    }
+                    ui.add_space(12.0);
+                }
+                if !index.undated.is_empty() {
+                    headers.push((ui.cursor().top() - top, "Undated".to_string()));
+                    let text = egui::RichText::new(format!(
+                        "Undated  \u{b7}  {}",
+                        cell_count(&index.undated)
+                    ))
+                    .heading();
+                    if ui
+                        .add(egui::Button::new(text).frame(false))
+                        .on_hover_text("No capture date in EXIF or the sidecar")
+                        .clicked()
+                    {
+                        picked = Some(("Undated".to_string(), index.undated.clone()));
+                    }
+                }
+                headers
+            });
+
+        // Pin the header of the section scrolled past to the top
+        let offset = output.state.offset.y;
+        let current = output.inner.iter().rev().find(|(y, _)| *y < offset);
+        if let Some((_, label)) = current {
+            let rect = egui::Rect::from_min_size(
+                output.inner_rect.min,
+                egui::vec2(output.inner_rect.width(), STICKY_HEIGHT),
+            );
+            let painter = ui.painter();
+            painter.rect_filled(rect, 0.0, ui.visuals().panel_fill);
+            painter.hline(
+                rect.x_range(),
+                rect.bottom(),
+                ui.visuals().widgets.noninteractive.bg_stroke,
+            );
+            painter.text(
+                rect.left_center() + egui::vec2(4.0, 0.0),
+                egui::Align2::LEFT_CENTER,
+                label,
+                egui::FontId::proportional(15.0),
+                ui.visuals().strong_text_color(),
+            );
+        }
+        picked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i64, month: u32, day: u32) -> Option<Date> {
+        Some(Date { year, month, day })
+    }
+
+    #[test]
+    fn exif_dates_parse_in_either_form() {
+        assert_eq!(Date::parse("2024:05:01 14:03:22"), date(2024, 5, 1));
+        assert_eq!(Date::parse("2024-05-01 14:03:22"), date(2024, 5, 1));
+        assert_eq!(Date::parse("0000:00:00 00:00:00"), None);
+        assert_eq!(Date::parse("2024"), None);
+        assert_eq!(date(2024, 5, 1).unwrap().weekday(), "Wed");
+    }
+
+    #[test]
+    fn photos_group_by_year_month_and_day() {
+        let index = TimelineIndex::new(
+            PathBuf::from("/photos"),
+            vec![
+                (PathBuf::from("/photos/b/2.jpg"), date(2024, 5, 1)),
+                (PathBuf::from("/photos/a/1.jpg"), date(2024, 5, 1)),
+                (PathBuf::from("/photos/a/3.jpg"), date(2024, 6, 9)),
+                (PathBuf::from("/photos/4.jpg"), date(2023, 12, 31)),
+                (PathBuf::from("/photos/5.png"), None),
+                // A RAW+JPEG pair, one grid cell
+                (PathBuf::from("/photos/a/6.NEF"), date(2024, 6, 9)),
+                (PathBuf::from("/photos/a/6.JPG"), date(2024, 6, 9)),
+            ],
+        );
+        assert_eq!(index.years(), vec![(2024, vec![6, 5]), (2023, vec![12])]);
+        assert_eq!(index.count(|d| d.year == 2024), 4);
+        assert_eq!(index.paths(|d| d.month == 6).len(), 3);
+        assert_eq!(
+            index.paths(|d| d.month == 5),
+            vec![
+                PathBuf::from("/photos/a/1.jpg"),
+                PathBuf::from("/photos/b/2.jpg")
+            ]
+        );
+        assert_eq!(index.undated, vec![PathBuf::from("/photos/5.png")]);
+    }
+}
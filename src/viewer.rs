@@ -1,15 +1,21 @@
 use std::{
     collections::hash_map::DefaultHasher,
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     hash::{Hash, Hasher},
     path::{Path, PathBuf},
-    sync::mpsc,
+    sync::{Arc, Mutex, mpsc},
     time::{Duration, Instant},
 };
 
-use image::DynamicImage;
+use image::{DynamicImage, GenericImageView};
+use rayon::prelude::*;
 
-use crate::state::{EditState, GradFilter, Rect};
+use crate::processing::{color, focus, icc, local, lut, spots, transform, upright};
+use crate::state::{
+    BrushStroke, ColorGrading, EditState, GradFilter, GradeWheel, Grain, HslAdjust,
+    LocalAdjustment, LutInterpolation, LutRef, MaskShape, Monochrome, NormPoint, Rect, Spot,
+    SpotMode, Vignette,
+};
 
 /// Downscale loaded images to this longest-edge size for the preview.
 const PREVIEW_MAX: u32 = 1920;
@@ -18,9 +24,23 @@ const INTERACTIVE_PREVIEW_MAX: u32 = 960;
 const DEBOUNCE: Duration = Duration::from_millis(300);
 const INTERACTIVE_REFRESH: Duration = Duration::from_millis(90);
 const PREVIEW_CACHE_CAPACITY: usize = 24;
+/// Edge length of the full-resolution tiles shown when zoomed past the preview.
+const TILE_SIZE: u32 = 512;
+/// Tile textures kept so panning back over visited areas is instant.
+const TILE_CACHE_CAPACITY: usize = 96;
+/// Coarsest tile level; level `n` tiles hold one pixel per 2^n source pixels.
+const MAX_TILE_LEVEL: u32 = 4;
+/// Deepest zoom, as full-resolution pixels per screen pixel.
+const MAX_PIXEL_ZOOM: f32 = 4.0;
 
 /// Size in screen pixels for crop corner drag handles.
 const HANDLE_SIZE: f32 = 8.0;
+/// Longest edge of the red coverage overlay drawn for the selected local mask.
+const LOCAL_MASK_OVERLAY_MAX: u32 = 512;
+/// Color of the focus-peaking overlay.
+const PEAKING_COLOR: [u8; 3] = [60, 255, 80];
+/// Guided upright keeps the most recent lines, enough for two per axis.
+const UPRIGHT_GUIDES_MAX: usize = 4;
 
 enum BgResult {
     Loaded {
@@ -35,6 +55,61 @@ enum BgResult {
         width: usize,
         height: usize,
     },
+    /// Inputs for rendering tiles of one edit signature; `frame` is `None`
+    /// when the source could not be decoded.
+    TileFramePrepared {
+        path: PathBuf,
+        generation: u64,
+        edit_signature: u64,
+        source: Option<Arc<DynamicImage>>,
+        frame: Option<Arc<TileFrame>>,
+    },
+    TileRendered {
+        path: PathBuf,
+        generation: u64,
+        key: TileKey,
+        img: Option<image::RgbaImage>,
+    },
+}
+
+/// Full-resolution tile by (edit signature, level, column, row).
+type TileKey = (u64, u32, u32, u32);
+
+/// What every tile rendered for one edit signature shares.
+struct TileFrame {
+    state: EditState,
+    /// Geometry output before the crop; the decoded source itself when the
+    /// edits do nothing but crop.
+    base: Arc<DynamicImage>,
+    /// The crop of `base` that is the output frame (x, y, width, height).
+    bounds: (u32, u32, u32, u32),
+    /// Vignette frame in full-resolution output pixels.
+    vignette: [f32; 4],
+    airlight: Option<[u8; 3]>,
+    display: Arc<icc::DisplayTransform>,
+}
+
+impl TileFrame {
+    /// Size of the output frame at tile `level`.
+    fn level_dims(&self, level: u32) -> (u32, u32) {
+        let (_, _, w, h) = self.bounds;
+        (w.div_ceil(1 << level), h.div_ceil(1 << level))
+    }
+}
+
+struct TileJob {
+    path: PathBuf,
+    generation: u64,
+    frame: Arc<TileFrame>,
+    key: TileKey,
+}
+
+/// Tiles waiting for the background renderer, in the order they are wanted.
+#[derive(Default)]
+struct TileQueue {
+    jobs: VecDeque<TileJob>,
+    /// Whether a renderer thread is draining the queue.
+    running: bool,
 }
 
 fn scale_to_cap(img: DynamicImage, cap: u32) -> DynamicImage {
@@ -114,6 +189,18 @@ struct PreviewCacheKey {
     input_width: u32,
     input_height: u32,
     quality: ProcessQuality,
+    sharpen_mask_view: bool,
+}
+
+impl PreviewCacheKey {
+    /// Long edge of the image actually processed for this key.
+    fn processed_long_edge(&self) -> u32 {
+        let long = self.input_width.max(self.input_height);
+        match self.quality {
+            ProcessQuality::Interactive => long.min(INTERACTIVE_PREVIEW_MAX),
+            ProcessQuality::Final => long,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -126,39 +213,87 @@ struct PreviewCacheEntry {
 #[derive(Clone, Copy, PartialEq)]
 enum CropAspect {
     Free,
+    Original,
     Square,
+    Photo3x2,
     Photo4x3,
+    Photo5x4,
+    Photo7x5,
     Wide16x9,
-    Original,
+    Pano65x24,
+    Custom,
 }
 
 impl CropAspect {
+    /// Landscape width/height in pixels.
     fn ratio(self) -> Option<f32> {
         match self {
             CropAspect::Free => None,
+            CropAspect::Original => None, // caller uses image aspect
             CropAspect::Square => Some(1.0),
+            CropAspect::Photo3x2 => Some(3.0 / 2.0),
             CropAspect::Photo4x3 => Some(4.0 / 3.0),
+            CropAspect::Photo5x4 => Some(5.0 / 4.0),
+            CropAspect::Photo7x5 => Some(7.0 / 5.0),
             CropAspect::Wide16x9 => Some(16.0 / 9.0),
-            CropAspect::Original => None, // caller uses image aspect
+            CropAspect::Pano65x24 => Some(65.0 / 24.0),
+            CropAspect::Custom => None, // caller uses the custom ratio
         }
     }
 
     fn label(self) -> &'static str {
         match self {
             CropAspect::Free => "Free",
+            CropAspect::Original => "Original",
             CropAspect::Square => "1:1",
+            CropAspect::Photo3x2 => "3:2",
             CropAspect::Photo4x3 => "4:3",
+            CropAspect::Photo5x4 => "5:4",
+            CropAspect::Photo7x5 => "7:5",
             CropAspect::Wide16x9 => "16:9",
-            CropAspect::Original => "Original",
+            CropAspect::Pano65x24 => "65:24",
+            CropAspect::Custom => "Custom",
         }
     }
 
-    const ALL: [CropAspect; 5] = [
+    const ALL: [CropAspect; 10] = [
         CropAspect::Free,
+        CropAspect::Original,
         CropAspect::Square,
+        CropAspect::Photo3x2,
         CropAspect::Photo4x3,
+        CropAspect::Photo5x4,
+        CropAspect::Photo7x5,
         CropAspect::Wide16x9,
-        CropAspect::Original,
+        CropAspect::Pano65x24,
+        CropAspect::Custom,
+    ];
+}
+
+/// Composition guide drawn inside the crop rect.
+#[derive(Clone, Copy, PartialEq)]
+enum CropOverlay {
+    Thirds,
+    GoldenRatio,
+    GoldenSpiral,
+    Diagonals,
+}
+
+impl CropOverlay {
+    fn label(self) -> &'static str {
+        match self {
+            CropOverlay::Thirds => "Rule of thirds",
+            CropOverlay::GoldenRatio => "Golden ratio",
+            CropOverlay::GoldenSpiral => "Golden spiral",
+            CropOverlay::Diagonals => "Diagonals",
+        }
+    }
+
+    const ALL: [CropOverlay; 4] = [
+        CropOverlay::Thirds,
+        CropOverlay::GoldenRatio,
+        CropOverlay::GoldenSpiral,
+        CropOverlay::Diagonals,
     ];
 }
 
@@ -169,11 +304,81 @@ enum DragTarget {
     Interior,
 }
 
+/// Mask shown and edited on the image.
+#[derive(Clone, Copy, PartialEq)]
+enum MaskTarget {
+    Graduated(usize),
+    Local(usize),
+}
+
+/// What a drag on the image edits while a mask is selected.
+#[derive(Clone, Copy, PartialEq)]
+enum LocalDrag {
+    /// Index into the handles returned by `local_handles`.
+    Handle(usize),
+    /// Appending points to the last brush stroke.
+    Stroke,
+}
+
+/// What dragging on the image changes in the selective color band under the cursor.
+#[derive(Clone, Copy, PartialEq)]
+enum TargetProperty {
+    Hue,
+    Saturation,
+    Lightness,
+}
+
+impl TargetProperty {
+    fn label(self) -> &'static str {
+        match self {
+            TargetProperty::Hue => "Hue",
+            TargetProperty::Saturation => "Saturation",
+            TargetProperty::Lightness => "Lightness",
+        }
+    }
+
+    const ALL: [TargetProperty; 3] = [
+        TargetProperty::Hue,
+        TargetProperty::Saturation,
+        TargetProperty::Lightness,
+    ];
+}
+
+/// Which end of a retouching spot is being dragged.
+#[derive(Clone, Copy, PartialEq)]
+enum SpotDrag {
+    Center(usize),
+    Source(usize),
+}
+
+/// Guided upright: lines drawn on the image that should end up vertical or horizontal.
+#[derive(Default)]
+struct UprightGuides {
+    /// Drags on the image draw guide lines.
+    active: bool,
+    /// Guide lines in normalized source-image coordinates.
+    lines: Vec<(NormPoint, NormPoint, upright::Axis)>,
+    /// Screen position where the line being drawn started.
+    drag_start: Option<egui::Pos2>,
+    /// Outcome of the last auto/guided correction.
+    status: Option<String>,
+}
+
+/// Brush used when painting new strokes into a brush mask.
+struct BrushSettings {
+    /// Fraction of the image's long edge.
+    radius: f32,
+    feather: f32,
+    erase: bool,
+}
+
 /// Image viewer/editor window state, including async preview processing.
 pub struct Viewer {
     id: usize,
     preview_backend: PreviewBackend,
     current_path: Option<PathBuf>,
+    /// The other half of the RAW+JPEG pair being edited, which shares the edits.
+    companion: Option<PathBuf>,
     preview: Option<DynamicImage>,
     pub edit_state: EditState,
     needs_process: bool,
@@ -183,8 +388,15 @@ pub struct Viewer {
     texture: Option<egui::TextureHandle>,
     original_texture: Option<egui::TextureHandle>,
     split_view: bool,
+    /// Show the sharpening edge mask instead of the image (alt-drag on Masking).
+    sharpen_mask_view: bool,
     crop_mode: bool,
     crop_aspect: CropAspect,
+    /// Width and height of the `CropAspect::Custom` ratio.
+    crop_custom_ratio: [f32; 2],
+    /// Swap the aspect ratio to portrait.
+    crop_portrait: bool,
+    crop_overlay: CropOverlay,
     /// Visual-only crop selection — not applied to processing until user confirms.
     pending_crop: Option<Rect>,
     /// Active drag operation on the pending crop rect.
@@ -195,18 +407,75 @@ pub struct Viewer {
     crop_drag_start_rect: Option<Rect>,
     /// Normalized position where the initial drag began (for creating new rects).
     crop_create_origin: Option<egui::Pos2>,
+    /// Inscribed bounds the current crop drag is held to, when constrained.
+    crop_drag_bounds: Option<Rect>,
+    /// Crop last filled in by the constraint; it keeps tracking geometry
+    /// changes until the user edits it.
+    auto_crop: Option<Rect>,
+    /// Graduated filter or local adjustment whose mask is edited on the image.
+    selected_mask: Option<MaskTarget>,
+    local_drag: Option<LocalDrag>,
+    brush: BrushSettings,
+    /// Tint the selected mask's coverage over the image.
+    show_local_mask: bool,
+    /// Coverage overlay for the selected mask, keyed by mask signature.
+    local_mask_texture: Option<(u64, egui::TextureHandle)>,
+    /// Highlight in-focus edges over the image.
+    pub focus_peaking: bool,
+    /// Pixels of `texture`, kept for the focus-peaking overlay.
+    texture_image: Option<Arc<egui::ColorImage>>,
+    /// Focus-peaking overlay for `texture`, built on demand.
+    peaking_texture: Option<egui::TextureHandle>,
+    /// Clicks on the image place heal/clone spots.
+    spot_mode: bool,
+    selected_spot: Option<usize>,
+    spot_drag: Option<SpotDrag>,
+    /// Radius for new spots, as a fraction of the image's long edge.
+    spot_radius: f32,
+    upright: UprightGuides,
+    /// Target adjustment tool: drags on the image edit the selective color band
+    /// under the cursor.
+    target_adjust: Option<TargetProperty>,
+    /// Band picked when the current target adjustment drag started.
+    target_band: Option<usize>,
+    /// Path typed into the LUT section, for loading or exporting a `.cube`.
+    lut_path: String,
+    lut_status: Option<String>,
+    /// Preview through `proof` instead of straight to the display.
+    soft_proof: bool,
+    proof: icc::SoftProof,
+    monitor_profile: Option<moxcms::ColorProfile>,
+    /// Working space to screen conversion; rebuilt when proofing or the
+    /// monitor profile changes.
+    display: Arc<icc::DisplayTransform>,
     zoom: f32,
     pan_offset: egui::Vec2,
     loading: bool,
-    /// Background reload for higher-res preview (doesn't show spinner).
-    reloading_preview: bool,
     processing: bool,
     requested_generation: u64,
     in_flight_generation: Option<u64>,
     pub metadata: Option<crate::metadata::ImageMetadata>,
     source_signature: u64,
-    preview_max: u32,
-    last_zoom_change: Option<Instant>,
+    /// Long edge of the full-resolution source, from EXIF or the file header
+    /// until the source is decoded.
+    source_long_edge: Option<u32>,
+    /// Long edge of the image the current texture was processed from.
+    texture_input_long: u32,
+    /// Texture pixels to physical screen pixels at fit zoom, from the last frame.
+    fit_pixels: f32,
+    /// Full-resolution source, decoded on the first zoom past the preview.
+    full_source: Option<Arc<DynamicImage>>,
+    /// Tile inputs for an edit signature; `None` inside when decoding failed.
+    tile_frame: Option<(u64, Option<Arc<TileFrame>>)>,
+    tile_frame_pending: bool,
+    /// Bumped per tile frame request, so tiles made for another image, edit
+    /// or display transform are dropped.
+    tile_generation: u64,
+    tiles: HashMap<TileKey, egui::TextureHandle>,
+    tiles_lru: VecDeque<TileKey>,
+    /// Tiles queued or being rendered.
+    tiles_requested: HashSet<TileKey>,
+    tile_queue: Arc<Mutex<TileQueue>>,
     preview_cache: HashMap<PreviewCacheKey, PreviewCacheEntry>,
     preview_cache_lru: VecDeque<PreviewCacheKey>,
     tx: mpsc::SyncSender<BgResult>,
@@ -221,6 +490,7 @@ impl Viewer {
             id,
             preview_backend,
             current_path: None,
+            companion: None,
             preview: None,
             edit_state: EditState::default(),
             needs_process: false,
@@ -230,24 +500,63 @@ impl Viewer {
             texture: None,
             original_texture: None,
             split_view: false,
+            sharpen_mask_view: false,
             crop_mode: false,
             crop_aspect: CropAspect::Free,
+            crop_custom_ratio: [2.0, 1.0],
+            crop_portrait: false,
+            crop_overlay: CropOverlay::Thirds,
             pending_crop: None,
             crop_drag: None,
             crop_drag_start_pos: None,
             crop_drag_start_rect: None,
             crop_create_origin: None,
+            crop_drag_bounds: None,
+            auto_crop: None,
+            selected_mask: None,
+            local_drag: None,
+            brush: BrushSettings {
+                radius: 0.05,
+                feather: 0.5,
+                erase: false,
+            },
+            show_local_mask: false,
+            local_mask_texture: None,
+            focus_peaking: false,
+            texture_image: None,
+            peaking_texture: None,
+            spot_mode: false,
+            selected_spot: None,
+            spot_drag: None,
+            spot_radius: 0.015,
+            upright: UprightGuides::default(),
+            target_adjust: None,
+            target_band: None,
+            lut_path: String::new(),
+            lut_status: None,
+            soft_proof: false,
+            proof: icc::SoftProof::default(),
+            monitor_profile: None,
+            display: Arc::new(icc::DisplayTransform::default()),
             zoom: 1.0,
             pan_offset: egui::Vec2::ZERO,
             loading: false,
-            reloading_preview: false,
             processing: false,
             requested_generation: 0,
             in_flight_generation: None,
             metadata: None,
             source_signature: 0,
-            preview_max: PREVIEW_MAX,
-            last_zoom_change: None,
+            source_long_edge: None,
+            texture_input_long: 1,
+            fit_pixels: 1.0,
+            full_source: None,
+            tile_frame: None,
+            tile_frame_pending: false,
+            tile_generation: 0,
+            tiles: HashMap::new(),
+            tiles_lru: VecDeque::new(),
+            tiles_requested: HashSet::new(),
+            tile_queue: Arc::default(),
             preview_cache: HashMap::new(),
             preview_cache_lru: VecDeque::new(),
             tx,
@@ -255,6 +564,29 @@ impl Viewer {
         }
     }
 
+    /// Uses `profile` for the display instead of sRGB.
+    pub fn set_monitor_profile(&mut self, profile: Option<moxcms::ColorProfile>) {
+        self.monitor_profile = profile;
+        self.rebuild_display();
+    }
+
+    /// Rebuilds the display transform and drops textures and cached
+    /// previews converted with the old one.
+    fn rebuild_display(&mut self) {
+        let proof = self.soft_proof.then_some(self.proof);
+        self.display = Arc::new(icc::DisplayTransform::new(
+            self.monitor_profile.as_ref(),
+            proof,
+        ));
+        self.preview_cache.clear();
+        self.preview_cache_lru.clear();
+        self.original_texture = None;
+        self.clear_tiles();
+        if self.preview.is_some() {
+            self.needs_process = true;
+        }
+    }
+
     pub fn is_loading(&self) -> bool {
         self.loading
     }
@@ -274,12 +606,11 @@ impl Viewer {
             .into_owned()
     }
 
-    /// Loads a new image path and resets viewer state for background preview loading.
     /// Persists current edits to the sidecar file, if any.
     pub fn save_edits(&self) {
         if let Some(path) = &self.current_path {
             if self.has_edits() {
-                let _ = self.edit_state.save(path);
+                let _ = self.edit_state.save(path, self.companion.as_deref());
             }
         }
     }
@@ -289,45 +620,63 @@ impl Viewer {
             != serde_json::to_string(&EditState::default()).ok()
     }
 
-    pub fn set_image(&mut self, path: PathBuf, ctx: &egui::Context) {
+    /// Loads a new image path and resets viewer state for background preview loading.
+    /// `companion` is the other half of the RAW+JPEG pair `path` belongs to.
+    pub fn set_image(&mut self, path: PathBuf, companion: Option<PathBuf>, ctx: &egui::Context) {
         if self.current_path.as_ref() == Some(&path) {
             return;
         }
         // Save current edits before switching
         if let Some(prev_path) = &self.current_path {
             if self.has_edits() {
-                let _ = self.edit_state.save(prev_path);
+                let _ = self.edit_state.save(prev_path, self.companion.as_deref());
             }
         }
         self.current_path = Some(path.clone());
+        self.companion = companion;
         self.source_signature = source_signature(&path);
         self.preview = None;
         self.texture = None;
+        self.texture_image = None;
+        self.peaking_texture = None;
         self.original_texture = None;
-        self.edit_state = EditState::load(&path).unwrap_or_default();
+        self.edit_state = EditState::load(&path, self.companion.as_deref()).unwrap_or_default();
         self.needs_process = false;
         self.needs_final_process = false;
         self.last_slider_change = None;
         self.last_interactive_process = None;
         self.loading = true;
-        self.reloading_preview = false;
         self.processing = false;
         // Invalidate any in-flight processing result from the previous image.
         self.requested_generation = self.requested_generation.wrapping_add(1);
         self.in_flight_generation = None;
+        self.sharpen_mask_view = false;
         self.crop_mode = false;
         self.pending_crop = None;
         self.crop_drag = None;
         self.crop_create_origin = None;
+        self.auto_crop = None;
+        self.selected_mask = None;
+        self.local_drag = None;
+        self.local_mask_texture = None;
+        self.spot_mode = false;
+        self.selected_spot = None;
+        self.spot_drag = None;
+        self.upright = UprightGuides::default();
         self.zoom = 1.0;
         self.pan_offset = egui::Vec2::ZERO;
-        self.preview_max = PREVIEW_MAX;
-        self.last_zoom_change = None;
+        self.full_source = None;
+        self.clear_tiles();
         self.metadata = crate::metadata::read(&path).ok();
+        self.source_long_edge = self
+            .metadata
+            .as_ref()
+            .and_then(|meta| Some(meta.width?.max(meta.height?)))
+            .or_else(|| image::image_dimensions(&path).ok().map(|(w, h)| w.max(h)));
 
         let tx = self.tx.clone();
         let ctx2 = ctx.clone();
-        let cap = self.preview_max;
+        let cap = PREVIEW_MAX;
         std::thread::spawn(move || {
             send_loaded_preview_stages(path, cap, &tx);
             ctx2.request_repaint();
@@ -360,11 +709,8 @@ impl Viewer {
         let cache_key = self.build_preview_cache_key(&preview, quality);
 
         if let Some(img) = self.cached_preview_image(&cache_key) {
-            self.texture = Some(ctx.load_texture(
-                format!("viewer_tex_{}", self.id),
-                img,
-                egui::TextureOptions::LINEAR,
-            ));
+            self.texture_input_long = cache_key.processed_long_edge();
+            self.upload_texture(ctx, img);
             self.processing = false;
             self.in_flight_generation = None;
             return;
@@ -373,6 +719,8 @@ impl Viewer {
         let img = preview;
         let state = self.edit_state.clone();
         let preview_backend = self.preview_backend;
+        let sharpen_mask_view = self.sharpen_mask_view;
+        let display = Arc::clone(&self.display);
         let tx = self.tx.clone();
         let ctx2 = ctx.clone();
         std::thread::spawn(move || {
@@ -380,8 +728,13 @@ impl Viewer {
                 ProcessQuality::Interactive => downscale_for_interactive(img),
                 ProcessQuality::Final => img,
             };
-            let result = process_preview_with_backend(&source, &state, preview_backend);
-            let rgba = result.to_rgba8();
+            let result = if sharpen_mask_view {
+                process_sharpen_mask_preview(&source, &state, preview_backend)
+            } else {
+                process_preview_with_backend(&source, &state, preview_backend)
+            };
+            let mut rgba = result.to_rgba8();
+            display.apply(&mut rgba);
             let w = rgba.width() as usize;
             let h = rgba.height() as usize;
             let _ = tx.send(BgResult::Processed {
@@ -415,6 +768,7 @@ impl Viewer {
             input_width: preview.width(),
             input_height: preview.height(),
             quality,
+            sharpen_mask_view: self.sharpen_mask_view,
         }
     }
 
@@ -466,7 +820,6 @@ impl Viewer {
                     if self.current_path.as_ref() == Some(&path) {
                         self.preview = Some(img);
                         self.loading = false;
-                        self.reloading_preview = false;
                         self.needs_process = true;
                         self.needs_final_process = false;
                         self.last_interactive_process = None;
@@ -475,7 +828,6 @@ impl Viewer {
                 BgResult::LoadFailed(path) => {
                     if self.current_path.as_ref() == Some(&path) {
                         self.loading = false;
-                        self.reloading_preview = false;
                     }
                 }
                 BgResult::Processed {
@@ -487,27 +839,125 @@ impl Viewer {
                 } => {
                     self.processing = false;
                     self.in_flight_generation = None;
+                    let input_long = cache_key.processed_long_edge();
                     self.store_preview_cache(cache_key, data.clone(), w, h);
                     if generation != self.requested_generation {
                         continue;
                     }
+                    self.texture_input_long = input_long;
                     let img = egui::ColorImage::from_rgba_unmultiplied([w, h], &data);
-                    self.texture = Some(ctx.load_texture(
-                        format!("viewer_tex_{}", self.id),
-                        img,
+                    self.upload_texture(ctx, img);
+                }
+                BgResult::TileFramePrepared {
+                    path,
+                    generation,
+                    edit_signature,
+                    source,
+                    frame,
+                } => {
+                    if self.current_path.as_ref() != Some(&path) {
+                        continue;
+                    }
+                    if let Some(source) = source {
+                        self.source_long_edge = Some(source.width().max(source.height()));
+                        self.full_source = Some(source);
+                    }
+                    if generation != self.tile_generation {
+                        continue;
+                    }
+                    self.tile_frame_pending = false;
+                    self.tile_frame = Some((edit_signature, frame));
+                    // Tiles of older edits are never shown again
+                    self.tiles.retain(|key, _| key.0 == edit_signature);
+                    self.tiles_lru.retain(|key| key.0 == edit_signature);
+                    self.tiles_requested.retain(|key| key.0 == edit_signature);
+                }
+                BgResult::TileRendered {
+                    path,
+                    generation,
+                    key,
+                    img,
+                } => {
+                    if self.current_path.as_ref() != Some(&path)
+                        || generation != self.tile_generation
+                    {
+                        continue;
+                    }
+                    self.tiles_requested.remove(&key);
+                    let Some(img) = img else {
+                        continue;
+                    };
+                    let color = egui::ColorImage::from_rgba_unmultiplied(
+                        [img.width() as usize, img.height() as usize],
+                        img.as_raw(),
+                    );
+                    let (_, level, col, row) = key;
+                    let tex = ctx.load_texture(
+                        format!("viewer_tile_{}_{}_{}_{}", self.id, level, col, row),
+                        color,
                         egui::TextureOptions::LINEAR,
-                    ));
+                    );
+                    if self.tiles.len() >= TILE_CACHE_CAPACITY
+                        && let Some(oldest) = self.tiles_lru.pop_front()
+                    {
+                        self.tiles.remove(&oldest);
+                    }
+                    self.tiles.insert(key, tex);
+                    self.tiles_lru.push_back(key);
                 }
             }
         }
     }
 
+    /// Shows `img` as the processed image.
+    fn upload_texture(&mut self, ctx: &egui::Context, img: egui::ColorImage) {
+        let img = Arc::new(img);
+        self.texture = Some(ctx.load_texture(
+            format!("viewer_tex_{}", self.id),
+            Arc::clone(&img),
+            egui::TextureOptions::LINEAR,
+        ));
+        self.texture_image = Some(img);
+        self.peaking_texture = None;
+    }
+
+    /// Draws the focus-peaking overlay for the processed image over
+    /// `img_rect`, clipped to `clip`.
+    fn draw_focus_peaking(&mut self, ui: &egui::Ui, img_rect: egui::Rect, clip: egui::Rect) {
+        if self.peaking_texture.is_none() {
+            let Some(image) = self.texture_image.as_ref() else {
+                return;
+            };
+            let [w, h] = image.size;
+            let rgba: Vec<u8> = image.pixels.iter().flat_map(|p| p.to_array()).collect();
+            let Some(rgba) = image::RgbaImage::from_raw(w as u32, h as u32, rgba) else {
+                return;
+            };
+            let overlay = focus::peaking_overlay(&rgba, PEAKING_COLOR);
+            let overlay = egui::ColorImage::from_rgba_unmultiplied([w, h], overlay.as_raw());
+            self.peaking_texture = Some(ui.ctx().load_texture(
+                format!("viewer_peaking_{}", self.id),
+                overlay,
+                egui::TextureOptions::NEAREST,
+            ));
+        }
+        if let Some(tex) = &self.peaking_texture {
+            ui.painter().with_clip_rect(clip).image(
+                tex.id(),
+                img_rect,
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+
     fn ensure_original_texture(&mut self, ctx: &egui::Context) {
         if self.original_texture.is_some() {
             return;
         }
         if let Some(ref preview) = self.preview {
-            let rgba = preview.to_rgba8();
+            let mut rgba = preview.to_rgba8();
+            self.display.apply(&mut rgba);
             let w = rgba.width() as usize;
             let h = rgba.height() as usize;
             let img = egui::ColorImage::from_rgba_unmultiplied([w, h], &rgba.into_raw());
@@ -519,21 +969,328 @@ impl Viewer {
         }
     }
 
+    /// Screen pixels per full-resolution pixel at the current zoom.
+    fn pixel_zoom(&self) -> f32 {
+        let source_long = self.source_long_edge.unwrap_or(self.texture_input_long);
+        self.zoom * self.fit_pixels * self.texture_input_long as f32 / source_long.max(1) as f32
+    }
+
+    /// Zoom that shows `pixel_zoom` screen pixels per full-resolution pixel.
+    fn zoom_for_pixel_zoom(&self, pixel_zoom: f32) -> f32 {
+        pixel_zoom * self.zoom / self.pixel_zoom().max(1e-6)
+    }
+
+    fn max_zoom(&self) -> f32 {
+        self.zoom_for_pixel_zoom(MAX_PIXEL_ZOOM).max(10.0)
+    }
+
+    /// Whether the preview is magnified on screen while a larger source exists.
+    fn wants_full_res_tiles(&self) -> bool {
+        let (Some(preview), Some(source_long)) = (&self.preview, self.source_long_edge) else {
+            return false;
+        };
+        let preview_long = preview.width().max(preview.height());
+        !self.sharpen_mask_view
+            && source_long > preview_long
+            && self.pixel_zoom() * source_long as f32 / preview_long as f32 > 1.0
+    }
+
+    /// Drops the tile frame and its tiles, discarding any render still in
+    /// flight.
+    fn clear_tiles(&mut self) {
+        self.tile_frame = None;
+        self.tile_frame_pending = false;
+        self.tile_generation = self.tile_generation.wrapping_add(1);
+        self.tiles.clear();
+        self.tiles_lru.clear();
+        self.tiles_requested.clear();
+        if let Ok(mut queue) = self.tile_queue.lock() {
+            queue.jobs.clear();
+        }
+    }
+
+    /// Prepares the tile frame of the current edits in the background,
+    /// decoding the source first if needed. The geometry of the previous
+    /// frame is reused when only tones changed.
+    fn request_tile_frame(&mut self, ctx: &egui::Context, edit_signature: u64) {
+        let Some(path) = self.current_path.clone() else {
+            return;
+        };
+        self.tile_generation = self.tile_generation.wrapping_add(1);
+        self.tile_frame_pending = true;
+        let generation = self.tile_generation;
+        let source = self.full_source.clone();
+        let previous = self
+            .tile_frame
+            .as_ref()
+            .and_then(|(_, frame)| frame.clone());
+        let state = self.edit_state.clone();
+        let display = Arc::clone(&self.display);
+        let tx = self.tx.clone();
+        let ctx2 = ctx.clone();
+        std::thread::spawn(move || {
+            let source = source.or_else(|| crate::thumbnail::open_image(&path).ok().map(Arc::new));
+            let frame = source.as_ref().map(|source| {
+                Arc::new(prepare_tile_frame(
+                    source,
+                    state,
+                    previous.as_deref(),
+                    display,
+                ))
+            });
+            let _ = tx.send(BgResult::TileFramePrepared {
+                path,
+                generation,
+                edit_signature,
+                source,
+                frame,
+            });
+            ctx2.request_repaint();
+        });
+    }
+
+    /// Draws full-resolution tiles of the current edits over the magnified
+    /// preview. Missing tiles show the preview until they are rendered;
+    /// only visible tiles are queued, nearest the viewport center first.
+    fn draw_full_res_tiles(
+        &mut self,
+        ui: &mut egui::Ui,
+        img_rect: egui::Rect,
+        viewport: egui::Rect,
+    ) {
+        let edit_signature = edit_state_signature(&self.edit_state);
+        let frame = match &self.tile_frame {
+            Some((signature, frame)) if *signature == edit_signature => frame.clone(),
+            _ => {
+                let since_change = self.last_slider_change.map(|t| t.elapsed());
+                match since_change {
+                    Some(elapsed) if elapsed < DEBOUNCE => {
+                        ui.ctx()
+                            .request_repaint_after(DEBOUNCE.saturating_sub(elapsed));
+                    }
+                    _ if !self.tile_frame_pending => {
+                        self.request_tile_frame(ui.ctx(), edit_signature);
+                    }
+                    _ => {}
+                }
+                return;
+            }
+        };
+        // The source failed to decode; keep showing the preview
+        let Some(frame) = frame else {
+            return;
+        };
+
+        let level = tile_level(self.pixel_zoom());
+        let painter = ui.painter().with_clip_rect(viewport);
+        let full_uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        let mut wanted = Vec::new();
+        for (col, row, tile_rect) in visible_tiles(frame.level_dims(level), img_rect, viewport) {
+            let key = (edit_signature, level, col, row);
+            let Some(tex) = self.tiles.get(&key) else {
+                wanted.push(key);
+                continue;
+            };
+            painter.image(tex.id(), tile_rect, full_uv, egui::Color32::WHITE);
+            if let Some(idx) = self.tiles_lru.iter().position(|k| *k == key) {
+                let _ = self.tiles_lru.remove(idx);
+            }
+            self.tiles_lru.push_back(key);
+        }
+        self.queue_tiles(ui.ctx(), &frame, wanted);
+    }
+
+    /// Replaces the queued tiles with `wanted`, in order, leaving tiles
+    /// already being rendered alone, and starts the renderer if it is idle.
+    fn queue_tiles(&mut self, ctx: &egui::Context, frame: &Arc<TileFrame>, wanted: Vec<TileKey>) {
+        let Ok(mut queue) = self.tile_queue.lock() else {
+            return;
+        };
+        for job in queue.jobs.drain(..) {
+            self.tiles_requested.remove(&job.key);
+        }
+        let Some(path) = &self.current_path else {
+            return;
+        };
+        for key in wanted {
+            if self.tiles_requested.insert(key) {
+                queue.jobs.push_back(TileJob {
+                    path: path.clone(),
+                    generation: self.tile_generation,
+                    frame: Arc::clone(frame),
+                    key,
+                });
+            }
+        }
+        if queue.running || queue.jobs.is_empty() {
+            return;
+        }
+        queue.running = true;
+        let tile_queue = Arc::clone(&self.tile_queue);
+        let tx = self.tx.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            // A batch per pool thread, so the queue can be reordered between batches
+            loop {
+                let batch: Vec<TileJob> = match tile_queue.lock() {
+                    Ok(mut queue) if !queue.jobs.is_empty() => {
+                        let n = rayon::current_num_threads().min(queue.jobs.len());
+                        queue.jobs.drain(..n).collect()
+                    }
+                    Ok(mut queue) => {
+                        queue.running = false;
+                        return;
+                    }
+                    Err(_) => return,
+                };
+                batch.into_par_iter().for_each(|job| {
+                    let (_, level, col, row) = job.key;
+                    let img = render_tile(&job.frame, level, col, row);
+                    let _ = tx.send(BgResult::TileRendered {
+                        path: job.path,
+                        generation: job.generation,
+                        key: job.key,
+                        img,
+                    });
+                    ctx.request_repaint();
+                });
+            }
+        });
+    }
+
+    /// Preview size after 90° rotation; crop rects are normalized to it.
+    fn crop_frame_size(&self) -> Option<(f32, f32)> {
+        let preview = self.preview.as_ref()?;
+        let (w, h) = (preview.width() as f32, preview.height() as f32);
+        Some(match self.edit_state.rotate.rem_euclid(360) {
+            90 | 270 => (h, w),
+            _ => (w, h),
+        })
+    }
+
+    /// Source pixel size of the crop frame, scaled up from the preview when
+    /// the source size is known.
+    fn crop_frame_pixels(&self) -> Option<(f32, f32)> {
+        let (w, h) = self.crop_frame_size()?;
+        let scale = self
+            .source_long_edge
+            .map_or(1.0, |long| long as f32 / w.max(h));
+        Some(((w * scale).round(), (h * scale).round()))
+    }
+
+    /// Locked aspect ratio as pixel width / height, oriented by `crop_portrait`.
     fn effective_crop_ratio(&self) -> Option<f32> {
-        match self.crop_aspect {
+        let ratio = match self.crop_aspect {
             CropAspect::Original => {
-                if let Some(ref preview) = self.preview {
-                    Some(preview.width() as f32 / preview.height() as f32)
-                } else {
-                    None
-                }
+                let (w, h) = self.crop_frame_size()?;
+                w / h
+            }
+            CropAspect::Custom => {
+                let [w, h] = self.crop_custom_ratio;
+                w / h
+            }
+            other => other.ratio()?,
+        };
+        let landscape = ratio.max(1.0 / ratio);
+        Some(if self.crop_portrait {
+            1.0 / landscape
+        } else {
+            landscape
+        })
+    }
+
+    /// `effective_crop_ratio` in normalized crop units, as compared by
+    /// `constrain_aspect` and `resize_from_corner`.
+    fn crop_norm_ratio(&self) -> Option<f32> {
+        let ratio = self.effective_crop_ratio()?;
+        let (w, h) = self.crop_frame_size()?;
+        Some(ratio * h / w)
+    }
+
+    /// Selects an aspect ratio, keeping the orientation of the current crop.
+    fn set_crop_aspect(&mut self, aspect: CropAspect) {
+        self.crop_aspect = aspect;
+        let (w, h) = self.crop_frame_size().unwrap_or((1.0, 1.0));
+        self.crop_portrait = match self.pending_crop.as_ref().or(self.edit_state.crop.as_ref()) {
+            Some(crop) => crop.height * h > crop.width * w,
+            None => h > w,
+        };
+        if aspect == CropAspect::Custom {
+            let [cw, ch] = self.crop_custom_ratio;
+            if (ch > cw) != self.crop_portrait {
+                self.crop_custom_ratio = [ch, cw];
             }
-            other => other.ratio(),
         }
+        self.apply_crop_aspect();
+    }
+
+    /// Reshapes the pending crop to the locked aspect ratio.
+    fn apply_crop_aspect(&mut self) {
+        let ratio = self.crop_norm_ratio();
+        if let Some(ref mut crop) = self.pending_crop {
+            constrain_aspect(crop, ratio);
+        }
+        self.enforce_crop_constraint();
+    }
+
+    /// Aspect ratio, orientation and composition overlay pickers.
+    fn show_crop_aspect_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Aspect");
+            for aspect in CropAspect::ALL {
+                if ui
+                    .selectable_label(self.crop_aspect == aspect, aspect.label())
+                    .clicked()
+                {
+                    self.set_crop_aspect(aspect);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            if self.crop_aspect == CropAspect::Custom {
+                let [w, h] = &mut self.crop_custom_ratio;
+                let mut changed = ui
+                    .add(egui::DragValue::new(w).range(1.0..=100.0).speed(0.05))
+                    .changed();
+                ui.label(":");
+                changed |= ui
+                    .add(egui::DragValue::new(h).range(1.0..=100.0).speed(0.05))
+                    .changed();
+                if changed {
+                    self.crop_portrait = self.crop_custom_ratio[1] > self.crop_custom_ratio[0];
+                    self.apply_crop_aspect();
+                }
+            }
+            let lockable = self.crop_aspect != CropAspect::Free;
+            if ui
+                .add_enabled(
+                    lockable,
+                    egui::Button::selectable(self.crop_portrait, "Portrait"),
+                )
+                .on_hover_text("Swap the aspect ratio's orientation")
+                .clicked()
+            {
+                self.crop_portrait = !self.crop_portrait;
+                if self.crop_aspect == CropAspect::Custom {
+                    let [w, h] = self.crop_custom_ratio;
+                    self.crop_custom_ratio = [h, w];
+                }
+                self.apply_crop_aspect();
+            }
+            egui::ComboBox::from_id_salt(ui.id().with("crop_overlay"))
+                .selected_text(self.crop_overlay.label())
+                .show_ui(ui, |ui| {
+                    for overlay in CropOverlay::ALL {
+                        ui.selectable_value(&mut self.crop_overlay, overlay, overlay.label());
+                    }
+                });
+        });
     }
 
     /// Renders the image viewport and kicks off preview processing when needed.
-    pub fn show_image(&mut self, ui: &mut egui::Ui) {
+    /// Starts preview processing when edits are pending: low-res interactive
+    /// passes during slider drags, then a full-quality pass once settled.
+    fn schedule_processing(&mut self, ctx: &egui::Context) {
         // If edits arrive while processing is active, bump the requested generation
         // so the in-flight result is ignored on arrival.
         self.mark_inflight_stale_if_needed();
@@ -546,70 +1303,29 @@ impl Viewer {
             let debounce_done = since_change.map(|d| d >= DEBOUNCE).unwrap_or(true);
 
             if debounce_done {
-                self.trigger_process(ui.ctx(), ProcessQuality::Final);
+                self.trigger_process(ctx, ProcessQuality::Final);
             } else if self.needs_process {
                 let interactive_ready = self
                     .last_interactive_process
                     .map(|t| t.elapsed() >= INTERACTIVE_REFRESH)
                     .unwrap_or(true);
                 if interactive_ready {
-                    self.trigger_process(ui.ctx(), ProcessQuality::Interactive);
+                    self.trigger_process(ctx, ProcessQuality::Interactive);
                 } else {
-                    ui.ctx().request_repaint_after(INTERACTIVE_REFRESH);
+                    ctx.request_repaint_after(INTERACTIVE_REFRESH);
                 }
                 if let Some(elapsed) = since_change {
                     let until_final = DEBOUNCE.saturating_sub(elapsed);
-                    ui.ctx()
-                        .request_repaint_after(until_final.min(INTERACTIVE_REFRESH));
+                    ctx.request_repaint_after(until_final.min(INTERACTIVE_REFRESH));
                 }
             } else if let Some(elapsed) = since_change {
-                ui.ctx()
-                    .request_repaint_after(DEBOUNCE.saturating_sub(elapsed));
+                ctx.request_repaint_after(DEBOUNCE.saturating_sub(elapsed));
             }
         }
+    }
 
-        // Adaptive preview reload: when zoomed in, load a higher-resolution preview
-        if let Some(zoom_changed_at) = self.last_zoom_change {
-            let elapsed = zoom_changed_at.elapsed();
-            if elapsed >= DEBOUNCE {
-                // Compute needed resolution based on zoom level
-                let needed_max = ((PREVIEW_MAX as f32 * self.zoom).ceil() as u32).max(PREVIEW_MAX);
-                // Cap at original image dimensions
-                let orig_max = self
-                    .metadata
-                    .as_ref()
-                    .and_then(|m| match (m.width, m.height) {
-                        (Some(w), Some(h)) => Some(w.max(h)),
-                        _ => None,
-                    })
-                    .unwrap_or(u32::MAX);
-                let needed_max = needed_max.min(orig_max);
-
-                if needed_max > self.preview_max && !self.loading && !self.reloading_preview {
-                    self.preview_max = needed_max;
-                    self.reloading_preview = true;
-                    self.last_zoom_change = None;
-                    // Invalidate in-flight processing so stale results are discarded
-                    self.requested_generation = self.requested_generation.wrapping_add(1);
-                    self.in_flight_generation = None;
-
-                    let tx = self.tx.clone();
-                    let ctx2 = ui.ctx().clone();
-                    let path = self.current_path.clone().unwrap();
-                    let cap = self.preview_max;
-                    std::thread::spawn(move || {
-                        send_loaded_preview_stages(path, cap, &tx);
-                        ctx2.request_repaint();
-                    });
-                } else {
-                    self.last_zoom_change = None;
-                }
-            } else {
-                // Still waiting for debounce — schedule a repaint
-                ui.ctx()
-                    .request_repaint_after(DEBOUNCE.saturating_sub(elapsed));
-            }
-        }
+    pub fn show_image(&mut self, ui: &mut egui::Ui) {
+        self.schedule_processing(ui.ctx());
 
         // Toolbar row
         ui.horizontal(|ui| {
@@ -619,6 +1335,11 @@ impl Viewer {
             if ui.selectable_label(self.crop_mode, "Crop").clicked() {
                 self.crop_mode = !self.crop_mode;
                 if self.crop_mode {
+                    self.selected_mask = None;
+                    self.local_drag = None;
+                    self.spot_mode = false;
+                    self.upright.active = false;
+                    self.target_adjust = None;
                     // Enter crop mode: start with full image or existing applied crop
                     self.pending_crop = Some(self.edit_state.crop.clone().unwrap_or(Rect {
                         x: 0.0,
@@ -634,6 +1355,23 @@ impl Viewer {
                 }
             }
 
+            if ui.selectable_label(self.spot_mode, "Spots").clicked() {
+                self.set_spot_mode(!self.spot_mode);
+            }
+
+            if ui
+                .selectable_label(self.focus_peaking, "Peaking")
+                .on_hover_text("Highlight in-focus edges")
+                .clicked()
+            {
+                self.focus_peaking = !self.focus_peaking;
+            }
+
+            if ui.selectable_label(self.soft_proof, "Soft proof").clicked() {
+                self.soft_proof = !self.soft_proof;
+                self.rebuild_display();
+            }
+
             if ui
                 .add_enabled(self.has_edits(), egui::Button::new("Save"))
                 .clicked()
@@ -641,7 +1379,7 @@ impl Viewer {
                 self.save_edits();
             }
 
-            if self.processing || self.reloading_preview {
+            if self.processing || self.tile_frame_pending || !self.tiles_requested.is_empty() {
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     ui.spinner();
                 });
@@ -650,21 +1388,7 @@ impl Viewer {
 
         // Crop mode toolbar: aspect ratio + apply/cancel/reset
         if self.crop_mode {
-            ui.horizontal(|ui| {
-                ui.label("Aspect:");
-                for aspect in CropAspect::ALL {
-                    if ui
-                        .selectable_label(self.crop_aspect == aspect, aspect.label())
-                        .clicked()
-                    {
-                        self.crop_aspect = aspect;
-                        let ratio = self.effective_crop_ratio();
-                        if let Some(ref mut crop) = self.pending_crop {
-                            constrain_aspect(crop, ratio);
-                        }
-                    }
-                }
-            });
+            self.show_crop_aspect_controls(ui);
             ui.horizontal(|ui| {
                 let has_pending = self.pending_crop.is_some();
                 let has_applied = self.edit_state.crop.is_some();
@@ -700,12 +1424,39 @@ impl Viewer {
                 }
             });
         }
-        ui.separator();
-
-        // Build original texture lazily when split view is on
-        if self.split_view {
-            self.ensure_original_texture(ui.ctx());
-        }
+
+        // Soft proof toolbar: simulated output profile, intent, gamut warning
+        if self.soft_proof {
+            let mut proof = self.proof;
+            ui.horizontal(|ui| {
+                ui.label("Proof:");
+                egui::ComboBox::from_id_salt(("proof_profile", self.id))
+                    .selected_text(proof.profile.label())
+                    .show_ui(ui, |ui| {
+                        for profile in icc::OutputProfile::ALL {
+                            ui.selectable_value(&mut proof.profile, profile, profile.label());
+                        }
+                    });
+                egui::ComboBox::from_id_salt(("proof_intent", self.id))
+                    .selected_text(proof.intent.label())
+                    .show_ui(ui, |ui| {
+                        for intent in icc::ProofIntent::ALL {
+                            ui.selectable_value(&mut proof.intent, intent, intent.label());
+                        }
+                    });
+                ui.checkbox(&mut proof.gamut_warning, "Gamut warning");
+            });
+            if proof != self.proof {
+                self.proof = proof;
+                self.rebuild_display();
+            }
+        }
+        ui.separator();
+
+        // Build original texture lazily when split view is on
+        if self.split_view {
+            self.ensure_original_texture(ui.ctx());
+        }
 
         let loading = self.loading;
         let texture = self.texture.clone();
@@ -741,78 +1492,25 @@ impl Viewer {
                     let img_rect =
                         draw_fitted_image(ui, tex, avail_w, img_max_h, 1.0, egui::Vec2::ZERO);
                     self.handle_crop_interaction(ui, img_rect);
-                } else {
-                    let zoom_before = self.zoom;
+                } else if self.spot_mode {
                     let img_rect =
-                        draw_fitted_image(ui, tex, avail_w, img_max_h, self.zoom, self.pan_offset);
-
-                    // Compute fit_size for clamping
-                    let tex_size = tex.size_vec2();
-                    let fit_scale = (avail_w / tex_size.x).min(img_max_h / tex_size.y);
-                    let fit_size = tex_size * fit_scale;
-                    let viewport_rect =
-                        egui::Rect::from_center_size(img_rect.center() - self.pan_offset, fit_size);
-
-                    // Single interaction widget for zoom/pan — only the hovered
-                    // viewer responds to scroll, so stacked windows don't conflict.
-                    let sense = if self.zoom > 1.0 {
-                        egui::Sense::click_and_drag()
-                    } else {
-                        egui::Sense::click()
-                    };
-                    let resp = ui.interact(viewport_rect, ui.id().with("zoom_pan"), sense);
-
-                    // Scroll-to-zoom and pinch-to-zoom (only when hovered)
-                    if resp.hovered() {
-                        // Mouse wheel zoom
-                        let scroll_delta = ui.input(|i| i.smooth_scroll_delta.y);
-                        // Trackpad pinch zoom (egui reports as a multiplier, e.g. 1.02)
-                        let pinch_delta = ui.input(|i| i.zoom_delta());
-
-                        let old_zoom = self.zoom;
-                        let new_zoom = if scroll_delta != 0.0 {
-                            (self.zoom * (1.1_f32).powf(scroll_delta / 50.0)).clamp(1.0, 10.0)
-                        } else if (pinch_delta - 1.0).abs() > 0.001 {
-                            (self.zoom * pinch_delta).clamp(1.0, 10.0)
-                        } else {
-                            old_zoom
-                        };
-
-                        if new_zoom != old_zoom {
-                            // Zoom toward cursor
-                            if let Some(cursor_pos) = ui.input(|i| i.pointer.hover_pos()) {
-                                let img_center = viewport_rect.center();
-                                let rel =
-                                    cursor_pos.to_vec2() - img_center.to_vec2() - self.pan_offset;
-                                self.pan_offset += rel * (1.0 - new_zoom / old_zoom);
-                            }
-                            self.zoom = new_zoom;
-                        }
-                    }
-
-                    // Drag-to-pan when zoomed in
-                    if self.zoom > 1.0 {
-                        if resp.dragged() {
-                            self.pan_offset += resp.drag_delta();
-                        }
-                        // Double-click to reset zoom
-                        if resp.double_clicked() {
-                            self.zoom = 1.0;
-                            self.pan_offset = egui::Vec2::ZERO;
-                        }
-                    }
-
-                    // Clamp pan so image doesn't leave viewport excessively
-                    let zoomed_size = fit_size * self.zoom;
-                    let max_pan_x = ((zoomed_size.x - fit_size.x) / 2.0).max(0.0);
-                    let max_pan_y = ((zoomed_size.y - fit_size.y) / 2.0).max(0.0);
-                    self.pan_offset.x = self.pan_offset.x.clamp(-max_pan_x, max_pan_x);
-                    self.pan_offset.y = self.pan_offset.y.clamp(-max_pan_y, max_pan_y);
-
-                    // Track zoom changes for adaptive preview reload
-                    if (self.zoom - zoom_before).abs() > 0.001 {
-                        self.last_zoom_change = Some(Instant::now());
-                    }
+                        draw_fitted_image(ui, tex, avail_w, img_max_h, 1.0, egui::Vec2::ZERO);
+                    self.handle_spot_interaction(ui, img_rect);
+                } else if self.target_adjust.is_some() {
+                    let img_rect =
+                        draw_fitted_image(ui, tex, avail_w, img_max_h, 1.0, egui::Vec2::ZERO);
+                    self.handle_target_adjust_interaction(ui, img_rect);
+                } else if let Some(mask) = self.selected_mask.and_then(|t| self.target_mask(t)) {
+                    // Mask editing uses the fitted view so drags paint/move handles
+                    let img_rect =
+                        draw_fitted_image(ui, tex, avail_w, img_max_h, 1.0, egui::Vec2::ZERO);
+                    self.handle_mask_interaction(ui, img_rect, mask);
+                } else if self.upright.active {
+                    let img_rect =
+                        draw_fitted_image(ui, tex, avail_w, img_max_h, 1.0, egui::Vec2::ZERO);
+                    self.handle_guide_interaction(ui, img_rect);
+                } else {
+                    self.show_zoomable_image(ui, tex, avail_w, img_max_h);
                 }
             }
         } else {
@@ -822,7 +1520,6 @@ impl Viewer {
         }
 
         // Bottom status bar: resolution + zoom control
-        let zoom_before_bar = self.zoom;
         ui.horizontal(|ui| {
             // Preview resolution (from texture) / Original resolution (from EXIF)
             if let Some(ref tex) = texture {
@@ -844,11 +1541,12 @@ impl Viewer {
 
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if !self.crop_mode {
-                    // Zoom presets menu
+                    // Zoom presets menu, in full-resolution pixels per screen pixel
+                    let pixel_zoom = self.pixel_zoom();
                     let zoom_label = if (self.zoom - 1.0).abs() < 0.01 {
                         "Fit".to_string()
                     } else {
-                        format!("{:.0}%", self.zoom * 100.0)
+                        format!("{:.0}%", pixel_zoom * 100.0)
                     };
                     egui::ComboBox::from_id_salt(ui.id().with("zoom_combo"))
                         .selected_text(&zoom_label)
@@ -861,25 +1559,30 @@ impl Viewer {
                                 self.zoom = 1.0;
                                 self.pan_offset = egui::Vec2::ZERO;
                             }
-                            for &pct in &[200, 300, 500, 1000] {
-                                let z = pct as f32 / 100.0;
+                            for &pct in &[50, 100, 200, 400] {
+                                let target = pct as f32 / 100.0;
+                                let z = self.zoom_for_pixel_zoom(target);
+                                if z <= 1.0 {
+                                    continue;
+                                }
                                 let label = format!("{}%", pct);
                                 if ui
-                                    .selectable_label((self.zoom - z).abs() < 0.01, label)
+                                    .selectable_label((pixel_zoom - target).abs() < 0.01, label)
                                     .clicked()
                                 {
-                                    self.pan_offset = egui::Vec2::ZERO;
+                                    self.pan_offset *= z / self.zoom;
                                     self.zoom = z;
                                 }
                             }
                         });
 
                     // +/- buttons
+                    let max_zoom = self.max_zoom();
                     if ui.small_button("+").clicked() {
-                        self.zoom = (self.zoom * 1.25).clamp(1.0, 10.0);
+                        self.zoom = (self.zoom * 1.25).clamp(1.0, max_zoom);
                     }
                     if ui.small_button("\u{2212}").clicked() {
-                        let new_zoom = (self.zoom / 1.25).clamp(1.0, 10.0);
+                        let new_zoom = (self.zoom / 1.25).clamp(1.0, max_zoom);
                         if new_zoom < 1.01 {
                             self.zoom = 1.0;
                             self.pan_offset = egui::Vec2::ZERO;
@@ -890,9 +1593,169 @@ impl Viewer {
                 }
             });
         });
-        // Track zoom changes from status bar controls (+/-, presets)
-        if (self.zoom - zoom_before_bar).abs() > 0.001 {
-            self.last_zoom_change = Some(Instant::now());
+    }
+
+    /// Shows only the processed image, zoomed and panned by `zoom` and `pan`
+    /// so side-by-side viewers stay in sync. Returns the image response.
+    pub fn show_synced_image(
+        &mut self,
+        ui: &mut egui::Ui,
+        zoom: &mut f32,
+        pan: &mut egui::Vec2,
+    ) -> Option<egui::Response> {
+        self.schedule_processing(ui.ctx());
+        let Some(tex) = self.texture.clone() else {
+            ui.horizontal(|ui| {
+                if self.loading || self.processing {
+                    ui.spinner();
+                    ui.weak("Loading...");
+                } else {
+                    ui.label("Could not open image");
+                }
+            });
+            return None;
+        };
+        self.zoom = *zoom;
+        self.pan_offset = *pan;
+        let resp = self.show_zoomable_image(
+            ui,
+            &tex,
+            ui.available_width(),
+            ui.available_height().max(180.0),
+        );
+        *zoom = self.zoom;
+        *pan = self.pan_offset;
+        Some(resp)
+    }
+
+    /// Draws the image with scroll/pinch zoom and drag panning, plus
+    /// full-resolution tiles when zoomed past the preview.
+    fn show_zoomable_image(
+        &mut self,
+        ui: &mut egui::Ui,
+        tex: &egui::TextureHandle,
+        avail_w: f32,
+        img_max_h: f32,
+    ) -> egui::Response {
+        let img_rect = draw_fitted_image(ui, tex, avail_w, img_max_h, self.zoom, self.pan_offset);
+
+        // Compute fit_size for clamping
+        let tex_size = tex.size_vec2();
+        let fit_scale = (avail_w / tex_size.x).min(img_max_h / tex_size.y);
+        let fit_size = tex_size * fit_scale;
+        let viewport_rect =
+            egui::Rect::from_center_size(img_rect.center() - self.pan_offset, fit_size);
+        self.fit_pixels = fit_scale * ui.ctx().pixels_per_point();
+        if self.wants_full_res_tiles() {
+            self.draw_full_res_tiles(ui, img_rect, viewport_rect);
+        }
+        if self.focus_peaking {
+            self.draw_focus_peaking(ui, img_rect, viewport_rect);
+        }
+
+        // Single interaction widget for zoom/pan — only the hovered
+        // viewer responds to scroll, so stacked windows don't conflict.
+        let sense = if self.zoom > 1.0 {
+            egui::Sense::click_and_drag()
+        } else {
+            egui::Sense::click()
+        };
+        let resp = ui.interact(viewport_rect, ui.id().with("zoom_pan"), sense);
+
+        // Scroll-to-zoom and pinch-to-zoom (only when hovered)
+        if resp.hovered() {
+            // Mouse wheel zoom
+            let scroll_delta = ui.input(|i| i.smooth_scroll_delta.y);
+            // Trackpad pinch zoom (egui reports as a multiplier, e.g. 1.02)
+            let pinch_delta = ui.input(|i| i.zoom_delta());
+
+            let old_zoom = self.zoom;
+            let max_zoom = self.max_zoom();
+            let new_zoom = if scroll_delta != 0.0 {
+                (self.zoom * (1.1_f32).powf(scroll_delta / 50.0)).clamp(1.0, max_zoom)
+            } else if (pinch_delta - 1.0).abs() > 0.001 {
+                (self.zoom * pinch_delta).clamp(1.0, max_zoom)
+            } else {
+                old_zoom
+            };
+
+            if new_zoom != old_zoom {
+                // Zoom toward cursor
+                if let Some(cursor_pos) = ui.input(|i| i.pointer.hover_pos()) {
+                    let img_center = viewport_rect.center();
+                    let rel = cursor_pos.to_vec2() - img_center.to_vec2() - self.pan_offset;
+                    self.pan_offset += rel * (1.0 - new_zoom / old_zoom);
+                }
+                self.zoom = new_zoom;
+            }
+        }
+
+        // Drag-to-pan when zoomed in
+        if self.zoom > 1.0 {
+            if resp.dragged() {
+                self.pan_offset += resp.drag_delta();
+            }
+            // Double-click to reset zoom
+            if resp.double_clicked() {
+                self.zoom = 1.0;
+                self.pan_offset = egui::Vec2::ZERO;
+            }
+        }
+
+        // Clamp pan so image doesn't leave viewport excessively
+        let zoomed_size = fit_size * self.zoom;
+        let max_pan_x = ((zoomed_size.x - fit_size.x) / 2.0).max(0.0);
+        let max_pan_y = ((zoomed_size.y - fit_size.y) / 2.0).max(0.0);
+        self.pan_offset.x = self.pan_offset.x.clamp(-max_pan_x, max_pan_x);
+        self.pan_offset.y = self.pan_offset.y.clamp(-max_pan_y, max_pan_y);
+        resp
+    }
+
+    /// Largest crop free of straighten/keystone borders, when the crop is
+    /// constrained; keeps a locked aspect ratio.
+    fn crop_bounds(&self) -> Option<Rect> {
+        if !self.edit_state.constrain_crop {
+            return None;
+        }
+        let preview = self.preview.as_ref()?;
+        let aspect = self.effective_crop_ratio();
+        Some(transform::inscribed_rect(
+            &self.edit_state,
+            preview.width(),
+            preview.height(),
+            aspect,
+        ))
+    }
+
+    /// Pulls the applied and pending crops inside the constrained bounds,
+    /// creating a crop when geometry exposes a border.
+    fn enforce_crop_constraint(&mut self) {
+        let Some(bounds) = self.crop_bounds() else {
+            return;
+        };
+        let full = bounds.x < 1e-4
+            && bounds.y < 1e-4
+            && bounds.width > 1.0 - 1e-4
+            && bounds.height > 1.0 - 1e-4;
+        let follows_geometry =
+            self.edit_state.crop.is_none() || self.edit_state.crop == self.auto_crop;
+        let constrained = match &self.edit_state.crop {
+            _ if follows_geometry && full => None,
+            _ if follows_geometry => Some(bounds.clone()),
+            Some(crop) => Some(transform::constrain_crop(crop, &bounds)),
+            None => None,
+        };
+        self.auto_crop = if follows_geometry {
+            constrained.clone()
+        } else {
+            None
+        };
+        if constrained != self.edit_state.crop {
+            self.edit_state.crop = constrained;
+            self.needs_process = true;
+        }
+        if let Some(pending) = &mut self.pending_crop {
+            *pending = transform::constrain_crop(pending, &bounds);
         }
     }
 
@@ -907,8 +1770,11 @@ impl Viewer {
         );
 
         let pointer = ui.input(|i| i.pointer.clone());
-        let aspect_ratio = self.effective_crop_ratio();
+        let aspect_ratio = self.crop_norm_ratio();
         let is_this_viewer = crop_resp.hovered() || crop_resp.dragged() || self.crop_drag.is_some();
+        if pointer.any_pressed() && is_this_viewer {
+            self.crop_drag_bounds = self.crop_bounds();
+        }
 
         // Determine which crop rect to show and interact with.
         // If there's a pending crop, that takes priority for interaction.
@@ -922,8 +1788,8 @@ impl Viewer {
 
         if let Some(crop) = visible_crop {
             let crop_screen = norm_to_screen(&crop, img_rect);
-            // Always draw interactive overlay (handles + thirds) for visible crop
-            draw_crop_overlay(ui, img_rect, crop_screen, true);
+            // Always draw interactive overlay (handles + guides) for visible crop
+            draw_crop_overlay(ui, img_rect, crop_screen, true, self.crop_overlay);
 
             // Handle drag initiation — only for the interacted viewer
             if is_this_viewer {
@@ -992,6 +1858,17 @@ impl Viewer {
                     self.crop_drag_start_rect = None;
                 }
             }
+
+            if let (Some(drag), Some(bounds), Some(pc)) = (
+                self.crop_drag,
+                &self.crop_drag_bounds,
+                &mut self.pending_crop,
+            ) {
+                *pc = match drag {
+                    DragTarget::Interior => shift_into_bounds(pc, bounds),
+                    DragTarget::Corner(_) => transform::constrain_crop(pc, bounds),
+                };
+            }
         } else if is_this_viewer {
             // No crop at all — drag to create a new pending one (only for this viewer)
             if crop_resp.drag_started() {
@@ -1028,6 +1905,12 @@ impl Viewer {
             }
             if crop_resp.drag_stopped() {
                 self.crop_create_origin = None;
+                if let (Some(bounds), Some(crop)) = (&self.crop_drag_bounds, &mut self.pending_crop)
+                    && crop.width >= 0.01
+                    && crop.height >= 0.01
+                {
+                    *crop = transform::constrain_crop(crop, bounds);
+                }
                 if let Some(ref crop) = self.pending_crop {
                     if crop.width < 0.01 || crop.height < 0.01 {
                         self.pending_crop = None;
@@ -1037,6 +1920,508 @@ impl Viewer {
         }
     }
 
+    /// Draw the selected mask and route image drags to its handles or, for
+    /// brush masks, to painting. `mask` is a copy of the selected mask; edits
+    /// are written back when it changes.
+    fn handle_mask_interaction(
+        &mut self,
+        ui: &mut egui::Ui,
+        img_rect: egui::Rect,
+        mut mask: MaskShape,
+    ) {
+        let Some(target) = self.selected_mask else {
+            return;
+        };
+        let original = mask.clone();
+        let resp = ui.interact(
+            img_rect,
+            ui.id().with("local_interact"),
+            egui::Sense::click_and_drag(),
+        );
+
+        if self.show_local_mask {
+            self.draw_local_mask_tint(ui, img_rect, &mask);
+        }
+
+        let long_edge = img_rect.width().max(img_rect.height());
+        let handles = local_handles(&mask, img_rect);
+        draw_local_overlay(ui, img_rect, &mask, &handles);
+
+        let is_brush = matches!(mask, MaskShape::Brush { .. });
+        if is_brush && let Some(pos) = resp.hover_pos() {
+            let color = if self.brush.erase {
+                egui::Color32::from_rgb(255, 120, 120)
+            } else {
+                egui::Color32::WHITE
+            };
+            let painter = ui.painter_at(img_rect);
+            let r = self.brush.radius * long_edge;
+            painter.circle_stroke(pos, r, egui::Stroke::new(1.0, color));
+            painter.circle_stroke(
+                pos,
+                r * (1.0 - self.brush.feather),
+                egui::Stroke::new(1.0, color.gamma_multiply(0.5)),
+            );
+        }
+
+        if resp.drag_started() {
+            // Start from the press position so strokes don't skip the drag threshold
+            let origin = ui.input(|i| i.pointer.press_origin());
+            if let Some(pos) = origin.or(resp.interact_pointer_pos()) {
+                let hit = handles
+                    .iter()
+                    .position(|h| h.distance(pos) <= HANDLE_SIZE * 1.5);
+                self.local_drag = match (hit, &mut mask) {
+                    (Some(i), _) => Some(LocalDrag::Handle(i)),
+                    (None, MaskShape::Brush { strokes }) => {
+                        strokes.push(BrushStroke {
+                            points: vec![screen_to_norm_point(pos, img_rect)],
+                            radius: self.brush.radius,
+                            feather: self.brush.feather,
+                            erase: self.brush.erase,
+                        });
+                        Some(LocalDrag::Stroke)
+                    }
+                    (None, _) => None,
+                };
+                if self.local_drag == Some(LocalDrag::Stroke) {
+                    self.needs_process = true;
+                    self.last_slider_change = Some(Instant::now());
+                }
+            }
+        }
+
+        if resp.dragged()
+            && let (Some(drag), Some(pos)) = (self.local_drag, resp.interact_pointer_pos())
+        {
+            let changed = match drag {
+                LocalDrag::Handle(i) => {
+                    drag_local_handle(&mut mask, i, pos, resp.drag_delta(), img_rect);
+                    true
+                }
+                LocalDrag::Stroke => {
+                    let point = screen_to_norm_point(pos, img_rect);
+                    match &mut mask {
+                        MaskShape::Brush { strokes } => match strokes.last_mut() {
+                            Some(stroke) => {
+                                let last = stroke.points.last().copied().unwrap_or(point);
+                                let moved = egui::vec2(
+                                    (point.x - last.x) * img_rect.width(),
+                                    (point.y - last.y) * img_rect.height(),
+                                );
+                                // Skip sub-pixel jitter to keep sidecars small
+                                if moved.length() >= 2.0 {
+                                    stroke.points.push(point);
+                                    true
+                                } else {
+                                    false
+                                }
+                            }
+                            None => false,
+                        },
+                        _ => false,
+                    }
+                }
+            };
+            if changed {
+                self.needs_process = true;
+                self.last_slider_change = Some(Instant::now());
+            }
+        }
+
+        if resp.drag_stopped() {
+            self.local_drag = None;
+        }
+
+        // A click without dragging paints a single dab
+        if resp.clicked()
+            && let (Some(pos), MaskShape::Brush { strokes }) =
+                (resp.interact_pointer_pos(), &mut mask)
+        {
+            strokes.push(BrushStroke {
+                points: vec![screen_to_norm_point(pos, img_rect)],
+                radius: self.brush.radius,
+                feather: self.brush.feather,
+                erase: self.brush.erase,
+            });
+            self.needs_process = true;
+            self.last_slider_change = None;
+        }
+
+        if mask != original {
+            self.set_target_mask(target, mask);
+        }
+    }
+
+    /// Target adjustment: a vertical drag on the image changes the selective
+    /// color band matching the color where the drag started.
+    fn handle_target_adjust_interaction(&mut self, ui: &mut egui::Ui, img_rect: egui::Rect) {
+        let (Some(property), Some(preview)) = (self.target_adjust, self.preview.as_ref()) else {
+            return;
+        };
+        let src_size = [preview.width(), preview.height()];
+        let resp = ui
+            .interact(img_rect, ui.id().with("target_adjust"), egui::Sense::drag())
+            .on_hover_cursor(egui::CursorIcon::ResizeVertical);
+
+        if resp.drag_started() {
+            let origin = ui.input(|i| i.pointer.press_origin());
+            self.target_band = origin.or(resp.interact_pointer_pos()).and_then(|pos| {
+                let p = screen_to_source_point(&self.edit_state, src_size, pos, img_rect);
+                let x = ((p.x * src_size[0] as f32) as u32).min(src_size[0] - 1);
+                let y = ((p.y * src_size[1] as f32) as u32).min(src_size[1] - 1);
+                let px = preview.get_pixel(x, y);
+                color::band_at(&self.edit_state, [px[0], px[1], px[2]])
+            });
+        }
+
+        if resp.dragged()
+            && let Some(band) = self.target_band
+        {
+            // Dragging up raises the value
+            let dy = -resp.drag_delta().y;
+            let adj = &mut self.edit_state.selective_color[band];
+            match property {
+                TargetProperty::Hue => adj.hue = (adj.hue + dy * 0.5).clamp(-45.0, 45.0),
+                TargetProperty::Saturation => {
+                    adj.saturation = (adj.saturation + dy * 0.005).clamp(-1.0, 1.0)
+                }
+                TargetProperty::Lightness => {
+                    adj.lightness = (adj.lightness + dy * 0.005).clamp(-1.0, 1.0)
+                }
+            }
+            if dy != 0.0 {
+                self.needs_process = true;
+                self.last_slider_change = Some(Instant::now());
+            }
+            if let Some(pos) = resp.interact_pointer_pos() {
+                ui.painter_at(img_rect).text(
+                    pos + egui::vec2(14.0, -14.0),
+                    egui::Align2::LEFT_BOTTOM,
+                    format!("{} {}", HUE_LABELS[band], property.label()),
+                    egui::FontId::proportional(13.0),
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+
+        if resp.drag_stopped() {
+            self.target_band = None;
+        }
+    }
+
+    /// Turns spot placement on or off; it replaces crop and mask editing on the image.
+    fn set_spot_mode(&mut self, on: bool) {
+        self.spot_mode = on;
+        self.spot_drag = None;
+        if on {
+            self.crop_mode = false;
+            self.pending_crop = None;
+            self.crop_drag = None;
+            self.crop_create_origin = None;
+            self.selected_mask = None;
+            self.local_drag = None;
+            self.upright.active = false;
+            self.target_adjust = None;
+        }
+    }
+
+    /// Places, selects and drags heal/clone spots, and draws their outlines.
+    fn handle_spot_interaction(&mut self, ui: &mut egui::Ui, img_rect: egui::Rect) {
+        let Some(src_size) = self.preview.as_ref().map(|p| [p.width(), p.height()]) else {
+            return;
+        };
+        let resp = ui.interact(
+            img_rect,
+            ui.id().with("spot_interact"),
+            egui::Sense::click_and_drag(),
+        );
+
+        // Spots live in source coordinates; map them through the current geometry.
+        let (out_w, _) = transform::output_dims(&self.edit_state, src_size[0], src_size[1]);
+        let px_scale = img_rect.width() / out_w as f32;
+        let long_edge = src_size[0].max(src_size[1]) as f32;
+        let placed: Vec<(egui::Pos2, egui::Pos2, f32)> = self
+            .edit_state
+            .spots
+            .iter()
+            .map(|spot| {
+                (
+                    source_point_to_screen(&self.edit_state, src_size, spot.center, img_rect),
+                    source_point_to_screen(&self.edit_state, src_size, spot.source, img_rect),
+                    spot.radius * long_edge * px_scale,
+                )
+            })
+            .collect();
+        draw_spot_overlay(ui, img_rect, &placed, self.selected_spot);
+
+        // Only the selected spot shows (and exposes) its source circle.
+        let selected = self.selected_spot;
+        let hit_test = |pos: egui::Pos2| {
+            placed
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(i, (center, source, r))| {
+                    let reach = r.max(HANDLE_SIZE);
+                    if center.distance(pos) <= reach {
+                        Some(SpotDrag::Center(i))
+                    } else if selected == Some(i) && source.distance(pos) <= reach {
+                        Some(SpotDrag::Source(i))
+                    } else {
+                        None
+                    }
+                })
+        };
+
+        if let Some(pos) = resp.hover_pos()
+            && hit_test(pos).is_none()
+        {
+            let r = self.spot_radius * long_edge * px_scale;
+            ui.painter_at(img_rect).circle_stroke(
+                pos,
+                r,
+                egui::Stroke::new(1.0, egui::Color32::from_white_alpha(160)),
+            );
+        }
+
+        if resp.drag_started() {
+            let origin = ui.input(|i| i.pointer.press_origin());
+            self.spot_drag = origin.or(resp.interact_pointer_pos()).and_then(hit_test);
+            if let Some(SpotDrag::Center(i) | SpotDrag::Source(i)) = self.spot_drag {
+                self.selected_spot = Some(i);
+            }
+        }
+
+        if resp.dragged()
+            && let (Some(drag), Some(pos)) = (self.spot_drag, resp.interact_pointer_pos())
+        {
+            let point = screen_to_source_point(&self.edit_state, src_size, pos, img_rect);
+            match drag {
+                SpotDrag::Center(i) => {
+                    if let Some(spot) = self.edit_state.spots.get_mut(i) {
+                        spot.center = point;
+                    }
+                }
+                SpotDrag::Source(i) => {
+                    if let Some(spot) = self.edit_state.spots.get_mut(i) {
+                        spot.source = point;
+                    }
+                }
+            }
+            self.needs_process = true;
+            self.last_slider_change = Some(Instant::now());
+        }
+
+        if resp.drag_stopped() {
+            self.spot_drag = None;
+        }
+
+        if resp.clicked()
+            && let Some(pos) = resp.interact_pointer_pos()
+        {
+            match hit_test(pos) {
+                Some(SpotDrag::Center(i) | SpotDrag::Source(i)) => {
+                    self.selected_spot = Some(i);
+                }
+                None => {
+                    let center = screen_to_source_point(&self.edit_state, src_size, pos, img_rect);
+                    let source = self.preview.as_ref().map_or(center, |preview| {
+                        spots::auto_source(&preview.to_rgba8(), center, self.spot_radius)
+                    });
+                    self.edit_state.spots.push(Spot {
+                        center,
+                        source,
+                        radius: self.spot_radius,
+                        feather: 0.5,
+                        mode: SpotMode::Heal,
+                    });
+                    self.selected_spot = Some(self.edit_state.spots.len() - 1);
+                    self.needs_process = true;
+                    self.last_slider_change = None;
+                }
+            }
+        }
+
+        if resp.hovered()
+            && ui.input(|i| i.key_pressed(egui::Key::Delete) || i.key_pressed(egui::Key::Backspace))
+            && let Some(i) = self.selected_spot
+        {
+            self.remove_spot(i);
+        }
+    }
+
+    /// Draws guide lines by dragging and re-fits straighten/keystone to them.
+    fn handle_guide_interaction(&mut self, ui: &mut egui::Ui, img_rect: egui::Rect) {
+        let Some(src_size) = self.preview.as_ref().map(|p| [p.width(), p.height()]) else {
+            return;
+        };
+        let resp = ui.interact(
+            img_rect,
+            ui.id().with("guide_interact"),
+            egui::Sense::drag(),
+        );
+
+        let painter = ui.painter_at(img_rect);
+        let shadow = egui::Stroke::new(3.0, egui::Color32::from_black_alpha(120));
+        let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(80, 200, 255));
+        for &(a, b, _) in &self.upright.lines {
+            let a = source_point_to_screen(&self.edit_state, src_size, a, img_rect);
+            let b = source_point_to_screen(&self.edit_state, src_size, b, img_rect);
+            painter.line_segment([a, b], shadow);
+            painter.line_segment([a, b], stroke);
+        }
+
+        if resp.drag_started() {
+            let origin = ui.input(|i| i.pointer.press_origin());
+            self.upright.drag_start = origin.or(resp.interact_pointer_pos());
+        }
+        let current = resp.interact_pointer_pos();
+        if let (Some(start), Some(end)) = (self.upright.drag_start, current) {
+            painter.line_segment([start, end], shadow);
+            painter.line_segment([start, end], stroke);
+        }
+        if resp.drag_stopped()
+            && let (Some(start), Some(end)) = (self.upright.drag_start.take(), current)
+            && start.distance(end) >= 12.0
+        {
+            let d = end - start;
+            let axis = if d.y.abs() > d.x.abs() {
+                upright::Axis::Vertical
+            } else {
+                upright::Axis::Horizontal
+            };
+            let a = screen_to_source_point(&self.edit_state, src_size, start, img_rect);
+            let b = screen_to_source_point(&self.edit_state, src_size, end, img_rect);
+            self.upright.lines.push((a, b, axis));
+            if self.upright.lines.len() > UPRIGHT_GUIDES_MAX {
+                self.upright.lines.remove(0);
+            }
+            self.apply_guides(src_size);
+        }
+    }
+
+    /// Re-solves straighten and keystone from the current guide lines.
+    fn apply_guides(&mut self, src_size: [u32; 2]) {
+        let [w, h] = src_size;
+        let lines: Vec<upright::GuideLine> = self
+            .upright
+            .lines
+            .iter()
+            .map(|&(a, b, axis)| upright::GuideLine {
+                a: [a.x * w as f32, a.y * h as f32],
+                b: [b.x * w as f32, b.y * h as f32],
+                axis,
+            })
+            .collect();
+        if let Some(suggestion) = upright::solve(&self.edit_state, w, h, &lines, false) {
+            self.edit_state.straighten = suggestion.straighten;
+            self.edit_state.keystone = suggestion.keystone;
+            self.needs_process = true;
+            self.last_slider_change = None;
+        }
+    }
+
+    fn remove_spot(&mut self, i: usize) {
+        if i >= self.edit_state.spots.len() {
+            return;
+        }
+        self.edit_state.spots.remove(i);
+        self.selected_spot = match self.selected_spot {
+            Some(sel) if sel == i => None,
+            Some(sel) if sel > i => Some(sel - 1),
+            other => other,
+        };
+        self.spot_drag = None;
+        self.needs_process = true;
+        self.last_slider_change = None;
+    }
+
+    /// Returns a copy of the mask for `target`, if it still exists.
+    /// Graduated filters are presented as linear masks.
+    fn target_mask(&self, target: MaskTarget) -> Option<MaskShape> {
+        match target {
+            MaskTarget::Graduated(i) => {
+                self.edit_state
+                    .graduated_filters
+                    .get(i)
+                    .map(|grad| MaskShape::Linear {
+                        start: grad.start,
+                        end: grad.end,
+                    })
+            }
+            MaskTarget::Local(i) => self
+                .edit_state
+                .local_adjustments
+                .get(i)
+                .map(|adj| adj.mask.clone()),
+        }
+    }
+
+    fn set_target_mask(&mut self, target: MaskTarget, mask: MaskShape) {
+        match target {
+            MaskTarget::Graduated(i) => {
+                if let (Some(grad), MaskShape::Linear { start, end }) =
+                    (self.edit_state.graduated_filters.get_mut(i), mask)
+                {
+                    grad.start = start;
+                    grad.end = end;
+                }
+            }
+            MaskTarget::Local(i) => {
+                if let Some(adj) = self.edit_state.local_adjustments.get_mut(i) {
+                    adj.mask = mask;
+                }
+            }
+        }
+    }
+
+    /// Tints the selected mask's coverage in red over the image.
+    fn draw_local_mask_tint(&mut self, ui: &mut egui::Ui, img_rect: egui::Rect, mask: &MaskShape) {
+        let Some(tex) = self.texture.as_ref() else {
+            return;
+        };
+        // Coverage is smooth, so a small overlay stretched over the image is enough.
+        let [tw, th] = tex.size();
+        let scale = (LOCAL_MASK_OVERLAY_MAX as f32 / tw.max(th) as f32).min(1.0);
+        let w = ((tw as f32 * scale).round() as u32).max(1);
+        let h = ((th as f32 * scale).round() as u32).max(1);
+
+        let signature = {
+            let mut hasher = DefaultHasher::new();
+            serde_json::to_vec(mask)
+                .unwrap_or_default()
+                .hash(&mut hasher);
+            (w, h).hash(&mut hasher);
+            hasher.finish()
+        };
+        let cached = matches!(&self.local_mask_texture, Some((sig, _)) if *sig == signature);
+        if !cached {
+            let weights = local::mask_weights(&local::resolve(mask, w, h), w, h);
+            let pixels: Vec<egui::Color32> = weights
+                .iter()
+                .map(|&v| {
+                    egui::Color32::from_rgba_unmultiplied(255, 40, 40, (v * 140.0).round() as u8)
+                })
+                .collect();
+            let image = egui::ColorImage::new([w as usize, h as usize], pixels);
+            let handle = ui.ctx().load_texture(
+                format!("local_mask_{}", self.id),
+                image,
+                egui::TextureOptions::LINEAR,
+            );
+            self.local_mask_texture = Some((signature, handle));
+        }
+        if let Some((_, handle)) = &self.local_mask_texture {
+            ui.painter_at(img_rect).image(
+                handle.id(),
+                img_rect,
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+
     /// Renders the editing controls panel for the current image.
     pub fn show_controls(&mut self, ui: &mut egui::Ui) {
         egui::ScrollArea::vertical()
@@ -1047,54 +2432,139 @@ impl Viewer {
 
                 ui.separator();
 
+                let was_guided = self.upright.active;
+                let geometry_before = (
+                    self.edit_state.rotate,
+                    self.edit_state.straighten,
+                    self.edit_state.keystone.clone(),
+                );
                 show_transform_section(
                     ui,
                     &mut self.edit_state,
+                    self.preview.as_ref(),
+                    &mut self.upright,
                     &mut self.needs_process,
                     &mut self.last_slider_change,
                 );
+                if self.upright.active && !was_guided {
+                    // Guide lines are drawn on the plain image view
+                    self.set_spot_mode(false);
+                    self.target_adjust = None;
+                    self.crop_mode = false;
+                    self.pending_crop = None;
+                    self.selected_mask = None;
+                    self.local_drag = None;
+                }
+                let geometry_after = (
+                    self.edit_state.rotate,
+                    self.edit_state.straighten,
+                    self.edit_state.keystone.clone(),
+                );
+                if geometry_after != geometry_before {
+                    self.enforce_crop_constraint();
+                }
 
                 ui.separator();
 
+                let was_targeting = self.target_adjust.is_some();
                 show_color_section(
                     ui,
                     &mut self.edit_state,
                     &mut self.needs_process,
                     &mut self.last_slider_change,
+                    &mut self.target_adjust,
                 );
+                if self.target_adjust.is_some() && !was_targeting {
+                    // Target adjustment drags replace the other image tools
+                    self.set_spot_mode(false);
+                    self.crop_mode = false;
+                    self.pending_crop = None;
+                    self.selected_mask = None;
+                    self.local_drag = None;
+                    self.upright.active = false;
+                }
 
                 ui.separator();
 
-                if let Some(ref meta) = self.metadata {
-                    show_exif(ui, meta);
-                } else {
-                    ui.label(egui::RichText::new("No EXIF data").weak());
-                }
-            });
+                show_black_white_section(
+                    ui,
+                    &mut self.edit_state,
+                    &mut self.needs_process,
+                    &mut self.last_slider_change,
+                );
+
+                ui.separator();
+
+                self.show_lut_section(ui);
+
+                ui.separator();
+
+                self.show_graduated_section(ui);
+
+                ui.separator();
+
+                show_sharpening_section(
+                    ui,
+                    &mut self.edit_state,
+                    &mut self.needs_process,
+                    &mut self.last_slider_change,
+                    &mut self.sharpen_mask_view,
+                );
+
+                ui.separator();
+
+                show_grain_section(
+                    ui,
+                    &mut self.edit_state,
+                    &mut self.needs_process,
+                    &mut self.last_slider_change,
+                );
+
+                ui.separator();
+
+                show_vignette_section(
+                    ui,
+                    &mut self.edit_state,
+                    &mut self.needs_process,
+                    &mut self.last_slider_change,
+                );
+
+                ui.separator();
+
+                self.show_local_section(ui);
+
+                ui.separator();
+
+                self.show_spot_section(ui);
+
+                ui.separator();
+
+                if let Some(ref meta) = self.metadata {
+                    show_exif(ui, meta);
+                } else {
+                    ui.label(egui::RichText::new("No EXIF data").weak());
+                }
+            });
     }
 
     fn show_crop_section(&mut self, ui: &mut egui::Ui) {
         ui.label(egui::RichText::new("Crop").strong());
         ui.add_space(4.0);
 
-        // Aspect ratio selector
-        ui.horizontal_wrapped(|ui| {
-            ui.label("Aspect");
-            for aspect in CropAspect::ALL {
-                if ui
-                    .selectable_label(self.crop_aspect == aspect, aspect.label())
-                    .clicked()
-                {
-                    self.crop_aspect = aspect;
-                    let ratio = self.effective_crop_ratio();
-                    if let Some(ref mut crop) = self.pending_crop {
-                        constrain_aspect(crop, ratio);
-                    }
-                }
-            }
-        });
+        self.show_crop_aspect_controls(ui);
+        if ui
+            .checkbox(&mut self.edit_state.constrain_crop, "Constrain to image")
+            .on_hover_text("Keep the crop clear of borders exposed by straighten and keystone")
+            .changed()
+        {
+            self.enforce_crop_constraint();
+            self.needs_process = true;
+            self.last_slider_change = None;
+        }
 
         // Show sliders for the pending crop if it exists
+        let frame_px = self.crop_frame_pixels();
+        let norm_ratio = self.crop_norm_ratio();
         if let Some(ref mut crop) = self.pending_crop {
             ui.horizontal(|ui| {
                 ui.label("X");
@@ -1128,6 +2598,43 @@ impl Viewer {
                         .clamping(egui::SliderClamping::Always),
                 );
             });
+            if let Some((fw, fh)) = frame_px {
+                ui.horizontal(|ui| {
+                    ui.label("Size");
+                    let mut w_px = (crop.width * fw).round();
+                    let mut h_px = (crop.height * fh).round();
+                    let w_changed = ui
+                        .add(
+                            egui::DragValue::new(&mut w_px)
+                                .range(1.0..=fw)
+                                .suffix(" px"),
+                        )
+                        .changed();
+                    ui.label("×");
+                    let h_changed = ui
+                        .add(
+                            egui::DragValue::new(&mut h_px)
+                                .range(1.0..=fh)
+                                .suffix(" px"),
+                        )
+                        .changed();
+                    if w_changed {
+                        crop.width = w_px / fw;
+                        if let Some(ratio) = norm_ratio {
+                            crop.height = crop.width / ratio;
+                        }
+                    } else if h_changed {
+                        crop.height = h_px / fh;
+                        if let Some(ratio) = norm_ratio {
+                            crop.width = crop.height * ratio;
+                        }
+                    }
+                    // Keep a locked ratio when the other side hits the frame
+                    let overflow = crop.width.max(crop.height).max(1.0);
+                    crop.width /= overflow;
+                    crop.height /= overflow;
+                });
+            }
             // Clamp position so rect stays in bounds
             crop.x = crop.x.min(1.0 - crop.width);
             crop.y = crop.y.min(1.0 - crop.height);
@@ -1172,6 +2679,459 @@ impl Viewer {
             });
         }
     }
+
+    fn show_graduated_section(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("Graduated Filters").strong());
+        ui.add_space(4.0);
+
+        if ui.button("+ Add").clicked() {
+            let mut grad =
+                GradFilter::new(NormPoint { x: 0.5, y: 0.0 }, NormPoint { x: 0.5, y: 0.6 });
+            grad.exposure = -0.7;
+            self.edit_state.graduated_filters.push(grad);
+            self.selected_mask = Some(MaskTarget::Graduated(
+                self.edit_state.graduated_filters.len() - 1,
+            ));
+            self.crop_mode = false;
+            self.spot_mode = false;
+            self.target_adjust = None;
+            self.pending_crop = None;
+            self.needs_process = true;
+            self.last_slider_change = None;
+        }
+
+        let mut remove = None;
+        for i in 0..self.edit_state.graduated_filters.len() {
+            ui.horizontal(|ui| {
+                let selected = self.selected_mask == Some(MaskTarget::Graduated(i));
+                if ui
+                    .selectable_label(selected, format!("Filter {}", i + 1))
+                    .clicked()
+                {
+                    self.selected_mask = if selected {
+                        None
+                    } else {
+                        Some(MaskTarget::Graduated(i))
+                    };
+                    self.local_drag = None;
+                    if !selected {
+                        self.crop_mode = false;
+                        self.pending_crop = None;
+                        self.spot_mode = false;
+                    }
+                }
+                if ui.small_button("✕").on_hover_text("Delete").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.edit_state.graduated_filters.remove(i);
+            self.selected_mask = match self.selected_mask {
+                Some(MaskTarget::Graduated(sel)) if sel == i => None,
+                Some(MaskTarget::Graduated(sel)) if sel > i => Some(MaskTarget::Graduated(sel - 1)),
+                other => other,
+            };
+            self.local_drag = None;
+            self.needs_process = true;
+            self.last_slider_change = None;
+        }
+
+        let Some(MaskTarget::Graduated(idx)) = self.selected_mask else {
+            return;
+        };
+        let Some(grad) = self.edit_state.graduated_filters.get_mut(idx) else {
+            return;
+        };
+        ui.add_space(4.0);
+        ui.weak("Drag the handles to set angle and position.");
+        let sliders = [
+            ("Exposure", &mut grad.exposure, -3.0..=3.0),
+            ("Contrast", &mut grad.contrast, -1.0..=1.0),
+            ("Highlights", &mut grad.highlights, -1.0..=1.0),
+            ("Temperature", &mut grad.temperature, -1.0..=1.0),
+            ("Saturation", &mut grad.saturation, -1.0..=1.0),
+        ];
+        for (label, value, range) in sliders {
+            adjustment_slider(
+                ui,
+                label,
+                value,
+                range,
+                &mut self.needs_process,
+                &mut self.last_slider_change,
+            );
+        }
+    }
+
+    fn show_local_section(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("Local Adjustments").strong());
+        ui.add_space(4.0);
+
+        ui.horizontal(|ui| {
+            let new_mask = if ui.button("+ Linear").clicked() {
+                Some(MaskShape::Linear {
+                    start: NormPoint { x: 0.5, y: 0.2 },
+                    end: NormPoint { x: 0.5, y: 0.6 },
+                })
+            } else if ui.button("+ Radial").clicked() {
+                Some(MaskShape::Radial {
+                    center: NormPoint { x: 0.5, y: 0.5 },
+                    radius_x: 0.25,
+                    radius_y: 0.18,
+                    angle: 0.0,
+                    feather: 0.5,
+                    invert: false,
+                })
+            } else if ui.button("+ Brush").clicked() {
+                Some(MaskShape::Brush {
+                    strokes: Vec::new(),
+                })
+            } else {
+                None
+            };
+            if let Some(mask) = new_mask {
+                self.edit_state
+                    .local_adjustments
+                    .push(LocalAdjustment::new(mask));
+                self.selected_mask = Some(MaskTarget::Local(
+                    self.edit_state.local_adjustments.len() - 1,
+                ));
+                self.crop_mode = false;
+                self.spot_mode = false;
+                self.target_adjust = None;
+                self.pending_crop = None;
+                self.needs_process = true;
+            }
+        });
+
+        let mut remove = None;
+        for (i, adj) in self.edit_state.local_adjustments.iter().enumerate() {
+            let kind = match adj.mask {
+                MaskShape::Linear { .. } => "Linear",
+                MaskShape::Radial { .. } => "Radial",
+                MaskShape::Brush { .. } => "Brush",
+            };
+            ui.horizontal(|ui| {
+                let selected = self.selected_mask == Some(MaskTarget::Local(i));
+                if ui
+                    .selectable_label(selected, format!("{} {}", i + 1, kind))
+                    .clicked()
+                {
+                    self.selected_mask = if selected {
+                        None
+                    } else {
+                        Some(MaskTarget::Local(i))
+                    };
+                    self.local_drag = None;
+                    if !selected {
+                        self.crop_mode = false;
+                        self.pending_crop = None;
+                        self.spot_mode = false;
+                    }
+                }
+                if ui.small_button("✕").on_hover_text("Delete").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.edit_state.local_adjustments.remove(i);
+            self.selected_mask = match self.selected_mask {
+                Some(MaskTarget::Local(sel)) if sel == i => None,
+                Some(MaskTarget::Local(sel)) if sel > i => Some(MaskTarget::Local(sel - 1)),
+                other => other,
+            };
+            self.local_drag = None;
+            self.needs_process = true;
+            self.last_slider_change = None;
+        }
+
+        let Some(MaskTarget::Local(idx)) = self.selected_mask else {
+            return;
+        };
+        let Some(adj) = self.edit_state.local_adjustments.get_mut(idx) else {
+            return;
+        };
+        let needs_process = &mut self.needs_process;
+        let last_slider_change = &mut self.last_slider_change;
+
+        ui.add_space(4.0);
+        let sliders = [
+            ("Exposure", &mut adj.exposure, -3.0..=3.0),
+            ("Contrast", &mut adj.contrast, -1.0..=1.0),
+            ("Temperature", &mut adj.temperature, -1.0..=1.0),
+            ("Saturation", &mut adj.saturation, -1.0..=1.0),
+            ("Sharpness", &mut adj.sharpness, -1.0..=1.0),
+        ];
+        for (label, value, range) in sliders {
+            adjustment_slider(ui, label, value, range, needs_process, last_slider_change);
+        }
+
+        match &mut adj.mask {
+            MaskShape::Linear { .. } => {
+                ui.weak("Drag the handles to place the gradient.");
+            }
+            MaskShape::Radial {
+                feather, invert, ..
+            } => {
+                ui.horizontal(|ui| {
+                    ui.label("Feather");
+                    let resp = ui.add(
+                        egui::Slider::new(feather, 0.0_f32..=1.0_f32)
+                            .fixed_decimals(2)
+                            .clamping(egui::SliderClamping::Always),
+                    );
+                    if resp.changed() {
+                        *needs_process = true;
+                        *last_slider_change = Some(Instant::now());
+                    }
+                });
+                if ui.checkbox(invert, "Invert").changed() {
+                    *needs_process = true;
+                    *last_slider_change = None;
+                }
+            }
+            MaskShape::Brush { strokes } => {
+                ui.horizontal(|ui| {
+                    ui.label("Size");
+                    ui.add(
+                        egui::Slider::new(&mut self.brush.radius, 0.005_f32..=0.25_f32)
+                            .fixed_decimals(3)
+                            .clamping(egui::SliderClamping::Always),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Feather");
+                    ui.add(
+                        egui::Slider::new(&mut self.brush.feather, 0.0_f32..=1.0_f32)
+                            .fixed_decimals(2)
+                            .clamping(egui::SliderClamping::Always),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.brush.erase, false, "Paint");
+                    ui.selectable_value(&mut self.brush.erase, true, "Erase");
+                    if ui
+                        .add_enabled(!strokes.is_empty(), egui::Button::new("Clear"))
+                        .clicked()
+                    {
+                        strokes.clear();
+                        *needs_process = true;
+                        *last_slider_change = None;
+                    }
+                });
+            }
+        }
+
+        ui.checkbox(&mut self.show_local_mask, "Show mask overlay");
+    }
+
+    fn show_lut_section(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("LUT").strong());
+        ui.add_space(4.0);
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.lut_path)
+                    .hint_text("/path/to/look.cube")
+                    .desired_width(180.0),
+            );
+            if ui.button("Load").clicked() {
+                let path = PathBuf::from(self.lut_path.trim());
+                match lut::load(&path) {
+                    Ok(loaded) => {
+                        self.lut_status = Some(format!("Loaded {}³ LUT", loaded.size));
+                        let lut = self.edit_state.lut.get_or_insert(LutRef {
+                            path: path.clone(),
+                            strength: 1.0,
+                            interpolation: LutInterpolation::default(),
+                        });
+                        lut.path = path;
+                        self.needs_process = true;
+                        self.last_slider_change = None;
+                    }
+                    Err(err) => self.lut_status = Some(format!("Could not load LUT: {err}")),
+                }
+            }
+            if ui
+                .button("Export .cube")
+                .on_hover_text("Save the global tone and color edits as a LUT")
+                .clicked()
+            {
+                let path = PathBuf::from(self.lut_path.trim());
+                self.lut_status = Some(match lut::export_cube(&self.edit_state, &path) {
+                    Ok(()) => format!("Exported {}", path.display()),
+                    Err(err) => format!("Could not export LUT: {err}"),
+                });
+            }
+        });
+
+        let mut remove = false;
+        if let Some(lut) = self.edit_state.lut.as_mut() {
+            ui.horizontal(|ui| {
+                let name = lut
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                ui.label(name);
+                if ui.small_button("✕").on_hover_text("Remove").clicked() {
+                    remove = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Strength");
+                let resp = ui.add(
+                    egui::Slider::new(&mut lut.strength, 0.0_f32..=1.0_f32)
+                        .fixed_decimals(2)
+                        .clamping(egui::SliderClamping::Always),
+                );
+                if resp.changed() {
+                    self.needs_process = true;
+                    self.last_slider_change = Some(Instant::now());
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Interpolation");
+                let before = lut.interpolation;
+                ui.selectable_value(
+                    &mut lut.interpolation,
+                    LutInterpolation::Tetrahedral,
+                    "Tetrahedral",
+                );
+                ui.selectable_value(
+                    &mut lut.interpolation,
+                    LutInterpolation::Trilinear,
+                    "Trilinear",
+                );
+                if lut.interpolation != before {
+                    self.needs_process = true;
+                    self.last_slider_change = None;
+                }
+            });
+        }
+        if remove {
+            self.edit_state.lut = None;
+            self.needs_process = true;
+            self.last_slider_change = None;
+        }
+
+        if let Some(status) = &self.lut_status {
+            ui.weak(status);
+        }
+    }
+
+    fn show_spot_section(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("Spot Removal").strong());
+        ui.add_space(4.0);
+
+        if ui.selectable_label(self.spot_mode, "Place spots").clicked() {
+            self.set_spot_mode(!self.spot_mode);
+        }
+        if self.spot_mode {
+            ui.weak("Click to add a spot. Drag a circle to move the spot or its source.");
+        }
+
+        let mut remove = None;
+        for (i, spot) in self.edit_state.spots.iter().enumerate() {
+            let kind = match spot.mode {
+                SpotMode::Heal => "Heal",
+                SpotMode::Clone => "Clone",
+            };
+            ui.horizontal(|ui| {
+                let selected = self.selected_spot == Some(i);
+                if ui
+                    .selectable_label(selected, format!("{} {}", i + 1, kind))
+                    .clicked()
+                {
+                    self.selected_spot = if selected { None } else { Some(i) };
+                }
+                if ui.small_button("✕").on_hover_text("Delete").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.remove_spot(i);
+        }
+
+        let Some(spot) = self
+            .selected_spot
+            .and_then(|i| self.edit_state.spots.get_mut(i))
+        else {
+            ui.horizontal(|ui| {
+                ui.label("Size");
+                ui.add(
+                    egui::Slider::new(&mut self.spot_radius, 0.002_f32..=0.15_f32)
+                        .fixed_decimals(3)
+                        .clamping(egui::SliderClamping::Always),
+                );
+            });
+            return;
+        };
+
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            let mut mode = spot.mode;
+            ui.selectable_value(&mut mode, SpotMode::Heal, "Heal");
+            ui.selectable_value(&mut mode, SpotMode::Clone, "Clone");
+            if mode != spot.mode {
+                spot.mode = mode;
+                self.needs_process = true;
+                self.last_slider_change = None;
+            }
+        });
+        let sliders = [
+            ("Size", &mut spot.radius, 0.002..=0.15),
+            ("Feather", &mut spot.feather, 0.0..=1.0),
+        ];
+        for (label, value, range) in sliders {
+            adjustment_slider(
+                ui,
+                label,
+                value,
+                range,
+                &mut self.needs_process,
+                &mut self.last_slider_change,
+            );
+        }
+        self.spot_radius = spot.radius;
+        if ui.button("Auto source").clicked()
+            && let Some(preview) = self.preview.as_ref()
+        {
+            spot.source = spots::auto_source(&preview.to_rgba8(), spot.center, spot.radius);
+            self.needs_process = true;
+            self.last_slider_change = None;
+        }
+    }
+}
+
+fn adjustment_slider(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut f32,
+    range: std::ops::RangeInclusive<f32>,
+    needs_process: &mut bool,
+    last_slider_change: &mut Option<Instant>,
+) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let resp = ui.add(
+            egui::Slider::new(value, range)
+                .fixed_decimals(2)
+                .clamping(egui::SliderClamping::Always),
+        );
+        if resp.changed() {
+            *needs_process = true;
+            *last_slider_change = Some(Instant::now());
+        }
+        if *value != 0.0 && ui.small_button("↺").clicked() {
+            *value = 0.0;
+            *needs_process = true;
+            *last_slider_change = None;
+        }
+    });
 }
 
 fn bump_requested_generation_for_pending_changes(
@@ -1200,13 +3160,113 @@ fn process_preview_with_backend(
     )
 }
 
-fn process_preview_with_backend_and_gpu_hook<F>(
+/// Renders the sharpening edge mask for the alt-drag preview. The mask is
+/// computed from the fully processed image minus the sharpening pass itself,
+/// so it lines up with crop/geometry and reflects the tones being sharpened.
+fn process_sharpen_mask_preview(
     source: &DynamicImage,
     state: &EditState,
     preview_backend: PreviewBackend,
-    allow_cpu_fallback: bool,
-    gpu_apply: F,
-) -> DynamicImage
+) -> DynamicImage {
+    let mut unsharpened = state.clone();
+    unsharpened.sharpness = 0.0;
+    let base = process_preview_with_backend(source, &unsharpened, preview_backend);
+    crate::processing::sharpness::mask_visualization(&base, state)
+}
+
+/// Gathers what the tiles of `state` share: the geometry output (reusing
+/// `previous` when only tones changed) and the frame's dehaze airlight.
+fn prepare_tile_frame(
+    source: &Arc<DynamicImage>,
+    state: EditState,
+    previous: Option<&TileFrame>,
+    display: Arc<icc::DisplayTransform>,
+) -> TileFrame {
+    let base = match previous {
+        Some(previous) if transform::same_warp(&previous.state, &state) => {
+            Arc::clone(&previous.base)
+        }
+        _ if !transform::has_warp(&state) => Arc::clone(source),
+        _ => Arc::new(transform::uncropped_geometry(source, &state)),
+    };
+    let (bw, bh) = (base.width(), base.height());
+    let bounds = transform::crop_bounds(&state, bw, bh).unwrap_or((0, 0, bw, bh));
+    let vignette = transform::vignette_frame(&state, source.width(), source.height());
+    let (x, y, w, h) = bounds;
+    let airlight = transform::frame_airlight(&*base.view(x, y, w, h), &state, vignette);
+    TileFrame {
+        state,
+        base,
+        bounds,
+        vignette,
+        airlight,
+        display,
+    }
+}
+
+/// Tile level for `pixel_zoom` screen pixels per full-resolution pixel: the
+/// coarsest whose tiles still have a pixel per screen pixel.
+fn tile_level(pixel_zoom: f32) -> u32 {
+    if pixel_zoom >= 1.0 {
+        return 0;
+    }
+    ((1.0 / pixel_zoom).log2().floor() as u32).min(MAX_TILE_LEVEL)
+}
+
+/// Renders one tile of `frame`: the tile plus enough margin for the blur,
+/// clarity and dehaze kernels goes through the effect stages, then the
+/// margin is cut away. Coarser levels shrink the sharpening radii with the
+/// pixels, so they match the full-resolution render downscaled. `None` past
+/// the edge of the frame.
+fn render_tile(frame: &TileFrame, level: u32, col: u32, row: u32) -> Option<image::RgbaImage> {
+    let (fw, fh) = frame.level_dims(level);
+    let (x, y) = (col * TILE_SIZE, row * TILE_SIZE);
+    if x >= fw || y >= fh {
+        return None;
+    }
+    let (w, h) = (TILE_SIZE.min(fw - x), TILE_SIZE.min(fh - y));
+    let scale = 1.0 / (1 << level) as f32;
+    let pad = transform::effect_padding(&frame.state, fw, fh, scale);
+    let (px, py) = (x.saturating_sub(pad), y.saturating_sub(pad));
+    let (pw, ph) = ((x + w + pad).min(fw) - px, (y + h + pad).min(fh) - py);
+
+    // The padded region in base pixels, downscaled for coarser levels
+    let step = 1 << level;
+    let (bx, by, bw, bh) = frame.bounds;
+    let (sx, sy) = (px * step, py * step);
+    let (sw, sh) = ((pw * step).min(bw - sx), (ph * step).min(bh - sy));
+    let view = frame.base.view(bx + sx, by + sy, sw, sh);
+    let padded = if level == 0 {
+        view.to_image()
+    } else {
+        image::imageops::resize(&*view, pw, ph, image::imageops::FilterType::Triangle)
+    };
+
+    let region = transform::Region {
+        origin: (px, py),
+        frame: (fw, fh),
+        airlight: frame.airlight,
+        scale,
+    };
+    let vignette = frame.vignette.map(|v| v / step as f32);
+    let out = transform::effects(
+        DynamicImage::ImageRgba8(padded),
+        &frame.state,
+        vignette,
+        &region,
+    );
+    let mut tile = out.crop_imm(x - px, y - py, w, h).to_rgba8();
+    frame.display.apply(&mut tile);
+    Some(tile)
+}
+
+fn process_preview_with_backend_and_gpu_hook<F>(
+    source: &DynamicImage,
+    state: &EditState,
+    preview_backend: PreviewBackend,
+    allow_cpu_fallback: bool,
+    gpu_apply: F,
+) -> DynamicImage
 where
     F: Fn(&DynamicImage, &EditState) -> Option<DynamicImage>,
 {
@@ -1256,7 +3316,14 @@ fn source_signature(path: &Path) -> u64 {
 }
 
 fn edit_state_signature(state: &EditState) -> u64 {
-    match serde_json::to_vec(state) {
+    // Rating, keywords and info do not change the rendered image
+    let state = EditState {
+        rating: 0,
+        keywords: Vec::new(),
+        info: Default::default(),
+        ..state.clone()
+    };
+    match serde_json::to_vec(&state) {
         Ok(bytes) => {
             let mut hasher = DefaultHasher::new();
             bytes.hash(&mut hasher);
@@ -1289,6 +3356,21 @@ fn screen_to_norm_pos(pos: egui::Pos2, img_rect: egui::Rect) -> egui::Pos2 {
     )
 }
 
+fn screen_to_norm_point(pos: egui::Pos2, img_rect: egui::Rect) -> NormPoint {
+    let n = screen_to_norm_pos(pos, img_rect);
+    NormPoint {
+        x: n.x.clamp(0.0, 1.0),
+        y: n.y.clamp(0.0, 1.0),
+    }
+}
+
+fn norm_point_to_screen(p: NormPoint, img_rect: egui::Rect) -> egui::Pos2 {
+    egui::pos2(
+        img_rect.min.x + p.x * img_rect.width(),
+        img_rect.min.y + p.y * img_rect.height(),
+    )
+}
+
 fn corner_rects(crop_screen: egui::Rect) -> [egui::Rect; 4] {
     let hs = HANDLE_SIZE;
     let corners = [
@@ -1366,6 +3448,21 @@ fn constrain_aspect(crop: &mut Rect, ratio: Option<f32>) {
 // Drawing helpers
 // ---------------------------------------------------------------------------
 
+/// Moves `crop` inside `bounds` without resizing it, or clips it when it is
+/// larger than the bounds.
+fn shift_into_bounds(crop: &Rect, bounds: &Rect) -> Rect {
+    if crop.width > bounds.width || crop.height > bounds.height {
+        return transform::constrain_crop(crop, bounds);
+    }
+    Rect {
+        x: crop.x.clamp(bounds.x, bounds.x + bounds.width - crop.width),
+        y: crop
+            .y
+            .clamp(bounds.y, bounds.y + bounds.height - crop.height),
+        ..crop.clone()
+    }
+}
+
 fn draw_fitted_image(
     ui: &mut egui::Ui,
     tex: &egui::TextureHandle,
@@ -1417,13 +3514,56 @@ fn draw_fitted_image(
     img_rect
 }
 
-/// Draw the crop overlay. `interactive` controls handle visibility:
-/// true for the pending (editable) crop, false for the applied (read-only) crop.
+/// Tiles of a `size` image drawn at `img_rect` that overlap `viewport`, as
+/// (column, row, screen rect), nearest the viewport center first.
+fn visible_tiles(
+    size: (u32, u32),
+    img_rect: egui::Rect,
+    viewport: egui::Rect,
+) -> Vec<(u32, u32, egui::Rect)> {
+    let (w, h) = size;
+    let visible = img_rect.intersect(viewport);
+    if w == 0 || h == 0 || !visible.is_positive() {
+        return Vec::new();
+    }
+    let scale = egui::vec2(img_rect.width() / w as f32, img_rect.height() / h as f32);
+    let tile = TILE_SIZE as f32;
+    let first = (visible.min - img_rect.min) / scale / tile;
+    let last = (visible.max - img_rect.min) / scale / tile;
+    let cols = (first.x as u32)..=(last.x as u32).min(w.div_ceil(TILE_SIZE) - 1);
+    let rows = (first.y as u32)..=(last.y as u32).min(h.div_ceil(TILE_SIZE) - 1);
+
+    let mut tiles = Vec::new();
+    for row in rows {
+        for col in cols.clone() {
+            let min = egui::vec2((col * TILE_SIZE) as f32, (row * TILE_SIZE) as f32);
+            let max = egui::vec2(
+                ((col + 1) * TILE_SIZE).min(w) as f32,
+                ((row + 1) * TILE_SIZE).min(h) as f32,
+            );
+            let rect =
+                egui::Rect::from_min_max(img_rect.min + min * scale, img_rect.min + max * scale);
+            tiles.push((col, row, rect));
+        }
+    }
+    let center = visible.center();
+    tiles.sort_by(|a, b| {
+        a.2.center()
+            .distance_sq(center)
+            .total_cmp(&b.2.center().distance_sq(center))
+    });
+    tiles
+}
+
+/// Draw the crop overlay. `interactive` controls handle and `overlay` guide
+/// visibility: true for the pending (editable) crop, false for the applied
+/// (read-only) crop.
 fn draw_crop_overlay(
     ui: &mut egui::Ui,
     img_rect: egui::Rect,
     crop_screen: egui::Rect,
     interactive: bool,
+    overlay: CropOverlay,
 ) {
     let painter = ui.painter();
     let dim = egui::Color32::from_black_alpha(if interactive { 120 } else { 80 });
@@ -1492,33 +3632,330 @@ fn draw_crop_overlay(
             );
         }
 
-        // Rule of thirds
-        let third_stroke = egui::Stroke::new(0.5, egui::Color32::from_white_alpha(120));
-        for i in 1..3 {
-            let t = i as f32 / 3.0;
-            let x = crop_screen.left() + t * crop_screen.width();
-            let y = crop_screen.top() + t * crop_screen.height();
+        draw_composition_guide(painter, crop_screen, overlay);
+    }
+}
+
+/// Draws a composition guide inside the crop rect.
+fn draw_composition_guide(painter: &egui::Painter, r: egui::Rect, overlay: CropOverlay) {
+    const PHI_INV: f32 = 0.618_034;
+    let stroke = egui::Stroke::new(0.5, egui::Color32::from_white_alpha(120));
+    let grid = |fractions: &[f32]| {
+        for &t in fractions {
+            let x = r.left() + t * r.width();
+            let y = r.top() + t * r.height();
+            painter.line_segment([egui::pos2(x, r.top()), egui::pos2(x, r.bottom())], stroke);
+            painter.line_segment([egui::pos2(r.left(), y), egui::pos2(r.right(), y)], stroke);
+        }
+    };
+    match overlay {
+        CropOverlay::Thirds => grid(&[1.0 / 3.0, 2.0 / 3.0]),
+        CropOverlay::GoldenRatio => grid(&[1.0 - PHI_INV, PHI_INV]),
+        CropOverlay::Diagonals => {
+            painter.line_segment([r.left_top(), r.right_bottom()], stroke);
+            painter.line_segment([r.right_top(), r.left_bottom()], stroke);
+            // 45° lines from each corner to the opposite long edge
+            let s = r.width().min(r.height());
+            painter.line_segment([r.left_top(), r.left_top() + egui::vec2(s, s)], stroke);
+            painter.line_segment([r.right_top(), r.right_top() + egui::vec2(-s, s)], stroke);
             painter.line_segment(
-                [
-                    egui::pos2(x, crop_screen.top()),
-                    egui::pos2(x, crop_screen.bottom()),
-                ],
-                third_stroke,
+                [r.right_bottom(), r.right_bottom() + egui::vec2(-s, -s)],
+                stroke,
             );
             painter.line_segment(
-                [
-                    egui::pos2(crop_screen.left(), y),
-                    egui::pos2(crop_screen.right(), y),
-                ],
-                third_stroke,
+                [r.left_bottom(), r.left_bottom() + egui::vec2(s, -s)],
+                stroke,
             );
         }
+        CropOverlay::GoldenSpiral => {
+            // Peel golden sections off the left, top, right and bottom in
+            // turn, drawing a quarter ellipse through each.
+            let mut rest = r;
+            let mut side = if r.height() > r.width() { 1 } else { 0 };
+            for _ in 0..10 {
+                let (piece, center) = match side {
+                    0 => {
+                        let split = rest.left() + rest.width() * PHI_INV;
+                        let piece = egui::Rect::from_min_max(
+                            rest.left_top(),
+                            egui::pos2(split, rest.bottom()),
+                        );
+                        rest.min.x = split;
+                        (piece, piece.right_bottom())
+                    }
+                    1 => {
+                        let split = rest.top() + rest.height() * PHI_INV;
+                        let piece = egui::Rect::from_min_max(
+                            rest.left_top(),
+                            egui::pos2(rest.right(), split),
+                        );
+                        rest.min.y = split;
+                        (piece, piece.left_bottom())
+                    }
+                    2 => {
+                        let split = rest.right() - rest.width() * PHI_INV;
+                        let piece = egui::Rect::from_min_max(
+                            egui::pos2(split, rest.top()),
+                            rest.right_bottom(),
+                        );
+                        rest.max.x = split;
+                        (piece, piece.left_top())
+                    }
+                    _ => {
+                        let split = rest.bottom() - rest.height() * PHI_INV;
+                        let piece = egui::Rect::from_min_max(
+                            egui::pos2(rest.left(), split),
+                            rest.right_bottom(),
+                        );
+                        rest.max.y = split;
+                        (piece, piece.right_top())
+                    }
+                };
+                if piece.width() < 1.0 || piece.height() < 1.0 {
+                    break;
+                }
+                let start = std::f32::consts::PI * (1.0 + side as f32 * 0.5);
+                let points = (0..=16)
+                    .map(|i| {
+                        let a = start + std::f32::consts::FRAC_PI_2 * i as f32 / 16.0;
+                        center + egui::vec2(piece.width() * a.cos(), piece.height() * a.sin())
+                    })
+                    .collect();
+                painter.add(egui::Shape::line(points, stroke));
+                painter.rect_stroke(piece, 0.0, stroke, egui::StrokeKind::Middle);
+                side = (side + 1) % 4;
+            }
+        }
+    }
+}
+
+/// Screen positions of the draggable handles for a mask.
+///
+/// Linear: start, end, midpoint. Radial: center, x-radius edge, y-radius edge.
+/// Brush masks have no handles.
+fn local_handles(mask: &MaskShape, img_rect: egui::Rect) -> Vec<egui::Pos2> {
+    let long_edge = img_rect.width().max(img_rect.height());
+    match mask {
+        MaskShape::Linear { start, end } => {
+            let a = norm_point_to_screen(*start, img_rect);
+            let b = norm_point_to_screen(*end, img_rect);
+            vec![a, b, a.lerp(b, 0.5)]
+        }
+        MaskShape::Radial {
+            center,
+            radius_x,
+            radius_y,
+            angle,
+            ..
+        } => {
+            let c = norm_point_to_screen(*center, img_rect);
+            let (sin, cos) = angle.to_radians().sin_cos();
+            vec![
+                c,
+                c + egui::vec2(cos, sin) * (radius_x * long_edge),
+                c + egui::vec2(-sin, cos) * (radius_y * long_edge),
+            ]
+        }
+        MaskShape::Brush { .. } => Vec::new(),
+    }
+}
+
+/// Moves handle `idx` of `mask` (see `local_handles`) to the pointer.
+fn drag_local_handle(
+    mask: &mut MaskShape,
+    idx: usize,
+    pos: egui::Pos2,
+    delta: egui::Vec2,
+    img_rect: egui::Rect,
+) {
+    let long_edge = img_rect.width().max(img_rect.height());
+    let point = screen_to_norm_point(pos, img_rect);
+    match mask {
+        MaskShape::Linear { start, end } => match idx {
+            0 => *start = point,
+            1 => *end = point,
+            _ => {
+                let dx = delta.x / img_rect.width();
+                let dy = delta.y / img_rect.height();
+                for p in [start, end] {
+                    p.x += dx;
+                    p.y += dy;
+                }
+            }
+        },
+        MaskShape::Radial {
+            center,
+            radius_x,
+            radius_y,
+            angle,
+            ..
+        } => {
+            let rel = pos - norm_point_to_screen(*center, img_rect);
+            match idx {
+                0 => *center = point,
+                1 => {
+                    *radius_x = (rel.length() / long_edge).max(0.005);
+                    *angle = rel.y.atan2(rel.x).to_degrees();
+                }
+                _ => {
+                    *radius_y = (rel.length() / long_edge).max(0.005);
+                    *angle = rel.y.atan2(rel.x).to_degrees() - 90.0;
+                }
+            }
+        }
+        MaskShape::Brush { .. } => {}
+    }
+}
+
+/// Draws mask outlines and handles for the selected local adjustment.
+fn draw_local_overlay(
+    ui: &mut egui::Ui,
+    img_rect: egui::Rect,
+    mask: &MaskShape,
+    handles: &[egui::Pos2],
+) {
+    let painter = ui.painter_at(img_rect);
+    let stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+    let faint = egui::Stroke::new(1.0, egui::Color32::from_white_alpha(140));
+    let long_edge = img_rect.width().max(img_rect.height());
+
+    match mask {
+        MaskShape::Linear { .. } => {
+            // Lines through start (full effect) and end (no effect), perpendicular
+            // to the gradient direction.
+            let (a, b) = (handles[0], handles[1]);
+            let dir = (b - a).normalized();
+            let perp = egui::vec2(-dir.y, dir.x) * (img_rect.width() + img_rect.height());
+            painter.line_segment([a - perp, a + perp], stroke);
+            painter.line_segment([b - perp, b + perp], faint);
+            painter.line_segment([a, b], faint);
+        }
+        MaskShape::Radial {
+            radius_x,
+            radius_y,
+            angle,
+            feather,
+            ..
+        } => {
+            let c = handles[0];
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let ellipse = |scale: f32| -> Vec<egui::Pos2> {
+                (0..=48)
+                    .map(|i| {
+                        let t = i as f32 / 48.0 * std::f32::consts::TAU;
+                        let x = t.cos() * radius_x * long_edge * scale;
+                        let y = t.sin() * radius_y * long_edge * scale;
+                        c + egui::vec2(x * cos - y * sin, x * sin + y * cos)
+                    })
+                    .collect()
+            };
+            painter.line(ellipse(1.0), stroke);
+            if *feather > 0.001 {
+                painter.line(ellipse(1.0 - feather), faint);
+            }
+        }
+        MaskShape::Brush { .. } => {}
+    }
+
+    for h in handles {
+        painter.circle_filled(*h, HANDLE_SIZE * 0.5, egui::Color32::WHITE);
+        painter.circle_stroke(
+            *h,
+            HANDLE_SIZE * 0.5,
+            egui::Stroke::new(1.0, egui::Color32::BLACK),
+        );
+    }
+}
+
+/// Maps a normalized source-image point to the screen through the current geometry.
+fn source_point_to_screen(
+    state: &EditState,
+    src_size: [u32; 2],
+    p: NormPoint,
+    img_rect: egui::Rect,
+) -> egui::Pos2 {
+    let [w, h] = src_size;
+    let (out_w, out_h) = transform::output_dims(state, w, h);
+    let (x, y) = transform::source_to_output(state, w, h, (p.x * w as f32, p.y * h as f32));
+    img_rect.min
+        + egui::vec2(
+            x / out_w as f32 * img_rect.width(),
+            y / out_h as f32 * img_rect.height(),
+        )
+}
+
+/// Maps a screen position on the processed image back to a normalized source-image point.
+fn screen_to_source_point(
+    state: &EditState,
+    src_size: [u32; 2],
+    pos: egui::Pos2,
+    img_rect: egui::Rect,
+) -> NormPoint {
+    let [w, h] = src_size;
+    let (out_w, out_h) = transform::output_dims(state, w, h);
+    let rel = pos - img_rect.min;
+    let (x, y) = transform::output_to_source(
+        state,
+        w,
+        h,
+        (
+            rel.x / img_rect.width() * out_w as f32,
+            rel.y / img_rect.height() * out_h as f32,
+        ),
+    );
+    NormPoint {
+        x: (x / w as f32).clamp(0.0, 1.0),
+        y: (y / h as f32).clamp(0.0, 1.0),
+    }
+}
+
+/// Draws each spot as a solid circle with a dashed source circle linked to it.
+fn draw_spot_overlay(
+    ui: &mut egui::Ui,
+    img_rect: egui::Rect,
+    placed: &[(egui::Pos2, egui::Pos2, f32)],
+    selected: Option<usize>,
+) {
+    let painter = ui.painter_at(img_rect);
+    for (i, &(center, source, r)) in placed.iter().enumerate() {
+        let color = if selected == Some(i) {
+            egui::Color32::from_rgb(255, 210, 80)
+        } else {
+            egui::Color32::WHITE
+        };
+        let shadow = egui::Stroke::new(3.0, egui::Color32::from_black_alpha(120));
+        painter.circle_stroke(center, r, shadow);
+        painter.circle_stroke(center, r, egui::Stroke::new(1.5, color));
+        if selected == Some(i) {
+            let dashed = egui::Shape::dashed_line(
+                &(0..=48)
+                    .map(|k| {
+                        let t = k as f32 / 48.0 * std::f32::consts::TAU;
+                        source + egui::vec2(t.cos(), t.sin()) * r
+                    })
+                    .collect::<Vec<_>>(),
+                egui::Stroke::new(1.5, color),
+                4.0,
+                4.0,
+            );
+            painter.extend(dashed);
+            let dir = (center - source).normalized();
+            if (center - source).length() > 2.0 * r {
+                painter.arrow(
+                    source + dir * r,
+                    center - source - dir * 2.0 * r,
+                    egui::Stroke::new(1.0, color.gamma_multiply(0.7)),
+                );
+            }
+        }
     }
 }
 
 fn show_transform_section(
     ui: &mut egui::Ui,
     state: &mut EditState,
+    preview: Option<&DynamicImage>,
+    guides: &mut UprightGuides,
     needs_process: &mut bool,
     last_slider_change: &mut Option<Instant>,
 ) {
@@ -1623,6 +4060,46 @@ fn show_transform_section(
         }
     });
 
+    // Auto / guided upright
+    ui.horizontal(|ui| {
+        let auto = ui
+            .add_enabled(preview.is_some(), egui::Button::new("Auto"))
+            .on_hover_text("Level the horizon and correct converging lines");
+        if auto.clicked()
+            && let Some(img) = preview
+        {
+            guides.status = Some(match upright::auto(img, state) {
+                Some(suggestion) => {
+                    state.straighten = suggestion.straighten;
+                    state.keystone = suggestion.keystone;
+                    *needs_process = true;
+                    *last_slider_change = None;
+                    format!("Aligned to {} detected lines", suggestion.lines)
+                }
+                None => "No straight lines found".to_string(),
+            });
+        }
+        if ui
+            .selectable_label(guides.active, "Guided")
+            .on_hover_text("Draw lines that should be vertical or horizontal")
+            .clicked()
+        {
+            guides.active = !guides.active;
+            guides.lines.clear();
+            guides.drag_start = None;
+            guides.status = None;
+        }
+        if guides.active && !guides.lines.is_empty() && ui.small_button("Clear lines").clicked() {
+            guides.lines.clear();
+        }
+    });
+    if guides.active {
+        ui.weak("Drag along edges that should be vertical or horizontal.");
+    }
+    if let Some(status) = &guides.status {
+        ui.weak(status);
+    }
+
     // Reset all
     let dirty = state.rotate != 0
         || state.flip_h
@@ -1650,6 +4127,7 @@ fn show_color_section(
     state: &mut EditState,
     needs_process: &mut bool,
     last_slider_change: &mut Option<Instant>,
+    target_adjust: &mut Option<TargetProperty>,
 ) {
     ui.label(egui::RichText::new("Color").strong());
     ui.add_space(4.0);
@@ -1691,44 +4169,508 @@ fn show_color_section(
         }
     });
 
-    ui.horizontal(|ui| {
-        ui.label("Highlights");
-        let resp = ui.add(
-            egui::Slider::new(&mut state.highlights, -1.0_f32..=1.0_f32)
-                .fixed_decimals(2)
-                .clamping(egui::SliderClamping::Always),
-        );
-        if resp.changed() {
-            *needs_process = true;
-            *last_slider_change = Some(Instant::now());
-        }
-        if state.highlights != 0.0 && ui.small_button("↺").clicked() {
-            state.highlights = 0.0;
+    ui.horizontal(|ui| {
+        ui.label("Highlights");
+        let resp = ui.add(
+            egui::Slider::new(&mut state.highlights, -1.0_f32..=1.0_f32)
+                .fixed_decimals(2)
+                .clamping(egui::SliderClamping::Always),
+        );
+        if resp.changed() {
+            *needs_process = true;
+            *last_slider_change = Some(Instant::now());
+        }
+        if state.highlights != 0.0 && ui.small_button("↺").clicked() {
+            state.highlights = 0.0;
+            *needs_process = true;
+            *last_slider_change = None;
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Shadows");
+        let resp = ui.add(
+            egui::Slider::new(&mut state.shadows, -1.0_f32..=1.0_f32)
+                .fixed_decimals(2)
+                .clamping(egui::SliderClamping::Always),
+        );
+        if resp.changed() {
+            *needs_process = true;
+            *last_slider_change = Some(Instant::now());
+        }
+        if state.shadows != 0.0 && ui.small_button("↺").clicked() {
+            state.shadows = 0.0;
+            *needs_process = true;
+            *last_slider_change = None;
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Temperature");
+        let resp = ui.add(
+            egui::Slider::new(&mut state.temperature, -1.0_f32..=1.0_f32)
+                .fixed_decimals(2)
+                .clamping(egui::SliderClamping::Always),
+        );
+        if resp.changed() {
+            *needs_process = true;
+            *last_slider_change = Some(Instant::now());
+        }
+        if state.temperature != 0.0 && ui.small_button("↺").clicked() {
+            state.temperature = 0.0;
+            *needs_process = true;
+            *last_slider_change = None;
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Saturation");
+        let resp = ui.add(
+            egui::Slider::new(&mut state.saturation, -1.0_f32..=1.0_f32)
+                .fixed_decimals(2)
+                .clamping(egui::SliderClamping::Always),
+        );
+        if resp.changed() {
+            *needs_process = true;
+            *last_slider_change = Some(Instant::now());
+        }
+        if state.saturation != 0.0 && ui.small_button("↺").clicked() {
+            state.saturation = 0.0;
+            *needs_process = true;
+            *last_slider_change = None;
+        }
+    });
+
+    adjustment_slider(
+        ui,
+        "Vibrance",
+        &mut state.vibrance,
+        -1.0..=1.0,
+        needs_process,
+        last_slider_change,
+    );
+    adjustment_slider(
+        ui,
+        "Texture",
+        &mut state.texture,
+        -1.0..=1.0,
+        needs_process,
+        last_slider_change,
+    );
+    adjustment_slider(
+        ui,
+        "Clarity",
+        &mut state.clarity,
+        -1.0..=1.0,
+        needs_process,
+        last_slider_change,
+    );
+    adjustment_slider(
+        ui,
+        "Dehaze",
+        &mut state.dehaze,
+        -1.0..=1.0,
+        needs_process,
+        last_slider_change,
+    );
+
+    ui.horizontal(|ui| {
+        ui.label("Hue Shift");
+        if state.hue_shift != 0.0 && ui.small_button("↺").clicked() {
+            state.hue_shift = 0.0;
+            *needs_process = true;
+            *last_slider_change = None;
+        }
+    });
+    if hue_slider(ui, &mut state.hue_shift, 0.0, 180.0) {
+        *needs_process = true;
+        *last_slider_change = Some(Instant::now());
+    }
+
+    ui.add_space(6.0);
+    ui.label(egui::RichText::new("Selective Color").strong());
+    ui.horizontal(|ui| {
+        ui.label("Drag on image:")
+            .on_hover_text("Drag up or down on a color in the image to adjust its band");
+        for property in TargetProperty::ALL {
+            let selected = *target_adjust == Some(property);
+            if ui.selectable_label(selected, property.label()).clicked() {
+                *target_adjust = if selected { None } else { Some(property) };
+            }
+        }
+    });
+    for (idx, label) in HUE_LABELS.iter().enumerate() {
+        let adj = &mut state.selective_color[idx];
+        let base = selective_base_color(idx);
+        let bg = selective_bg_color(base);
+        let label_color = selective_label_color(base);
+        egui::Frame::group(ui.style()).fill(bg).show(ui, |ui| {
+            egui::CollapsingHeader::new(egui::RichText::new(*label).strong().color(label_color))
+                .show(ui, |ui| {
+                    let (center, _) = color::band_hue(idx, adj);
+                    if hue_slider(ui, &mut adj.hue, center, 45.0) {
+                        *needs_process = true;
+                        *last_slider_change = Some(Instant::now());
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Saturation");
+                        let resp = ui.add(
+                            egui::Slider::new(&mut adj.saturation, -1.0_f32..=1.0_f32)
+                                .fixed_decimals(2)
+                                .clamping(egui::SliderClamping::Always),
+                        );
+                        if resp.changed() {
+                            *needs_process = true;
+                            *last_slider_change = Some(Instant::now());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Lightness");
+                        let resp = ui.add(
+                            egui::Slider::new(&mut adj.lightness, -1.0_f32..=1.0_f32)
+                                .fixed_decimals(2)
+                                .clamping(egui::SliderClamping::Always),
+                        );
+                        if resp.changed() {
+                            *needs_process = true;
+                            *last_slider_change = Some(Instant::now());
+                        }
+                    });
+                    egui::CollapsingHeader::new("Range")
+                        .id_salt(("selective_range", idx))
+                        .show(ui, |ui| {
+                            if show_band_range(ui, adj) {
+                                *needs_process = true;
+                                *last_slider_change = Some(Instant::now());
+                            }
+                        });
+                });
+        });
+        ui.add_space(4.0);
+    }
+
+    ui.add_space(6.0);
+    ui.label(egui::RichText::new("Color Grading").strong());
+    ui.columns(3, |columns| {
+        let grading = &mut state.color_grading;
+        let wheels = [
+            ("Shadows", &mut grading.shadows),
+            ("Midtones", &mut grading.midtones),
+            ("Highlights", &mut grading.highlights),
+        ];
+        for (ui, (label, wheel)) in columns.iter_mut().zip(wheels) {
+            ui.vertical_centered(|ui| {
+                ui.label(label);
+                if grade_wheel(ui, wheel) {
+                    *needs_process = true;
+                    *last_slider_change = Some(Instant::now());
+                }
+                let resp = ui
+                    .add(
+                        egui::Slider::new(&mut wheel.luminance, -1.0_f32..=1.0_f32)
+                            .show_value(false)
+                            .clamping(egui::SliderClamping::Always),
+                    )
+                    .on_hover_text(format!("Luminance {:.2}", wheel.luminance));
+                if resp.changed() {
+                    *needs_process = true;
+                    *last_slider_change = Some(Instant::now());
+                }
+            });
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Balance");
+        let resp = ui.add(
+            egui::Slider::new(&mut state.color_grading.balance, -1.0_f32..=1.0_f32)
+                .fixed_decimals(2)
+                .clamping(egui::SliderClamping::Always),
+        );
+        if resp.changed() {
+            *needs_process = true;
+            *last_slider_change = Some(Instant::now());
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Blending");
+        let resp = ui.add(
+            egui::Slider::new(&mut state.color_grading.blending, 0.0_f32..=1.0_f32)
+                .fixed_decimals(2)
+                .clamping(egui::SliderClamping::Always),
+        );
+        if resp.changed() {
+            *needs_process = true;
+            *last_slider_change = Some(Instant::now());
+        }
+    });
+
+    let selective_dirty = state
+        .selective_color
+        .iter()
+        .any(|adj| *adj != HslAdjust::default());
+    let color_dirty = state.exposure != 0.0
+        || state.contrast != 0.0
+        || state.highlights != 0.0
+        || state.shadows != 0.0
+        || state.temperature != 0.0
+        || state.saturation != 0.0
+        || state.vibrance != 0.0
+        || state.texture != 0.0
+        || state.clarity != 0.0
+        || state.dehaze != 0.0
+        || state.hue_shift != 0.0
+        || selective_dirty
+        || state.color_grading != ColorGrading::default();
+    if color_dirty {
+        ui.add_space(4.0);
+        if ui.small_button("Reset color").clicked() {
+            state.exposure = 0.0;
+            state.contrast = 0.0;
+            state.highlights = 0.0;
+            state.shadows = 0.0;
+            state.temperature = 0.0;
+            state.saturation = 0.0;
+            state.vibrance = 0.0;
+            state.texture = 0.0;
+            state.clarity = 0.0;
+            state.dehaze = 0.0;
+            state.hue_shift = 0.0;
+            state.selective_color = Default::default();
+            state.color_grading = ColorGrading::default();
+            *needs_process = true;
+            *last_slider_change = None;
+        }
+    }
+}
+
+/// Band center, width and the luminance/saturation qualifiers; returns true
+/// when any of them changed.
+fn show_band_range(ui: &mut egui::Ui, adj: &mut HslAdjust) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Center");
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut adj.center_shift, -30.0_f32..=30.0_f32)
+                    .suffix("°")
+                    .fixed_decimals(0)
+                    .clamping(egui::SliderClamping::Always),
+            )
+            .changed();
+    });
+    ui.horizontal(|ui| {
+        ui.label("Width");
+        changed |= ui
+            .add(
+                egui::Slider::new(
+                    &mut adj.width,
+                    color::MIN_BAND_WIDTH_DEG..=color::MAX_BAND_WIDTH_DEG,
+                )
+                .suffix("°")
+                .fixed_decimals(0)
+                .clamping(egui::SliderClamping::Always),
+            )
+            .changed();
+    });
+    for (label, range) in [
+        ("Luminance", &mut adj.luminance_range),
+        ("Saturation", &mut adj.saturation_range),
+    ] {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            let [lo, hi] = range;
+            changed |= ui
+                .add(
+                    egui::DragValue::new(lo)
+                        .range(0.0..=*hi)
+                        .speed(0.005)
+                        .fixed_decimals(2),
+                )
+                .changed();
+            ui.label("–");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(hi)
+                        .range(*lo..=1.0)
+                        .speed(0.005)
+                        .fixed_decimals(2),
+                )
+                .changed();
+        });
+    }
+    changed
+}
+
+fn show_black_white_section(
+    ui: &mut egui::Ui,
+    state: &mut EditState,
+    needs_process: &mut bool,
+    last_slider_change: &mut Option<Instant>,
+) {
+    ui.label(egui::RichText::new("Black & White").strong());
+    ui.add_space(4.0);
+
+    let mono = &mut state.monochrome;
+    if ui
+        .checkbox(&mut mono.enabled, "Convert to black & white")
+        .changed()
+    {
+        *needs_process = true;
+        *last_slider_change = None;
+    }
+    if !mono.enabled {
+        return;
+    }
+
+    for (idx, label) in HUE_LABELS.iter().enumerate() {
+        ui.horizontal(|ui| {
+            let (swatch, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+            ui.painter().circle_filled(
+                swatch.center(),
+                5.0,
+                hue_to_rgb(color::SELECTIVE_CENTERS_DEG[idx]),
+            );
+            ui.label(*label);
+            let resp = ui.add(
+                egui::Slider::new(&mut mono.mix[idx], -1.0_f32..=1.0_f32)
+                    .fixed_decimals(2)
+                    .clamping(egui::SliderClamping::Always),
+            );
+            if resp.changed() {
+                *needs_process = true;
+                *last_slider_change = Some(Instant::now());
+            }
+        });
+    }
+
+    ui.add_space(6.0);
+    ui.label("Toning");
+    if hue_slider(ui, &mut mono.tone_hue, 0.0, 180.0) {
+        *needs_process = true;
+        *last_slider_change = Some(Instant::now());
+    }
+    ui.horizontal(|ui| {
+        ui.label("Amount");
+        let resp = ui.add(
+            egui::Slider::new(&mut mono.tone_amount, 0.0_f32..=1.0_f32)
+                .fixed_decimals(2)
+                .clamping(egui::SliderClamping::Always),
+        );
+        if resp.changed() {
+            *needs_process = true;
+            *last_slider_change = Some(Instant::now());
+        }
+    });
+
+    if *mono != Monochrome::default() {
+        ui.add_space(4.0);
+        if ui.small_button("Reset black & white").clicked() {
+            *mono = Monochrome::default();
+            *needs_process = true;
+            *last_slider_change = None;
+        }
+    }
+}
+
+fn show_grain_section(
+    ui: &mut egui::Ui,
+    state: &mut EditState,
+    needs_process: &mut bool,
+    last_slider_change: &mut Option<Instant>,
+) {
+    ui.label(egui::RichText::new("Grain").strong());
+    ui.add_space(4.0);
+
+    let grain = &mut state.grain;
+    for (label, value) in [
+        ("Amount", &mut grain.amount),
+        ("Size", &mut grain.size),
+        ("Roughness", &mut grain.roughness),
+    ] {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            let resp = ui.add(
+                egui::Slider::new(value, 0.0_f32..=1.0_f32)
+                    .fixed_decimals(2)
+                    .clamping(egui::SliderClamping::Always),
+            );
+            if resp.changed() {
+                *needs_process = true;
+                *last_slider_change = Some(Instant::now());
+            }
+        });
+    }
+
+    if *grain != Grain::default() {
+        ui.add_space(4.0);
+        if ui.small_button("Reset grain").clicked() {
+            *grain = Grain::default();
             *needs_process = true;
             *last_slider_change = None;
         }
-    });
+    }
+}
 
-    ui.horizontal(|ui| {
-        ui.label("Shadows");
-        let resp = ui.add(
-            egui::Slider::new(&mut state.shadows, -1.0_f32..=1.0_f32)
-                .fixed_decimals(2)
-                .clamping(egui::SliderClamping::Always),
-        );
-        if resp.changed() {
-            *needs_process = true;
-            *last_slider_change = Some(Instant::now());
-        }
-        if state.shadows != 0.0 && ui.small_button("↺").clicked() {
-            state.shadows = 0.0;
+fn show_vignette_section(
+    ui: &mut egui::Ui,
+    state: &mut EditState,
+    needs_process: &mut bool,
+    last_slider_change: &mut Option<Instant>,
+) {
+    ui.label(egui::RichText::new("Vignette").strong());
+    ui.add_space(4.0);
+
+    let vignette = &mut state.vignette;
+    for (label, value, range) in [
+        ("Amount", &mut vignette.amount, -1.0_f32..=1.0_f32),
+        ("Midpoint", &mut vignette.midpoint, 0.0_f32..=1.0_f32),
+        ("Roundness", &mut vignette.roundness, -1.0_f32..=1.0_f32),
+        ("Feather", &mut vignette.feather, 0.0_f32..=1.0_f32),
+    ] {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            let resp = ui.add(
+                egui::Slider::new(value, range)
+                    .fixed_decimals(2)
+                    .clamping(egui::SliderClamping::Always),
+            );
+            if resp.changed() {
+                *needs_process = true;
+                *last_slider_change = Some(Instant::now());
+            }
+        });
+    }
+    if ui
+        .checkbox(&mut vignette.uncropped, "Follow uncropped frame")
+        .on_hover_text("Fit the vignette to the whole image instead of the crop")
+        .changed()
+    {
+        *needs_process = true;
+        *last_slider_change = None;
+    }
+
+    if *vignette != Vignette::default() {
+        ui.add_space(4.0);
+        if ui.small_button("Reset vignette").clicked() {
+            *vignette = Vignette::default();
             *needs_process = true;
             *last_slider_change = None;
         }
-    });
+    }
+}
+
+fn show_sharpening_section(
+    ui: &mut egui::Ui,
+    state: &mut EditState,
+    needs_process: &mut bool,
+    last_slider_change: &mut Option<Instant>,
+    mask_view: &mut bool,
+) {
+    ui.label(egui::RichText::new("Sharpening").strong());
+    ui.add_space(4.0);
 
     ui.horizontal(|ui| {
-        ui.label("Sharpness");
+        ui.label("Amount");
         let resp = ui.add(
             egui::Slider::new(&mut state.sharpness, 0.0_f32..=2.0_f32)
                 .fixed_decimals(2)
@@ -1746,201 +4688,72 @@ fn show_color_section(
     });
 
     ui.horizontal(|ui| {
-        ui.label("Temperature");
+        ui.label("Radius");
         let resp = ui.add(
-            egui::Slider::new(&mut state.temperature, -1.0_f32..=1.0_f32)
-                .fixed_decimals(2)
+            egui::Slider::new(&mut state.sharpen_radius, 0.5_f32..=3.0_f32)
+                .suffix(" px")
+                .fixed_decimals(1)
                 .clamping(egui::SliderClamping::Always),
         );
         if resp.changed() {
             *needs_process = true;
             *last_slider_change = Some(Instant::now());
         }
-        if state.temperature != 0.0 && ui.small_button("↺").clicked() {
-            state.temperature = 0.0;
+        if state.sharpen_radius != 1.5 && ui.small_button("↺").clicked() {
+            state.sharpen_radius = 1.5;
             *needs_process = true;
             *last_slider_change = None;
         }
     });
 
     ui.horizontal(|ui| {
-        ui.label("Saturation");
+        ui.label("Threshold");
         let resp = ui.add(
-            egui::Slider::new(&mut state.saturation, -1.0_f32..=1.0_f32)
-                .fixed_decimals(2)
+            egui::Slider::new(&mut state.sharpen_threshold, 0.0_f32..=0.2_f32)
+                .fixed_decimals(3)
                 .clamping(egui::SliderClamping::Always),
         );
         if resp.changed() {
             *needs_process = true;
             *last_slider_change = Some(Instant::now());
         }
-        if state.saturation != 0.0 && ui.small_button("↺").clicked() {
-            state.saturation = 0.0;
+        if state.sharpen_threshold != 0.0 && ui.small_button("↺").clicked() {
+            state.sharpen_threshold = 0.0;
             *needs_process = true;
             *last_slider_change = None;
         }
     });
 
     ui.horizontal(|ui| {
-        ui.label("Hue Shift");
-        if state.hue_shift != 0.0 && ui.small_button("↺").clicked() {
-            state.hue_shift = 0.0;
-            *needs_process = true;
-            *last_slider_change = None;
-        }
-    });
-    if hue_slider(ui, &mut state.hue_shift, 0.0, 180.0) {
-        *needs_process = true;
-        *last_slider_change = Some(Instant::now());
-    }
-
-    ui.add_space(6.0);
-    ui.label(egui::RichText::new("Selective Color").strong());
-    const HUE_LABELS: [&str; 8] = [
-        "Red", "Orange", "Yellow", "Green", "Cyan", "Blue", "Purple", "Pink",
-    ];
-    for (idx, label) in HUE_LABELS.iter().enumerate() {
-        let adj = &mut state.selective_color[idx];
-        let base = selective_base_color(idx);
-        let bg = selective_bg_color(base);
-        let label_color = selective_label_color(base);
-        egui::Frame::group(ui.style()).fill(bg).show(ui, |ui| {
-            egui::CollapsingHeader::new(egui::RichText::new(*label).strong().color(label_color))
-                .show(ui, |ui| {
-                    if hue_slider(ui, &mut adj.hue, SELECTIVE_CENTER_HUES[idx], 45.0) {
-                        *needs_process = true;
-                        *last_slider_change = Some(Instant::now());
-                    }
-                    ui.horizontal(|ui| {
-                        ui.label("Saturation");
-                        let resp = ui.add(
-                            egui::Slider::new(&mut adj.saturation, -1.0_f32..=1.0_f32)
-                                .fixed_decimals(2)
-                                .clamping(egui::SliderClamping::Always),
-                        );
-                        if resp.changed() {
-                            *needs_process = true;
-                            *last_slider_change = Some(Instant::now());
-                        }
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Lightness");
-                        let resp = ui.add(
-                            egui::Slider::new(&mut adj.lightness, -1.0_f32..=1.0_f32)
-                                .fixed_decimals(2)
-                                .clamping(egui::SliderClamping::Always),
-                        );
-                        if resp.changed() {
-                            *needs_process = true;
-                            *last_slider_change = Some(Instant::now());
-                        }
-                    });
-                });
-        });
-        ui.add_space(4.0);
-    }
-
-    ui.add_space(6.0);
-    ui.label(egui::RichText::new("Graduated Filter").strong());
-    let mut grad_enabled = state.graduated_filter.is_some();
-    if ui.checkbox(&mut grad_enabled, "Enable").changed() {
-        if grad_enabled {
-            if state.graduated_filter.is_none() {
-                state.graduated_filter = Some(GradFilter {
-                    top: 0.0,
-                    bottom: 0.6,
-                    exposure: -0.7,
-                });
-            }
-        } else {
-            state.graduated_filter = None;
-        }
-        *needs_process = true;
-        *last_slider_change = None;
-    }
-
-    if let Some(ref mut grad) = state.graduated_filter {
-        ui.horizontal(|ui| {
-            ui.label("Top");
-            let resp = ui.add(
-                egui::Slider::new(&mut grad.top, 0.0_f32..=1.0_f32)
-                    .fixed_decimals(2)
-                    .clamping(egui::SliderClamping::Always),
-            );
-            if resp.changed() {
-                *needs_process = true;
-                *last_slider_change = Some(Instant::now());
-            }
-        });
-        ui.horizontal(|ui| {
-            ui.label("Bottom");
-            let resp = ui.add(
-                egui::Slider::new(&mut grad.bottom, 0.0_f32..=1.0_f32)
+        ui.label("Masking");
+        let resp = ui
+            .add(
+                egui::Slider::new(&mut state.sharpen_masking, 0.0_f32..=1.0_f32)
                     .fixed_decimals(2)
                     .clamping(egui::SliderClamping::Always),
-            );
-            if resp.changed() {
-                *needs_process = true;
-                *last_slider_change = Some(Instant::now());
-            }
-        });
-        if grad.bottom < grad.top + 0.01 {
-            grad.bottom = (grad.top + 0.01).min(1.0);
+            )
+            .on_hover_text("Hold Alt while dragging to show the edge mask");
+        if resp.changed() {
+            *needs_process = true;
+            *last_slider_change = Some(Instant::now());
         }
-        if grad.top > grad.bottom - 0.01 {
-            grad.top = (grad.bottom - 0.01).max(0.0);
+        // Alt-drag previews the mask: white areas get sharpened.
+        let show_mask = resp.dragged() && ui.input(|i| i.modifiers.alt);
+        if show_mask != *mask_view {
+            *mask_view = show_mask;
+            *needs_process = true;
         }
-        ui.horizontal(|ui| {
-            ui.label("Exposure");
-            let resp = ui.add(
-                egui::Slider::new(&mut grad.exposure, -3.0_f32..=3.0_f32)
-                    .suffix(" EV")
-                    .fixed_decimals(2)
-                    .clamping(egui::SliderClamping::Always),
-            );
-            if resp.changed() {
-                *needs_process = true;
-                *last_slider_change = Some(Instant::now());
-            }
-            if grad.exposure != 0.0 && ui.small_button("↺").clicked() {
-                grad.exposure = 0.0;
-                *needs_process = true;
-                *last_slider_change = None;
-            }
-        });
-    }
-
-    let selective_dirty = state.selective_color.iter().any(|adj| {
-        adj.hue.abs() > 0.001 || adj.saturation.abs() > 0.001 || adj.lightness.abs() > 0.001
-    });
-    let color_dirty = state.exposure != 0.0
-        || state.contrast != 0.0
-        || state.highlights != 0.0
-        || state.shadows != 0.0
-        || state.temperature != 0.0
-        || state.saturation != 0.0
-        || state.hue_shift != 0.0
-        || selective_dirty
-        || state.graduated_filter.is_some();
-    if color_dirty {
-        ui.add_space(4.0);
-        if ui.small_button("Reset color").clicked() {
-            state.exposure = 0.0;
-            state.contrast = 0.0;
-            state.highlights = 0.0;
-            state.shadows = 0.0;
-            state.temperature = 0.0;
-            state.saturation = 0.0;
-            state.hue_shift = 0.0;
-            state.selective_color = Default::default();
-            state.graduated_filter = None;
+        if state.sharpen_masking != 0.0 && ui.small_button("↺").clicked() {
+            state.sharpen_masking = 0.0;
             *needs_process = true;
             *last_slider_change = None;
         }
-    }
+    });
 }
 
-const SELECTIVE_CENTER_HUES: [f32; 8] = [0.0, 30.0, 60.0, 120.0, 180.0, 240.0, 285.0, 330.0];
+const HUE_LABELS: [&str; 8] = [
+    "Red", "Orange", "Yellow", "Green", "Cyan", "Blue", "Purple", "Pink",
+];
 
 fn hue_to_rgb(hue_deg: f32) -> egui::Color32 {
     let h = ((hue_deg % 360.0) + 360.0) % 360.0;
@@ -2080,6 +4893,84 @@ fn hue_slider(ui: &mut egui::Ui, value: &mut f32, center_hue: f32, half_range: f
     changed
 }
 
+/// Hue/saturation wheel: the handle's angle is the tint hue and its distance
+/// from the center the tint strength. Double-click to clear the tint.
+fn grade_wheel(ui: &mut egui::Ui, wheel: &mut GradeWheel) -> bool {
+    let size = ui.available_width().clamp(48.0, 96.0);
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click_and_drag());
+    let center = rect.center();
+    let radius = size * 0.5 - 2.0;
+    let old = (wheel.hue, wheel.saturation);
+
+    if response.double_clicked() {
+        wheel.hue = 0.0;
+        wheel.saturation = 0.0;
+    } else if (response.dragged() || response.clicked())
+        && let Some(pos) = response.interact_pointer_pos()
+    {
+        let d = pos - center;
+        // Screen y points down; hue increases counterclockwise
+        wheel.hue = (-d.y).atan2(d.x).to_degrees().rem_euclid(360.0).round();
+        wheel.saturation = (d.length() / radius).clamp(0.0, 1.0);
+        if wheel.saturation < 0.03 {
+            wheel.saturation = 0.0;
+        }
+    }
+    let changed = (wheel.hue, wheel.saturation) != old;
+
+    if !ui.is_rect_visible(rect) {
+        return changed;
+    }
+
+    // Gray center fading out to the hue ring
+    let segments = 48;
+    let mut mesh = egui::Mesh::default();
+    mesh.vertices.push(egui::epaint::Vertex {
+        pos: center,
+        uv: egui::epaint::WHITE_UV,
+        color: egui::Color32::from_gray(128),
+    });
+    for i in 0..=segments {
+        let hue = i as f32 * 360.0 / segments as f32;
+        let angle = hue.to_radians();
+        mesh.vertices.push(egui::epaint::Vertex {
+            pos: center + radius * egui::vec2(angle.cos(), -angle.sin()),
+            uv: egui::epaint::WHITE_UV,
+            color: hue_to_rgb(hue),
+        });
+        if i > 0 {
+            mesh.indices.extend_from_slice(&[0, i, i + 1]);
+        }
+    }
+    ui.painter().add(egui::Shape::mesh(mesh));
+    ui.painter().circle_stroke(
+        center,
+        radius,
+        egui::Stroke::new(1.0, egui::Color32::from_gray(80)),
+    );
+
+    let angle = wheel.hue.to_radians();
+    let handle = center + radius * wheel.saturation * egui::vec2(angle.cos(), -angle.sin());
+    ui.painter()
+        .circle_filled(handle, 5.0, egui::Color32::from_gray(230));
+    ui.painter().circle_stroke(
+        handle,
+        5.0,
+        egui::Stroke::new(1.0, egui::Color32::from_gray(40)),
+    );
+
+    if response.hovered() || response.dragged() {
+        response.on_hover_text(format!(
+            "{:.0}° · {:.0}%",
+            wheel.hue,
+            wheel.saturation * 100.0
+        ));
+    }
+
+    changed
+}
+
 fn selective_base_color(idx: usize) -> egui::Color32 {
     match idx {
         0 => egui::Color32::from_rgb(220, 64, 64),   // Red
@@ -2143,11 +5034,14 @@ fn show_exif(ui: &mut egui::Ui, meta: &crate::metadata::ImageMetadata) {
 mod tests {
     use image::{DynamicImage, ImageBuffer, Rgba};
     use std::path::Path;
+    use std::sync::Arc;
 
     use super::{
-        INTERACTIVE_PREVIEW_MAX, PreviewBackend, bump_requested_generation_for_pending_changes,
-        downscale_for_interactive, edit_state_signature, load_preview_stages_with_hooks,
-        process_preview_with_backend_and_gpu_hook, source_signature,
+        INTERACTIVE_PREVIEW_MAX, MAX_TILE_LEVEL, PreviewBackend, TILE_SIZE,
+        bump_requested_generation_for_pending_changes, downscale_for_interactive,
+        edit_state_signature, load_preview_stages_with_hooks, prepare_tile_frame,
+        process_preview_with_backend_and_gpu_hook, render_tile, source_signature, tile_level,
+        visible_tiles,
     };
     use crate::state::EditState;
 
@@ -2232,6 +5126,118 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn tiles_render_the_pixels_of_the_whole_frame() {
+        let source = DynamicImage::ImageRgba8(ImageBuffer::from_fn(TILE_SIZE + 40, 90, |x, y| {
+            let v = ((x * 3 + y * 5) % 200) as u8;
+            Rgba([v, 200 - v, (y * 2) as u8, 255])
+        }));
+        let mut state = EditState {
+            exposure: 0.3,
+            clarity: 0.5,
+            crop: Some(crate::state::Rect {
+                x: 0.0,
+                y: 0.1,
+                width: 1.0,
+                height: 0.8,
+            }),
+            ..EditState::default()
+        };
+        state.vignette.amount = -0.4;
+        state.grain.amount = 0.4;
+        let display = Arc::new(crate::processing::icc::DisplayTransform::new(None, None));
+        let frame = prepare_tile_frame(&Arc::new(source.clone()), state.clone(), None, display);
+        assert_eq!(frame.level_dims(0), (TILE_SIZE + 40, 72));
+
+        let mut whole = crate::processing::transform::apply(&source, &state).to_rgba8();
+        frame.display.apply(&mut whole);
+        let right = render_tile(&frame, 0, 1, 0).unwrap();
+        let expected = image::imageops::crop_imm(&whole, TILE_SIZE, 0, 40, 72).to_image();
+        assert_eq!(right, expected);
+        assert!(render_tile(&frame, 0, 2, 0).is_none());
+        assert_eq!(
+            render_tile(&frame, 1, 0, 0).unwrap().dimensions(),
+            (276, 36)
+        );
+    }
+
+    #[test]
+    fn coarse_tiles_match_the_downscaled_full_render() {
+        // 16 px blocks for the sharpening, a smooth gradient for dehaze,
+        // whose dark channel does not survive downscaling hard edges
+        let image = |block: u32| {
+            DynamicImage::ImageRgba8(ImageBuffer::from_fn(400, 300, |x, y| {
+                let b = if (x / 16 + y / 16) % 2 == 0 { block } else { 0 };
+                Rgba([(x / 3 + b) as u8, (y / 2 + 60) as u8, (20 + b) as u8, 255])
+            }))
+        };
+        let sharpen = EditState {
+            sharpness: 1.5,
+            sharpen_radius: 3.0,
+            ..EditState::default()
+        };
+        let dehaze = EditState {
+            dehaze: 0.6,
+            ..sharpen.clone()
+        };
+
+        for (source, state) in [(image(40), sharpen), (image(0), dehaze)] {
+            let display = Arc::new(crate::processing::icc::DisplayTransform::new(None, None));
+            let frame = prepare_tile_frame(&Arc::new(source.clone()), state.clone(), None, display);
+            let whole = crate::processing::transform::apply(&source, &state).to_rgba8();
+            let (w, h) = frame.level_dims(1);
+            let mut expected =
+                image::imageops::resize(&whole, w, h, image::imageops::FilterType::Triangle);
+            frame.display.apply(&mut expected);
+
+            let tile = render_tile(&frame, 1, 0, 0).unwrap();
+            assert_eq!(tile.dimensions(), (w, h));
+            let diff: u64 = tile
+                .as_raw()
+                .iter()
+                .zip(expected.as_raw())
+                .map(|(a, b)| a.abs_diff(*b) as u64)
+                .sum();
+            let mean = diff as f32 / tile.as_raw().len() as f32;
+            assert!(mean < 1.5, "mean difference {mean}");
+        }
+    }
+
+    #[test]
+    fn tile_level_keeps_a_tile_pixel_per_screen_pixel() {
+        assert_eq!(tile_level(2.0), 0);
+        assert_eq!(tile_level(1.0), 0);
+        assert_eq!(tile_level(0.6), 0);
+        assert_eq!(tile_level(0.4), 1);
+        assert_eq!(tile_level(0.2), 2);
+        assert_eq!(tile_level(0.0), MAX_TILE_LEVEL);
+    }
+
+    #[test]
+    fn visible_tiles_cover_the_viewport_center_first() {
+        // A 3×2 tile image shown at one screen point per pixel, with the
+        // viewport over the middle of the top row.
+        let size = (TILE_SIZE * 3, TILE_SIZE * 2 - 100);
+        let img_rect = egui::Rect::from_min_size(
+            egui::pos2(0.0, 0.0),
+            egui::vec2(size.0 as f32, size.1 as f32),
+        );
+        let t = TILE_SIZE as f32;
+        let viewport =
+            egui::Rect::from_min_max(egui::pos2(t * 0.75, 10.0), egui::pos2(t * 2.25, t * 0.9));
+
+        let tiles = visible_tiles(size, img_rect, viewport);
+        let mut cells: Vec<_> = tiles.iter().map(|(col, row, _)| (*col, *row)).collect();
+        assert_eq!(cells[0], (1, 0));
+        cells.sort();
+        assert_eq!(cells, vec![(0, 0), (1, 0), (2, 0)]);
+
+        // The last row is clipped to the image
+        let bottom = visible_tiles(size, img_rect, img_rect);
+        let (_, _, rect) = bottom.iter().find(|(_, row, _)| *row == 1).unwrap();
+        assert_eq!(rect.max.y, size.1 as f32);
+    }
+
     #[test]
     fn cpu_mode_uses_cpu_only_when_debug_fallback_enabled() {
         let img = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(3, 3, Rgba([20, 30, 40, 255])));
@@ -2278,6 +5284,14 @@ mod tests {
         assert_ne!(edit_state_signature(&base), edit_state_signature(&changed));
     }
 
+    #[test]
+    fn edit_state_signature_ignores_rating() {
+        let base = EditState::default();
+        let mut rated = base.clone();
+        rated.rating = 4;
+        assert_eq!(edit_state_signature(&base), edit_state_signature(&rated));
+    }
+
     #[test]
     fn source_signature_is_stable_for_same_path() {
         let path = Path::new("/tmp/photograph-nonexistent-raw.raf");
@@ -0,0 +1,638 @@
+use std::path::{Path, PathBuf};
+
+use crate::state::EditState;
+
+/// XMP properties Photograph writes to `.xmp` sidecars and exports.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XmpMetadata {
+    /// Hierarchical keywords (see `keywords::SEPARATOR`).
+    pub keywords: Vec<String>,
+    /// `dc:title`
+    pub title: String,
+    /// `dc:description`
+    pub caption: String,
+    /// `dc:creator`
+    pub creator: String,
+    /// `dc:rights`
+    pub copyright: String,
+    /// `photoshop:DateCreated`, in ISO 8601 form (`2024-05-01T14:03:22`).
+    pub date_created: Option<String>,
+    /// `exif:GPSLatitude` and `exif:GPSLongitude`, in degrees.
+    pub location: Option<[f64; 2]>,
+}
+
+impl XmpMetadata {
+    /// The XMP view of the keywords and info saved in `state`.
+    pub fn from_state(state: &EditState) -> Self {
+        let info = &state.info;
+        Self {
+            keywords: state.keywords.clone(),
+            title: info.title.clone(),
+            caption: info.caption.clone(),
+            creator: info.creator.clone(),
+            copyright: info.copyright.clone(),
+            date_created: info.capture_time.as_deref().map(iso_datetime),
+            location: info.location,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keywords.is_empty()
+            && self.title.is_empty()
+            && self.caption.is_empty()
+            && self.creator.is_empty()
+            && self.copyright.is_empty()
+            && self.date_created.is_none()
+            && self.location.is_none()
+    }
+}
+
+const NS_DC: &str = "http://purl.org/dc/elements/1.1/";
+const NS_LR: &str = "http://ns.adobe.com/lightroom/1.0/";
+const NS_PHOTOSHOP: &str = "http://ns.adobe.com/photoshop/1.0/";
+const NS_EXIF: &str = "http://ns.adobe.com/exif/1.0/";
+/// Properties owned by Photograph; replaced when merging into existing XMP.
+const MANAGED: [&str; 9] = [
+    "dc:subject",
+    "lr:hierarchicalSubject",
+    "dc:title",
+    "dc:description",
+    "dc:creator",
+    "dc:rights",
+    "photoshop:DateCreated",
+    "exif:GPSLatitude",
+    "exif:GPSLongitude",
+];
+/// Signature that starts a JPEG APP1 XMP segment.
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// `.xmp` sidecar next to `image`, shared by both halves of a RAW+JPEG pair
+/// as other editors expect.
+pub fn sidecar_path(image: &Path) -> PathBuf {
+    image.with_extension("xmp")
+}
+
+/// Whether `image` gets an interoperable `.xmp` sidecar. Only RAW files do:
+/// other formats carry their XMP inside the file, which is never rewritten.
+pub fn uses_sidecar(image: &Path) -> bool {
+    crate::thumbnail::is_raw_image(image)
+}
+
+/// Writes `meta` to the `.xmp` sidecar of `image`, keeping any properties
+/// other tools stored there.
+pub fn write_sidecar(image: &Path, meta: &XmpMetadata) -> anyhow::Result<()> {
+    let path = sidecar_path(image);
+    let xml = match std::fs::read_to_string(&path) {
+        Ok(existing) => merge(&existing, meta),
+        Err(_) => packet(meta),
+    };
+    std::fs::write(path, xml)?;
+    Ok(())
+}
+
+/// Keywords from the `.xmp` sidecar of `image`.
+pub fn read_sidecar_keywords(image: &Path) -> Vec<String> {
+    read_sidecar(image)
+        .map(|meta| meta.keywords)
+        .unwrap_or_default()
+}
+
+/// Photograph's properties from the `.xmp` sidecar of `image`.
+pub fn read_sidecar(image: &Path) -> Option<XmpMetadata> {
+    std::fs::read_to_string(sidecar_path(image))
+        .ok()
+        .map(|xml| parse(&xml))
+}
+
+/// Photograph's properties from an XMP document. Keywords are the
+/// hierarchical list when present, else the flat `dc:subject` one.
+fn parse(xml: &str) -> XmpMetadata {
+    let first = |tag| bag_items(xml, tag).into_iter().next().unwrap_or_default();
+    let hierarchical = bag_items(xml, "lr:hierarchicalSubject");
+    XmpMetadata {
+        keywords: if hierarchical.is_empty() {
+            bag_items(xml, "dc:subject")
+        } else {
+            hierarchical
+        },
+        title: first("dc:title"),
+        caption: first("dc:description"),
+        creator: first("dc:creator"),
+        copyright: first("dc:rights"),
+        date_created: simple_value(xml, "photoshop:DateCreated"),
+        location: simple_value(xml, "exif:GPSLatitude")
+            .zip(simple_value(xml, "exif:GPSLongitude"))
+            .and_then(|(lat, lon)| Some([parse_coordinate(&lat)?, parse_coordinate(&lon)?])),
+    }
+}
+
+/// ISO 8601 form of an EXIF date-time (`YYYY:MM:DD HH:MM:SS`).
+pub fn iso_datetime(exif: &str) -> String {
+    let mut out = exif.trim().replacen(':', "-", 2);
+    if let Some(i) = out.find(' ') {
+        out.replace_range(i..i + 1, "T");
+    }
+    out
+}
+
+/// A complete XMP packet holding `meta`.
+pub fn packet(meta: &XmpMetadata) -> String {
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+         <rdf:Description rdf:about=\"\" xmlns:dc=\"{NS_DC}\" xmlns:lr=\"{NS_LR}\" \
+         xmlns:photoshop=\"{NS_PHOTOSHOP}\" xmlns:exif=\"{NS_EXIF}\">\n\
+         {}</rdf:Description>\n\
+         </rdf:RDF>\n\
+         </x:xmpmeta>\n\
+         <?xpacket end=\"w\"?>",
+        properties(meta)
+    )
+}
+
+/// Property elements for `meta`, one per line.
+fn properties(meta: &XmpMetadata) -> String {
+    let mut out = String::new();
+    for (tag, text) in [
+        ("dc:title", &meta.title),
+        ("dc:description", &meta.caption),
+        ("dc:rights", &meta.copyright),
+    ] {
+        if !text.is_empty() {
+            out.push_str(&format!(
+                "<{tag}><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></{tag}>\n",
+                escape(text)
+            ));
+        }
+    }
+    if !meta.creator.is_empty() {
+        out.push_str(&format!(
+            "<dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>\n",
+            escape(&meta.creator)
+        ));
+    }
+    if let Some([lat, lon]) = meta.location {
+        out.push_str(&format!(
+            "<exif:GPSLatitude>{}</exif:GPSLatitude>\n\
+             <exif:GPSLongitude>{}</exif:GPSLongitude>\n",
+            format_coordinate(lat, ['N', 'S']),
+            format_coordinate(lon, ['E', 'W'])
+        ));
+    }
+    if let Some(date) = &meta.date_created {
+        out.push_str(&format!(
+            "<photoshop:DateCreated>{}</photoshop:DateCreated>\n",
+            escape(date)
+        ));
+    }
+    if !meta.keywords.is_empty() {
+        // Flat subjects list every level, as Lightroom does
+        let mut flat: Vec<&str> = Vec::new();
+        for keyword in &meta.keywords {
+            for level in keyword.split(crate::keywords::SEPARATOR) {
+                if !flat.contains(&level) {
+                    flat.push(level);
+                }
+            }
+        }
+        out.push_str(&bag("dc:subject", flat.iter().copied()));
+        out.push_str(&bag(
+            "lr:hierarchicalSubject",
+            meta.keywords.iter().map(String::as_str),
+        ));
+    }
+    out
+}
+
+fn bag<'a>(tag: &str, items: impl Iterator<Item = &'a str>) -> String {
+    let items: String = items
+        .map(|item| format!("<rdf:li>{}</rdf:li>", escape(item)))
+        .collect();
+    format!("<{tag}><rdf:Bag>{items}</rdf:Bag></{tag}>\n")
+}
+
+/// Replaces Photograph's properties in an existing XMP document, adding
+/// the namespaces it needs. Falls back to a fresh packet when the document
+/// has no `rdf:Description` to extend.
+fn merge(existing: &str, meta: &XmpMetadata) -> String {
+    let mut xml = existing.to_string();
+    for tag in MANAGED {
+        while let Some((start, end)) = element_span(&xml, tag) {
+            xml.replace_range(start..end, "");
+        }
+        // Simple properties may also be written as attributes
+        while let Some(start) = xml.find(&format!(" {tag}=\"")) {
+            let value = start + tag.len() + 3;
+            let Some(len) = xml[value..].find('"') else {
+                break;
+            };
+            xml.replace_range(start..value + len + 1, "");
+        }
+    }
+    let Some(open) = xml.find("<rdf:Description") else {
+        return packet(meta);
+    };
+    let Some(close) = xml[open..].find('>').map(|i| open + i) else {
+        return packet(meta);
+    };
+    let self_closing = xml[..close].ends_with('/');
+    let mut attrs = String::new();
+    for (prefix, ns) in [
+        ("dc", NS_DC),
+        ("lr", NS_LR),
+        ("photoshop", NS_PHOTOSHOP),
+        ("exif", NS_EXIF),
+    ] {
+        if !xml.contains(&format!("xmlns:{prefix}=")) {
+            attrs.push_str(&format!(" xmlns:{prefix}=\"{ns}\""));
+        }
+    }
+    let tag_end = if self_closing { close - 1 } else { close };
+    let body = properties(meta);
+    let replacement = if self_closing {
+        format!("{attrs}>\n{body}</rdf:Description>")
+    } else {
+        format!("{attrs}>\n{body}")
+    };
+    xml.replace_range(tag_end..close + 1, &replacement);
+    xml
+}
+
+/// Byte range of the first `<tag>…</tag>` element (or `<tag/>`).
+fn element_span(xml: &str, tag: &str) -> Option<(usize, usize)> {
+    let open = format!("<{tag}");
+    let mut from = 0;
+    loop {
+        let start = from + xml[from..].find(&open)?;
+        // Skip longer tag names sharing the prefix
+        let next = xml[start + open.len()..].chars().next()?;
+        if next == '>' || next == '/' || next.is_whitespace() {
+            let head_end = start + xml[start..].find('>')?;
+            if xml[..head_end].ends_with('/') {
+                return Some((start, head_end + 1));
+            }
+            let close = format!("</{tag}>");
+            let end = head_end + xml[head_end..].find(&close)? + close.len();
+            return Some((start, end));
+        }
+        from = start + open.len();
+    }
+}
+
+/// Text of the `rdf:li` items inside the first `tag` element.
+fn bag_items(xml: &str, tag: &str) -> Vec<String> {
+    let Some((start, end)) = element_span(xml, tag) else {
+        return Vec::new();
+    };
+    let mut items = Vec::new();
+    let mut rest = &xml[start..end];
+    while let Some(i) = rest.find("<rdf:li") {
+        rest = &rest[i..];
+        let (Some(open_end), Some(close)) = (rest.find('>'), rest.find("</rdf:li>")) else {
+            break;
+        };
+        if open_end < close {
+            let text = unescape(rest[open_end + 1..close].trim());
+            if !text.is_empty() {
+                items.push(text);
+            }
+        }
+        rest = &rest[close + "</rdf:li>".len()..];
+    }
+    items
+}
+
+/// XMP GPS coordinate (`DDD,MM.mmmmmmK`) for signed `degrees`, with the
+/// positive and negative hemisphere letters.
+fn format_coordinate(degrees: f64, [positive, negative]: [char; 2]) -> String {
+    let hemisphere = if degrees < 0.0 { negative } else { positive };
+    let degrees = degrees.abs();
+    let whole = degrees.trunc();
+    format!("{whole},{:.6}{hemisphere}", (degrees - whole) * 60.0)
+}
+
+/// Signed degrees of an XMP GPS coordinate (`DDD,MM.mmK` or `DDD,MM,SSK`).
+fn parse_coordinate(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let hemisphere = text.chars().last()?;
+    let sign = match hemisphere.to_ascii_uppercase() {
+        'N' | 'E' => 1.0,
+        'S' | 'W' => -1.0,
+        _ => return None,
+    };
+    let degrees = text[..text.len() - 1]
+        .split(',')
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, unit)| part.trim().parse::<f64>().ok().map(|v| v / unit))
+        .sum::<Option<f64>>()?;
+    Some(sign * degrees)
+}
+
+/// Text of the first `tag` element, or of a `tag` attribute.
+fn simple_value(xml: &str, tag: &str) -> Option<String> {
+    let text = match element_span(xml, tag) {
+        Some((start, end)) => {
+            let inner = &xml[start..end];
+            let open_end = inner.find('>')? + 1;
+            let close = inner.rfind("</")?;
+            inner.get(open_end..close)?.to_string()
+        }
+        None => {
+            let value = xml.find(&format!(" {tag}=\""))? + tag.len() + 3;
+            xml[value..value + xml[value..].find('"')?].to_string()
+        }
+    };
+    let text = unescape(text.trim());
+    (!text.is_empty()).then_some(text)
+}
+
+/// The XMP packet embedded in a file's bytes, if any.
+pub fn find_packet(bytes: &[u8]) -> Option<String> {
+    const OPEN: &[u8] = b"<x:xmpmeta";
+    const CLOSE: &[u8] = b"</x:xmpmeta>";
+    let start = bytes.windows(OPEN.len()).position(|w| w == OPEN)?;
+    let len = bytes[start..]
+        .windows(CLOSE.len())
+        .position(|w| w == CLOSE)?;
+    Some(String::from_utf8_lossy(&bytes[start..start + len + CLOSE.len()]).into_owned())
+}
+
+/// Every property in an XMP document as (qualified name, value) pairs in
+/// document order, for the metadata inspector. Array items and the fields
+/// of structures are joined into one value.
+pub fn list_properties(xml: &str) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = Vec::new();
+    let mut push = |name: &str, value: String| {
+        if let Some((_, existing)) = out.iter_mut().find(|(n, _)| n == name) {
+            existing.push_str("; ");
+            existing.push_str(&value);
+        } else {
+            out.push((name.to_string(), value));
+        }
+    };
+    // Property elements currently open; structure markup (`rdf:`, `x:`)
+    // is transparent.
+    let mut open: Vec<String> = Vec::new();
+    let mut rest = xml;
+    while let Some(lt) = rest.find('<') {
+        let text = rest[..lt].trim();
+        if !text.is_empty()
+            && let Some(name) = open.last()
+        {
+            push(name, unescape(text));
+        }
+        rest = &rest[lt..];
+        let Some(gt) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            if open.last().is_some_and(|n| n == name.trim()) {
+                open.pop();
+            }
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        for (attr, value) in attributes(tag) {
+            let structural =
+                attr.starts_with("xmlns") || attr.starts_with("rdf:") || attr == "xml:lang";
+            if !structural {
+                push(attr, unescape(value));
+            }
+        }
+        let structural = name.starts_with("rdf:") || name.starts_with("x:");
+        if !structural && !self_closing {
+            open.push(name.to_string());
+        }
+    }
+    out
+}
+
+/// `name="value"` pairs of a start tag's text.
+fn attributes(tag: &str) -> Vec<(&str, &str)> {
+    let mut attrs = Vec::new();
+    let mut rest = tag.split_once(char::is_whitespace).map_or("", |(_, r)| r);
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim();
+        let after = rest[eq + 1..].trim_start();
+        let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let Some(len) = after[1..].find(quote) else {
+            break;
+        };
+        attrs.push((name, &after[1..1 + len]));
+        rest = &after[len + 2..];
+    }
+    attrs
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Inserts `packet` as an APP1 XMP segment after the JFIF header.
+pub fn embed_in_jpeg(bytes: &[u8], packet: &str) -> anyhow::Result<Vec<u8>> {
+    anyhow::ensure!(bytes.starts_with(&[0xFF, 0xD8]), "not a JPEG stream");
+    let len = JPEG_XMP_HEADER.len() + packet.len() + 2;
+    anyhow::ensure!(len <= u16::MAX as usize, "XMP packet too large for JPEG");
+    let mut at = 2;
+    if bytes.get(2..4) == Some(&[0xFF, 0xE0]) {
+        let app0 = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        at = 4 + app0;
+    }
+    let mut out = Vec::with_capacity(bytes.len() + len + 2);
+    out.extend_from_slice(&bytes[..at]);
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&(len as u16).to_be_bytes());
+    out.extend_from_slice(JPEG_XMP_HEADER);
+    out.extend_from_slice(packet.as_bytes());
+    out.extend_from_slice(&bytes[at..]);
+    Ok(out)
+}
+
+/// Inserts `packet` as an `iTXt` chunk right after `IHDR`.
+pub fn embed_in_png(bytes: &[u8], packet: &str) -> anyhow::Result<Vec<u8>> {
+    const IHDR_END: usize = 8 + 8 + 13 + 4;
+    anyhow::ensure!(
+        bytes.len() > IHDR_END && &bytes[12..16] == b"IHDR",
+        "not a PNG stream"
+    );
+    let mut data = b"XML:com.adobe.xmp\0\0\0\0\0".to_vec();
+    data.extend_from_slice(packet.as_bytes());
+    let mut chunk = b"iTXt".to_vec();
+    chunk.extend_from_slice(&data);
+
+    let mut out = Vec::with_capacity(bytes.len() + chunk.len() + 8);
+    out.extend_from_slice(&bytes[..IHDR_END]);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(&chunk);
+    out.extend_from_slice(&crc32(&chunk).to_be_bytes());
+    out.extend_from_slice(&bytes[IHDR_END..]);
+    Ok(out)
+}
+
+/// Appends `packet` as an `XMP ` chunk of an extended (`VP8X`) WebP.
+pub fn embed_in_webp(bytes: &[u8], packet: &str) -> anyhow::Result<Vec<u8>> {
+    anyhow::ensure!(
+        bytes.len() > 30 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP",
+        "not a WebP stream"
+    );
+    anyhow::ensure!(
+        &bytes[12..16] == b"VP8X",
+        "WebP without an extended header cannot carry XMP"
+    );
+    let mut out = bytes.to_vec();
+    // XMP-present flag
+    out[20] |= 0x04;
+    out.extend_from_slice(b"XMP ");
+    out.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    out.extend_from_slice(packet.as_bytes());
+    if packet.len() % 2 == 1 {
+        out.push(0);
+    }
+    let riff_size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Ok(out)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(keywords: &[&str]) -> XmpMetadata {
+        XmpMetadata {
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            ..XmpMetadata::default()
+        }
+    }
+
+    #[test]
+    fn packet_lists_flat_and_hierarchical_subjects() {
+        let xml = packet(&meta(&["Places|Paris", "Cats & Dogs"]));
+        assert_eq!(
+            bag_items(&xml, "dc:subject"),
+            ["Places", "Paris", "Cats & Dogs"]
+        );
+        assert_eq!(
+            bag_items(&xml, "lr:hierarchicalSubject"),
+            ["Places|Paris", "Cats & Dogs"]
+        );
+    }
+
+    #[test]
+    fn merge_keeps_foreign_properties_and_replaces_keywords() {
+        let existing = "<x:xmpmeta><rdf:RDF><rdf:Description rdf:about=\"\" \
+            xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmp:Rating=\"3\">\
+            <dc:subject><rdf:Bag><rdf:li>old</rdf:li></rdf:Bag></dc:subject>\
+            </rdf:Description></rdf:RDF></x:xmpmeta>";
+        let merged = merge(existing, &meta(&["new"]));
+        assert!(merged.contains("xmp:Rating=\"3\""));
+        assert!(merged.contains(&format!("xmlns:dc=\"{NS_DC}\"")));
+        assert_eq!(bag_items(&merged, "dc:subject"), ["new"]);
+
+        let self_closing = "<rdf:RDF><rdf:Description rdf:about=\"\"/></rdf:RDF>";
+        let merged = merge(self_closing, &meta(&["a"]));
+        assert_eq!(bag_items(&merged, "lr:hierarchicalSubject"), ["a"]);
+        assert!(merged.contains("</rdf:Description></rdf:RDF>"));
+    }
+
+    #[test]
+    fn info_round_trips_and_replaces_attribute_forms() {
+        let info = XmpMetadata {
+            title: "Rooftops".to_string(),
+            caption: "Dawn <over> Paris".to_string(),
+            creator: "A. Person".to_string(),
+            copyright: "\u{a9} 2024".to_string(),
+            date_created: Some(iso_datetime("2024:05:01 06:12:00")),
+            location: Some([48.8584, 2.2945]),
+            ..meta(&["Places|Paris"])
+        };
+        assert_eq!(info.date_created.as_deref(), Some("2024-05-01T06:12:00"));
+        let round_trip = parse(&packet(&info));
+        let [lat, lon] = round_trip.location.unwrap();
+        assert!((lat - 48.8584).abs() < 1e-6 && (lon - 2.2945).abs() < 1e-6);
+        assert_eq!(
+            XmpMetadata {
+                location: info.location,
+                ..round_trip
+            },
+            info
+        );
+        assert_eq!(parse_coordinate("122,15,0W"), Some(-122.25));
+
+        let existing = "<rdf:RDF><rdf:Description rdf:about=\"\" \
+            photoshop:DateCreated=\"2001-01-01\" xmp:Rating=\"2\"/></rdf:RDF>";
+        let merged = merge(existing, &info);
+        assert_eq!(merged.matches("photoshop:DateCreated").count(), 2);
+        assert!(merged.contains("xmp:Rating=\"2\""));
+        assert_eq!(parse(&merged).title, info.title);
+    }
+
+    #[test]
+    fn properties_list_attributes_elements_and_arrays() {
+        let xml = "<x:xmpmeta><rdf:RDF><rdf:Description rdf:about=\"\" \
+            xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmp:Rating=\"4\">\
+            <dc:subject><rdf:Bag><rdf:li>cat</rdf:li><rdf:li>dog</rdf:li></rdf:Bag></dc:subject>\
+            <xmp:Label>Red</xmp:Label>\
+            </rdf:Description></rdf:RDF></x:xmpmeta>";
+        assert_eq!(
+            list_properties(xml),
+            [
+                ("xmp:Rating".to_string(), "4".to_string()),
+                ("dc:subject".to_string(), "cat; dog".to_string()),
+                ("xmp:Label".to_string(), "Red".to_string()),
+            ]
+        );
+        assert_eq!(
+            find_packet(format!("JUNK{xml}JUNK").as_bytes()).as_deref(),
+            Some(xml)
+        );
+    }
+
+    #[test]
+    fn jpeg_segment_follows_jfif_header() {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0xAA, 0xBB, 0xFF, 0xD9];
+        let out = embed_in_jpeg(&jpeg, "<x/>").unwrap();
+        assert_eq!(&out[..8], &jpeg[..8]);
+        assert_eq!(&out[8..10], &[0xFF, 0xE1]);
+        assert_eq!(&out[12..12 + JPEG_XMP_HEADER.len()], JPEG_XMP_HEADER);
+        assert!(out.ends_with(&[0xFF, 0xD9]));
+    }
+
+    #[test]
+    fn png_chunk_checksum_matches() {
+        // The standard check value for CRC-32
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+}